@@ -0,0 +1,40 @@
+-- Baseline: patient tables (previously patient_tables::setup_all_patient_tables)
+CREATE TABLE IF NOT EXISTS patients (
+    patient_id SERIAL PRIMARY KEY,
+    mr_number VARCHAR(100) NOT NULL UNIQUE,
+    first_name VARCHAR(255) NOT NULL,
+    last_name VARCHAR(255) NOT NULL,
+    date_of_birth DATE NOT NULL,
+    gender VARCHAR(10) CHECK (gender IN ('MALE', 'FEMALE', 'OTHERS')) NOT NULL,
+    patient_photo BYTEA, -- Encrypted field for photos
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS procedures (
+    procedure_id SERIAL PRIMARY KEY,
+    procedure_name BYTEA NOT NULL,
+    description BYTEA,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS patient_activity (
+    activity_id SERIAL PRIMARY KEY,
+    patient_id INT REFERENCES patients(patient_id) ON DELETE CASCADE,
+    status VARCHAR(20) CHECK (status IN ('COMPLETED', 'INCOMPLETE', 'TO_BE_REVIEWED')) NOT NULL,
+    procedure_id INT REFERENCES procedures(procedure_id) ON DELETE CASCADE,
+    doctor_id INT REFERENCES users(user_id) ON DELETE CASCADE,
+    doctors_note BYTEA, -- Encrypted field for doctor's note
+    patient_complaint BYTEA NOT NULL, -- Encrypted feild for patient's complaint
+    comments BYTEA,
+    activity_time TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS patient_history (
+    history_id SERIAL PRIMARY KEY,
+    patient_id INT REFERENCES patients(patient_id) ON DELETE CASCADE UNIQUE,
+    medical_conditions BYTEA NOT NULL, -- Encrypted field for medical conditions list
+    medications BYTEA NOT NULL, -- Encrypted field for medications list
+    allergies BYTEA NOT NULL, -- Encrypted field for allergies list
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
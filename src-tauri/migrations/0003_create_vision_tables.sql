@@ -0,0 +1,48 @@
+-- Baseline: vision tables (previously vision_tables::setup_vision_tables)
+CREATE TABLE IF NOT EXISTS vision (
+    vision_id SERIAL PRIMARY KEY,
+    patient_id INT REFERENCES patients(patient_id) ON DELETE CASCADE,
+    near_vision BYTEA,
+    distant_vision BYTEA,
+    side VARCHAR(10) CHECK (side IN ('LEFT', 'RIGHT')) NOT NULL,
+    value_type VARCHAR(10) CHECK (value_type IN ('UC', 'BCVA', 'PH')) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    created_by INT REFERENCES users(user_id) ON DELETE CASCADE,
+    updated_at TIMESTAMPTZ DEFAULT NULL,
+    updated_by INT REFERENCES users(user_id) ON DELETE CASCADE DEFAULT NULL,
+    CONSTRAINT unique_patient_vision UNIQUE (patient_id, side, value_type)
+);
+CREATE INDEX IF NOT EXISTS idx_vision_patient_side_value_vision ON vision(patient_id, side, value_type);
+
+CREATE TABLE IF NOT EXISTS refraction (
+    refraction_id SERIAL PRIMARY KEY,
+    patient_id INT REFERENCES patients(patient_id) ON DELETE CASCADE,
+    spherical BYTEA,
+    cylindrical BYTEA,
+    axis BYTEA,
+    side VARCHAR(10) CHECK (side IN ('LEFT', 'RIGHT')) NOT NULL,
+    value_type VARCHAR(10) CHECK (value_type IN ('DL', 'UD')) NOT NULL,
+    vision_type VARCHAR(10) CHECK (vision_type IN ('DV', 'NV')) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    created_by INT REFERENCES users(user_id) ON DELETE CASCADE,
+    updated_at TIMESTAMPTZ DEFAULT NULL,
+    updated_by INT REFERENCES users(user_id) ON DELETE CASCADE DEFAULT NULL,
+    CONSTRAINT unique_patient_refraction UNIQUE (patient_id, side, value_type, vision_type)
+);
+CREATE INDEX IF NOT EXISTS idx_refraction_patient_side_value_vision ON refraction(patient_id, side, value_type, vision_type);
+
+CREATE TABLE IF NOT EXISTS eye_measurement (
+    measurement_id SERIAL PRIMARY KEY,
+    patient_id INT REFERENCES patients(patient_id) ON DELETE CASCADE,
+    iop_at BYTEA,
+    iop_nct BYTEA,
+    cct BYTEA,
+    tond BYTEA,
+    side VARCHAR(10) CHECK (side IN ('LEFT', 'RIGHT')) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    created_by INT REFERENCES users(user_id) ON DELETE CASCADE,
+    updated_at TIMESTAMPTZ DEFAULT NULL,
+    updated_by INT REFERENCES users(user_id) ON DELETE CASCADE DEFAULT NULL,
+    CONSTRAINT unique_patient_eye_measurement UNIQUE (patient_id, side)
+);
+CREATE INDEX IF NOT EXISTS idx_eye_measurement_patient_side_value_vision ON eye_measurement(patient_id, side);
@@ -0,0 +1,41 @@
+-- Baseline: messaging tables (previously messaging_tables::setup_messaging_tables)
+CREATE TABLE IF NOT EXISTS conversations (
+    conversation_id SERIAL PRIMARY KEY,
+    user1 INT REFERENCES users(user_id) ON DELETE CASCADE,
+    user2 INT REFERENCES users(user_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT unique_conversation UNIQUE (user1, user2),
+    CONSTRAINT user_order CHECK (user1 < user2)
+);
+CREATE INDEX IF NOT EXISTS idx_conversation_users ON conversations (user1, user2);
+
+CREATE TABLE IF NOT EXISTS messages (
+    message_id SERIAL PRIMARY KEY,
+    conversation_id INT REFERENCES conversations(conversation_id) ON DELETE CASCADE,
+    sender_id INT REFERENCES users(user_id) ON DELETE SET NULL,
+    content TEXT,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_messages_conversation ON messages (conversation_id, created_at DESC);
+ALTER TABLE conversations
+ADD COLUMN IF NOT EXISTS last_message INT REFERENCES messages(message_id);
+
+CREATE TABLE IF NOT EXISTS message_status (
+    message_status_id SERIAL PRIMARY KEY,
+    message_id INT REFERENCES messages(message_id) ON DELETE CASCADE,
+    recipient_id INT REFERENCES users(user_id) ON DELETE CASCADE,
+    status TEXT CHECK (status IN ('delivered', 'read')) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_message_status_user ON message_status (recipient_id, status);
+CREATE INDEX IF NOT EXISTS idx_message_status_message ON message_status (message_id);
+
+CREATE TABLE IF NOT EXISTS files (
+    file_id SERIAL PRIMARY KEY,
+    message_id INT REFERENCES messages(message_id) ON DELETE CASCADE,
+    file_url TEXT NOT NULL,
+    file_type TEXT NOT NULL, -- e.g., 'image/png', 'application/pdf'
+    file_size BIGINT NOT NULL, -- in bytes
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_messages_files ON files (message_id, created_at DESC);
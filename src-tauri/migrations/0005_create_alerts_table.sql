@@ -0,0 +1,11 @@
+-- Baseline: alerts table (previously alert_tables::create_alerts_table)
+CREATE TABLE IF NOT EXISTS alerts (
+    alert_id SERIAL PRIMARY KEY,
+    priority_level VARCHAR(20) CHECK (priority_level in ('EMERGENCY', 'NORMAL')) NOT NULL,
+    title TEXT NOT NULL,
+    message TEXT,
+    issued_for INT REFERENCES users(user_id) ON DELETE CASCADE,
+    issued_by INT REFERENCES users(user_id) ON DELETE CASCADE,
+    status VARCHAR(20) CHECK (status IN ('delivered', 'read')) DEFAULT 'delivered',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
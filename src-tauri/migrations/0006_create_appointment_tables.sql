@@ -0,0 +1,15 @@
+-- Baseline: appointment tables (previously appointment_tables::setup_all_appointment_tables)
+CREATE TABLE IF NOT EXISTS appointments (
+    appointment_id SERIAL PRIMARY KEY,
+    description TEXT NOT NULL,
+    appointment_time TIMESTAMPTZ NOT NULL,
+    appointment_duration INTERVAL NOT NULL,
+    created_by INT REFERENCES users(user_id) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS appointment_users (
+    appointment_id INT REFERENCES appointments(appointment_id),
+    user_id INT REFERENCES users(user_id),
+    PRIMARY KEY (appointment_id, user_id)
+);
@@ -0,0 +1,16 @@
+-- Failed login counters and temporary lockout
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS failed_login_attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ DEFAULT NULL;
+
+-- Every login attempt, kept for security review
+CREATE TABLE IF NOT EXISTS login_attempts (
+    attempt_id BIGSERIAL PRIMARY KEY,
+    user_id INT REFERENCES users(user_id) ON DELETE SET NULL,
+    email VARCHAR(255) NOT NULL,
+    success BOOLEAN NOT NULL,
+    reason VARCHAR(50) NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_user ON login_attempts (user_id, attempted_at);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_email ON login_attempts (LOWER(email), attempted_at);
@@ -0,0 +1,31 @@
+-- Append-only record of every read and write of patient data.
+-- user_id and patient_id are plain columns so rows outlive the users and patients they mention.
+CREATE TABLE IF NOT EXISTS audit_log (
+    audit_id BIGSERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    action VARCHAR(20) CHECK (action IN ('READ', 'CREATE', 'UPDATE', 'DELETE', 'EXPORT')) NOT NULL,
+    entity VARCHAR(50) NOT NULL,
+    entity_id INT DEFAULT NULL,
+    patient_id INT DEFAULT NULL,
+    before_hash TEXT DEFAULT NULL,
+    after_hash TEXT DEFAULT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_audit_log_patient ON audit_log (patient_id, occurred_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log (user_id, occurred_at);
+
+CREATE OR REPLACE FUNCTION audit_log_reject_change() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_no_update_delete ON audit_log;
+CREATE TRIGGER audit_log_no_update_delete
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_reject_change();
+
+DROP TRIGGER IF EXISTS audit_log_no_truncate ON audit_log;
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_reject_change();
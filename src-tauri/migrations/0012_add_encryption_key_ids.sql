@@ -0,0 +1,21 @@
+-- Every row with pgcrypto encrypted columns records which key encrypted it.
+-- Existing rows were all written with the original ENCRYPTION_KEY, which becomes key 1.
+ALTER TABLE patients ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+ALTER TABLE procedures ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+ALTER TABLE patient_activity ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+ALTER TABLE patient_history ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+ALTER TABLE vision ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+ALTER TABLE refraction ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+ALTER TABLE eye_measurement ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+ALTER TABLE user_totp ADD COLUMN IF NOT EXISTS key_id INT NOT NULL DEFAULT 1;
+
+-- Checkpoint of the re-encryption job, one row per table, so an interrupted run resumes where it stopped
+CREATE TABLE IF NOT EXISTS key_rotation_progress (
+    table_name VARCHAR(63) PRIMARY KEY,
+    target_key_id INT NOT NULL,
+    last_id BIGINT NOT NULL DEFAULT 0,
+    rows_rotated BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ DEFAULT NULL
+);
@@ -0,0 +1,15 @@
+-- Extended demographics, encrypted with pgcrypto like the other patient fields and covered by key_id
+ALTER TABLE patients
+    ADD COLUMN IF NOT EXISTS phone BYTEA,
+    ADD COLUMN IF NOT EXISTS email BYTEA,
+    ADD COLUMN IF NOT EXISTS address BYTEA,
+    ADD COLUMN IF NOT EXISTS emergency_contact_name BYTEA,
+    ADD COLUMN IF NOT EXISTS emergency_contact_phone BYTEA,
+    ADD COLUMN IF NOT EXISTS preferred_language BYTEA,
+    ADD COLUMN IF NOT EXISTS national_id BYTEA,
+    ADD COLUMN IF NOT EXISTS active BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ DEFAULT NULL,
+    ADD COLUMN IF NOT EXISTS deactivated_at TIMESTAMPTZ DEFAULT NULL;
+
+-- Counter behind the {SEQ} part of generated MR numbers
+CREATE SEQUENCE IF NOT EXISTS patient_mr_number_seq;
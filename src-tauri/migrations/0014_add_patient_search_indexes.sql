@@ -0,0 +1,13 @@
+-- Blind index for phone search. Holds an HMAC of every leading run of 4 or more phone digits,
+-- so phone prefixes can be matched without decrypting the phone column.
+ALTER TABLE patients ADD COLUMN IF NOT EXISTS phone_search_tokens TEXT[] NOT NULL DEFAULT '{}';
+CREATE INDEX IF NOT EXISTS idx_patients_phone_search_tokens ON patients USING GIN (phone_search_tokens);
+
+-- Prefix search on the plaintext columns
+CREATE INDEX IF NOT EXISTS idx_patients_first_name_prefix ON patients (LOWER(first_name) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_patients_last_name_prefix ON patients (LOWER(last_name) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_patients_mr_number_prefix ON patients (mr_number text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_patients_date_of_birth ON patients (date_of_birth);
+
+-- Last visit and today's appointments per patient
+CREATE INDEX IF NOT EXISTS idx_patient_activity_patient_time ON patient_activity (patient_id, activity_time);
@@ -0,0 +1,25 @@
+-- Duplicate patient merges. The merged patient is deactivated and points at the surviving one.
+ALTER TABLE patients ADD COLUMN IF NOT EXISTS merged_into INT REFERENCES patients(patient_id) DEFAULT NULL;
+
+CREATE TABLE IF NOT EXISTS patient_merges (
+    merge_id SERIAL PRIMARY KEY,
+    surviving_patient_id INT NOT NULL REFERENCES patients(patient_id) ON DELETE CASCADE,
+    merged_patient_id INT NOT NULL REFERENCES patients(patient_id) ON DELETE CASCADE,
+    merged_by INT NOT NULL,
+    merged_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    undone_by INT DEFAULT NULL,
+    undone_at TIMESTAMPTZ DEFAULT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_patient_merges_surviving ON patient_merges (surviving_patient_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_patient_merges_open ON patient_merges (merged_patient_id) WHERE undone_at IS NULL;
+
+-- Undo record: every row whose patient_id a merge changed. MOVED rows went from the merged
+-- patient to the surviving one; DISPLACED rows of the surviving patient lost a unique key
+-- conflict to a newer row and went the other way.
+CREATE TABLE IF NOT EXISTS patient_merge_rows (
+    merge_id INT NOT NULL REFERENCES patient_merges(merge_id) ON DELETE CASCADE,
+    table_name VARCHAR(50) NOT NULL,
+    row_id INT NOT NULL,
+    direction VARCHAR(10) CHECK (direction IN ('MOVED', 'DISPLACED')) NOT NULL,
+    PRIMARY KEY (merge_id, table_name, row_id)
+);
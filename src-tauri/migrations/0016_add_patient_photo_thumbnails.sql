@@ -0,0 +1,4 @@
+-- Patient photos are stored as encrypted JPEG data URLs. patient_photo holds the full-size image
+-- and patient_photo_thumbnail a small square crop used by list views.
+ALTER TABLE patients ADD COLUMN IF NOT EXISTS patient_photo_thumbnail BYTEA DEFAULT NULL;
+ALTER TABLE patients ADD COLUMN IF NOT EXISTS photo_updated_at TIMESTAMPTZ DEFAULT NULL;
@@ -0,0 +1,32 @@
+-- Structured medical history: one row per condition, medication or allergy, with the typed fields
+-- stored as encrypted JSON. Replaces the free-text lists in patient_history, which stay readable.
+-- Removed entries are kept with removed_at set so their history remains complete.
+CREATE TABLE IF NOT EXISTS medical_history_entries (
+    entry_id SERIAL PRIMARY KEY,
+    patient_id INT REFERENCES patients(patient_id) ON DELETE CASCADE,
+    kind VARCHAR(20) CHECK (kind IN ('CONDITION', 'MEDICATION', 'ALLERGY')) NOT NULL,
+    details BYTEA NOT NULL,
+    key_id INT NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ DEFAULT NULL,
+    updated_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    removed_at TIMESTAMPTZ DEFAULT NULL,
+    removed_by INT REFERENCES users(user_id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_medical_history_entries_patient ON medical_history_entries (patient_id, kind);
+
+-- Every version of every entry, with who wrote it. details is the entry after the change,
+-- or as it was when removed.
+CREATE TABLE IF NOT EXISTS medical_history_changes (
+    change_id SERIAL PRIMARY KEY,
+    entry_id INT NOT NULL REFERENCES medical_history_entries(entry_id) ON DELETE CASCADE,
+    patient_id INT REFERENCES patients(patient_id) ON DELETE CASCADE,
+    action VARCHAR(20) CHECK (action IN ('CREATE', 'UPDATE', 'DELETE')) NOT NULL,
+    details BYTEA NOT NULL,
+    key_id INT NOT NULL DEFAULT 1,
+    changed_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_medical_history_changes_entry ON medical_history_changes (entry_id, changed_at);
+CREATE INDEX IF NOT EXISTS idx_medical_history_changes_patient ON medical_history_changes (patient_id, changed_at);
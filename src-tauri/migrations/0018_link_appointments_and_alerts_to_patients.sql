@@ -0,0 +1,6 @@
+-- Appointments and alerts may concern a patient, which places them on that patient's timeline.
+-- Both stay optional: staff meetings and general alerts have no patient.
+ALTER TABLE appointments ADD COLUMN IF NOT EXISTS patient_id INT REFERENCES patients(patient_id) ON DELETE SET NULL;
+ALTER TABLE alerts ADD COLUMN IF NOT EXISTS patient_id INT REFERENCES patients(patient_id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_appointments_patient ON appointments (patient_id, appointment_time) WHERE patient_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_alerts_patient ON alerts (patient_id, created_at) WHERE patient_id IS NOT NULL;
@@ -0,0 +1,19 @@
+-- Clinical workflow for activities: SCHEDULED -> CHECKED_IN -> IN_PROGRESS -> TO_BE_REVIEWED -> COMPLETED,
+-- or CANCELLED before completion. INCOMPLETE activities had been started but not finished.
+ALTER TABLE patient_activity DROP CONSTRAINT IF EXISTS patient_activity_status_check;
+UPDATE patient_activity SET status = 'IN_PROGRESS' WHERE status = 'INCOMPLETE';
+ALTER TABLE patient_activity ADD CONSTRAINT patient_activity_status_check
+    CHECK (status IN ('SCHEDULED', 'CHECKED_IN', 'IN_PROGRESS', 'TO_BE_REVIEWED', 'COMPLETED', 'CANCELLED'));
+
+-- Every status change with who made it. from_status is NULL for the status an activity was created with.
+CREATE TABLE IF NOT EXISTS activity_transitions (
+    transition_id SERIAL PRIMARY KEY,
+    activity_id INT NOT NULL REFERENCES patient_activity(activity_id) ON DELETE CASCADE,
+    from_status VARCHAR(20),
+    to_status VARCHAR(20) NOT NULL,
+    reason BYTEA, -- Encrypted, e.g. why an activity was cancelled
+    key_id INT NOT NULL DEFAULT 1,
+    transitioned_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    transitioned_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_activity_transitions_activity ON activity_transitions (activity_id, transitioned_at);
@@ -0,0 +1,31 @@
+-- Comments on an activity, one row each with author and time, replacing the newline-joined
+-- patient_activity.comments blob. Replies point at their parent. Deleted comments are kept with
+-- deleted_at set so threads and their history stay complete.
+-- Existing blobs are encrypted with keys the database does not hold, so the application splits
+-- them into rows at startup (procedure_comments::migrate_legacy_comments) and clears the column.
+CREATE TABLE IF NOT EXISTS procedure_comments (
+    comment_id SERIAL PRIMARY KEY,
+    activity_id INT NOT NULL REFERENCES patient_activity(activity_id) ON DELETE CASCADE,
+    parent_id INT REFERENCES procedure_comments(comment_id) ON DELETE CASCADE,
+    body BYTEA NOT NULL,
+    key_id INT NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ DEFAULT NULL,
+    updated_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    deleted_at TIMESTAMPTZ DEFAULT NULL,
+    deleted_by INT REFERENCES users(user_id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_procedure_comments_activity ON procedure_comments (activity_id, created_at);
+
+-- Every version of every comment. body is the comment after the change, or as it was when deleted.
+CREATE TABLE IF NOT EXISTS procedure_comment_changes (
+    change_id SERIAL PRIMARY KEY,
+    comment_id INT NOT NULL REFERENCES procedure_comments(comment_id) ON DELETE CASCADE,
+    action VARCHAR(20) CHECK (action IN ('CREATE', 'UPDATE', 'DELETE')) NOT NULL,
+    body BYTEA NOT NULL,
+    key_id INT NOT NULL DEFAULT 1,
+    changed_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_procedure_comment_changes_comment ON procedure_comment_changes (comment_id, changed_at);
@@ -0,0 +1,18 @@
+-- Catalog fields for procedures. Codes are optional: CPT for billing, SNOMED CT for clinical coding.
+-- Inactive procedures stay attached to past activities but cannot be picked for new ones.
+ALTER TABLE procedures
+    ADD COLUMN IF NOT EXISTS category VARCHAR(20) NOT NULL DEFAULT 'DIAGNOSTIC'
+        CHECK (category IN ('DIAGNOSTIC', 'SURGICAL', 'IMAGING', 'OPTOMETRY')),
+    ADD COLUMN IF NOT EXISTS default_duration_minutes INT CHECK (default_duration_minutes > 0),
+    ADD COLUMN IF NOT EXISTS default_cost_cents BIGINT CHECK (default_cost_cents >= 0),
+    ADD COLUMN IF NOT EXISTS cpt_code VARCHAR(5),
+    ADD COLUMN IF NOT EXISTS snomed_code VARCHAR(18),
+    ADD COLUMN IF NOT EXISTS active BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS created_by INT REFERENCES users(user_id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ DEFAULT NULL,
+    ADD COLUMN IF NOT EXISTS updated_by INT REFERENCES users(user_id) ON DELETE SET NULL;
+
+-- Deleting a procedure used to cascade into the clinical records that reference it
+ALTER TABLE patient_activity DROP CONSTRAINT IF EXISTS patient_activity_procedure_id_fkey;
+ALTER TABLE patient_activity ADD CONSTRAINT patient_activity_procedure_id_fkey
+    FOREIGN KEY (procedure_id) REFERENCES procedures(procedure_id) ON DELETE RESTRICT;
@@ -0,0 +1,12 @@
+-- Attending doctor and assisting nurse of an activity.
+-- Removing a user used to cascade into the clinical records they were attached to.
+ALTER TABLE patient_activity DROP CONSTRAINT IF EXISTS patient_activity_doctor_id_fkey;
+ALTER TABLE patient_activity ADD CONSTRAINT patient_activity_doctor_id_fkey
+    FOREIGN KEY (doctor_id) REFERENCES users(user_id) ON DELETE SET NULL;
+
+ALTER TABLE patient_activity
+    ADD COLUMN IF NOT EXISTS nurse_id INT REFERENCES users(user_id) ON DELETE SET NULL;
+
+-- Worklists look activities up by clinician and day
+CREATE INDEX IF NOT EXISTS idx_patient_activity_doctor ON patient_activity (doctor_id, activity_time);
+CREATE INDEX IF NOT EXISTS idx_patient_activity_nurse ON patient_activity (nurse_id, activity_time);
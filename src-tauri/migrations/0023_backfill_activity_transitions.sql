@@ -0,0 +1,5 @@
+-- Activities that predate the workflow have no history. Give each one a starting row with the status it has now.
+INSERT INTO activity_transitions (activity_id, from_status, to_status, transitioned_by, transitioned_at)
+SELECT a.activity_id, NULL, a.status, a.doctor_id, COALESCE(a.created_at, a.activity_time)
+FROM patient_activity a
+WHERE NOT EXISTS (SELECT 1 FROM activity_transitions t WHERE t.activity_id = a.activity_id);
@@ -0,0 +1,26 @@
+#!/usr/bin/env bash
+# Runs the Postgres integration tests against a throwaway server.
+#
+# Starts a fresh cluster with initdb in a temporary directory, listening only on a unix socket,
+# points TEST_DATABASE_URL at it and removes it again when the tests finish.
+# Extra arguments are passed to cargo test, e.g. scripts/test-postgres.sh vision::
+# Requires initdb and pg_ctl on PATH; Postgres refuses to start as root.
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+PORT="${TEST_PG_PORT:-55432}"
+WORK_DIR="$(mktemp -d)"
+
+cleanup() {
+    pg_ctl -D "$WORK_DIR/data" -m immediate stop >/dev/null 2>&1 || true
+    rm -rf "$WORK_DIR"
+}
+trap cleanup EXIT
+
+initdb -D "$WORK_DIR/data" -U postgres --auth=trust >/dev/null
+pg_ctl -D "$WORK_DIR/data" -l "$WORK_DIR/postgres.log" -w \
+    -o "-p $PORT -k $WORK_DIR -c listen_addresses=''" start >/dev/null
+
+export TEST_DATABASE_URL="postgres://postgres@localhost:$PORT/postgres?host=$WORK_DIR"
+cargo test --test postgres -- "$@"
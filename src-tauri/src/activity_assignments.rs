@@ -0,0 +1,407 @@
+// src-tauri/src/activity_assignments.rs
+
+// Dependencies
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+use tauri::State;
+use crate::activity_workflow::ActivityStatus;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_PATIENT_ACTIVITY};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::{AppError, FieldError};
+use crate::patients::AppointmentData;
+use crate::permissions::{authenticate, require, Permission, Role};
+use crate::repository::{ActivityAssignmentRepo, AuditRepo, PgRepository};
+
+// Struct to store the clinicians responsible for an activity
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ActivityAssignment {
+    pub activity_id: i32,
+    pub patient_id: i32,
+    pub status: String,
+    // Attending doctor
+    pub doctor_id: Option<i32>,
+    pub doctor_name: Option<String>,
+    // Assisting nurse
+    pub nurse_id: Option<i32>,
+    pub nurse_name: Option<String>,
+}
+
+// Personal worklists of the signed-in clinician
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Worklist {
+    // Today's activities the user attends or assists, cancelled ones left out, earliest first.
+    // The day runs from start to end in the clinician's own time zone, as sent by the client.
+    Today { start: DateTime<Utc>, end: DateTime<Utc> },
+    // Activities waiting for the attending doctor's review, oldest first
+    AwaitingReview,
+}
+
+impl Worklist {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Worklist::Today { .. } => "TODAY",
+            Worklist::AwaitingReview => "AWAITING_REVIEW",
+        }
+    }
+}
+
+// Function to check that the attending doctor and assisting nurse are active staff in those roles
+pub async fn validate_clinicians<R: ActivityAssignmentRepo>(
+    repo: &R,
+    doctor_id: Option<i32>,
+    nurse_id: Option<i32>
+) -> Result<(), AppError> {
+    let mut errors = vec![];
+    let checks = [(doctor_id, Role::Doctor, "doctor_id", "Choose an active doctor."), (nurse_id, Role::Nurse, "nurse_id", "Choose an active nurse.")];
+
+    for (user_id, role, field, message) in checks {
+        let Some(user_id) = user_id else { continue };
+        if repo.find_active_role(user_id).await?.as_deref() != Some(role.as_str()) {
+            errors.push(FieldError { field: field.to_string(), message: message.to_string() });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+// Function to check, under the row lock, that an activity is still open to reassignment
+pub fn check_reassignable(status: &str) -> Result<(), AppError> {
+    match ActivityStatus::parse(status) {
+        Some(ActivityStatus::Completed | ActivityStatus::Cancelled) => {
+            Err(AppError::conflict("Completed and cancelled activities cannot be reassigned."))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Audit event for a change of clinicians, shared by every ActivityAssignmentRepo implementation
+pub fn assignment_changed_event(user_id: i32, before: &ActivityAssignment, after: &ActivityAssignment) -> AuditEvent {
+    AuditEvent::new(user_id, AuditAction::Update, ENTITY_PATIENT_ACTIVITY)
+        .patient(after.patient_id)
+        .entity_id(after.activity_id)
+        .before(Some(&(before.doctor_id, before.nurse_id)))
+        .after(&(after.doctor_id, after.nurse_id))
+}
+
+async fn fetch_assignment(conn: &mut PgConnection, activity_id: i32) -> Result<Option<ActivityAssignment>, AppError> {
+    sqlx::query_as!(
+        ActivityAssignment,
+        r#"
+        SELECT
+            pa.activity_id,
+            pa.patient_id as "patient_id!",
+            pa.status,
+            pa.doctor_id,
+            d.first_name || ' ' || d.last_name as doctor_name,
+            pa.nurse_id,
+            n.first_name || ' ' || n.last_name as nurse_name
+        FROM patient_activity pa
+        LEFT JOIN users d ON d.user_id = pa.doctor_id
+        LEFT JOIN users n ON n.user_id = pa.nurse_id
+        WHERE pa.activity_id = $1
+        "#,
+        activity_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| AppError::database("Error while fetching activity clinicians", e))
+}
+
+impl ActivityAssignmentRepo for PgRepository<'_> {
+    async fn find_active_role(&self, user_id: i32) -> Result<Option<String>, AppError> {
+        sqlx::query_scalar!("SELECT role FROM users WHERE user_id = $1 AND status = 'ACTIVE'", user_id)
+            .fetch_optional(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching user role", e))
+    }
+
+    async fn find_assignment(&self, activity_id: i32) -> Result<Option<ActivityAssignment>, AppError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| AppError::database("Error while acquiring connection", e))?;
+
+        fetch_assignment(&mut conn, activity_id).await
+    }
+
+    async fn assign_clinicians(
+        &self,
+        user_id: i32,
+        activity_id: i32,
+        doctor_id: i32,
+        nurse_id: Option<i32>
+    ) -> Result<Option<ActivityAssignment>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        // Lock the row so the status check and the audited before and after belong to the same change
+        let Some(status) = sqlx::query_scalar!("SELECT status FROM patient_activity WHERE activity_id = $1 FOR UPDATE", activity_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::database("Error while locking patient activity", e))?
+        else {
+            return Ok(None);
+        };
+        check_reassignable(&status)?;
+        let Some(before) = fetch_assignment(&mut tx, activity_id).await? else {
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            "UPDATE patient_activity SET doctor_id = $2, nurse_id = $3 WHERE activity_id = $1",
+            activity_id,
+            doctor_id,
+            nurse_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while assigning clinicians", e))?;
+
+        let after = fetch_assignment(&mut tx, activity_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Activity was not saved.".to_string()))?;
+        record_audit(&mut *tx, self.keyring, assignment_changed_event(user_id, &before, &after)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(after))
+    }
+
+    async fn list_worklist(&self, user_id: i32, worklist: Worklist) -> Result<Vec<AppointmentData>, AppError> {
+        let (day_start, day_end) = match worklist {
+            Worklist::Today { start, end } => (Some(start), Some(end)),
+            Worklist::AwaitingReview => (None, None),
+        };
+
+        sqlx::query_as!(
+            AppointmentData,
+            r#"
+            SELECT
+                p.patient_id,
+                p.mr_number,
+                p.first_name,
+                p.last_name,
+                p.date_of_birth,
+                p.gender,
+                pgp_sym_decrypt(p.patient_photo_thumbnail::bytea, ($1::TEXT[])[p.key_id]) as patient_photo,
+                p.created_at,
+                pa.activity_id,
+                pa.status,
+                pgp_sym_decrypt(pr.procedure_name::bytea, ($1::TEXT[])[pr.key_id]) as activity,
+                pgp_sym_decrypt(pa.doctors_note::bytea, ($1::TEXT[])[pa.key_id]) as doctors_note,
+                pgp_sym_decrypt(pa.patient_complaint::bytea, ($1::TEXT[])[pa.key_id]) as patient_complaint,
+                pa.activity_time,
+                pa.created_at as activity_created_at,
+                pa.doctor_id,
+                d.first_name || ' ' || d.last_name as doctor_name,
+                pa.nurse_id,
+                n.first_name || ' ' || n.last_name as nurse_name
+            FROM patient_activity pa
+            JOIN patients p ON p.patient_id = pa.patient_id
+            LEFT JOIN procedures pr ON pr.procedure_id = pa.procedure_id
+            LEFT JOIN users d ON d.user_id = pa.doctor_id
+            LEFT JOIN users n ON n.user_id = pa.nurse_id
+            WHERE
+                ($3 = 'TODAY' AND pa.activity_time >= $4 AND pa.activity_time < $5 AND pa.status <> 'CANCELLED'
+                    AND $2 IN (pa.doctor_id, pa.nurse_id))
+                OR ($3 = 'AWAITING_REVIEW' AND pa.status = 'TO_BE_REVIEWED' AND pa.doctor_id = $2)
+            ORDER BY pa.activity_time ASC
+            "#,
+            &self.keyring.sql_keys(),
+            user_id,
+            worklist.as_str(),
+            day_start,
+            day_end
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Failed to fetch worklist", e))
+    }
+}
+
+// Function to change the attending doctor and assisting nurse of an open activity
+pub async fn reassign_activity<R: ActivityAssignmentRepo>(
+    repo: &R,
+    user: &AuthUser,
+    activity_id: i32,
+    doctor_id: i32,
+    nurse_id: Option<i32>
+) -> Result<ActivityAssignment, AppError> {
+    require(user, Permission::AssignActivity)?;
+
+    repo.find_assignment(activity_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Activity does not exist."))?;
+    validate_clinicians(repo, Some(doctor_id), nurse_id).await?;
+
+    repo.assign_clinicians(user.user_id, activity_id, doctor_id, nurse_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Activity does not exist."))
+}
+
+// Function to list one of the signed-in user's worklists
+pub async fn list_my_worklist<R: ActivityAssignmentRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    worklist: Worklist
+) -> Result<Vec<AppointmentData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+    if let Worklist::Today { start, end } = worklist {
+        // A local day is 23 to 25 hours long around daylight saving changes
+        if end <= start || end - start > Duration::hours(25) {
+            return Err(AppError::validation("day_end", "The day must end after it starts and last at most 25 hours."));
+        }
+    }
+
+    let entries = repo.list_worklist(user.user_id, worklist).await?;
+    let patient_ids: Vec<i32> = entries.iter().map(|entry| entry.patient_id).collect();
+    repo.record_patient_reads(user.user_id, ENTITY_PATIENT_ACTIVITY, &patient_ids).await?;
+
+    Ok(entries)
+}
+
+// Endpoint to change who attends and assists an activity
+#[tauri::command]
+pub async fn reassign_patient_activity(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    activity_id: i32,
+    doctor_id: i32,
+    nurse_id: Option<i32>,
+) -> Result<ActivityAssignment, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    reassign_activity(&PgRepository::new(pool, &config.keyring), &user, activity_id, doctor_id, nurse_id).await
+}
+
+// Endpoint to get today's patients of the signed-in doctor or nurse.
+// day_start and day_end bound the clinician's local day, so the list turns over at their midnight.
+#[tauri::command]
+pub async fn get_my_patients_today(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    day_start: DateTime<Utc>,
+    day_end: DateTime<Utc>,
+) -> Result<Vec<AppointmentData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_my_worklist(&PgRepository::new(pool, &config.keyring), &user, Worklist::Today { start: day_start, end: day_end }).await
+}
+
+// Endpoint to get the activities awaiting the signed-in doctor's review
+#[tauri::command]
+pub async fn get_my_pending_reviews(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+) -> Result<Vec<AppointmentData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_my_worklist(&PgRepository::new(pool, &config.keyring), &user, Worklist::AwaitingReview).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity_workflow::{move_activity, ActivityStatus};
+    use crate::memory_repository::{new_activity, test_user, MemoryRepository};
+    use crate::patients::{add_patient_activity, list_patient_activity, NewPatientActivity};
+
+    #[tokio::test]
+    async fn the_recording_doctor_attends_unless_someone_else_is_named() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let colleague = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let procedure_id = repo.add_procedure("Tonometry", "Eye pressure");
+
+        let mine = new_activity(repo.add_patient("Ada", "Lovelace"), procedure_id);
+        add_patient_activity(&repo, &doctor, &mine).await.unwrap();
+        let theirs = NewPatientActivity {
+            doctor_id: Some(colleague.user_id),
+            nurse_id: Some(nurse.user_id),
+            ..new_activity(repo.add_patient("Alan", "Turing"), procedure_id)
+        };
+        add_patient_activity(&repo, &doctor, &theirs).await.unwrap();
+
+        let recorded = list_patient_activity(&repo, &doctor, mine.patient_id).await.unwrap();
+        assert_eq!((recorded[0].doctor_id, recorded[0].nurse_id), (Some(doctor.user_id), None));
+        let recorded = list_patient_activity(&repo, &doctor, theirs.patient_id).await.unwrap();
+        assert_eq!((recorded[0].doctor_id, recorded[0].nurse_id), (Some(colleague.user_id), Some(nurse.user_id)));
+        assert!(recorded[0].nurse_name.is_some());
+
+        // Roles are checked both ways round
+        let swapped = NewPatientActivity { doctor_id: Some(nurse.user_id), nurse_id: Some(doctor.user_id), ..mine.clone() };
+        let Err(AppError::Validation(errors)) = add_patient_activity(&repo, &doctor, &swapped).await else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(errors.iter().map(|error| error.field.as_str()).collect::<Vec<_>>(), vec!["doctor_id", "nurse_id"]);
+    }
+
+    // A local day around now, whatever the time zone of the machine running the tests
+    fn around_now() -> Worklist {
+        Worklist::Today { start: Utc::now() - Duration::hours(12), end: Utc::now() + Duration::hours(12) }
+    }
+
+    #[tokio::test]
+    async fn worklists_follow_reassignment() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let colleague = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let procedure_id = repo.add_procedure("Tonometry", "Eye pressure");
+
+        let checked_in = NewPatientActivity { status: "CHECKED_IN".to_string(), ..new_activity(repo.add_patient("Ada", "Lovelace"), procedure_id) };
+        add_patient_activity(&repo, &doctor, &checked_in).await.unwrap();
+        let mut completed_id = 0;
+        for (days_ago, status) in [(3, ActivityStatus::ToBeReviewed), (1, ActivityStatus::Completed)] {
+            let activity = NewPatientActivity {
+                activity_time: Utc::now() - Duration::days(days_ago),
+                ..new_activity(repo.add_patient("Ada", "Lovelace"), procedure_id)
+            };
+            add_patient_activity(&repo, &doctor, &activity).await.unwrap();
+            completed_id = list_patient_activity(&repo, &doctor, activity.patient_id).await.unwrap()[0].activity_id;
+            move_activity(&repo, &doctor, completed_id, status, None).await.unwrap();
+        }
+        let today = list_my_worklist(&repo, &doctor, around_now()).await.unwrap();
+        let reviews = list_my_worklist(&repo, &doctor, Worklist::AwaitingReview).await.unwrap();
+        assert_eq!((today.len(), reviews.len()), (1, 1));
+        let yesterday = Worklist::Today { start: Utc::now() - Duration::hours(36), end: Utc::now() - Duration::hours(12) };
+        assert_eq!(list_my_worklist(&repo, &doctor, yesterday).await.unwrap().len(), 1);
+        let backwards = Worklist::Today { start: Utc::now(), end: Utc::now() - Duration::hours(1) };
+        assert_eq!(list_my_worklist(&repo, &doctor, backwards).await.unwrap_err().code(), "VALIDATION");
+
+        let activity_id = reviews[0].activity_id.unwrap();
+        let moved = reassign_activity(&repo, &nurse, activity_id, colleague.user_id, Some(nurse.user_id)).await.unwrap();
+        assert_eq!((moved.doctor_id, moved.nurse_id), (Some(colleague.user_id), Some(nurse.user_id)));
+        assert!(list_my_worklist(&repo, &doctor, Worklist::AwaitingReview).await.unwrap().is_empty());
+        assert_eq!(list_my_worklist(&repo, &colleague, Worklist::AwaitingReview).await.unwrap().len(), 1);
+        // Review is the attending doctor's job; the nurse only sees the visits of the day
+        assert!(list_my_worklist(&repo, &nurse, Worklist::AwaitingReview).await.unwrap().is_empty());
+
+        let today_id = today[0].activity_id.unwrap();
+        reassign_activity(&repo, &doctor, today_id, doctor.user_id, Some(nurse.user_id)).await.unwrap();
+        assert_eq!(list_my_worklist(&repo, &nurse, around_now()).await.unwrap().len(), 1);
+
+        let closed = reassign_activity(&repo, &doctor, completed_id, colleague.user_id, None).await;
+        assert!(matches!(closed, Err(AppError::Conflict(_))));
+    }
+}
@@ -0,0 +1,471 @@
+// src-tauri/src/activity_workflow.rs
+
+// Dependencies
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tauri::State;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_PATIENT_ACTIVITY};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::keys::KeyRing;
+use crate::permissions::{authenticate, require, Permission, Role};
+use crate::repository::{ActivityWorkflowRepo, AuditRepo, PgRepository};
+
+const MAX_REASON_LENGTH: usize = 1000;
+
+// Statuses of a patient activity, as stored in patient_activity.status
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ActivityStatus {
+    Scheduled,
+    CheckedIn,
+    InProgress,
+    ToBeReviewed,
+    Completed,
+    Cancelled,
+}
+
+impl ActivityStatus {
+    pub fn parse(status: &str) -> Option<ActivityStatus> {
+        match status {
+            "SCHEDULED" => Some(ActivityStatus::Scheduled),
+            "CHECKED_IN" => Some(ActivityStatus::CheckedIn),
+            "IN_PROGRESS" => Some(ActivityStatus::InProgress),
+            "TO_BE_REVIEWED" => Some(ActivityStatus::ToBeReviewed),
+            "COMPLETED" => Some(ActivityStatus::Completed),
+            "CANCELLED" => Some(ActivityStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityStatus::Scheduled => "SCHEDULED",
+            ActivityStatus::CheckedIn => "CHECKED_IN",
+            ActivityStatus::InProgress => "IN_PROGRESS",
+            ActivityStatus::ToBeReviewed => "TO_BE_REVIEWED",
+            ActivityStatus::Completed => "COMPLETED",
+            ActivityStatus::Cancelled => "CANCELLED",
+        }
+    }
+
+    // Name used in messages to the user
+    fn label(&self) -> &'static str {
+        match self {
+            ActivityStatus::Scheduled => "scheduled",
+            ActivityStatus::CheckedIn => "checked in",
+            ActivityStatus::InProgress => "in progress",
+            ActivityStatus::ToBeReviewed => "to be reviewed",
+            ActivityStatus::Completed => "completed",
+            ActivityStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+// Moves an activity can make and the roles allowed to make them. Anything else is rejected.
+// Front desk staff can check patients in and cancel visits that have not started;
+// once a procedure is under way only clinicians move it on, and only doctors sign it off.
+const TRANSITIONS: &[(ActivityStatus, ActivityStatus, &[Role])] = &[
+    (ActivityStatus::Scheduled, ActivityStatus::CheckedIn, &[Role::Doctor, Role::Nurse, Role::Admin]),
+    (ActivityStatus::Scheduled, ActivityStatus::Cancelled, &[Role::Doctor, Role::Nurse, Role::Admin]),
+    (ActivityStatus::CheckedIn, ActivityStatus::InProgress, &[Role::Doctor, Role::Nurse]),
+    (ActivityStatus::CheckedIn, ActivityStatus::Cancelled, &[Role::Doctor, Role::Nurse, Role::Admin]),
+    (ActivityStatus::InProgress, ActivityStatus::ToBeReviewed, &[Role::Doctor, Role::Nurse]),
+    (ActivityStatus::InProgress, ActivityStatus::Completed, &[Role::Doctor]),
+    (ActivityStatus::InProgress, ActivityStatus::Cancelled, &[Role::Doctor]),
+    (ActivityStatus::ToBeReviewed, ActivityStatus::Completed, &[Role::Doctor]),
+    (ActivityStatus::ToBeReviewed, ActivityStatus::Cancelled, &[Role::Doctor]),
+];
+
+// Struct to store one status change of an activity. from_status is None for the status it was created with.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ActivityTransition {
+    pub transition_id: i32,
+    pub activity_id: i32,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub reason: Option<String>,
+    pub transitioned_by: Option<i32>,
+    pub transitioned_by_name: Option<String>,
+    pub transitioned_at: DateTime<Utc>,
+}
+
+// Function to list the statuses a role may move an activity to from its current status
+pub fn next_statuses(role: Role, from: ActivityStatus) -> Vec<ActivityStatus> {
+    TRANSITIONS
+        .iter()
+        .filter(|(rule_from, _, roles)| *rule_from == from && roles.contains(&role))
+        .map(|(_, to, _)| *to)
+        .collect()
+}
+
+// Function to check that the workflow allows the move and that the role may make it
+pub fn check_transition(role: Role, from: ActivityStatus, to: ActivityStatus) -> Result<(), AppError> {
+    if from == to {
+        return Err(AppError::validation("to_status", &format!("Activity is already {}.", to.label())));
+    }
+
+    let roles = TRANSITIONS
+        .iter()
+        .find(|(rule_from, rule_to, _)| *rule_from == from && *rule_to == to)
+        .map(|(_, _, roles)| *roles)
+        .ok_or_else(|| {
+            AppError::validation(
+                "to_status",
+                &format!("An activity that is {} cannot be moved to {}.", from.label(), to.label()),
+            )
+        })?;
+
+    if !roles.contains(&role) {
+        return Err(AppError::Forbidden(format!(
+            "Your role cannot move an activity from {} to {}.",
+            from.label(),
+            to.label()
+        )));
+    }
+
+    Ok(())
+}
+
+// Function to check the status a new activity starts in. Review, completion and cancellation
+// are only reached through transitions.
+pub fn validate_initial_status(status: &str) -> Result<ActivityStatus, AppError> {
+    match ActivityStatus::parse(status) {
+        Some(status @ (ActivityStatus::Scheduled | ActivityStatus::CheckedIn | ActivityStatus::InProgress)) => Ok(status),
+        _ => Err(AppError::validation("status", "A new activity must start as SCHEDULED, CHECKED_IN or IN_PROGRESS.")),
+    }
+}
+
+// Function to tidy the reason for a transition. Cancelling requires one.
+fn validate_reason(to: ActivityStatus, reason: Option<&str>) -> Result<Option<String>, AppError> {
+    let reason = reason.map(str::trim).filter(|reason| !reason.is_empty());
+
+    match reason {
+        None if to == ActivityStatus::Cancelled => Err(AppError::validation("reason", "Give a reason for cancelling.")),
+        Some(reason) if reason.chars().count() > MAX_REASON_LENGTH => {
+            Err(AppError::validation("reason", &format!("Use at most {} characters.", MAX_REASON_LENGTH)))
+        }
+        reason => Ok(reason.map(str::to_string)),
+    }
+}
+
+// Audit event for a status change, shared by every ActivityWorkflowRepo implementation
+pub fn activity_transition_event(user_id: i32, patient_id: i32, transition: &ActivityTransition) -> AuditEvent {
+    AuditEvent::new(user_id, AuditAction::Update, ENTITY_PATIENT_ACTIVITY)
+        .patient(patient_id)
+        .entity_id(transition.activity_id)
+        .before(transition.from_status.as_ref())
+        .after(&(&transition.to_status, &transition.reason))
+}
+
+// Function to append a transition to activity_transitions, also used when an activity is created
+pub async fn insert_transition(
+    conn: &mut PgConnection,
+    keyring: &KeyRing,
+    user_id: i32,
+    activity_id: i32,
+    from: Option<&str>,
+    to: &str,
+    reason: Option<&str>
+) -> Result<ActivityTransition, AppError> {
+    sqlx::query_as!(
+        ActivityTransition,
+        r#"
+        WITH inserted AS (
+            INSERT INTO activity_transitions (activity_id, from_status, to_status, reason, key_id, transitioned_by)
+            VALUES ($1, $2, $3, pgp_sym_encrypt($4, $5), $6, $7)
+            RETURNING transition_id, activity_id, from_status, to_status, transitioned_by, transitioned_at
+        )
+        SELECT
+            i.transition_id,
+            i.activity_id,
+            i.from_status,
+            i.to_status,
+            $4::TEXT as reason,
+            i.transitioned_by,
+            u.first_name || ' ' || u.last_name as transitioned_by_name,
+            i.transitioned_at
+        FROM inserted i
+        LEFT JOIN users u ON u.user_id = i.transitioned_by
+        "#,
+        activity_id,
+        from,
+        to,
+        reason,
+        &keyring.current().secret,
+        keyring.current().id,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| AppError::database("Error while recording activity transition", e))
+}
+
+impl ActivityWorkflowRepo for PgRepository<'_> {
+    async fn find_activity_status(&self, activity_id: i32) -> Result<Option<String>, AppError> {
+        sqlx::query_scalar!("SELECT status FROM patient_activity WHERE activity_id = $1", activity_id)
+            .fetch_optional(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching activity status", e))
+    }
+
+    async fn transition_activity(
+        &self,
+        user_id: i32,
+        activity_id: i32,
+        from: ActivityStatus,
+        to: ActivityStatus,
+        reason: Option<&str>
+    ) -> Result<Option<ActivityTransition>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        // Only moves the activity if nobody changed its status since it was read
+        let patient_id = sqlx::query_scalar!(
+            r#"
+            UPDATE patient_activity
+            SET status = $3
+            WHERE activity_id = $1 AND status = $2
+            RETURNING patient_id
+            "#,
+            activity_id,
+            from.as_str(),
+            to.as_str()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating activity status", e))?;
+        let Some(patient_id) = patient_id else {
+            return Ok(None);
+        };
+
+        let transition = insert_transition(&mut tx, self.keyring, user_id, activity_id, Some(from.as_str()), to.as_str(), reason).await?;
+        record_audit(&mut *tx, self.keyring, activity_transition_event(user_id, patient_id.unwrap_or_default(), &transition)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(transition))
+    }
+
+    async fn list_activity_transitions(&self, activity_id: i32) -> Result<Vec<ActivityTransition>, AppError> {
+        sqlx::query_as!(
+            ActivityTransition,
+            r#"
+            SELECT
+                t.transition_id,
+                t.activity_id,
+                t.from_status,
+                t.to_status,
+                pgp_sym_decrypt(t.reason::bytea, ($1::TEXT[])[t.key_id]) as reason,
+                t.transitioned_by,
+                u.first_name || ' ' || u.last_name as transitioned_by_name,
+                t.transitioned_at
+            FROM activity_transitions t
+            LEFT JOIN users u ON u.user_id = t.transitioned_by
+            WHERE t.activity_id = $2
+            ORDER BY t.transitioned_at, t.transition_id
+            "#,
+            &self.keyring.sql_keys(),
+            activity_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching activity transitions", e))
+    }
+}
+
+// Function to move an activity to another status of the workflow
+pub async fn move_activity<R: ActivityWorkflowRepo>(
+    repo: &R,
+    user: &AuthUser,
+    activity_id: i32,
+    to: ActivityStatus,
+    reason: Option<&str>
+) -> Result<ActivityTransition, AppError> {
+    require(user, Permission::TransitionActivity)?;
+
+    let reason = validate_reason(to, reason)?;
+    let current = repo
+        .find_activity_status(activity_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Activity does not exist."))?;
+    let from = ActivityStatus::parse(&current)
+        .ok_or_else(|| AppError::Database(format!("Activity has an unknown status: {}", current)))?;
+    let role = Role::parse(&user.role).map_err(|_| AppError::forbidden())?;
+    check_transition(role, from, to)?;
+
+    repo.transition_activity(user.user_id, activity_id, from, to, reason.as_deref())
+        .await?
+        .ok_or_else(|| AppError::conflict("The activity's status was changed by someone else. Reload it and try again."))
+}
+
+// Function to list the status changes of an activity, oldest first
+pub async fn list_activity_transitions<R: ActivityWorkflowRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    activity_id: i32
+) -> Result<Vec<ActivityTransition>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let transitions = repo.list_activity_transitions(activity_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_ACTIVITY).entity_id(activity_id))
+        .await?;
+
+    Ok(transitions)
+}
+
+// Endpoint to move an activity through the clinical workflow
+#[tauri::command]
+pub async fn transition_activity(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    activity_id: i32,
+    to_status: ActivityStatus,
+    reason: Option<String>,
+) -> Result<ActivityTransition, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    move_activity(&PgRepository::new(pool, &config.keyring), &user, activity_id, to_status, reason.as_deref()).await
+}
+
+// Endpoint to get who moved an activity through the workflow and when
+#[tauri::command]
+pub async fn get_activity_transitions(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    activity_id: i32,
+) -> Result<Vec<ActivityTransition>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_activity_transitions(&PgRepository::new(pool, &config.keyring), &user, activity_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{new_activity, test_user, MemoryRepository};
+    use crate::patients::{add_patient_activity, NewPatientActivity};
+    use crate::repository::PatientRepo;
+
+    async fn scheduled_activity(repo: &MemoryRepository) -> i32 {
+        let doctor = test_user(repo, "DOCTOR");
+        let activity = new_activity(repo.add_patient("Ada", "Lovelace"), repo.add_procedure("Tonometry", "Eye pressure"));
+        repo.create_activity(doctor.user_id, &NewPatientActivity { status: "SCHEDULED".to_string(), ..activity }).await.unwrap()
+    }
+
+    async fn status(repo: &MemoryRepository, activity_id: i32) -> Option<String> {
+        repo.find_activity_status(activity_id).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn walks_an_activity_through_the_workflow() {
+        let repo = MemoryRepository::default();
+        let activity_id = scheduled_activity(&repo).await;
+        let admin = test_user(&repo, "ADMIN");
+        let nurse = test_user(&repo, "NURSE");
+        let doctor = test_user(&repo, "DOCTOR");
+
+        move_activity(&repo, &admin, activity_id, ActivityStatus::CheckedIn, None).await.unwrap();
+        move_activity(&repo, &nurse, activity_id, ActivityStatus::InProgress, None).await.unwrap();
+        move_activity(&repo, &nurse, activity_id, ActivityStatus::ToBeReviewed, Some("  ")).await.unwrap();
+        let done = move_activity(&repo, &doctor, activity_id, ActivityStatus::Completed, Some("Reviewed"))
+            .await
+            .unwrap();
+        assert_eq!(done.from_status.as_deref(), Some("TO_BE_REVIEWED"));
+        assert_eq!(done.reason.as_deref(), Some("Reviewed"));
+        assert_eq!(done.transitioned_by, Some(doctor.user_id));
+
+        let history = list_activity_transitions(&repo, &doctor, activity_id).await.unwrap();
+        let moves: Vec<(Option<&str>, &str)> = history
+            .iter()
+            .map(|transition| (transition.from_status.as_deref(), transition.to_status.as_str()))
+            .collect();
+        assert_eq!(
+            moves,
+            vec![
+                (None, "SCHEDULED"),
+                (Some("SCHEDULED"), "CHECKED_IN"),
+                (Some("CHECKED_IN"), "IN_PROGRESS"),
+                (Some("IN_PROGRESS"), "TO_BE_REVIEWED"),
+                (Some("TO_BE_REVIEWED"), "COMPLETED"),
+            ]
+        );
+        assert_eq!(history[3].reason, None);
+        assert_eq!(status(&repo, activity_id).await.as_deref(), Some("COMPLETED"));
+        let updates = repo
+            .audit_events()
+            .iter()
+            .filter(|event| event.action == AuditAction::Update && event.entity_id == Some(activity_id))
+            .count();
+        assert_eq!(updates, 4);
+    }
+
+    #[tokio::test]
+    async fn rejects_illegal_transitions_and_roles() {
+        let repo = MemoryRepository::default();
+        let activity_id = scheduled_activity(&repo).await;
+        let admin = test_user(&repo, "ADMIN");
+        let nurse = test_user(&repo, "NURSE");
+
+        let skipped = move_activity(&repo, &nurse, activity_id, ActivityStatus::Completed, None).await;
+        assert!(matches!(skipped, Err(AppError::Validation(ref errors)) if errors[0].field == "to_status"));
+
+        move_activity(&repo, &admin, activity_id, ActivityStatus::CheckedIn, None).await.unwrap();
+        let again = move_activity(&repo, &admin, activity_id, ActivityStatus::CheckedIn, None).await;
+        assert!(matches!(again, Err(AppError::Validation(_))));
+        let started = move_activity(&repo, &admin, activity_id, ActivityStatus::InProgress, None).await;
+        assert!(matches!(started, Err(AppError::Forbidden(_))));
+
+        move_activity(&repo, &nurse, activity_id, ActivityStatus::InProgress, None).await.unwrap();
+        let signed_off = move_activity(&repo, &nurse, activity_id, ActivityStatus::Completed, None).await;
+        assert!(matches!(signed_off, Err(AppError::Forbidden(_))));
+        assert_eq!(status(&repo, activity_id).await.as_deref(), Some("IN_PROGRESS"));
+
+        let missing = move_activity(&repo, &nurse, activity_id + 100, ActivityStatus::Cancelled, Some("No show")).await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn cancelling_requires_a_reason_and_ends_the_workflow() {
+        let repo = MemoryRepository::default();
+        let activity_id = scheduled_activity(&repo).await;
+        let admin = test_user(&repo, "ADMIN");
+
+        let unexplained = move_activity(&repo, &admin, activity_id, ActivityStatus::Cancelled, Some(" ")).await;
+        assert!(matches!(unexplained, Err(AppError::Validation(ref errors)) if errors[0].field == "reason"));
+
+        let cancelled = move_activity(&repo, &admin, activity_id, ActivityStatus::Cancelled, Some(" Patient called to cancel "))
+            .await
+            .unwrap();
+        assert_eq!(cancelled.reason.as_deref(), Some("Patient called to cancel"));
+        assert!(next_statuses(Role::Doctor, ActivityStatus::Cancelled).is_empty());
+        let reopened = move_activity(&repo, &admin, activity_id, ActivityStatus::Scheduled, None).await;
+        assert!(matches!(reopened, Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn new_activities_start_in_a_workflow_status() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let activity = new_activity(repo.add_patient("Ada", "Lovelace"), repo.add_procedure("Tonometry", "Eye pressure"));
+
+        for status in ["INCOMPLETE", "TO_BE_REVIEWED", "COMPLETED", "CANCELLED", "scheduled"] {
+            let result = add_patient_activity(&repo, &doctor, &NewPatientActivity { status: status.to_string(), ..activity.clone() }).await;
+            assert!(matches!(result, Err(AppError::Validation(ref errors)) if errors[0].field == "status"), "{}", status);
+        }
+        add_patient_activity(&repo, &doctor, &activity).await.unwrap();
+        assert_eq!(next_statuses(Role::Nurse, ActivityStatus::InProgress), vec![ActivityStatus::ToBeReviewed]);
+    }
+}
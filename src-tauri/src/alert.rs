@@ -2,88 +2,165 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
 use crate::db::DatabaseState;
-use crate::auth::get_user_from_token;
+use crate::error::AppError;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AlertRepo, PgRepository};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Alert {
-    alert_id: i32,
-    priority_level: Option<String>,
-    title: Option<String>,
-    message: Option<String>,
-    issued_for: Option<i32>,
-    issued_for_name: Option<String>,
-    issued_by: Option<i32>,
-    issued_by_name: Option<String>,
-    status: Option<String>,
-    created_at: Option<DateTime<Utc>>
+    pub alert_id: i32,
+    pub priority_level: Option<String>,
+    pub title: Option<String>,
+    pub message: Option<String>,
+    pub issued_for: Option<i32>,
+    pub issued_for_name: Option<String>,
+    pub issued_by: Option<i32>,
+    pub issued_by_name: Option<String>,
+    pub status: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    // Patient the alert concerns, if any
+    pub patient_id: Option<i32>
+}
+
+// Input for create_alert
+#[derive(Clone, Debug)]
+pub struct NewAlert {
+    pub priority_level: String,
+    pub title: String,
+    pub message: String,
+    pub issued_for: i32,
+    pub patient_id: Option<i32>
+}
+
+impl AlertRepo for PgRepository<'_> {
+    async fn list_alerts(&self, user_id: i32) -> Result<Vec<Alert>, AppError> {
+        sqlx::query_as!(
+            Alert,
+            r#"
+            SELECT
+                a.alert_id, a.priority_level, a.title, a.message, a.issued_for,
+                COALESCE(uf.first_name || ' ' || uf.last_name, NULL) AS issued_for_name,
+                a.issued_by,
+                COALESCE(ub.first_name || ' ' || ub.last_name, NULL) AS issued_by_name,
+                a.status, a.created_at, a.patient_id
+            FROM
+                alerts a
+            LEFT JOIN users uf ON a.issued_for = uf.user_id
+            LEFT JOIN users ub ON a.issued_by = ub.user_id
+            WHERE
+                a.issued_for = $1
+            OR
+                a.issued_by = $1
+            "#,
+            &user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching alerts for user", e))
+    }
+
+    async fn create_alert(&self, issued_by: i32, alert: &NewAlert) -> Result<Alert, AppError> {
+        sqlx::query_as!(
+            Alert,
+            r#"
+            INSERT INTO
+                alerts (priority_level, title, message, issued_for, issued_by, patient_id)
+            VALUES
+                ($1, $2, $3, $4, $5, $6)
+            RETURNING
+                alert_id, priority_level, title, message, issued_for, NULL as issued_for_name,
+                issued_by, NULL as issued_by_name, status, created_at, patient_id
+            "#,
+            &alert.priority_level,
+            &alert.title,
+            &alert.message,
+            &alert.issued_for,
+            &issued_by,
+            alert.patient_id
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while creating new alert", e))
+    }
+}
+
+// Function to list the alerts issued for or by a user
+pub async fn list_alerts<R: AlertRepo>(repo: &R, user: &AuthUser) -> Result<Vec<Alert>, AppError> {
+    require(user, Permission::StaffCoordination)?;
+
+    repo.list_alerts(user.user_id).await
+}
+
+// Function to issue an alert to another user
+pub async fn issue_alert<R: AlertRepo>(repo: &R, user: &AuthUser, alert: &NewAlert) -> Result<Alert, AppError> {
+    require(user, Permission::StaffCoordination)?;
+
+    repo.create_alert(user.user_id, alert).await
 }
 
 // Endpoint to get all alerts for a user
 #[tauri::command]
 pub async fn get_alerts(
-    state: tauri::State<'_, DatabaseState>, 
+    state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String
-) -> Result<Vec<Alert>, String> {
-    let pool = state.pool.lock().await;
-    let user = get_user_from_token(token)?;
-
-    sqlx::query_as!(
-        Alert,
-        r#"
-        SELECT
-            a.alert_id, a.priority_level, a.title, a.message, a.issued_for,
-            COALESCE(uf.first_name || ' ' || uf.last_name, NULL) AS issued_for_name,
-            a.issued_by,
-            COALESCE(ub.first_name || ' ' || ub.last_name, NULL) AS issued_by_name,
-            a.status, a.created_at
-        FROM 
-            alerts a
-        LEFT JOIN users uf ON a.issued_for = uf.user_id
-        LEFT JOIN users ub ON a.issued_by = ub.user_id
-        WHERE
-            a.issued_for = $1
-        OR
-            a.issued_by = $1
-        "#,
-        &user.user_id
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| format!("Error while fetching alerts for user: {}", e))
+) -> Result<Vec<Alert>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_alerts(&PgRepository::new(pool, &config.keyring), &user).await
 }
 
 // Endpoint to create a new alert
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_alert(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String,
     priority_level: String,
     title: String,
     message: String,
-    issued_for: i32
-) -> Result<Alert, String> {
-    let pool = state.pool.lock().await;
-    let user = get_user_from_token(token)?;
-
-    sqlx::query_as!(
-        Alert,
-        r#"
-        INSERT INTO 
-            alerts (priority_level, title, message, issued_for, issued_by)
-        VALUES
-            ($1, $2, $3, $4, $5)
-        RETURNING
-            alert_id, priority_level, title, message, issued_for, NULL as issued_for_name,
-            issued_by, NULL as issued_by_name, status, created_at
-        "#,
-        &priority_level,
-        &title,
-        &message,
-        &issued_for,
-        &user.user_id
-    )
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| format!("Error while creating new alert: {}", e))
+    issued_for: i32,
+    patient_id: Option<i32>
+) -> Result<Alert, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    let alert = NewAlert { priority_level, title, message, issued_for, patient_id };
+    issue_alert(&PgRepository::new(pool, &config.keyring), &user, &alert).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{test_user, MemoryRepository};
+
+    #[tokio::test]
+    async fn alerts_are_visible_to_both_ends() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let admin = test_user(&repo, "ADMIN");
+        let alert = NewAlert {
+            priority_level: "EMERGENCY".to_string(),
+            title: "Room 3".to_string(),
+            message: "Patient waiting".to_string(),
+            issued_for: nurse.user_id,
+            patient_id: None
+        };
+
+        let created = issue_alert(&repo, &doctor, &alert).await.unwrap();
+        assert_eq!(created.issued_by, Some(doctor.user_id));
+        assert_eq!(created.issued_for_name, None);
+
+        let for_nurse = list_alerts(&repo, &nurse).await.unwrap();
+        assert_eq!(for_nurse.len(), 1);
+        assert!(for_nurse[0].issued_by_name.is_some());
+        assert_eq!(list_alerts(&repo, &doctor).await.unwrap().len(), 1);
+        assert!(list_alerts(&repo, &admin).await.unwrap().is_empty());
+    }
 }
@@ -1,120 +1,236 @@
 // src-tauri/src/appointment.rs
 
 // Dependencies
-use crate::{auth::get_user_from_token, DatabaseState};
-use chrono::{DateTime, Duration, Utc};
-use serde::{Serialize, Deserialize};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::repository::{AppointmentRepo, PgRepository};
+use crate::{permissions::{authenticate, require, Permission}, DatabaseState};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use sqlx::postgres::types::PgInterval;
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Debug)]
 pub struct Appointment {
-    appointment_id: i32,
-    description: Option<String>,
-    appointment_time: Option<DateTime<Utc>>,
-    appointment_duration: Option<i64>,
-    created_by: Option<i32>,
-    created_at: Option<DateTime<Utc>>
+    pub appointment_id: i32,
+    pub description: Option<String>,
+    pub appointment_time: Option<DateTime<Utc>>,
+    // Microseconds
+    pub appointment_duration: Option<i64>,
+    pub created_by: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+    // Patient the appointment concerns, if any
+    pub patient_id: Option<i32>
+}
+
+// Input for create_appointment
+#[derive(Clone, Debug)]
+pub struct NewAppointment {
+    pub description: String,
+    pub appointment_time: DateTime<Utc>,
+    // Seconds
+    pub appointment_duration: i64,
+    pub users: Vec<i32>,
+    pub patient_id: Option<i32>
+}
+
+impl AppointmentRepo for PgRepository<'_> {
+    async fn list_appointments(&self, user_id: i32) -> Result<Vec<Appointment>, AppError> {
+        sqlx::query_as!(
+            Appointment,
+            r#"
+            SELECT DISTINCT
+                a.appointment_id,
+                a.description,
+                a.appointment_time,
+                (EXTRACT(EPOCH FROM a.appointment_duration) * 1000000)::BIGINT as "appointment_duration?",
+                a.created_by,
+                a.created_at,
+                a.patient_id
+            FROM
+                appointments a
+            JOIN
+                appointment_users au
+            ON
+                au.appointment_id = a.appointment_id
+            WHERE
+                au.user_id = $1
+            ORDER BY
+                a.appointment_time DESC
+            "#,
+            &user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching appointments", e))
+    }
+
+    async fn create_appointment(&self, created_by: i32, appointment: &NewAppointment) -> Result<Appointment, AppError> {
+        let appointment_duration = PgInterval {
+            months: 0,
+            days: 0,
+            microseconds: appointment.appointment_duration * 1_000_000
+        };
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let created = sqlx::query_as!(
+            Appointment,
+            r#"
+            INSERT INTO
+                appointments (description, appointment_time, appointment_duration, created_by, patient_id)
+            VALUES
+                ($1, $2, $3, $4, $5)
+            RETURNING
+                appointment_id,
+                description,
+                appointment_time,
+                (EXTRACT(EPOCH FROM appointment_duration) * 1000000)::BIGINT as "appointment_duration?",
+                created_by,
+                created_at,
+                patient_id
+            "#,
+            &appointment.description,
+            &appointment.appointment_time,
+            &appointment_duration,
+            &created_by,
+            appointment.patient_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while creating appointment", e))?;
+
+        for user in appointment.users.iter() {
+            sqlx::query!(
+                r#"
+                INSERT INTO appointment_users (appointment_id, user_id)
+                VALUES ($1, $2)
+                RETURNING *
+                "#,
+                &created.appointment_id,
+                user
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::database("Error while adding user to appointment", e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(created)
+    }
+}
+
+// Function to list a user's appointments, defaulting to the caller
+pub async fn list_appointments<R: AppointmentRepo>(
+    repo: &R,
+    user: &AuthUser,
+    user_id: Option<i32>
+) -> Result<Vec<Appointment>, AppError> {
+    require(user, Permission::StaffCoordination)?;
+
+    repo.list_appointments(user_id.unwrap_or(user.user_id)).await
+}
+
+// Function to schedule an appointment between users
+pub async fn schedule_appointment<R: AppointmentRepo>(
+    repo: &R,
+    user: &AuthUser,
+    appointment: &NewAppointment
+) -> Result<Appointment, AppError> {
+    require(user, Permission::StaffCoordination)?;
+
+    if appointment.appointment_duration <= 0 {
+        return Err(AppError::validation("appointment_duration", "Duration must be positive."));
+    }
+
+    repo.create_appointment(user.user_id, appointment).await
 }
 
 #[tauri::command]
 pub async fn get_appointments(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String,
     user_id: Option<i32>
-) -> Result<Vec<Appointment>, String> {
-    let pool = state.pool.lock().await;
-    let user = get_user_from_token(token)?;
-
-    let user_id = match user_id {
-        Some(val) => val,
-        None => user.user_id
-    };
-
-    sqlx::query_as!(
-        Appointment,
-        r#"
-        SELECT DISTINCT 
-            a.appointment_id,
-            a.description,
-            a.appointment_time,
-            (EXTRACT(EPOCH FROM a.appointment_duration) * 1000000)::BIGINT as "appointment_duration?",
-            a.created_by,
-            a.created_at
-        FROM
-            appointments a
-        JOIN
-            appointment_users au 
-        ON
-            au.appointment_id = a.appointment_id
-        WHERE
-            au.user_id = $1
-        ORDER BY
-            a.appointment_time DESC
-        "#,
-        &user_id
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| format!("Error while fetching appointments: {}", e))
+) -> Result<Vec<Appointment>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_appointments(&PgRepository::new(pool, &config.keyring), &user, user_id).await
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_appointment(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String,
     description: String,
     appointment_time: String,
     appointment_duration: i64,
-    users: Vec<i32>
-) -> Result<Appointment, String> {
-    let pool = state.pool.lock().await;
-    let user = get_user_from_token(token)?;
-
-    let appointment_time = appointment_time.parse::<DateTime<Utc>>().map_err(|e| format!("Invalid appointment time: {}", e))?;
-    let appointment_duration = PgInterval {
-        months: 0,
-        days: 0,
-        microseconds: appointment_duration * 1_000_000
-    };
-
-    let appointment = sqlx::query_as!(
-        Appointment,
-        r#"
-        INSERT INTO
-            appointments (description, appointment_time, appointment_duration, created_by)
-        VALUES
-            ($1, $2, $3, $4)
-        RETURNING
-            appointment_id,
-            description,
-            appointment_time,
-            (EXTRACT(EPOCH FROM appointment_duration) * 1000000)::BIGINT as "appointment_duration?",
-            created_by,
-            created_at
-        "#,
-        &description,
-        &appointment_time,
-        &appointment_duration,
-        &user.user_id
-    )
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| format!("Error while creating appointment: {}", e))?;
-
-    for user in users.iter() {
-        sqlx::query!(
-            r#"
-            INSERT INTO appointment_users (appointment_id, user_id)
-            VALUES ($1, $2)
-            RETURNING *
-            "#,
-            &appointment.appointment_id,
-            user
-        )
-        .fetch_one(&*pool)
-        .await
-        .map_err(|e| format!("Error while adding user to appointment: {}", e))?;
+    users: Vec<i32>,
+    patient_id: Option<i32>
+) -> Result<Appointment, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    let appointment_time = appointment_time.parse::<DateTime<Utc>>().map_err(|_| AppError::validation("appointment_time", "Invalid appointment time."))?;
+
+    let appointment = NewAppointment { description, appointment_time, appointment_duration, users, patient_id };
+    schedule_appointment(&PgRepository::new(pool, &config.keyring), &user, &appointment).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{test_user, MemoryRepository};
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn appointments_are_listed_for_participants() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let now = Utc::now();
+
+        for (offset, description) in [(1, "Ward round"), (2, "Theatre briefing")] {
+            let appointment = NewAppointment {
+                description: description.to_string(),
+                appointment_time: now + Duration::days(offset),
+                appointment_duration: 1800,
+                users: vec![doctor.user_id, nurse.user_id],
+                patient_id: None
+            };
+            let created = schedule_appointment(&repo, &doctor, &appointment).await.unwrap();
+            assert_eq!(created.appointment_duration, Some(1_800_000_000));
+        }
+
+        let for_nurse = list_appointments(&repo, &doctor, Some(nurse.user_id)).await.unwrap();
+        let descriptions: Vec<_> = for_nurse.iter().map(|appointment| appointment.description.as_deref()).collect();
+        assert_eq!(descriptions, vec![Some("Theatre briefing"), Some("Ward round")]);
+        assert_eq!(list_appointments(&repo, &nurse, None).await.unwrap().len(), 2);
     }
 
-    Ok(appointment)
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn duration_must_be_positive() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let appointment = NewAppointment {
+            description: "Ward round".to_string(),
+            appointment_time: Utc::now(),
+            appointment_duration: 0,
+            users: vec![doctor.user_id],
+            patient_id: None
+        };
+
+        let err = schedule_appointment(&repo, &doctor, &appointment).await.unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+    }
+}
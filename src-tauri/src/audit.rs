@@ -0,0 +1,234 @@
+// src-tauri/src/audit.rs
+
+// Dependencies
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use tauri::State;
+use crate::config::AppConfig;
+use crate::crypto::hmac_sha256_hex;
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::keys::KeyRing;
+use crate::permissions::{authorize, Permission};
+use crate::repository::{AuditRepo, PgRepository};
+
+// Entities recorded in audit_log.entity
+pub const ENTITY_PATIENT: &str = "patient";
+pub const ENTITY_PATIENT_ACTIVITY: &str = "patient_activity";
+pub const ENTITY_PATIENT_HISTORY: &str = "patient_history";
+pub const ENTITY_MEDICAL_HISTORY: &str = "medical_history";
+pub const ENTITY_VISION: &str = "vision";
+pub const ENTITY_REFRACTION: &str = "refraction";
+pub const ENTITY_EYE_MEASUREMENT: &str = "eye_measurement";
+pub const ENTITY_DOCUMENT: &str = "document";
+pub const ENTITY_PATIENT_MERGE: &str = "patient_merge";
+pub const ENTITY_PATIENT_PHOTO: &str = "patient_photo";
+pub const ENTITY_PATIENT_SUMMARY: &str = "patient_summary";
+pub const ENTITY_PATIENT_TIMELINE: &str = "patient_timeline";
+pub const ENTITY_PROCEDURE_COMMENT: &str = "procedure_comment";
+pub const ENTITY_PROCEDURE: &str = "procedure";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Read,
+    Create,
+    Update,
+    Delete,
+    Export,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Read => "READ",
+            AuditAction::Create => "CREATE",
+            AuditAction::Update => "UPDATE",
+            AuditAction::Delete => "DELETE",
+            AuditAction::Export => "EXPORT",
+        }
+    }
+}
+
+// One audit_log row before it is written
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub user_id: i32,
+    pub action: AuditAction,
+    pub entity: &'static str,
+    pub entity_id: Option<i32>,
+    pub patient_id: Option<i32>,
+    // JSON of the record before and after the change, hashed with the audit key when written
+    pub before: Option<Vec<u8>>,
+    pub after: Option<Vec<u8>>,
+}
+
+impl AuditEvent {
+    pub fn new(user_id: i32, action: AuditAction, entity: &'static str) -> Self {
+        AuditEvent { user_id, action, entity, entity_id: None, patient_id: None, before: None, after: None }
+    }
+
+    pub fn patient(mut self, patient_id: i32) -> Self {
+        self.patient_id = Some(patient_id);
+        self
+    }
+
+    pub fn entity_id(mut self, entity_id: i32) -> Self {
+        self.entity_id = Some(entity_id);
+        self
+    }
+
+    pub fn before<T: Serialize>(mut self, value: Option<&T>) -> Self {
+        self.before = value.map(to_json);
+        self
+    }
+
+    pub fn after<T: Serialize>(mut self, value: &T) -> Self {
+        self.after = Some(to_json(value));
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntry {
+    audit_id: i64,
+    user_id: i32,
+    user_name: Option<String>,
+    action: String,
+    entity: String,
+    entity_id: Option<i32>,
+    patient_id: Option<i32>,
+    before_hash: Option<String>,
+    after_hash: Option<String>,
+    occurred_at: DateTime<Utc>
+}
+
+fn to_json<T: Serialize>(value: &T) -> Vec<u8> {
+    serde_json::to_vec(value).unwrap_or_default()
+}
+
+// HMAC-SHA256 of the JSON form of a record, so changes can be proven without storing PHI twice.
+// It is keyed so a copy of audit_log cannot be used to confirm a guessed value.
+pub fn hash_value(audit_key: &str, json: &[u8]) -> String {
+    hmac_sha256_hex(audit_key.as_bytes(), json)
+}
+
+// Function to append an audit event. Pass the transaction of a write so both commit together.
+pub async fn record_audit<'e, E: PgExecutor<'e>>(executor: E, keyring: &KeyRing, event: AuditEvent) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (user_id, action, entity, entity_id, patient_id, before_hash, after_hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        &event.user_id,
+        event.action.as_str(),
+        event.entity,
+        event.entity_id,
+        event.patient_id,
+        event.before.map(|json| hash_value(keyring.audit_key(), &json)),
+        event.after.map(|json| hash_value(keyring.audit_key(), &json))
+    )
+    .execute(executor)
+    .await
+    .map(|_| ())
+    .map_err(|e| AppError::database("Error while writing audit log", e))
+}
+
+// Function to record one READ per patient for list views
+pub async fn record_patient_reads<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i32,
+    entity: &'static str,
+    patient_ids: &[i32]
+) -> Result<(), AppError> {
+    if patient_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut unique_ids = patient_ids.to_vec();
+    unique_ids.sort_unstable();
+    unique_ids.dedup();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (user_id, action, entity, patient_id)
+        SELECT $1, 'READ', $2, patient_id
+        FROM UNNEST($3::INT[]) AS t(patient_id)
+        "#,
+        &user_id,
+        entity,
+        &unique_ids
+    )
+    .execute(executor)
+    .await
+    .map(|_| ())
+    .map_err(|e| AppError::database("Error while writing audit log", e))
+}
+
+impl AuditRepo for PgRepository<'_> {
+    async fn record_audit(&self, event: AuditEvent) -> Result<(), AppError> {
+        record_audit(self.pool, self.keyring, event).await
+    }
+
+    async fn record_patient_reads(&self, user_id: i32, entity: &'static str, patient_ids: &[i32]) -> Result<(), AppError> {
+        record_patient_reads(self.pool, user_id, entity, patient_ids).await
+    }
+}
+
+// Endpoint for admins to list access history for a patient and/or a user, newest first
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_audit_log(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: Option<i32>,
+    user_id: Option<i32>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: Option<i64>
+) -> Result<Vec<AuditEntry>, AppError> {
+    let pool = &state.pool;
+    let _user = authorize(pool, &config, &token, Permission::ViewAuditLog).await?;
+
+    if patient_id.is_none() && user_id.is_none() {
+        return Err(AppError::validation("patient_id", "Provide a patient_id or a user_id."));
+    }
+
+    sqlx::query_as!(
+        AuditEntry,
+        r#"
+        SELECT
+            a.audit_id,
+            a.user_id,
+            u.first_name || ' ' || u.last_name as user_name,
+            a.action,
+            a.entity,
+            a.entity_id,
+            a.patient_id,
+            a.before_hash,
+            a.after_hash,
+            a.occurred_at
+        FROM
+            audit_log a
+        LEFT JOIN
+            users u
+        ON
+            u.user_id = a.user_id
+        WHERE ($1::INT IS NULL OR a.patient_id = $1)
+        AND ($2::INT IS NULL OR a.user_id = $2)
+        AND ($3::TIMESTAMPTZ IS NULL OR a.occurred_at >= $3)
+        AND ($4::TIMESTAMPTZ IS NULL OR a.occurred_at < $4)
+        ORDER BY a.occurred_at DESC, a.audit_id DESC
+        LIMIT $5
+        "#,
+        patient_id,
+        user_id,
+        from,
+        to,
+        limit.unwrap_or(200).clamp(1, 5000)
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::database("Error while fetching audit log", e))
+}
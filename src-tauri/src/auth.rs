@@ -3,12 +3,35 @@
 use chrono::{DateTime, Duration, Utc};
 // Dependencies
 use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
 use tauri::State;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use crate::config::AppConfig;
+use crate::crypto::{random_token, sha256_hex};
 use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::permissions::{authorize, Permission, Role};
+use crate::repository::{PgRepository, UserRepo};
+use crate::security::{
+    clear_failed_logins, record_login_attempt, register_failed_login, LOGIN_FAILED,
+    REASON_BAD_PASSWORD, REASON_DEACTIVATED, REASON_LOCKED, REASON_PENDING, REASON_SUCCESS, REASON_UNKNOWN_EMAIL, PasswordPolicy
+};
+use crate::users::NewUser;
+use std::sync::OnceLock;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+// Access tokens are short lived; the refresh token keeps the session going
+const ACCESS_TOKEN_MINUTES: i64 = 15;
+const REFRESH_TOKEN_DAYS: i64 = 7;
+// Time allowed between the password step and the second factor step
+const MFA_TOKEN_MINUTES: i64 = 5;
+
+// Purposes of the short lived token handed out after a correct password
+pub const MFA_PURPOSE_VERIFY: &str = "MFA_VERIFY";
+pub const MFA_PURPOSE_ENROLL: &str = "MFA_ENROLL";
+
+// Full users row including the password hash. Never sent to the frontend.
+#[derive(Debug, Clone)]
 pub struct User {
     pub user_id: i32,
     pub role: String,
@@ -16,130 +39,558 @@ pub struct User {
     pub last_name: String,
     pub email: String,
     pub password: String,
+    pub status: String,
+    pub must_reset_password: bool,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>
+}
+
+// User as returned to the frontend
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserProfile {
+    pub user_id: i32,
+    pub role: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub status: String,
+    pub must_reset_password: bool,
+    pub locked_until: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>
 }
 
+impl From<User> for UserProfile {
+    fn from(user: User) -> Self {
+        UserProfile {
+            user_id: user.user_id,
+            role: user.role,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            email: user.email,
+            status: user.status,
+            must_reset_password: user.must_reset_password,
+            locked_until: user.locked_until,
+            created_at: user.created_at,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
-    user: User,
+    sub: i32, // User id
+    role: String,
+    sid: i32, // Session id
+    iat: usize, // Issued at
     exp: usize // Expiry
 }
 
+// Claims of the token that only allows completing the second factor step
+#[derive(Debug, Serialize, Deserialize)]
+struct MfaClaims {
+    sub: i32, // User id
+    purpose: String,
+    iat: usize,
+    exp: usize
+}
+
+// Caller identity after the token and its session have been verified
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: i32,
+    pub role: String,
+    pub session_id: i32
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SignupQuery {
-    first_name: String,
-    last_name: String,
-    role: String,
-    email: String,
-    password: String
+    pub first_name: String,
+    pub last_name: String,
+    pub role: String,
+    pub email: String,
+    pub password: String
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct LoginResponse {
-    user: User,
-    token: String
+    pub user: UserProfile,
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_at: DateTime<Utc>
 }
 
-fn create_jwt(user: &User, secret: &[u8]) -> Result<String, jsonwebtoken::errors::Error> {
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
-        .expect("Valid timestamp")
-        .timestamp() as usize;
+// Result of the password step. A session is only created once every required factor is verified.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LoginOutcome {
+    Authenticated(LoginResponse),
+    // Submit a TOTP or recovery code with mfa::verify_login_totp
+    MfaRequired { mfa_token: String },
+    // The role requires TOTP; enroll with the mfa module commands using this token
+    MfaEnrollmentRequired { mfa_token: String }
+}
+
+// Hash checked when the email is unknown so both failure paths take about as long
+fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash(random_token(16), DEFAULT_COST).unwrap_or_default())
+}
+
+fn create_jwt(user_id: i32, role: &str, session_id: i32, secret: &[u8]) -> Result<(String, DateTime<Utc>), jsonwebtoken::errors::Error> {
+    let issued_at = Utc::now();
+    let expires_at = issued_at + Duration::minutes(ACCESS_TOKEN_MINUTES);
 
     let claims = Claims {
-        user: (*user).clone(),
-        exp: expiration
+        sub: user_id,
+        role: role.to_string(),
+        sid: session_id,
+        iat: issued_at.timestamp() as usize,
+        exp: expires_at.timestamp() as usize
+    };
+
+    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(secret))?;
+    Ok((token, expires_at))
+}
+
+pub(crate) fn create_mfa_token(config: &AppConfig, user_id: i32, purpose: &str) -> Result<String, AppError> {
+    let issued_at = Utc::now();
+
+    let claims = MfaClaims {
+        sub: user_id,
+        purpose: purpose.to_string(),
+        iat: issued_at.timestamp() as usize,
+        exp: (issued_at + Duration::minutes(MFA_TOKEN_MINUTES)).timestamp() as usize
     };
 
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret))
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(config.jwt_secret.as_bytes()))
+        .map_err(|e| AppError::crypto("Error while creating mfa token", e))
 }
 
-fn decode_jwt(token: &str, secret: &[u8]) -> Result<Claims, String> {
+// Function to verify a second factor token and return its user id
+pub(crate) fn decode_mfa_token(config: &AppConfig, token: &str, purpose: &str) -> Result<i32, AppError> {
+    let claims = decode::<MfaClaims>(token, &DecodingKey::from_secret(config.jwt_secret.as_bytes()), &Validation::default())
+        .map(|token_data| token_data.claims)
+        .map_err(|_| AppError::unauthorized())?;
+
+    if claims.purpose != purpose {
+        return Err(AppError::unauthorized());
+    }
+
+    Ok(claims.sub)
+}
+
+fn decode_jwt(token: &str, secret: &[u8]) -> Result<Claims, AppError> {
     decode::<Claims>(token, &DecodingKey::from_secret(secret), &Validation::default())
         .map(|token_data| token_data.claims)
-        .map_err(|e| format!("Error while decoding token: {}", e))
+        .map_err(|_| AppError::unauthorized())
 }
 
-pub fn get_user_from_token(token: String) -> Result<User, String> {
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string()
-    };
+// Function to verify an access token and check that its session is still active.
+// The role comes from the users table so role changes apply immediately.
+// Users who must reset their password are refused until they do; see verify_session.
+pub async fn get_user_from_token(pool: &Pool<Postgres>, config: &AppConfig, token: &str) -> Result<AuthUser, AppError> {
+    let (user, must_reset_password) = verify_session(pool, config, token).await?;
+    if must_reset_password {
+        return Err(AppError::password_change_required());
+    }
 
-    let claims = decode_jwt(&token, encryption_key.as_bytes())?;
-    Ok(claims.user)
+    Ok(user)
 }
 
-#[tauri::command]
-pub async fn login(state: State<'_, DatabaseState>, email: String, password: String) -> Result<LoginResponse, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string()
-    };
+// Function to verify an access token and its session, and report whether the password must be
+// changed first. Only change_password and logout accept such a session.
+async fn verify_session(pool: &Pool<Postgres>, config: &AppConfig, token: &str) -> Result<(AuthUser, bool), AppError> {
+    let claims = decode_jwt(token, config.jwt_secret.as_bytes())?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden."));
+    let record = sqlx::query!(
+        r#"
+        SELECT
+            u.user_id,
+            u.role,
+            u.must_reset_password
+        FROM
+            sessions s
+        JOIN
+            users u
+        ON
+            u.user_id = s.user_id
+        WHERE
+            s.session_id = $1
+        AND
+            s.user_id = $2
+        AND
+            s.revoked_at IS NULL
+        AND
+            s.expires_at > NOW()
+        AND
+            u.status = 'ACTIVE'
+        "#,
+        &claims.sid,
+        &claims.sub
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::database("Error while verifying session", e))?;
+
+    match record {
+        Some(record) => Ok((
+            AuthUser {
+                user_id: record.user_id,
+                role: record.role,
+                session_id: claims.sid
+            },
+            record.must_reset_password
+        )),
+        None => Err(AppError::Unauthorized("Session expired or revoked.".to_string()))
     }
+}
+
+// Function to revoke every active session of a user
+pub async fn revoke_all_sessions(pool: &Pool<Postgres>, user_id: i32, reason: &str) -> Result<u64, AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW(), revoked_reason = $2
+        WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+        &user_id,
+        reason
+    )
+    .execute(pool)
+    .await
+    .map(|result| result.rows_affected())
+    .map_err(|e| AppError::database("Error while revoking sessions", e))
+}
 
-    match sqlx::query_as!(
+// Function for the password step of a login
+pub async fn password_login(pool: &Pool<Postgres>, config: &AppConfig, email: &str, password: &str) -> Result<LoginOutcome, AppError> {
+    let user = sqlx::query_as!(
         User,
         r#"
-            SELECT 
+            SELECT
                 user_id,
                 role,
                 first_name,
                 last_name,
                 email,
                 password,
+                status,
+                must_reset_password,
+                locked_until,
                 created_at
             FROM users
-            WHERE email = $1
+            WHERE LOWER(email) = LOWER($1)
+        "#,
+        email.trim()
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::database("Error while finding user", e))?;
+
+    let user = match user {
+        Some(user) => user,
+        None => {
+            let _ = verify(password, dummy_password_hash());
+            record_login_attempt(pool, None, email, false, REASON_UNKNOWN_EMAIL).await;
+            return Err(AppError::Unauthorized(LOGIN_FAILED.to_string()));
+        }
+    };
+
+    if user.locked_until.is_some_and(|locked_until| locked_until > Utc::now()) {
+        record_login_attempt(pool, Some(user.user_id), email, false, REASON_LOCKED).await;
+        return Err(AppError::Unauthorized(LOGIN_FAILED.to_string()));
+    }
+
+    if !verify(password, &user.password).map_err(|e| AppError::crypto("Error while verifying password", e))? {
+        register_failed_login(pool, user.user_id, &config.lockout_policy).await?;
+        record_login_attempt(pool, Some(user.user_id), email, false, REASON_BAD_PASSWORD).await;
+        return Err(AppError::Unauthorized(LOGIN_FAILED.to_string()));
+    }
+
+    // Status is only checked after the password so it is never revealed to a guesser
+    let status_reason = match user.status.as_str() {
+        "ACTIVE" => None,
+        "PENDING" => Some(REASON_PENDING),
+        _ => Some(REASON_DEACTIVATED)
+    };
+    if let Some(reason) = status_reason {
+        record_login_attempt(pool, Some(user.user_id), email, false, reason).await;
+        return Err(AppError::Unauthorized(LOGIN_FAILED.to_string()));
+    }
+
+    // A correct password only yields a session once the second factor is satisfied
+    let totp_enabled = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(
+            (SELECT enabled FROM user_totp WHERE user_id = $1),
+            FALSE
+        ) as "totp_enabled!"
         "#,
-        &email
+        &user.user_id
     )
-    .fetch_all(&*pool)
-    .await {
-        Ok(records) => {
-            if records.len() == 1 {
-                if verify(password, &records[0].password).map_err(|e| e.to_string())? {
-                    let token = match create_jwt(&records[0], encryption_key.as_bytes()) {
-                        Ok(token) => token,
-                        Err(err) => return Err(format!("Error while creating jwt token: {}", err))
-                    };
-                    Ok(LoginResponse { user: records[0].clone(), token })
-                } else {
-                    Err(format!("Invalid password."))
-                }
-            } else {
-                Err(format!("User not found."))
-            }
-        },
-        Err(err) => Err(format!("Error while finding user: {}", err))
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::database("Error while checking second factor", e))?;
+
+    if totp_enabled {
+        let mfa_token = create_mfa_token(config, user.user_id, MFA_PURPOSE_VERIFY)?;
+        return Ok(LoginOutcome::MfaRequired { mfa_token });
     }
+    if config.totp_required_for(&user.role) {
+        let mfa_token = create_mfa_token(config, user.user_id, MFA_PURPOSE_ENROLL)?;
+        return Ok(LoginOutcome::MfaEnrollmentRequired { mfa_token });
+    }
+
+    clear_failed_logins(pool, user.user_id).await?;
+    record_login_attempt(pool, Some(user.user_id), email, true, REASON_SUCCESS).await;
+
+    Ok(LoginOutcome::Authenticated(create_session(pool, config, user).await?))
 }
 
+// Endpoint to log in with email and password
 #[tauri::command]
-pub async fn signup(state: State<'_, DatabaseState>, signup_query: SignupQuery) -> Result<String, String> {
-    let hashed_password = hash(signup_query.password, DEFAULT_COST).map_err(|e| e.to_string())?;
-    let pool = state.pool.lock().await;
+pub async fn login(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, email: String, password: String) -> Result<LoginOutcome, AppError> {
+    password_login(&state.pool, &config, &email, &password).await
+}
 
-    match sqlx::query_as!(
+// Function to load an active user by id
+pub(crate) async fn find_active_user(pool: &Pool<Postgres>, user_id: i32) -> Result<Option<User>, AppError> {
+    sqlx::query_as!(
         User,
         r#"
-            INSERT INTO users ( role, first_name, last_name, email, password )
-            VALUES ( $1, $2, $3, $4, $5 )
-            RETURNING *
+        SELECT user_id, role, first_name, last_name, email, password, status, must_reset_password, locked_until, created_at
+        FROM users
+        WHERE user_id = $1 AND status = 'ACTIVE'
+        "#,
+        &user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::database("Error while finding user", e))
+}
+
+// Function to open a new session for a fully authenticated user
+pub(crate) async fn create_session(pool: &Pool<Postgres>, config: &AppConfig, user: User) -> Result<LoginResponse, AppError> {
+    let refresh_token = random_token(32);
+    let session_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sessions (user_id, refresh_token_hash, expires_at)
+        VALUES ($1, $2, $3)
+        RETURNING session_id
+        "#,
+        &user.user_id,
+        sha256_hex(refresh_token.as_bytes()),
+        Utc::now() + Duration::days(REFRESH_TOKEN_DAYS)
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::database("Error while creating session", e))?;
+
+    let (token, expires_at) = create_jwt(user.user_id, &user.role, session_id, config.jwt_secret.as_bytes())
+        .map_err(|e| AppError::crypto("Error while creating jwt token", e))?;
+
+    Ok(LoginResponse { user: user.into(), token, refresh_token, expires_at })
+}
+
+// Endpoint to exchange a refresh token for a new access token.
+// The refresh token is rotated on every use.
+#[tauri::command]
+pub async fn refresh_session(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, refresh_token: String) -> Result<LoginResponse, AppError> {
+    let pool = &state.pool;
+    let next_refresh_token = random_token(32);
+
+    let session = sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET
+            refresh_token_hash = $2,
+            last_refreshed_at = NOW(),
+            expires_at = $3
+        WHERE
+            refresh_token_hash = $1
+        AND
+            revoked_at IS NULL
+        AND
+            expires_at > NOW()
+        RETURNING session_id, user_id
         "#,
-        &signup_query.role,
-        &signup_query.first_name,
-        &signup_query.last_name,
-        &signup_query.email,
+        sha256_hex(refresh_token.as_bytes()),
+        sha256_hex(next_refresh_token.as_bytes()),
+        Utc::now() + Duration::days(REFRESH_TOKEN_DAYS)
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::database("Error while refreshing session", e))?
+    .ok_or_else(|| AppError::Unauthorized("Session expired or revoked.".to_string()))?;
+
+    let user = find_active_user(pool, session.user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Session expired or revoked.".to_string()))?;
+
+    let (token, expires_at) = create_jwt(user.user_id, &user.role, session.session_id, config.jwt_secret.as_bytes())
+        .map_err(|e| AppError::crypto("Error while creating jwt token", e))?;
+
+    Ok(LoginResponse { user: user.into(), token, refresh_token: next_refresh_token, expires_at })
+}
+
+// Endpoint to end the caller's current session
+#[tauri::command]
+pub async fn logout(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String) -> Result<String, AppError> {
+    let pool = &state.pool;
+    let (user, _) = verify_session(pool, &config, &token).await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW(), revoked_reason = 'LOGOUT'
+        WHERE session_id = $1
+        "#,
+        &user.session_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::database("Error while logging out", e))?;
+
+    Ok("Successfully logged out".to_string())
+}
+
+// Endpoint for admins to sign a user out of every device
+#[tauri::command]
+pub async fn revoke_user_sessions(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, user_id: i32) -> Result<u64, AppError> {
+    let pool = &state.pool;
+    let _user = authorize(pool, &config, &token, Permission::ManageUsers).await?;
+
+    revoke_all_sessions(pool, user_id, "ADMIN_REVOKED").await
+}
+
+// Endpoint for the logged in user to set a new password.
+// Clears a forced reset and signs out every other session.
+#[tauri::command]
+pub async fn change_password(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    current_password: String,
+    new_password: String
+) -> Result<String, AppError> {
+    let pool = &state.pool;
+    let (user, _) = verify_session(pool, &config, &token).await.map_err(|_| AppError::unauthorized())?;
+
+    let stored_hash = sqlx::query_scalar!(
+        "SELECT password FROM users WHERE user_id = $1",
+        &user.user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::database("Error while finding user", e))?;
+
+    if !verify(current_password, &stored_hash).map_err(|e| AppError::crypto("Error while verifying password", e))? {
+        return Err(AppError::validation("current_password", "Invalid password."));
+    }
+
+    config.password_policy.validate(&new_password).map_err(|e| AppError::validation("new_password", &e))?;
+
+    let hashed_password = hash(new_password, DEFAULT_COST).map_err(|e| AppError::crypto("Error while hashing password", e))?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password = $2, must_reset_password = FALSE, updated_at = NOW()
+        WHERE user_id = $1
+        "#,
+        &user.user_id,
         &hashed_password
     )
-    .fetch_one(&*pool)
-    .await {
-        Ok(_record) => Ok(format!("Successfully created user")),
-        Err(err) => Err(format!("Error while creating user: {}", err))
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::database("Error while changing password", e))?;
+
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW(), revoked_reason = 'PASSWORD_CHANGED'
+        WHERE user_id = $1 AND session_id <> $2 AND revoked_at IS NULL
+        "#,
+        &user.user_id,
+        &user.session_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::database("Error while revoking sessions", e))?;
+
+    Ok("Successfully changed password".to_string())
+}
+
+// Function for self-service signup. Accounts start out PENDING until an admin approves them,
+// and admin accounts can only be created by another admin.
+pub async fn register_user<R: UserRepo>(repo: &R, policy: &PasswordPolicy, signup_query: &SignupQuery) -> Result<String, AppError> {
+    let role = Role::parse(&signup_query.role).map_err(|e| AppError::validation("role", &e))?;
+    match role {
+        Role::Admin => return Err(AppError::Forbidden("Admin accounts must be invited by an administrator.".to_string())),
+        Role::Doctor | Role::Nurse => {}
     }
-}
\ No newline at end of file
+    policy.validate(&signup_query.password).map_err(|e| AppError::validation("password", &e))?;
+
+    let password_hash = hash(&signup_query.password, DEFAULT_COST).map_err(|e| AppError::crypto("Error while hashing password", e))?;
+
+    repo.create_user(&NewUser {
+        role: role.as_str().to_string(),
+        first_name: signup_query.first_name.clone(),
+        last_name: signup_query.last_name.clone(),
+        email: signup_query.email.trim().to_string(),
+        password_hash,
+        status: "PENDING".to_string(),
+        must_reset_password: false
+    })
+    .await?;
+
+    Ok("Successfully created user. An administrator must approve the account before you can log in.".to_string())
+}
+
+// Endpoint for self-service signup
+#[tauri::command]
+pub async fn signup(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, signup_query: SignupQuery) -> Result<String, AppError> {
+    let pool = &state.pool;
+
+    register_user(&PgRepository::new(pool, &config.keyring), &config.password_policy, &signup_query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::MemoryRepository;
+
+    fn signup_query(role: &str, password: &str) -> SignupQuery {
+        SignupQuery {
+            first_name: "Grace".to_string(),
+            last_name: "Hopper".to_string(),
+            role: role.to_string(),
+            email: "grace@example.com".to_string(),
+            password: password.to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn signups_wait_for_approval() {
+        let repo = MemoryRepository::default();
+        let policy = PasswordPolicy::default();
+
+        register_user(&repo, &policy, &signup_query("NURSE", "Correct-Horse-42")).await.unwrap();
+        let users = repo.list_users(None, Some("PENDING")).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert!(!users[0].must_reset_password);
+
+        let err = register_user(&repo, &policy, &signup_query("DOCTOR", "Correct-Horse-42")).await.unwrap_err();
+        assert_eq!(err.code(), "CONFLICT");
+    }
+
+    #[tokio::test]
+    async fn signup_rejects_admins_and_weak_passwords() {
+        let repo = MemoryRepository::default();
+        let policy = PasswordPolicy::default();
+
+        let err = register_user(&repo, &policy, &signup_query("ADMIN", "Correct-Horse-42")).await.unwrap_err();
+        assert_eq!(err.code(), "FORBIDDEN");
+        let err = register_user(&repo, &policy, &signup_query("NURSE", "short")).await.unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        assert!(repo.list_users(None, None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+// src-tauri/src/bin/pool_bench.rs
+//
+// Measures command throughput under concurrent load, comparing the shared pool commands use now
+// with the previous design where every command held a global lock around the pool.
+// Usage: cargo run --release --bin pool_bench -- --tasks 16 --requests 200 --latency-ms 5
+
+// Dependencies
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use ehrportal_lib::{config::AppConfig, db, keys::KeyRing};
+use sqlx::{Pool, Postgres};
+use tokio::sync::Mutex;
+
+const USAGE: &str = r#"Usage: pool_bench [options]
+
+Options:
+    --tasks <n>                      Concurrent callers (default 16)
+    --requests <n>                   Requests per caller (default 200)
+    --latency-ms <n>                 Simulated network round trip per request (default 0)
+    --env-file <path>                Env file to load (default .env.development)
+"#;
+
+struct BenchConfig {
+    tasks: usize,
+    requests: usize,
+    latency_ms: u64,
+}
+
+fn parse_args(args: &[String]) -> Result<(BenchConfig, String), String> {
+    let mut config = BenchConfig { tasks: 16, requests: 200, latency_ms: 0 };
+    let mut env_file = ".env.development".to_string();
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for {}\n\n{}", flag, USAGE))?;
+
+        if flag == "--env-file" {
+            env_file = value.clone();
+            continue;
+        }
+
+        let count: usize = value
+            .parse()
+            .map_err(|_| format!("Invalid number for {}: {}", flag, value))?;
+
+        match flag.as_str() {
+            "--tasks" => config.tasks = count.max(1),
+            "--requests" => config.requests = count.max(1),
+            "--latency-ms" => config.latency_ms = count as u64,
+            _ => return Err(format!("Unknown option {}\n\n{}", flag, USAGE)),
+        }
+    }
+
+    Ok((config, env_file))
+}
+
+// A typical patient read: one patient's activities with their encrypted notes decrypted.
+// The optional delay stands in for the round trip to a remote database.
+async fn patient_activity_read(pool: &Pool<Postgres>, keyring: &KeyRing, patient_id: i32, latency: Duration) -> Result<i64, String> {
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(pgp_sym_decrypt(a.doctors_note::bytea, ($1::TEXT[])[a.key_id])) as "count!"
+        FROM patient_activity a
+        WHERE a.patient_id = $2
+        "#,
+        &keyring.sql_keys(),
+        patient_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Error while reading patient activity: {}", e))
+}
+
+async fn patient_ids(pool: &Pool<Postgres>) -> Result<Vec<i32>, String> {
+    sqlx::query_scalar!("SELECT patient_id FROM patients ORDER BY patient_id LIMIT 100")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error while fetching patients: {}", e))
+}
+
+// Runs every caller concurrently and returns requests per second.
+// With `locked` set each request holds one global mutex around the pool, as commands used to.
+async fn run(pool: Pool<Postgres>, keyring: Arc<KeyRing>, ids: Arc<Vec<i32>>, config: &BenchConfig, locked: bool) -> Result<f64, String> {
+    let shared = Arc::new(Mutex::new(pool.clone()));
+    let started = Instant::now();
+
+    let mut handles = vec![];
+    for task in 0..config.tasks {
+        let pool = pool.clone();
+        let shared = shared.clone();
+        let keyring = keyring.clone();
+        let ids = ids.clone();
+        let requests = config.requests;
+        let latency = Duration::from_millis(config.latency_ms);
+
+        handles.push(tokio::spawn(async move {
+            for request in 0..requests {
+                let patient_id = ids[(task * requests + request) % ids.len()];
+                if locked {
+                    let pool = shared.lock().await;
+                    patient_activity_read(&pool, &keyring, patient_id, latency).await?;
+                } else {
+                    patient_activity_read(&pool, &keyring, patient_id, latency).await?;
+                }
+            }
+            Ok::<(), String>(())
+        }));
+    }
+
+    for handle in handles {
+        handle.await.map_err(|e| format!("Benchmark task failed: {}", e))??;
+    }
+
+    let total = (config.tasks * config.requests) as f64;
+    Ok(total / started.elapsed().as_secs_f64())
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (bench_config, env_file) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = dotenv::from_filename(&env_file) {
+        eprintln!("Error while loading env from {}: {}", env_file, err);
+    }
+
+    let config = match AppConfig::load(None) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let pool = match db::connect_to_database(&config.database_url, &config.pool).await {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("Failed to connect to database: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let ids = match patient_ids(&pool).await {
+        Ok(ids) if !ids.is_empty() => Arc::new(ids),
+        Ok(_) => {
+            eprintln!("No patients found. Run the seed bin first.");
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let keyring = Arc::new(config.keyring.clone());
+
+    println!(
+        "{} callers x {} requests, {} ms latency, pool of {} connections",
+        bench_config.tasks, bench_config.requests, bench_config.latency_ms, config.pool.max_connections
+    );
+    for (label, locked) in [("global lock", true), ("shared pool", false)] {
+        match run(pool.clone(), keyring.clone(), ids.clone(), &bench_config, locked).await {
+            Ok(rate) => println!("{:<12} {:>10.1} requests/s", label, rate),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
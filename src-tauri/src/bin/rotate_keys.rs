@@ -0,0 +1,93 @@
+// src-tauri/src/bin/rotate_keys.rs
+//
+// Re-encrypts every encrypted column with the current ENCRYPTION_KEY while the app keeps running.
+// Set ENCRYPTION_KEY / ENCRYPTION_KEY_ID to the new key and list every earlier key in ENCRYPTION_RETIRED_KEYS,
+// restart the app with that config, then run: cargo run --bin rotate_keys -- --batch-size 500
+// An interrupted run can simply be started again; it resumes after the last committed batch.
+
+// Dependencies
+use ehrportal_lib::{config::AppConfig, db, key_rotation, migrations};
+
+const USAGE: &str = r#"Usage: rotate_keys [options]
+
+Options:
+    --batch-size <n>                 Rows re-encrypted per transaction (default 500)
+    --env-file <path>                Env file to load (default .env.development)
+"#;
+
+fn parse_args(args: &[String]) -> Result<(i64, String), String> {
+    let mut batch_size = key_rotation::DEFAULT_BATCH_SIZE;
+    let mut env_file = ".env.development".to_string();
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for {}\n\n{}", flag, USAGE))?;
+
+        match flag.as_str() {
+            "--env-file" => env_file = value.clone(),
+            "--batch-size" => {
+                batch_size = value
+                    .parse()
+                    .map_err(|_| format!("Invalid number for {}: {}", flag, value))?
+            }
+            _ => return Err(format!("Unknown option {}\n\n{}", flag, USAGE)),
+        }
+    }
+
+    Ok((batch_size, env_file))
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (batch_size, env_file) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = dotenv::from_filename(&env_file) {
+        eprintln!("Error while loading env from {}: {}", env_file, err);
+    }
+
+    let config = match AppConfig::load(None) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let pool = match db::connect_to_database(&config.database_url, &config.pool).await {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("Failed to connect to database: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = migrations::run_migrations(&pool).await {
+        eprintln!("Error while running migrations: {}", err);
+        std::process::exit(1);
+    }
+
+    match key_rotation::rotate_encryption_keys(&pool, &config.keyring, batch_size).await {
+        Ok(summary) => {
+            for table in summary {
+                println!("{}: {} rows moved to key {}", table.table_name, table.rows_rotated, config.keyring.current().id);
+            }
+        }
+        Err(err) => {
+            eprintln!("Key rotation stopped: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// src-tauri/src/bin/seed.rs
+//
+// Fills an empty database with generated demo / load-test data.
+// Usage: cargo run --bin seed -- --patients 500 --activities-per-patient 4
+
+// Dependencies
+use ehrportal_lib::{config::AppConfig, db, migrations, seed};
+
+const USAGE: &str = r#"Usage: seed [options]
+
+Options:
+    --doctors <n>                    Number of doctors (default 3)
+    --nurses <n>                     Number of nurses (default 3)
+    --admins <n>                     Number of admins (default 1)
+    --patients <n>                   Number of patients (default 25)
+    --activities-per-patient <n>     Activities per patient (default 3)
+    --conversations <n>              Staff conversations (default 4)
+    --messages-per-conversation <n>  Messages per conversation (default 6)
+    --appointments <n>               Staff appointments (default 10)
+    --rng-seed <n>                   Seed for reproducible output (default 42)
+    --env-file <path>                Env file to load (default .env.development)
+"#;
+
+fn parse_args(args: &[String]) -> Result<(seed::SeedConfig, String), String> {
+    let mut config = seed::SeedConfig::default();
+    let mut env_file = ".env.development".to_string();
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for {}\n\n{}", flag, USAGE))?;
+
+        if flag == "--env-file" {
+            env_file = value.clone();
+            continue;
+        }
+
+        let number: u64 = value
+            .parse()
+            .map_err(|_| format!("Invalid number for {}: {}", flag, value))?;
+        let count = number as usize;
+
+        match flag.as_str() {
+            "--doctors" => config.doctors = count,
+            "--nurses" => config.nurses = count,
+            "--admins" => config.admins = count,
+            "--patients" => config.patients = count,
+            "--activities-per-patient" => config.activities_per_patient = count,
+            "--conversations" => config.conversations = count,
+            "--messages-per-conversation" => config.messages_per_conversation = count,
+            "--appointments" => config.appointments = count,
+            "--rng-seed" => config.rng_seed = number,
+            _ => return Err(format!("Unknown option {}\n\n{}", flag, USAGE)),
+        }
+    }
+
+    Ok((config, env_file))
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (config, env_file) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = dotenv::from_filename(&env_file) {
+        eprintln!("Error while loading env from {}: {}", env_file, err);
+    }
+
+    let app_config = match AppConfig::load(None) {
+        Ok(app_config) => app_config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let pool = match db::connect_to_database(&app_config.database_url, &app_config.pool).await {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("Failed to connect to database: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = migrations::run_migrations(&pool).await {
+        eprintln!("Error while running migrations: {}", err);
+        std::process::exit(1);
+    }
+
+    match seed::seed_database(&pool, app_config.keyring.current(), &config).await {
+        Ok((summary, password)) => {
+            println!("Seeded database: {:?}", summary);
+            println!("All generated users share the one-time password '{}' and must change it when they first sign in.", password);
+        }
+        Err(err) => {
+            eprintln!("Seeding failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+// src-tauri/src/config.rs
+
+// Dependencies
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use crate::db::PoolSettings;
+use crate::keys::{parse_key_list, EncryptionKey, KeyRing};
+use crate::patients::{MrNumberPattern, DEFAULT_MR_NUMBER_PATTERN};
+use crate::security::{LockoutPolicy, PasswordPolicy};
+
+// File read from the app config dir, a JSON object using the same names as the env variables
+pub const CONFIG_FILE_NAME: &str = "config.json";
+// Service name secrets are stored under in the OS keyring
+pub const KEYRING_SERVICE: &str = "ehrportal";
+
+// Every setting the app reads. Anything else in the config file is rejected as a likely typo.
+const SETTINGS: &[&str] = &[
+    "DATABASE_URL",
+    "DB_MAX_CONNECTIONS",
+    "DB_MIN_CONNECTIONS",
+    "DB_ACQUIRE_TIMEOUT_SECONDS",
+    "DB_IDLE_TIMEOUT_SECONDS",
+    "DB_MAX_LIFETIME_SECONDS",
+    "ENCRYPTION_KEY",
+    "ENCRYPTION_KEY_ID",
+    "ENCRYPTION_RETIRED_KEYS",
+    "JWT_SECRET",
+    "SEED_DEMO_DATA",
+    "PASSWORD_MIN_LENGTH",
+    "PASSWORD_REQUIRE_UPPERCASE",
+    "PASSWORD_REQUIRE_LOWERCASE",
+    "PASSWORD_REQUIRE_DIGIT",
+    "PASSWORD_REQUIRE_SYMBOL",
+    "LOGIN_MAX_FAILED_ATTEMPTS",
+    "LOGIN_LOCKOUT_MINUTES",
+    "TOTP_REQUIRED_ROLES",
+    "MR_NUMBER_PATTERN",
+    "SEARCH_INDEX_KEY",
+    "AUDIT_HASH_KEY",
+];
+
+// Settings that may be kept in the OS keyring instead of a file or the environment
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+const SECRET_SETTINGS: &[&str] = &["DATABASE_URL", "ENCRYPTION_KEY", "ENCRYPTION_RETIRED_KEYS", "JWT_SECRET", "SEARCH_INDEX_KEY", "AUDIT_HASH_KEY"];
+
+// Typed configuration, loaded once at startup and kept in Tauri managed state
+pub struct AppConfig {
+    pub database_url: String,
+    pub pool: PoolSettings,
+    pub keyring: KeyRing,
+    pub jwt_secret: String,
+    pub password_policy: PasswordPolicy,
+    pub lockout_policy: LockoutPolicy,
+    pub totp_required_roles: Vec<String>,
+    pub seed_demo_data: bool,
+    pub mr_number_pattern: MrNumberPattern,
+}
+
+impl AppConfig {
+    // Loads the layers in increasing priority: config file, OS keyring, environment.
+    // Every invalid or missing setting is reported in one error.
+    pub fn load(config_dir: Option<&Path>) -> Result<AppConfig, String> {
+        AppConfig::load_layers(config_dir, read_keyring(), read_env())
+    }
+
+    fn load_layers(
+        config_dir: Option<&Path>,
+        keyring: HashMap<String, String>,
+        env: HashMap<String, String>
+    ) -> Result<AppConfig, String> {
+        let mut settings = HashMap::new();
+
+        if let Some(dir) = config_dir {
+            settings.extend(read_config_file(&dir.join(CONFIG_FILE_NAME))?);
+        }
+        settings.extend(keyring);
+        settings.extend(env);
+
+        AppConfig::from_settings(&settings)
+    }
+
+    pub fn from_settings(settings: &HashMap<String, String>) -> Result<AppConfig, String> {
+        let mut errors = vec![];
+        let get = |key: &str| settings.get(key).map(|value| value.trim()).filter(|value| !value.is_empty());
+
+        let database_url = get("DATABASE_URL").unwrap_or_default().to_string();
+        if database_url.is_empty() {
+            errors.push("DATABASE_URL is required".to_string());
+        }
+
+        let pool_default = PoolSettings::default();
+        let pool = PoolSettings {
+            max_connections: parse_setting(&mut errors, get("DB_MAX_CONNECTIONS"), "DB_MAX_CONNECTIONS", pool_default.max_connections),
+            min_connections: parse_setting(&mut errors, get("DB_MIN_CONNECTIONS"), "DB_MIN_CONNECTIONS", pool_default.min_connections),
+            acquire_timeout_seconds: parse_setting(
+                &mut errors,
+                get("DB_ACQUIRE_TIMEOUT_SECONDS"),
+                "DB_ACQUIRE_TIMEOUT_SECONDS",
+                pool_default.acquire_timeout_seconds,
+            ),
+            idle_timeout_seconds: parse_setting(
+                &mut errors,
+                get("DB_IDLE_TIMEOUT_SECONDS"),
+                "DB_IDLE_TIMEOUT_SECONDS",
+                pool_default.idle_timeout_seconds,
+            ),
+            max_lifetime_seconds: parse_setting(
+                &mut errors,
+                get("DB_MAX_LIFETIME_SECONDS"),
+                "DB_MAX_LIFETIME_SECONDS",
+                pool_default.max_lifetime_seconds,
+            ),
+        };
+        if pool.max_connections < 1 || pool.min_connections > pool.max_connections {
+            errors.push("DB_MAX_CONNECTIONS must be at least 1 and no less than DB_MIN_CONNECTIONS".to_string());
+        }
+        if pool.acquire_timeout_seconds < 1 {
+            errors.push("DB_ACQUIRE_TIMEOUT_SECONDS must be at least 1".to_string());
+        }
+
+        let key_id = parse_setting(&mut errors, get("ENCRYPTION_KEY_ID"), "ENCRYPTION_KEY_ID", 1);
+        let retired = parse_key_list(get("ENCRYPTION_RETIRED_KEYS").unwrap_or_default()).unwrap_or_else(|err| {
+            errors.push(format!("ENCRYPTION_RETIRED_KEYS: {}", err));
+            vec![]
+        });
+        // Phone search tokens are never re-encrypted, so they need a key that rotations leave alone
+        let search_key = get("SEARCH_INDEX_KEY").unwrap_or_default().to_string();
+        if search_key.is_empty() {
+            errors.push("SEARCH_INDEX_KEY is required".to_string());
+        }
+        let audit_key = get("AUDIT_HASH_KEY").unwrap_or_default().to_string();
+        if audit_key.is_empty() {
+            errors.push("AUDIT_HASH_KEY is required".to_string());
+        }
+        let keyring = match get("ENCRYPTION_KEY") {
+            Some(secret) => KeyRing::new(EncryptionKey { id: key_id, secret: secret.to_string() }, retired, search_key, audit_key)
+                .map_err(|err| errors.push(format!("ENCRYPTION_KEY: {}", err)))
+                .ok(),
+            None => {
+                errors.push("ENCRYPTION_KEY is required".to_string());
+                None
+            }
+        };
+        // Signing tokens with the encryption key would log everyone out on every key rotation
+        let jwt_secret = get("JWT_SECRET").unwrap_or_default().to_string();
+        if jwt_secret.is_empty() {
+            errors.push("JWT_SECRET is required".to_string());
+        } else if get("ENCRYPTION_KEY") == Some(jwt_secret.as_str()) {
+            errors.push("JWT_SECRET must differ from ENCRYPTION_KEY".to_string());
+        }
+
+        let password_default = PasswordPolicy::default();
+        let password_policy = PasswordPolicy {
+            min_length: parse_setting(&mut errors, get("PASSWORD_MIN_LENGTH"), "PASSWORD_MIN_LENGTH", password_default.min_length),
+            require_uppercase: parse_setting(
+                &mut errors,
+                get("PASSWORD_REQUIRE_UPPERCASE"),
+                "PASSWORD_REQUIRE_UPPERCASE",
+                password_default.require_uppercase,
+            ),
+            require_lowercase: parse_setting(
+                &mut errors,
+                get("PASSWORD_REQUIRE_LOWERCASE"),
+                "PASSWORD_REQUIRE_LOWERCASE",
+                password_default.require_lowercase,
+            ),
+            require_digit: parse_setting(&mut errors, get("PASSWORD_REQUIRE_DIGIT"), "PASSWORD_REQUIRE_DIGIT", password_default.require_digit),
+            require_symbol: parse_setting(&mut errors, get("PASSWORD_REQUIRE_SYMBOL"), "PASSWORD_REQUIRE_SYMBOL", password_default.require_symbol),
+        };
+        if password_policy.min_length < 8 {
+            errors.push("PASSWORD_MIN_LENGTH must be at least 8".to_string());
+        }
+
+        let lockout_default = LockoutPolicy::default();
+        let lockout_policy = LockoutPolicy {
+            max_failed_attempts: parse_setting(
+                &mut errors,
+                get("LOGIN_MAX_FAILED_ATTEMPTS"),
+                "LOGIN_MAX_FAILED_ATTEMPTS",
+                lockout_default.max_failed_attempts,
+            ),
+            lockout_minutes: parse_setting(&mut errors, get("LOGIN_LOCKOUT_MINUTES"), "LOGIN_LOCKOUT_MINUTES", lockout_default.lockout_minutes),
+        };
+        if lockout_policy.max_failed_attempts < 1 || lockout_policy.lockout_minutes < 1 {
+            errors.push("LOGIN_MAX_FAILED_ATTEMPTS and LOGIN_LOCKOUT_MINUTES must be positive".to_string());
+        }
+
+        let totp_required_roles = get("TOTP_REQUIRED_ROLES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|role| role.trim().to_uppercase())
+            .filter(|role| !role.is_empty())
+            .collect::<Vec<_>>();
+        if let Some(role) = totp_required_roles.iter().find(|role| !["DOCTOR", "NURSE", "ADMIN"].contains(&role.as_str())) {
+            errors.push(format!("TOTP_REQUIRED_ROLES has an unknown role: {}", role));
+        }
+
+        let seed_demo_data = parse_setting(&mut errors, get("SEED_DEMO_DATA"), "SEED_DEMO_DATA", false);
+
+        let mr_number_pattern = MrNumberPattern::parse(get("MR_NUMBER_PATTERN").unwrap_or(DEFAULT_MR_NUMBER_PATTERN))
+            .unwrap_or_else(|err| {
+                errors.push(format!("MR_NUMBER_PATTERN {}", err));
+                MrNumberPattern::default()
+            });
+
+        match keyring {
+            Some(keyring) if errors.is_empty() => Ok(AppConfig {
+                database_url,
+                pool,
+                jwt_secret,
+                keyring,
+                password_policy,
+                lockout_policy,
+                totp_required_roles,
+                seed_demo_data,
+                mr_number_pattern,
+            }),
+            _ => Err(format!("Invalid configuration: {}.", errors.join("; "))),
+        }
+    }
+
+    pub fn totp_required_for(&self, role: &str) -> bool {
+        self.totp_required_roles.iter().any(|required| required.eq_ignore_ascii_case(role))
+    }
+}
+
+fn parse_setting<T: std::str::FromStr>(errors: &mut Vec<String>, value: Option<&str>, key: &str, default: T) -> T {
+    match value {
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            errors.push(format!("{} has an invalid value: {}", key, value));
+            default
+        }),
+        None => default,
+    }
+}
+
+// Function to read the optional config file. A missing file is fine; a malformed one is an error.
+fn read_config_file(path: &Path) -> Result<HashMap<String, String>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(err) => return Err(format!("Error while reading {}: {}", path.display(), err)),
+    };
+
+    let values: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&contents)
+        .map_err(|e| format!("Error while parsing {}: {}", path.display(), e))?;
+
+    values
+        .into_iter()
+        .map(|(key, value)| {
+            if !SETTINGS.contains(&key.as_str()) {
+                return Err(format!("Unknown setting {} in {}", key, path.display()));
+            }
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                serde_json::Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            Ok((key, value))
+        })
+        .collect()
+}
+
+fn read_env() -> HashMap<String, String> {
+    SETTINGS
+        .iter()
+        .filter_map(|key| env::var(key).ok().map(|value| (key.to_string(), value)))
+        .collect()
+}
+
+// Function to read secrets from the OS keyring. Platforms without one, or missing entries, are skipped.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn read_keyring() -> HashMap<String, String> {
+    SECRET_SETTINGS
+        .iter()
+        .filter_map(|key| {
+            let entry = keyring::Entry::new(KEYRING_SERVICE, key).ok()?;
+            match entry.get_password() {
+                Ok(value) => Some((key.to_string(), value)),
+                Err(keyring::Error::NoEntry) => None,
+                Err(err) => {
+                    eprintln!("Skipping OS keyring for {}: {}", key, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn read_keyring() -> HashMap<String, String> {
+    HashMap::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn required() -> HashMap<String, String> {
+        settings(&[
+            ("DATABASE_URL", "postgres://localhost/ehr"),
+            ("ENCRYPTION_KEY", "encryption-key"),
+            ("JWT_SECRET", "jwt-secret"),
+            ("SEARCH_INDEX_KEY", "search-key"),
+            ("AUDIT_HASH_KEY", "audit-key"),
+        ])
+    }
+
+    #[test]
+    fn environment_beats_keyring_beats_config_file() {
+        let dir = std::env::temp_dir().join(format!("ehrportal-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = serde_json::json!({
+            "DATABASE_URL": "postgres://file/ehr",
+            "ENCRYPTION_KEY": "encryption-key",
+            "JWT_SECRET": "from-file",
+            "SEARCH_INDEX_KEY": "search-key",
+            "AUDIT_HASH_KEY": "audit-key",
+            "DB_MAX_CONNECTIONS": 7,
+        });
+        std::fs::write(dir.join(CONFIG_FILE_NAME), file.to_string()).unwrap();
+
+        let from_keyring = AppConfig::load_layers(Some(&dir), settings(&[("JWT_SECRET", "from-keyring")]), HashMap::new());
+        let from_env = AppConfig::load_layers(
+            Some(&dir),
+            settings(&[("JWT_SECRET", "from-keyring")]),
+            settings(&[("JWT_SECRET", "from-env"), ("DATABASE_URL", "postgres://env/ehr")]),
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let from_keyring = from_keyring.unwrap();
+        assert_eq!((from_keyring.jwt_secret.as_str(), from_keyring.pool.max_connections), ("from-keyring", 7));
+        let from_env = from_env.unwrap();
+        assert_eq!((from_env.jwt_secret.as_str(), from_env.database_url.as_str()), ("from-env", "postgres://env/ehr"));
+    }
+
+    #[test]
+    fn jwt_secret_is_required_and_separate_from_the_encryption_key() {
+        let mut missing = required();
+        missing.remove("JWT_SECRET");
+        let error = AppConfig::from_settings(&missing).err().unwrap();
+        assert!(error.contains("JWT_SECRET is required"), "{}", error);
+
+        let mut shared = required();
+        shared.insert("JWT_SECRET".to_string(), "encryption-key".to_string());
+        let error = AppConfig::from_settings(&shared).err().unwrap();
+        assert!(error.contains("JWT_SECRET must differ from ENCRYPTION_KEY"), "{}", error);
+
+        assert!(AppConfig::from_settings(&required()).is_ok());
+    }
+}
@@ -3,22 +3,60 @@
 // Dependancies
 use sqlx::postgres::PgPool;
 use sqlx::postgres::PgPoolOptions;
-use std::{env, sync::Arc};
-use tokio::sync::Mutex;
+use std::time::Duration;
 
-// Struct for storing pool globally in the state
+// Struct for storing pool globally in the state.
+// PgPool is already shared and synchronized, so commands use it concurrently without a lock.
 pub struct DatabaseState {
-    pub pool: Arc<Mutex<PgPool>>,
+    pub pool: PgPool,
+}
+
+// Pool sizing and timeouts. An idle timeout or max lifetime of 0 disables it; the acquire timeout
+// is mandatory and must be at least 1 second.
+#[derive(Debug, Clone)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_seconds: u64,
+    pub idle_timeout_seconds: u64,
+    pub max_lifetime_seconds: u64,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        PoolSettings {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_seconds: 30,
+            idle_timeout_seconds: 600,
+            max_lifetime_seconds: 1800,
+        }
+    }
+}
+
+fn optional_duration(seconds: u64) -> Option<Duration> {
+    (seconds > 0).then(|| Duration::from_secs(seconds))
 }
 
 // Function to connect to postgresql db
-pub async fn connect_to_database() -> sqlx::Result<sqlx::Pool<sqlx::Postgres>> {
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is required");
+pub async fn connect_to_database(database_url: &str, settings: &PoolSettings) -> sqlx::Result<sqlx::Pool<sqlx::Postgres>> {
     let pool = PgPoolOptions::new()
-        .max_connections(10)
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(Duration::from_secs(settings.acquire_timeout_seconds))
+        .idle_timeout(optional_duration(settings.idle_timeout_seconds))
+        .max_lifetime(optional_duration(settings.max_lifetime_seconds))
         .test_before_acquire(true)
-        .connect(&database_url)
+        .connect(database_url)
         .await?;
 
     Ok(pool)
-}
\ No newline at end of file
+}
+
+// Function to check whether a query failed on a unique constraint
+pub fn is_unique_violation(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Database(db_err) => db_err.code().as_deref() == Some("23505"),
+        _ => false,
+    }
+}
@@ -1,36 +1,29 @@
 // src-tauri/src/doctor.rs
 
 // Dependencies
-use std::time::Duration;
-use chrono::{DateTime, Utc};
-use log::info;
-use sqlx::{Postgres, Pool};
-use serde::{Deserialize, Serialize};
-use tauri::State;
+use crate::config::AppConfig;
 use crate::db::DatabaseState;
-use crate::auth::{get_user_from_token, User};
+use crate::error::AppError;
+use crate::auth::{AuthUser, UserProfile};
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{PgRepository, UserRepo};
+
+// Function to list active doctors
+pub async fn list_doctors<R: UserRepo>(repo: &R, user: &AuthUser) -> Result<Vec<UserProfile>, AppError> {
+    require(user, Permission::ReadStaff)?;
+
+    repo.list_active_doctors().await
+}
 
 // Endpoint to get all doctors
 #[tauri::command]
 pub async fn get_all_doctors(
-    state: tauri::State<'_, DatabaseState>, 
+    state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String
-) -> Result<Vec<User>, String> {
-    let _user = get_user_from_token(token)?;
-    let pool = state.pool.lock().await;
+) -> Result<Vec<UserProfile>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    sqlx::query_as!(
-        User,
-        r#"
-        SELECT
-            *
-        FROM
-            users 
-        WHERE
-            role = 'DOCTOR'
-        "#
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| format!("Error while fetching doctors: {}", e))
-}
\ No newline at end of file
+    list_doctors(&PgRepository::new(pool, &config.keyring), &user).await
+}
@@ -0,0 +1,186 @@
+// src-tauri/src/error.rs
+
+// Dependencies
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+// A problem with one input field, shown next to that field by the frontend
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+// Error returned by every command. Sent to the frontend as { code, message, fields? }.
+// The codes are part of the frontend contract; add new variants rather than renaming them.
+// Database and crypto errors carry a context for the UI; the underlying error is only logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
+    Validation(Vec<FieldError>),
+    Conflict(String),
+    Database(String),
+    Crypto(String),
+    // Signed in with a temporary password; only change_password and logout are accepted
+    PasswordChangeRequired(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::Validation(_) => "VALIDATION",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::Database(_) => "DATABASE",
+            AppError::Crypto(_) => "CRYPTO",
+            AppError::PasswordChangeRequired(_) => "PASSWORD_CHANGE_REQUIRED",
+        }
+    }
+
+    // Missing, invalid or expired token or session
+    pub fn unauthorized() -> AppError {
+        AppError::Unauthorized("Unauthorized.".to_string())
+    }
+
+    // Authenticated, but the role or relationship does not allow the action
+    pub fn forbidden() -> AppError {
+        AppError::Forbidden("Forbidden.".to_string())
+    }
+
+    pub fn password_change_required() -> AppError {
+        AppError::PasswordChangeRequired("Change your temporary password to continue.".to_string())
+    }
+
+    pub fn not_found(message: &str) -> AppError {
+        AppError::NotFound(message.to_string())
+    }
+
+    pub fn conflict(message: &str) -> AppError {
+        AppError::Conflict(message.to_string())
+    }
+
+    // Validation error for a single field
+    pub fn validation(field: &str, message: &str) -> AppError {
+        AppError::Validation(vec![FieldError { field: field.to_string(), message: message.to_string() }])
+    }
+
+    // Function to classify a failed query. Constraint violations become conflicts and pgcrypto
+    // failures become crypto errors; anything else is logged and reported with the context only.
+    pub fn database(context: &str, err: sqlx::Error) -> AppError {
+        if let sqlx::Error::RowNotFound = err {
+            return AppError::NotFound("Record not found.".to_string());
+        }
+
+        if let sqlx::Error::Database(db_err) = &err {
+            match db_err.code().as_deref() {
+                Some("23505") => return AppError::conflict("A record with the same values already exists."),
+                Some("23503") => return AppError::conflict("The record is still referenced or refers to a missing record."),
+                // pgcrypto raises external_routine_invocation_exception for a wrong key or corrupt data
+                Some("39000") => {
+                    eprintln!("{}: {}", context, err);
+                    return AppError::Crypto(context.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        eprintln!("{}: {}", context, err);
+        AppError::Database(context.to_string())
+    }
+
+    // Function to report a failure to hash, sign or decode. The underlying error is only logged.
+    pub fn crypto(context: &str, err: impl fmt::Display) -> AppError {
+        eprintln!("{}: {}", context, err);
+        AppError::Crypto(context.to_string())
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::Conflict(message)
+            | AppError::Database(message)
+            | AppError::Crypto(message)
+            | AppError::PasswordChangeRequired(message) => write!(f, "{}", message),
+            AppError::Validation(fields) => write!(
+                f,
+                "{}",
+                fields.iter().map(|field| field.message.as_str()).collect::<Vec<_>>().join(" ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        AppError::database("Database error", err)
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = match self {
+            AppError::Validation(fields) => fields.as_slice(),
+            _ => &[],
+        };
+
+        let mut state = serializer.serialize_struct("AppError", if fields.is_empty() { 2 } else { 3 })?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        if !fields.is_empty() {
+            state.serialize_field("fields", fields)?;
+        } else {
+            state.skip_field("fields")?;
+        }
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_code_and_message() {
+        assert_eq!(
+            serde_json::to_value(AppError::not_found("Patient not found.")).unwrap(),
+            json!({ "code": "NOT_FOUND", "message": "Patient not found." })
+        );
+        assert_eq!(
+            serde_json::to_value(AppError::forbidden()).unwrap(),
+            json!({ "code": "FORBIDDEN", "message": "Forbidden." })
+        );
+    }
+
+    #[test]
+    fn serializes_validation_fields() {
+        assert_eq!(
+            serde_json::to_value(AppError::validation("side", "Side must be LEFT or RIGHT.")).unwrap(),
+            json!({
+                "code": "VALIDATION",
+                "message": "Side must be LEFT or RIGHT.",
+                "fields": [{ "field": "side", "message": "Side must be LEFT or RIGHT." }]
+            })
+        );
+    }
+
+    #[test]
+    fn database_errors_hide_the_underlying_error() {
+        let err = AppError::database("Error while fetching patients", sqlx::Error::PoolTimedOut);
+        assert_eq!(
+            serde_json::to_value(err).unwrap(),
+            json!({ "code": "DATABASE", "message": "Error while fetching patients" })
+        );
+        assert_eq!(AppError::database("Error", sqlx::Error::RowNotFound).code(), "NOT_FOUND");
+    }
+}
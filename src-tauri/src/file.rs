@@ -4,15 +4,24 @@
 use std::{fs::File, io::Write, path::Path};
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine as _;  // Required for engine methods
+use crate::config::AppConfig;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_DOCUMENT};
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::permissions::{authorize, Permission};
 
 #[tauri::command]
-pub async fn save_pdf_file(file_name: String, base_64_data: String, download_path: String) -> Result<(), String> {
-    let file_bytes = STANDARD.decode(&base_64_data).map_err(|e| format!("Base64 decode error: {}", e))?;
+pub async fn save_pdf_file(state: tauri::State<'_, DatabaseState>, config: tauri::State<'_, AppConfig>, token: String, file_name: String, base_64_data: String, download_path: String) -> Result<(), AppError> {
+    let pool = &state.pool;
+    let user = authorize(pool, &config, &token, Permission::ExportDocuments).await?;
+    record_audit(pool, &config.keyring, AuditEvent::new(user.user_id, AuditAction::Export, ENTITY_DOCUMENT)).await?;
+
+    let file_bytes = STANDARD.decode(&base_64_data).map_err(|_| AppError::validation("base_64_data", "The document is not valid base64."))?;
 
     let dest_path = Path::new(&download_path).join(&file_name);
 
-    let mut file = File::create(&dest_path).map_err(|e| format!("File creation error: {}", e))?;
-    file.write_all(&file_bytes).map_err(|e| format!("File write error: {}", e));
+    let mut file = File::create(&dest_path).map_err(|e| AppError::validation("download_path", &format!("Could not create the file: {}", e)))?;
+    file.write_all(&file_bytes).map_err(|e| AppError::validation("download_path", &format!("Could not write the file: {}", e)))?;
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,366 @@
+// src-tauri/src/key_rotation.rs
+
+// Dependencies
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::pool::PoolConnection;
+use sqlx::{Acquire, PgConnection, Pool, Postgres};
+use tauri::State;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::keys::KeyRing;
+use crate::permissions::{authorize, Permission};
+
+// Arbitrary key for the advisory lock that keeps a single re-encryption job running at a time
+const ROTATION_LOCK_KEY: i64 = 0x4548_525f_4b45_5953;
+
+pub const DEFAULT_BATCH_SIZE: i64 = 500;
+
+// A table with pgcrypto encrypted columns and the integer primary key used as the job's cursor
+pub struct EncryptedTable {
+    pub table: &'static str,
+    pub id_column: &'static str,
+    pub columns: &'static [&'static str],
+}
+
+// Every encrypted column in the schema. Add new ones here so rotation covers them.
+pub const ENCRYPTED_TABLES: &[EncryptedTable] = &[
+    EncryptedTable {
+        table: "patients",
+        id_column: "patient_id",
+        columns: &[
+            "patient_photo",
+            "patient_photo_thumbnail",
+            "phone",
+            "email",
+            "address",
+            "emergency_contact_name",
+            "emergency_contact_phone",
+            "preferred_language",
+            "national_id",
+        ],
+    },
+    EncryptedTable { table: "procedures", id_column: "procedure_id", columns: &["procedure_name", "description"] },
+    EncryptedTable {
+        table: "patient_activity",
+        id_column: "activity_id",
+        columns: &["doctors_note", "patient_complaint", "comments"],
+    },
+    EncryptedTable {
+        table: "patient_history",
+        id_column: "history_id",
+        columns: &["medical_conditions", "medications", "allergies"],
+    },
+    EncryptedTable { table: "medical_history_entries", id_column: "entry_id", columns: &["details"] },
+    EncryptedTable { table: "medical_history_changes", id_column: "change_id", columns: &["details"] },
+    EncryptedTable { table: "procedure_comments", id_column: "comment_id", columns: &["body"] },
+    EncryptedTable { table: "procedure_comment_changes", id_column: "change_id", columns: &["body"] },
+    EncryptedTable { table: "activity_transitions", id_column: "transition_id", columns: &["reason"] },
+    EncryptedTable { table: "vision", id_column: "vision_id", columns: &["near_vision", "distant_vision"] },
+    EncryptedTable { table: "refraction", id_column: "refraction_id", columns: &["spherical", "cylindrical", "axis"] },
+    EncryptedTable { table: "eye_measurement", id_column: "measurement_id", columns: &["iop_at", "iop_nct", "cct", "tond"] },
+    EncryptedTable { table: "user_totp", id_column: "user_id", columns: &["secret"] },
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TableRotation {
+    pub table_name: String,
+    pub rows_rotated: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyRotationStatus {
+    table_name: String,
+    current_key_id: i32,
+    rows_remaining: i64,
+    target_key_id: Option<i32>,
+    rows_rotated: Option<i64>,
+    started_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+// SQL that re-encrypts one batch of rows not yet on the target key, walking the primary key upwards.
+// Table and column names come from ENCRYPTED_TABLES only, never from input.
+fn batch_sql(table: &EncryptedTable) -> String {
+    let assignments = table
+        .columns
+        .iter()
+        .map(|column| {
+            format!(
+                "{column} = pgp_sym_encrypt(pgp_sym_decrypt(t.{column}, ($1::TEXT[])[t.key_id]), $2)",
+                column = column
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        r#"
+        WITH batch AS (
+            SELECT {id}
+            FROM {table}
+            WHERE key_id <> $3 AND {id} > $4
+            ORDER BY {id}
+            LIMIT $5
+            FOR UPDATE
+        )
+        UPDATE {table} t
+        SET {assignments}, key_id = $3
+        FROM batch
+        WHERE t.{id} = batch.{id}
+        RETURNING t.{id}
+        "#,
+        id = table.id_column,
+        table = table.table,
+        assignments = assignments
+    )
+}
+
+async fn count_remaining(conn: &mut PgConnection, table: &EncryptedTable, key_id: i32) -> Result<i64, AppError> {
+    sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM {} WHERE key_id <> $1", table.table))
+        .bind(key_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| AppError::database(&format!("Error while counting rows in {}", table.table), e))
+}
+
+// Function to move every row of one table to the current key, one committed batch at a time.
+// The cursor is saved with each batch, so a restarted job continues after the last committed row.
+async fn rotate_table(
+    conn: &mut PgConnection,
+    keyring: &KeyRing,
+    table: &EncryptedTable,
+    batch_size: i64
+) -> Result<i64, AppError> {
+    let target = keyring.current();
+    let keys = keyring.sql_keys();
+    let sql = batch_sql(table);
+
+    // A new target key starts the table over; the same target resumes from the saved cursor
+    let mut last_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO key_rotation_progress (table_name, target_key_id)
+        VALUES ($1, $2)
+        ON CONFLICT (table_name) DO UPDATE
+        SET
+            target_key_id = EXCLUDED.target_key_id,
+            last_id = CASE WHEN key_rotation_progress.target_key_id = EXCLUDED.target_key_id
+                THEN key_rotation_progress.last_id ELSE 0 END,
+            rows_rotated = CASE WHEN key_rotation_progress.target_key_id = EXCLUDED.target_key_id
+                THEN key_rotation_progress.rows_rotated ELSE 0 END,
+            started_at = CASE WHEN key_rotation_progress.target_key_id = EXCLUDED.target_key_id
+                THEN key_rotation_progress.started_at ELSE NOW() END,
+            completed_at = NULL,
+            updated_at = NOW()
+        RETURNING last_id
+        "#,
+        table.table,
+        &target.id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| AppError::database(&format!("Error while loading rotation progress for {}", table.table), e))?;
+
+    let mut rotated = 0;
+    let mut restarted = last_id == 0;
+    loop {
+        let mut tx = conn
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting rotation batch", e))?;
+
+        let ids = sqlx::query_scalar::<_, i32>(&sql)
+            .bind(&keys)
+            .bind(&target.secret)
+            .bind(target.id)
+            .bind(last_id)
+            .bind(batch_size)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| AppError::database(&format!("Error while re-encrypting {}", table.table), e))?;
+
+        if let Some(max_id) = ids.iter().max() {
+            last_id = *max_id as i64;
+            sqlx::query!(
+                r#"
+                UPDATE key_rotation_progress
+                SET last_id = $2, rows_rotated = rows_rotated + $3, updated_at = NOW()
+                WHERE table_name = $1
+                "#,
+                table.table,
+                &last_id,
+                ids.len() as i64
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::database(&format!("Error while saving rotation progress for {}", table.table), e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing rotation batch", e))?;
+
+        rotated += ids.len() as i64;
+        if !ids.is_empty() {
+            continue;
+        }
+
+        // The cursor reached the end. Rows behind it can only be left if an instance still writes with an old key.
+        if count_remaining(conn, table, target.id).await? == 0 {
+            break;
+        }
+        if restarted {
+            return Err(AppError::Conflict(format!(
+                "Rows in {} are still being written with an old key. Make sure every instance uses key {}.",
+                table.table, target.id
+            )));
+        }
+        restarted = true;
+        last_id = 0;
+    }
+
+    sqlx::query!(
+        "UPDATE key_rotation_progress SET completed_at = NOW(), updated_at = NOW() WHERE table_name = $1",
+        table.table
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| AppError::database(&format!("Error while saving rotation progress for {}", table.table), e))?;
+
+    Ok(rotated)
+}
+
+// Function to take the rotation lock on a dedicated connection. Fails if another job holds it.
+pub async fn lock_rotation(pool: &Pool<Postgres>) -> Result<PoolConnection<Postgres>, AppError> {
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| AppError::database("Error while acquiring connection for key rotation", e))?;
+
+    let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+        .bind(ROTATION_LOCK_KEY)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| AppError::database("Error while acquiring key rotation lock", e))?;
+
+    if !locked {
+        return Err(AppError::conflict("A key rotation is already running."));
+    }
+
+    Ok(conn)
+}
+
+// Function to re-encrypt every table on a connection returned by lock_rotation, then release the lock
+pub async fn run_rotation(
+    mut conn: PoolConnection<Postgres>,
+    keyring: &KeyRing,
+    batch_size: i64
+) -> Result<Vec<TableRotation>, AppError> {
+    let batch_size = batch_size.clamp(1, 10_000);
+    let mut result = Ok(vec![]);
+
+    for table in ENCRYPTED_TABLES {
+        match rotate_table(&mut conn, keyring, table, batch_size).await {
+            Ok(rows_rotated) => {
+                if let Ok(summary) = result.as_mut() {
+                    summary.push(TableRotation { table_name: table.table.to_string(), rows_rotated });
+                }
+            }
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        }
+    }
+
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(ROTATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::database("Error while releasing key rotation lock", e))?;
+
+    result
+}
+
+// Function to move all encrypted data to the keyring's current key.
+// Readers keep working throughout because every row names the key that encrypted it.
+pub async fn rotate_encryption_keys(
+    pool: &Pool<Postgres>,
+    keyring: &KeyRing,
+    batch_size: i64
+) -> Result<Vec<TableRotation>, AppError> {
+    let conn = lock_rotation(pool).await?;
+    run_rotation(conn, keyring, batch_size).await
+}
+
+// Endpoint for admins to start re-encrypting stored data with the current key in the background
+#[tauri::command]
+pub async fn start_key_rotation(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    batch_size: Option<i64>
+) -> Result<String, AppError> {
+    let pool = state.pool.clone();
+    let _user = authorize(&pool, &config, &token, Permission::ManageEncryptionKeys).await?;
+    let keyring = config.keyring.clone();
+    let conn = lock_rotation(&pool).await?;
+
+    tauri::async_runtime::spawn(async move {
+        match run_rotation(conn, &keyring, batch_size.unwrap_or(DEFAULT_BATCH_SIZE)).await {
+            Ok(summary) => eprintln!("Key rotation finished: {:?}", summary),
+            Err(err) => eprintln!("Key rotation stopped: {}", err),
+        }
+    });
+
+    Ok("Key rotation started".to_string())
+}
+
+// Endpoint for admins to see how far the re-encryption has progressed per table
+#[tauri::command]
+pub async fn get_key_rotation_status(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String
+) -> Result<Vec<KeyRotationStatus>, AppError> {
+    let pool = &state.pool;
+    let _user = authorize(pool, &config, &token, Permission::ManageEncryptionKeys).await?;
+    let keyring = &config.keyring;
+    let current_key_id = keyring.current().id;
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| AppError::database("Error while acquiring connection", e))?;
+
+    let mut statuses = vec![];
+    for table in ENCRYPTED_TABLES {
+        let rows_remaining = count_remaining(&mut conn, table, current_key_id).await?;
+        let progress = sqlx::query!(
+            r#"
+            SELECT target_key_id, rows_rotated, started_at, updated_at, completed_at
+            FROM key_rotation_progress
+            WHERE table_name = $1
+            "#,
+            table.table
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| AppError::database("Error while fetching rotation progress", e))?;
+
+        statuses.push(KeyRotationStatus {
+            table_name: table.table.to_string(),
+            current_key_id,
+            rows_remaining,
+            target_key_id: progress.as_ref().map(|p| p.target_key_id),
+            rows_rotated: progress.as_ref().map(|p| p.rows_rotated),
+            started_at: progress.as_ref().map(|p| p.started_at),
+            updated_at: progress.as_ref().map(|p| p.updated_at),
+            completed_at: progress.and_then(|p| p.completed_at),
+        });
+    }
+
+    Ok(statuses)
+}
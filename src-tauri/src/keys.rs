@@ -0,0 +1,118 @@
+// src-tauri/src/keys.rs
+
+// Key ids index a SQL array, so keep them small
+pub const MAX_KEY_ID: i32 = 1000;
+
+// A pgcrypto passphrase and the id stored in the key_id column of rows it encrypted
+#[derive(Debug, Clone)]
+pub struct EncryptionKey {
+    pub id: i32,
+    pub secret: String,
+}
+
+// The current key, used for every write, and retired keys that may still be needed to decrypt
+// rows a re-encryption job has not reached yet. The search key is separate because blind search
+// indexes are never re-encrypted and must outlive key rotations. The audit key only keys the
+// HMACs of audit_log, which are never decrypted.
+#[derive(Debug, Clone)]
+pub struct KeyRing {
+    current: EncryptionKey,
+    retired: Vec<EncryptionKey>,
+    search_key: String,
+    audit_key: String,
+}
+
+impl KeyRing {
+    pub fn new(current: EncryptionKey, retired: Vec<EncryptionKey>, search_key: String, audit_key: String) -> Result<KeyRing, String> {
+        let mut seen = vec![];
+        for key in std::iter::once(&current).chain(retired.iter()) {
+            if key.id < 1 || key.id > MAX_KEY_ID {
+                return Err(format!("Encryption key id must be between 1 and {}: {}", MAX_KEY_ID, key.id));
+            }
+            if key.secret.is_empty() {
+                return Err(format!("Encryption key {} is empty.", key.id));
+            }
+            if seen.contains(&key.id) {
+                return Err(format!("Encryption key id {} is configured twice.", key.id));
+            }
+            seen.push(key.id);
+        }
+
+        // sql_keys() is indexed by key id, so a gap would leave some id without a passphrase
+        let max_id = seen.iter().copied().max().unwrap_or(1);
+        if let Some(missing) = (1..=max_id).find(|id| !seen.contains(id)) {
+            return Err(format!(
+                "Encryption key id {} is missing. Keep every key from 1 to {} in ENCRYPTION_RETIRED_KEYS.",
+                missing, max_id
+            ));
+        }
+
+        Ok(KeyRing { current, retired, search_key, audit_key })
+    }
+
+    pub fn current(&self) -> &EncryptionKey {
+        &self.current
+    }
+
+    // Secret for HMAC search tokens
+    pub fn search_key(&self) -> &str {
+        &self.search_key
+    }
+
+    // Secret for HMAC audit hashes
+    pub fn audit_key(&self) -> &str {
+        &self.audit_key
+    }
+
+    pub fn get(&self, id: i32) -> Option<&EncryptionKey> {
+        std::iter::once(&self.current)
+            .chain(self.retired.iter())
+            .find(|key| key.id == id)
+    }
+
+    // Every key as a SQL TEXT[] where element key_id holds that key's secret.
+    // Queries decrypt with pgp_sym_decrypt(column, ($1::TEXT[])[key_id]).
+    // new() guarantees ids 1 to the highest id are all present, so there are no gaps.
+    pub fn sql_keys(&self) -> Vec<String> {
+        let mut keys: Vec<&EncryptionKey> = std::iter::once(&self.current).chain(self.retired.iter()).collect();
+        keys.sort_by_key(|key| key.id);
+        keys.into_iter().map(|key| key.secret.clone()).collect()
+    }
+}
+
+// Parses "1:old-secret,2:older-secret". Secrets may contain ':' but not ','.
+pub fn parse_key_list(value: &str) -> Result<Vec<EncryptionKey>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (id, secret) = entry
+                .split_once(':')
+                .ok_or_else(|| "Retired keys must be written as id:key.".to_string())?;
+            let id = id
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid encryption key id: {}", id))?;
+            Ok(EncryptionKey { id, secret: secret.to_string() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: i32, secret: &str) -> EncryptionKey {
+        EncryptionKey { id, secret: secret.to_string() }
+    }
+
+    #[test]
+    fn key_ids_must_not_leave_gaps_in_sql_keys() {
+        let gap = KeyRing::new(key(3, "third"), vec![key(1, "first")], "search".to_string(), "audit".to_string());
+        assert!(gap.unwrap_err().contains("id 2 is missing"));
+
+        let ring = KeyRing::new(key(3, "third"), vec![key(2, "second"), key(1, "first")], "search".to_string(), "audit".to_string()).unwrap();
+        assert_eq!(ring.sql_keys(), vec!["first", "second", "third"]);
+    }
+}
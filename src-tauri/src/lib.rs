@@ -2,31 +2,92 @@
 
 // Dependancies
 use db::DatabaseState;
-use std::{env, sync::Arc};
+use config::AppConfig;
+use sqlx::{Pool, Postgres};
 use tauri::{Listener, Manager};
-use tokio::sync::Mutex;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 
 // Modules
+pub mod config;
 pub mod db;
+pub mod error;
 pub mod auth;
+pub mod crypto;
 pub mod patients;
+pub mod patient_search;
+pub mod patient_merge;
+pub mod patient_photos;
+pub mod medical_history;
+pub mod patient_summary;
+pub mod patient_timeline;
+pub mod activity_workflow;
+pub mod activity_assignments;
+pub mod procedure_catalog;
+pub mod procedure_comments;
 pub mod doctors;
 pub mod vision;
 pub mod file;
 pub mod alert;
 pub mod messaging;
 pub mod appointment;
-pub mod common_tables;
-pub mod patient_tables;
-pub mod vision_tables;
-pub mod messaging_tables;
-pub mod alert_tables;
-pub mod appointment_tables;
+pub mod migrations;
+pub mod permissions;
+pub mod seed;
+pub mod audit;
+pub mod security;
+pub mod totp;
+pub mod mfa;
+pub mod users;
+pub mod keys;
+pub mod key_rotation;
+pub mod repository;
+pub mod memory_repository;
 
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
+// Function to connect, bring the schema up to date and optionally seed demo rows before any command runs
+async fn prepare_database(config: &AppConfig) -> Result<Pool<Postgres>, String> {
+    let pool = db::connect_to_database(&config.database_url, &config.pool)
+        .await
+        .map_err(|err| format!("Failed to connect to database: {}", err))?;
+    eprintln!("Database connected successfully.");
+
+    let applied = migrations::run_migrations(&pool)
+        .await
+        .map_err(|err| format!("Error while running migrations: {}", err))?;
+    eprintln!("Applied {} pending migration(s).", applied.len());
+
+    // Needs the keys, so it cannot run as a SQL migration
+    match procedure_comments::migrate_legacy_comments(&pool, &config.keyring).await {
+        Ok(0) => {}
+        Ok(moved) => eprintln!("Moved the comments of {} activit(ies) to procedure_comments.", moved),
+        Err(err) => return Err(format!("Error while moving procedure comments: {}", err)),
+    }
+
+    // Demo rows are opt-in and only ever written to an empty database
+    if config.seed_demo_data {
+        match seed::seed_database(&pool, config.keyring.current(), &seed::SeedConfig::default()).await {
+            Ok((summary, password)) => eprintln!(
+                "Seeded demo data: {:?}. Demo users sign in once with '{}' and must then change it.",
+                summary, password
+            ),
+            Err(err) => eprintln!("Skipped demo data: {}", err),
+        }
+    }
+
+    Ok(pool)
+}
+
+// Function to tell the user why the app cannot start and quit once they close the message.
+// The dialog needs the event loop, so setup returns and the dialog's callback ends the process.
+fn fail_startup(app: &tauri::App, message: String) {
+    eprintln!("{}", message);
+    for window in app.webview_windows().values() {
+        let _ = window.hide();
+    }
+    app.dialog()
+        .message(message)
+        .title("ehrportal cannot start")
+        .kind(MessageDialogKind::Error)
+        .show(|_| std::process::exit(1));
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -34,73 +95,31 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_updater::Builder::new().build())
         .setup(|app| {
-            let pool = tauri::async_runtime::block_on(async {
-                match db::connect_to_database().await {
-                    Ok(pool) => {
-                        eprintln!("Database connected successfully.");
-
-                        // match common_tables::setup_all_tables(&pool, true).await {
-                        //     Ok(_) => eprintln!("Setup common tables."),
-                        //     Err(err) => {
-                        //         eprintln!("Error while setting up common tables.");
-                        //         std::process::exit(1);
-                        //     }
-                        // }
-
-                        // match patient_tables::setup_all_patient_tables(&pool, true).await {
-                        //     Ok(_) => eprintln!("Setup patient tables"),
-                        //     Err(err) => {
-                        //         eprintln!("Error while setting up patient tables: {}", err)
-                        //     }
-                        // }
-
-                        match vision_tables::setup_vision_tables(&pool, true).await {
-                            Ok(_) => eprintln!("Setup vision tables"),
-                            Err(err) => {
-                                eprintln!("Error while setting up vision table: {}", err)
-                            }
-                        }
-
-                        // match messaging_tables::setup_messaging_tables(&pool).await {
-                        //     Ok(_) => eprintln!("Setup messaging tables"),
-                        //     Err(err) => {
-                        //         eprintln!("Error while setting up messaging tables: {}", err)
-                        //     }
-                        // }
-
-                        // match alert_tables::create_alerts_table(&pool).await {
-                        //     Ok(_) => eprintln!("Setup alert tables"),
-                        //     Err(err) => {
-                        //         eprintln!("Error while setting up alert tables: {}", err);
-                        //         std::process::exit(1);
-                        //     }
-                        // }
-
-                        // match appointment_tables::setup_all_appointment_tables(&pool).await {
-                        //     Ok(_) => eprintln!("Setup appointment tables."),
-                        //     Err(err) => {
-                        //         eprintln!("Error while setting up appointment tables: {}", err);
-                        //         std::process::exit(1);
-                        //     }
-                        // }
+            // Secrets and settings are read once here; commands get them from managed state
+            let config = match AppConfig::load(app.path().app_config_dir().ok().as_deref()) {
+                Ok(config) => config,
+                Err(err) => {
+                    fail_startup(app, err);
+                    return Ok(());
+                }
+            };
 
-                        pool
-                    }
-                    Err(err) => {
-                        eprintln!("Failed to connect to database: {}", err);
-                        std::process::exit(1);
-                    }
+            let pool = match tauri::async_runtime::block_on(prepare_database(&config)) {
+                Ok(pool) => pool,
+                Err(err) => {
+                    fail_startup(app, err);
+                    return Ok(());
                 }
-            });
+            };
 
-            let pool = Arc::new(Mutex::new(pool));
             app.manage(DatabaseState { pool: pool.clone() });
+            app.manage(config);
 
             // Cleanup when app exits
             app.listen("tauri://clone-requested", move |_event| {
                 let pool = pool.clone();
                 tauri::async_runtime::spawn(async move {
-                    drop(pool.lock().await);
+                    pool.close().await;
                     eprintln!("Database pool closed.");
                 });
             });
@@ -118,20 +137,79 @@ pub fn run() {
         ))
         .build())
         .invoke_handler(tauri::generate_handler![
-            greet,
             file::save_pdf_file,
             auth::login,
             auth::signup,
+            auth::refresh_session,
+            auth::logout,
+            auth::revoke_user_sessions,
+            auth::change_password,
+            users::list_users,
+            users::invite_user,
+            users::approve_user,
+            users::deactivate_user,
+            users::reactivate_user,
+            users::change_user_role,
+            users::force_password_reset,
+            users::update_user,
+            users::unlock_user,
+            security::get_login_attempts,
+            mfa::verify_login_totp,
+            mfa::begin_totp_enrollment,
+            mfa::confirm_totp_enrollment,
+            mfa::get_totp_status,
+            mfa::regenerate_recovery_codes,
+            mfa::disable_totp,
+            mfa::reset_user_totp,
+            audit::get_audit_log,
+            key_rotation::start_key_rotation,
+            key_rotation::get_key_rotation_status,
             patients::get_patient_data,
             patients::get_patient_activity_data,
             patients::get_patients_data,
+            patients::create_patient,
+            patients::update_patient,
+            patients::deactivate_patient,
+            patient_search::search_patients,
+            patient_merge::find_duplicate_patients,
+            patient_merge::merge_patients,
+            patient_merge::undo_patient_merge,
+            patient_photos::upload_patient_photo,
+            patient_photos::get_patient_photo,
+            patient_photos::get_patient_thumbnails,
+            medical_history::get_medical_history,
+            medical_history::get_medical_history_changes,
+            medical_history::add_condition,
+            medical_history::update_condition,
+            medical_history::remove_condition,
+            medical_history::add_medication,
+            medical_history::update_medication,
+            medical_history::remove_medication,
+            medical_history::add_allergy,
+            medical_history::update_allergy,
+            medical_history::remove_allergy,
             patients::get_appointment_data,
-            patients::get_patient_summary_data,
+            patient_summary::get_patient_summary_data,
+            patient_timeline::get_patient_timeline,
+            activity_workflow::transition_activity,
+            activity_workflow::get_activity_transitions,
+            activity_assignments::reassign_patient_activity,
+            activity_assignments::get_my_patients_today,
+            activity_assignments::get_my_pending_reviews,
             patients::get_patient_history_data,
             patients::get_patient_doctor_data,
             patients::get_patient_procedures,
-            patients::add_comment_to_procedure,
-            patients::get_all_procedures,
+            procedure_comments::get_procedure_comments,
+            procedure_comments::add_comment_to_procedure,
+            procedure_comments::update_procedure_comment,
+            procedure_comments::remove_procedure_comment,
+            procedure_comments::get_procedure_comment_history,
+            procedure_catalog::get_all_procedures,
+            procedure_catalog::create_procedure,
+            procedure_catalog::update_procedure,
+            procedure_catalog::deactivate_procedure,
+            procedure_catalog::reactivate_procedure,
+            procedure_catalog::delete_procedure,
             patients::create_patient_activity,
             patients::get_patient_complaints,
             vision::get_vision_data,
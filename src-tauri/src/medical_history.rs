@@ -0,0 +1,919 @@
+// src-tauri/src/medical_history.rs
+
+// Dependencies
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tauri::State;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_MEDICAL_HISTORY};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::{AppError, FieldError};
+use crate::keys::KeyRing;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AuditRepo, MedicalHistoryRepo, PgRepository};
+
+pub const CONDITION_STATUSES: &[&str] = &["ACTIVE", "RESOLVED", "IN_REMISSION"];
+pub const ALLERGY_SEVERITIES: &[&str] = &["MILD", "MODERATE", "SEVERE", "UNKNOWN"];
+const MAX_NAME_LENGTH: usize = 200;
+const MAX_TEXT_LENGTH: usize = 2000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HistoryKind {
+    Condition,
+    Medication,
+    Allergy,
+}
+
+impl HistoryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryKind::Condition => "CONDITION",
+            HistoryKind::Medication => "MEDICATION",
+            HistoryKind::Allergy => "ALLERGY",
+        }
+    }
+
+    // Name used in messages to the user
+    fn label(&self) -> &'static str {
+        match self {
+            HistoryKind::Condition => "condition",
+            HistoryKind::Medication => "medication",
+            HistoryKind::Allergy => "allergy",
+        }
+    }
+}
+
+// Struct to store a diagnosed condition
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub name: String,
+    pub onset_date: Option<NaiveDate>,
+    // One of CONDITION_STATUSES
+    pub status: String,
+    pub notes: Option<String>,
+}
+
+// Struct to store a medication the patient takes or took
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Medication {
+    pub name: String,
+    pub dose: Option<String>,
+    pub frequency: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    // None while the patient still takes it
+    pub stop_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+// Struct to store a known allergy
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Allergy {
+    pub substance: String,
+    pub reaction: Option<String>,
+    // One of ALLERGY_SEVERITIES
+    pub severity: String,
+    pub notes: Option<String>,
+}
+
+// Typed fields of an entry. Stored encrypted as JSON, tagged with its kind.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HistoryDetails {
+    Condition(Condition),
+    Medication(Medication),
+    Allergy(Allergy),
+}
+
+impl HistoryDetails {
+    pub fn kind(&self) -> HistoryKind {
+        match self {
+            HistoryDetails::Condition(_) => HistoryKind::Condition,
+            HistoryDetails::Medication(_) => HistoryKind::Medication,
+            HistoryDetails::Allergy(_) => HistoryKind::Allergy,
+        }
+    }
+}
+
+// Struct to store one entry of the medical history
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub entry_id: i32,
+    pub patient_id: i32,
+    #[serde(flatten)]
+    pub details: HistoryDetails,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Option<i32>,
+    pub created_by_name: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub updated_by: Option<i32>,
+    pub updated_by_name: Option<String>,
+    pub removed_at: Option<DateTime<Utc>>,
+    pub removed_by: Option<i32>,
+}
+
+// Struct to store result of get_medical_history
+#[derive(Serialize, Clone, Debug)]
+pub struct MedicalHistory {
+    pub patient_id: i32,
+    pub conditions: Vec<HistoryEntry>,
+    pub medications: Vec<HistoryEntry>,
+    pub allergies: Vec<HistoryEntry>,
+}
+
+// Struct to store one version of an entry, as returned by get_medical_history_changes
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HistoryChange {
+    pub change_id: i32,
+    pub entry_id: i32,
+    // CREATE, UPDATE or DELETE
+    pub action: String,
+    // The entry after the change, or as it was when removed
+    pub details: HistoryDetails,
+    pub changed_by: Option<i32>,
+    pub changed_by_name: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+// Entry as read from Postgres, with details still in JSON
+struct HistoryEntryRow {
+    entry_id: i32,
+    patient_id: Option<i32>,
+    details: Option<String>,
+    created_at: DateTime<Utc>,
+    created_by: Option<i32>,
+    created_by_name: Option<String>,
+    updated_at: Option<DateTime<Utc>>,
+    updated_by: Option<i32>,
+    updated_by_name: Option<String>,
+    removed_at: Option<DateTime<Utc>>,
+    removed_by: Option<i32>,
+}
+
+pub(crate) struct HistoryChangeRow {
+    pub(crate) change_id: i32,
+    pub(crate) entry_id: i32,
+    pub(crate) action: String,
+    pub(crate) details: Option<String>,
+    pub(crate) changed_by: Option<i32>,
+    pub(crate) changed_by_name: Option<String>,
+    pub(crate) changed_at: DateTime<Utc>,
+}
+
+fn parse_details(details: Option<String>) -> Result<HistoryDetails, AppError> {
+    details
+        .and_then(|details| serde_json::from_str(&details).ok())
+        .ok_or_else(|| AppError::Database("Stored medical history entry could not be read.".to_string()))
+}
+
+fn details_json(details: &HistoryDetails) -> String {
+    serde_json::to_string(details).unwrap_or_default()
+}
+
+impl TryFrom<HistoryEntryRow> for HistoryEntry {
+    type Error = AppError;
+
+    fn try_from(row: HistoryEntryRow) -> Result<Self, AppError> {
+        Ok(HistoryEntry {
+            entry_id: row.entry_id,
+            patient_id: row.patient_id.unwrap_or_default(),
+            details: parse_details(row.details)?,
+            created_at: row.created_at,
+            created_by: row.created_by,
+            created_by_name: row.created_by_name,
+            updated_at: row.updated_at,
+            updated_by: row.updated_by,
+            updated_by_name: row.updated_by_name,
+            removed_at: row.removed_at,
+            removed_by: row.removed_by,
+        })
+    }
+}
+
+impl TryFrom<HistoryChangeRow> for HistoryChange {
+    type Error = AppError;
+
+    fn try_from(row: HistoryChangeRow) -> Result<Self, AppError> {
+        Ok(HistoryChange {
+            change_id: row.change_id,
+            entry_id: row.entry_id,
+            action: row.action,
+            details: parse_details(row.details)?,
+            changed_by: row.changed_by,
+            changed_by_name: row.changed_by_name,
+            changed_at: row.changed_at,
+        })
+    }
+}
+
+// Function to check an entry and tidy its text. Every problem is reported at once.
+pub fn validate_details(details: &HistoryDetails, today: NaiveDate) -> Result<HistoryDetails, AppError> {
+    let mut errors = vec![];
+    let mut error = |field: &str, message: String| errors.push(FieldError { field: field.to_string(), message });
+    let optional = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+    let too_long = |value: &Option<String>| value.as_deref().is_some_and(|value| value.chars().count() > MAX_TEXT_LENGTH);
+
+    let mut required = |field: &str, value: &str| {
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            error(field, "This field is required.".to_string());
+        } else if value.chars().count() > MAX_NAME_LENGTH {
+            error(field, format!("Use at most {} characters.", MAX_NAME_LENGTH));
+        }
+        value
+    };
+
+    let cleaned = match details {
+        HistoryDetails::Condition(condition) => HistoryDetails::Condition(Condition {
+            name: required("name", &condition.name),
+            onset_date: condition.onset_date,
+            status: condition.status.trim().to_uppercase(),
+            notes: optional(&condition.notes),
+        }),
+        HistoryDetails::Medication(medication) => HistoryDetails::Medication(Medication {
+            name: required("name", &medication.name),
+            dose: optional(&medication.dose),
+            frequency: optional(&medication.frequency),
+            start_date: medication.start_date,
+            stop_date: medication.stop_date,
+            notes: optional(&medication.notes),
+        }),
+        HistoryDetails::Allergy(allergy) => HistoryDetails::Allergy(Allergy {
+            substance: required("substance", &allergy.substance),
+            reaction: optional(&allergy.reaction),
+            severity: allergy.severity.trim().to_uppercase(),
+            notes: optional(&allergy.notes),
+        }),
+    };
+
+    match &cleaned {
+        HistoryDetails::Condition(condition) => {
+            if !CONDITION_STATUSES.contains(&condition.status.as_str()) {
+                error("status", "Status must be ACTIVE, RESOLVED or IN_REMISSION.".to_string());
+            }
+            if condition.onset_date.is_some_and(|onset| onset > today) {
+                error("onset_date", "Onset date cannot be in the future.".to_string());
+            }
+            if too_long(&condition.notes) {
+                error("notes", format!("Use at most {} characters.", MAX_TEXT_LENGTH));
+            }
+        }
+        HistoryDetails::Medication(medication) => {
+            if let (Some(start), Some(stop)) = (medication.start_date, medication.stop_date) {
+                if stop < start {
+                    error("stop_date", "Stop date cannot be before the start date.".to_string());
+                }
+            }
+            for (field, value) in [("dose", &medication.dose), ("frequency", &medication.frequency), ("notes", &medication.notes)] {
+                if too_long(value) {
+                    error(field, format!("Use at most {} characters.", MAX_TEXT_LENGTH));
+                }
+            }
+        }
+        HistoryDetails::Allergy(allergy) => {
+            if !ALLERGY_SEVERITIES.contains(&allergy.severity.as_str()) {
+                error("severity", "Severity must be MILD, MODERATE, SEVERE or UNKNOWN.".to_string());
+            }
+            for (field, value) in [("reaction", &allergy.reaction), ("notes", &allergy.notes)] {
+                if too_long(value) {
+                    error(field, format!("Use at most {} characters.", MAX_TEXT_LENGTH));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(cleaned)
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+// Audit event for a change to an entry, shared by every MedicalHistoryRepo implementation
+pub fn history_changed_event(
+    user_id: i32,
+    action: AuditAction,
+    before: Option<&HistoryEntry>,
+    after: &HistoryEntry
+) -> AuditEvent {
+    AuditEvent::new(user_id, action, ENTITY_MEDICAL_HISTORY)
+        .patient(after.patient_id)
+        .entity_id(after.entry_id)
+        .before(before.map(|entry| &entry.details))
+        .after(&after.details)
+}
+
+async fn fetch_entry(conn: &mut PgConnection, keyring: &KeyRing, entry_id: i32) -> Result<Option<HistoryEntry>, AppError> {
+    sqlx::query_as!(
+        HistoryEntryRow,
+        r#"
+        SELECT
+            e.entry_id,
+            e.patient_id,
+            pgp_sym_decrypt(e.details::bytea, ($1::TEXT[])[e.key_id]) as details,
+            e.created_at,
+            e.created_by,
+            c.first_name || ' ' || c.last_name as created_by_name,
+            e.updated_at,
+            e.updated_by,
+            u.first_name || ' ' || u.last_name as updated_by_name,
+            e.removed_at,
+            e.removed_by
+        FROM medical_history_entries e
+        LEFT JOIN users c ON c.user_id = e.created_by
+        LEFT JOIN users u ON u.user_id = e.updated_by
+        WHERE e.entry_id = $2
+        "#,
+        &keyring.sql_keys(),
+        entry_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| AppError::database("Error while fetching medical history entry", e))?
+    .map(HistoryEntry::try_from)
+    .transpose()
+}
+
+// Function to append a version of an entry to medical_history_changes
+async fn insert_change(
+    conn: &mut PgConnection,
+    keyring: &KeyRing,
+    user_id: i32,
+    action: AuditAction,
+    entry: &HistoryEntry
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO medical_history_changes (entry_id, patient_id, action, details, key_id, changed_by)
+        VALUES ($1, $2, $3, pgp_sym_encrypt($4, $5), $6, $7)
+        "#,
+        entry.entry_id,
+        entry.patient_id,
+        action.as_str(),
+        details_json(&entry.details),
+        &keyring.current().secret,
+        keyring.current().id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map(|_| ())
+    .map_err(|e| AppError::database("Error while recording medical history change", e))
+}
+
+impl MedicalHistoryRepo for PgRepository<'_> {
+    async fn list_history_entries(&self, patient_id: i32) -> Result<Vec<HistoryEntry>, AppError> {
+        sqlx::query_as!(
+            HistoryEntryRow,
+            r#"
+            SELECT
+                e.entry_id,
+                e.patient_id,
+                pgp_sym_decrypt(e.details::bytea, ($1::TEXT[])[e.key_id]) as details,
+                e.created_at,
+                e.created_by,
+                c.first_name || ' ' || c.last_name as created_by_name,
+                e.updated_at,
+                e.updated_by,
+                u.first_name || ' ' || u.last_name as updated_by_name,
+                e.removed_at,
+                e.removed_by
+            FROM medical_history_entries e
+            LEFT JOIN users c ON c.user_id = e.created_by
+            LEFT JOIN users u ON u.user_id = e.updated_by
+            WHERE e.patient_id = $2 AND e.removed_at IS NULL
+            ORDER BY e.created_at, e.entry_id
+            "#,
+            &self.keyring.sql_keys(),
+            patient_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching medical history", e))?
+        .into_iter()
+        .map(HistoryEntry::try_from)
+        .collect()
+    }
+
+    async fn list_history_changes(&self, patient_id: i32) -> Result<Vec<HistoryChange>, AppError> {
+        sqlx::query_as!(
+            HistoryChangeRow,
+            r#"
+            SELECT
+                h.change_id,
+                h.entry_id,
+                h.action,
+                pgp_sym_decrypt(h.details::bytea, ($1::TEXT[])[h.key_id]) as details,
+                h.changed_by,
+                u.first_name || ' ' || u.last_name as changed_by_name,
+                h.changed_at
+            FROM medical_history_changes h
+            LEFT JOIN users u ON u.user_id = h.changed_by
+            WHERE h.patient_id = $2
+            ORDER BY h.changed_at DESC, h.change_id DESC
+            "#,
+            &self.keyring.sql_keys(),
+            patient_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching medical history changes", e))?
+        .into_iter()
+        .map(HistoryChange::try_from)
+        .collect()
+    }
+
+    async fn create_history_entry(&self, user_id: i32, patient_id: i32, details: &HistoryDetails) -> Result<HistoryEntry, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let entry_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO medical_history_entries (patient_id, kind, details, key_id, created_by)
+            VALUES ($1, $2, pgp_sym_encrypt($3, $4), $5, $6)
+            RETURNING entry_id
+            "#,
+            patient_id,
+            details.kind().as_str(),
+            details_json(details),
+            &keyring.current().secret,
+            keyring.current().id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while adding medical history entry", e))?;
+
+        let created = fetch_entry(&mut tx, keyring, entry_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Medical history entry was not saved.".to_string()))?;
+        insert_change(&mut tx, keyring, user_id, AuditAction::Create, &created).await?;
+        record_audit(&mut *tx, self.keyring, history_changed_event(user_id, AuditAction::Create, None, &created)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(created)
+    }
+
+    async fn update_history_entry(&self, user_id: i32, entry_id: i32, details: &HistoryDetails) -> Result<Option<HistoryEntry>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let updated = sqlx::query_scalar!(
+            r#"
+            UPDATE medical_history_entries
+            SET details = pgp_sym_encrypt($3, $4), key_id = $5, updated_at = NOW(), updated_by = $6
+            WHERE entry_id = $1 AND kind = $2 AND removed_at IS NULL
+            RETURNING entry_id
+            "#,
+            entry_id,
+            details.kind().as_str(),
+            details_json(details),
+            &keyring.current().secret,
+            keyring.current().id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating medical history entry", e))?;
+        if updated.is_none() {
+            return Ok(None);
+        }
+
+        // The previous version is the latest change recorded for the entry
+        let before = sqlx::query_as!(
+            HistoryChangeRow,
+            r#"
+            SELECT
+                change_id,
+                entry_id,
+                action,
+                pgp_sym_decrypt(details::bytea, ($1::TEXT[])[key_id]) as details,
+                changed_by,
+                NULL::TEXT as changed_by_name,
+                changed_at
+            FROM medical_history_changes
+            WHERE entry_id = $2
+            ORDER BY change_id DESC
+            LIMIT 1
+            "#,
+            &keyring.sql_keys(),
+            entry_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while fetching medical history changes", e))?
+        .map(HistoryChange::try_from)
+        .transpose()?;
+
+        let after = fetch_entry(&mut tx, keyring, entry_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Medical history entry was not saved.".to_string()))?;
+        let before = before.map(|change| HistoryEntry { details: change.details, ..after.clone() });
+        insert_change(&mut tx, keyring, user_id, AuditAction::Update, &after).await?;
+        record_audit(&mut *tx, self.keyring, history_changed_event(user_id, AuditAction::Update, before.as_ref(), &after)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(after))
+    }
+
+    async fn remove_history_entry(&self, user_id: i32, entry_id: i32, kind: HistoryKind) -> Result<Option<HistoryEntry>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let removed = sqlx::query_scalar!(
+            r#"
+            UPDATE medical_history_entries
+            SET removed_at = NOW(), removed_by = $3
+            WHERE entry_id = $1 AND kind = $2 AND removed_at IS NULL
+            RETURNING entry_id
+            "#,
+            entry_id,
+            kind.as_str(),
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while removing medical history entry", e))?;
+        if removed.is_none() {
+            return Ok(None);
+        }
+
+        let removed = fetch_entry(&mut tx, keyring, entry_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Medical history entry was not saved.".to_string()))?;
+        insert_change(&mut tx, keyring, user_id, AuditAction::Delete, &removed).await?;
+        record_audit(&mut *tx, self.keyring, history_changed_event(user_id, AuditAction::Delete, Some(&removed), &removed)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(removed))
+    }
+}
+
+// Function to read a patient's current conditions, medications and allergies, oldest first
+pub async fn find_medical_history<R: MedicalHistoryRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<MedicalHistory, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let entries = repo.list_history_entries(patient_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_MEDICAL_HISTORY).patient(patient_id))
+        .await?;
+
+    let of_kind = |kind: HistoryKind| entries.iter().filter(|entry| entry.details.kind() == kind).cloned().collect();
+    Ok(MedicalHistory {
+        patient_id,
+        conditions: of_kind(HistoryKind::Condition),
+        medications: of_kind(HistoryKind::Medication),
+        allergies: of_kind(HistoryKind::Allergy),
+    })
+}
+
+// Function to list every change to a patient's medical history, newest first
+pub async fn list_medical_history_changes<R: MedicalHistoryRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<Vec<HistoryChange>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let changes = repo.list_history_changes(patient_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_MEDICAL_HISTORY).patient(patient_id))
+        .await?;
+
+    Ok(changes)
+}
+
+// Function to add a condition, medication or allergy to a patient's history
+pub async fn add_history_entry<R: MedicalHistoryRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32,
+    details: &HistoryDetails
+) -> Result<HistoryEntry, AppError> {
+    require(user, Permission::WriteHistory)?;
+
+    let details = validate_details(details, Utc::now().date_naive())?;
+    repo.create_history_entry(user.user_id, patient_id, &details).await
+}
+
+// Function to replace the fields of an entry. The entry must be of the same kind.
+pub async fn edit_history_entry<R: MedicalHistoryRepo>(
+    repo: &R,
+    user: &AuthUser,
+    entry_id: i32,
+    details: &HistoryDetails
+) -> Result<HistoryEntry, AppError> {
+    require(user, Permission::WriteHistory)?;
+
+    let details = validate_details(details, Utc::now().date_naive())?;
+    repo.update_history_entry(user.user_id, entry_id, &details)
+        .await?
+        .ok_or_else(|| AppError::not_found(&format!("No {} with this id.", details.kind().label())))
+}
+
+// Function to remove an entry from a patient's current history. Its changes are kept.
+pub async fn delete_history_entry<R: MedicalHistoryRepo>(
+    repo: &R,
+    user: &AuthUser,
+    entry_id: i32,
+    kind: HistoryKind
+) -> Result<HistoryEntry, AppError> {
+    require(user, Permission::WriteHistory)?;
+
+    repo.remove_history_entry(user.user_id, entry_id, kind)
+        .await?
+        .ok_or_else(|| AppError::not_found(&format!("No {} with this id.", kind.label())))
+}
+
+// Endpoint to get a patient's structured medical history
+#[tauri::command]
+pub async fn get_medical_history(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<MedicalHistory, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    find_medical_history(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
+}
+
+// Endpoint to get who changed a patient's medical history and when
+#[tauri::command]
+pub async fn get_medical_history_changes(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<Vec<HistoryChange>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_medical_history_changes(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
+}
+
+// Endpoint to add a condition
+#[tauri::command]
+pub async fn add_condition(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+    condition: Condition,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    add_history_entry(&PgRepository::new(pool, &config.keyring), &user, patient_id, &HistoryDetails::Condition(condition)).await
+}
+
+// Endpoint to update a condition
+#[tauri::command]
+pub async fn update_condition(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    entry_id: i32,
+    condition: Condition,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    edit_history_entry(&PgRepository::new(pool, &config.keyring), &user, entry_id, &HistoryDetails::Condition(condition)).await
+}
+
+// Endpoint to remove a condition
+#[tauri::command]
+pub async fn remove_condition(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    entry_id: i32,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    delete_history_entry(&PgRepository::new(pool, &config.keyring), &user, entry_id, HistoryKind::Condition).await
+}
+
+// Endpoint to add a medication
+#[tauri::command]
+pub async fn add_medication(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+    medication: Medication,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    add_history_entry(&PgRepository::new(pool, &config.keyring), &user, patient_id, &HistoryDetails::Medication(medication)).await
+}
+
+// Endpoint to update a medication
+#[tauri::command]
+pub async fn update_medication(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    entry_id: i32,
+    medication: Medication,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    edit_history_entry(&PgRepository::new(pool, &config.keyring), &user, entry_id, &HistoryDetails::Medication(medication)).await
+}
+
+// Endpoint to remove a medication
+#[tauri::command]
+pub async fn remove_medication(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    entry_id: i32,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    delete_history_entry(&PgRepository::new(pool, &config.keyring), &user, entry_id, HistoryKind::Medication).await
+}
+
+// Endpoint to add an allergy
+#[tauri::command]
+pub async fn add_allergy(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+    allergy: Allergy,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    add_history_entry(&PgRepository::new(pool, &config.keyring), &user, patient_id, &HistoryDetails::Allergy(allergy)).await
+}
+
+// Endpoint to update an allergy
+#[tauri::command]
+pub async fn update_allergy(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    entry_id: i32,
+    allergy: Allergy,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    edit_history_entry(&PgRepository::new(pool, &config.keyring), &user, entry_id, &HistoryDetails::Allergy(allergy)).await
+}
+
+// Endpoint to remove an allergy
+#[tauri::command]
+pub async fn remove_allergy(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    entry_id: i32,
+) -> Result<HistoryEntry, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    delete_history_entry(&PgRepository::new(pool, &config.keyring), &user, entry_id, HistoryKind::Allergy).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{test_user, MemoryRepository};
+
+    fn allergy(substance: &str, severity: &str) -> HistoryDetails {
+        HistoryDetails::Allergy(Allergy {
+            substance: substance.to_string(),
+            reaction: Some("  Hives ".to_string()),
+            severity: severity.to_string(),
+            notes: Some(" ".to_string()),
+        })
+    }
+
+    #[test]
+    fn validation_tidies_text_and_reports_every_problem() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+
+        let cleaned = validate_details(&allergy(" Penicillin ", "severe"), today).unwrap();
+        assert_eq!(
+            cleaned,
+            HistoryDetails::Allergy(Allergy {
+                substance: "Penicillin".to_string(),
+                reaction: Some("Hives".to_string()),
+                severity: "SEVERE".to_string(),
+                notes: None,
+            })
+        );
+
+        let medication = HistoryDetails::Medication(Medication {
+            name: "".to_string(),
+            dose: Some("500 mg".to_string()),
+            frequency: None,
+            start_date: NaiveDate::from_ymd_opt(2024, 5, 1),
+            stop_date: NaiveDate::from_ymd_opt(2024, 4, 1),
+            notes: None,
+        });
+        let AppError::Validation(errors) = validate_details(&medication, today).unwrap_err() else {
+            panic!("expected a validation error");
+        };
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, vec!["name", "stop_date"]);
+
+        let condition = HistoryDetails::Condition(Condition {
+            name: "Glaucoma".to_string(),
+            onset_date: NaiveDate::from_ymd_opt(2024, 7, 1),
+            status: "CURED".to_string(),
+            notes: None,
+        });
+        let AppError::Validation(errors) = validate_details(&condition, today).unwrap_err() else {
+            panic!("expected a validation error");
+        };
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, vec!["status", "onset_date"]);
+    }
+
+    #[tokio::test]
+    async fn changes_record_who_wrote_each_version() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+        let doctor = test_user(&repo, "DOCTOR");
+        let patient_id = repo.add_patient("Grace", "Hopper");
+
+        let added = add_history_entry(&repo, &nurse, patient_id, &allergy("Penicillin", "MILD")).await.unwrap();
+        let updated = edit_history_entry(&repo, &doctor, added.entry_id, &allergy("Penicillin", "SEVERE")).await.unwrap();
+        assert_eq!(updated.created_by, Some(nurse.user_id));
+        assert_eq!(updated.updated_by, Some(doctor.user_id));
+
+        let history = find_medical_history(&repo, &doctor, patient_id).await.unwrap();
+        assert_eq!(history.allergies, vec![updated.clone()]);
+        assert!(history.conditions.is_empty() && history.medications.is_empty());
+
+        delete_history_entry(&repo, &doctor, added.entry_id, HistoryKind::Allergy).await.unwrap();
+        assert!(find_medical_history(&repo, &doctor, patient_id).await.unwrap().allergies.is_empty());
+
+        let changes = list_medical_history_changes(&repo, &doctor, patient_id).await.unwrap();
+        let summary: Vec<(&str, Option<i32>)> = changes.iter().map(|change| (change.action.as_str(), change.changed_by)).collect();
+        assert_eq!(summary, vec![("DELETE", Some(doctor.user_id)), ("UPDATE", Some(doctor.user_id)), ("CREATE", Some(nurse.user_id))]);
+        assert_eq!(changes[2].details, validate_details(&allergy("Penicillin", "MILD"), Utc::now().date_naive()).unwrap());
+
+        let writes = repo.audit_events().iter().filter(|event| event.action != AuditAction::Read).count();
+        assert_eq!(writes, 3);
+    }
+
+    #[tokio::test]
+    async fn entries_are_only_changed_through_their_own_kind() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let admin = test_user(&repo, "ADMIN");
+        let patient_id = repo.add_patient("Grace", "Hopper");
+        let added = add_history_entry(&repo, &doctor, patient_id, &allergy("Latex", "MODERATE")).await.unwrap();
+
+        let condition = HistoryDetails::Condition(Condition {
+            name: "Latex".to_string(),
+            onset_date: None,
+            status: "ACTIVE".to_string(),
+            notes: None,
+        });
+        let error = edit_history_entry(&repo, &doctor, added.entry_id, &condition).await.unwrap_err();
+        assert_eq!(error.code(), "NOT_FOUND");
+        let error = delete_history_entry(&repo, &doctor, added.entry_id, HistoryKind::Medication).await.unwrap_err();
+        assert_eq!(error.code(), "NOT_FOUND");
+
+        let error = add_history_entry(&repo, &admin, patient_id, &allergy("Latex", "MILD")).await.unwrap_err();
+        assert_eq!(error.code(), "FORBIDDEN");
+    }
+}
@@ -0,0 +1,1843 @@
+// src-tauri/src/memory_repository.rs
+//
+// In-memory implementation of every repository trait, for exercising the service functions
+// without a database. It mirrors the Postgres behaviour the services rely on: ordering, unique
+// emails and conversations, foreign keys reported as conflicts, and audit events recorded with
+// writes. Values are kept in plaintext; encryption is only covered by the Postgres tests.
+
+// Dependencies
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::activity_assignments::{assignment_changed_event, check_reassignable, ActivityAssignment, Worklist};
+use crate::activity_workflow::{activity_transition_event, ActivityStatus, ActivityTransition};
+use crate::alert::{Alert, NewAlert};
+use crate::appointment::{Appointment, NewAppointment};
+use crate::audit::{AuditAction, AuditEvent, ENTITY_EYE_MEASUREMENT, ENTITY_PATIENT, ENTITY_REFRACTION, ENTITY_VISION};
+use crate::auth::{AuthUser, UserProfile};
+use crate::error::AppError;
+use crate::medical_history::{history_changed_event, HistoryChange, HistoryDetails, HistoryEntry, HistoryKind};
+use crate::messaging::{Conversation, Message, MessageData};
+use crate::patient_merge::{merge_events, plan_table_merge, DuplicateCandidateRow, OwnedRow, PatientMerge, TableMove, PATIENT_TABLES};
+use crate::patient_photos::{photo_saved_event, PatientPhoto, ProcessedPhoto};
+use crate::patient_search::{phone_digits, sort_key, PatientFilter, PatientSearchRow};
+use crate::patient_timeline::{activity_events, comment_events, measurement_events, record_events, TimelineEvent, TimelineFilter};
+use crate::patients::{
+    activity_created_event, patient_changed_event, AppointmentData, NewPatientActivity, PatientActivityData, PatientData,
+    PatientDoctorData, PatientHistoryData, PatientInput, PatientProcedureData
+};
+use crate::procedure_catalog::{procedure_changed_event, Procedure, ProcedureInput};
+use crate::procedure_comments::{comment_changed_event, CommentChange, ProcedureComment};
+use crate::repository::{
+    ActivityAssignmentRepo, ActivityWorkflowRepo, AlertRepo, AppointmentRepo, AuditRepo, MedicalHistoryRepo, MessagingRepo, PatientMergeRepo, PatientPhotoRepo, PatientRepo, PatientSearchRepo, ProcedureCatalogRepo, ProcedureCommentRepo, TimelineRepo, UserRepo, VisionRepo
+};
+use crate::users::NewUser;
+use crate::vision::{
+    EyeMeasurementData, EyeMeasurementInput, RefractionData, RefractionInput, VisionData, VisionInput
+};
+
+#[derive(Clone)]
+struct ActivityRow {
+    activity_id: i32,
+    patient_id: i32,
+    procedure_id: i32,
+    status: String,
+    doctors_note: Option<String>,
+    patient_complaint: String,
+    activity_time: chrono::DateTime<Utc>,
+    created_at: chrono::DateTime<Utc>,
+    doctor_id: Option<i32>,
+    nurse_id: Option<i32>,
+}
+
+struct MessageStatusRow {
+    message_id: i32,
+    recipient_id: i32,
+    status: String,
+}
+
+#[derive(Default)]
+struct MemoryStore {
+    next_id: i32,
+    next_mr_sequence: i64,
+    patients: Vec<PatientData>,
+    procedures: Vec<Procedure>,
+    activities: Vec<ActivityRow>,
+    activity_transitions: Vec<ActivityTransition>,
+    // Comments keep the patient_id they were written under; reads take it from the activity
+    comments: Vec<ProcedureComment>,
+    comment_changes: Vec<CommentChange>,
+    histories: Vec<PatientHistoryData>,
+    visions: Vec<VisionData>,
+    refractions: Vec<RefractionData>,
+    eye_measurements: Vec<EyeMeasurementData>,
+    users: Vec<UserProfile>,
+    revoked_sessions: Vec<(i32, String)>,
+    alerts: Vec<Alert>,
+    appointments: Vec<(Appointment, Vec<i32>)>,
+    conversations: Vec<Conversation>,
+    messages: Vec<Message>,
+    message_statuses: Vec<MessageStatusRow>,
+    merges: Vec<PatientMerge>,
+    thumbnails: Vec<PatientPhoto>,
+    history_entries: Vec<HistoryEntry>,
+    // Changes with the patient they belong to
+    history_changes: Vec<(i32, HistoryChange)>,
+    audit: Vec<AuditEvent>,
+}
+
+impl MemoryStore {
+    // Ids are shared across tables, which keeps them unique like SERIAL columns do per table
+    fn next_id(&mut self) -> i32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn has_patient(&self, patient_id: i32) -> bool {
+        self.patients.iter().any(|patient| patient.patient_id == patient_id)
+    }
+
+    fn has_user(&self, user_id: i32) -> bool {
+        self.users.iter().any(|user| user.user_id == user_id)
+    }
+
+    fn user_name(&self, user_id: Option<i32>) -> Option<String> {
+        self.users
+            .iter()
+            .find(|user| Some(user.user_id) == user_id)
+            .map(|user| format!("{} {}", user.first_name, user.last_name))
+    }
+
+    // Today's appointment view of an activity; None when its patient is gone
+    fn appointment(&self, row: &ActivityRow) -> Option<AppointmentData> {
+        let patient = self.patients.iter().find(|patient| patient.patient_id == row.patient_id)?;
+        Some(AppointmentData {
+            patient_id: patient.patient_id,
+            mr_number: patient.mr_number.clone(),
+            first_name: patient.first_name.clone(),
+            last_name: patient.last_name.clone(),
+            date_of_birth: patient.date_of_birth,
+            gender: patient.gender.clone(),
+            patient_photo: self.thumbnail(patient.patient_id),
+            created_at: patient.created_at,
+            activity_id: Some(row.activity_id),
+            status: Some(row.status.clone()),
+            activity: self.procedure(row.procedure_id).and_then(|procedure| procedure.procedure_name.clone()),
+            doctors_note: row.doctors_note.clone(),
+            patient_complaint: Some(row.patient_complaint.clone()),
+            activity_time: Some(row.activity_time),
+            activity_created_at: Some(row.created_at),
+            doctor_id: row.doctor_id,
+            doctor_name: self.user_name(row.doctor_id),
+            nurse_id: row.nurse_id,
+            nurse_name: self.user_name(row.nurse_id),
+        })
+    }
+
+    fn assignment(&self, activity_id: i32) -> Option<ActivityAssignment> {
+        let row = self.activities.iter().find(|row| row.activity_id == activity_id)?;
+        Some(ActivityAssignment {
+            activity_id,
+            patient_id: row.patient_id,
+            status: row.status.clone(),
+            doctor_id: row.doctor_id,
+            doctor_name: self.user_name(row.doctor_id),
+            nurse_id: row.nurse_id,
+            nurse_name: self.user_name(row.nurse_id),
+        })
+    }
+
+    fn procedure(&self, procedure_id: i32) -> Option<&Procedure> {
+        self.procedures.iter().find(|procedure| procedure.procedure_id == procedure_id)
+    }
+
+    fn record_transition(
+        &mut self,
+        user_id: i32,
+        activity_id: i32,
+        from: Option<&str>,
+        to: &str,
+        reason: Option<&str>
+    ) -> ActivityTransition {
+        let transition = ActivityTransition {
+            transition_id: self.next_id(),
+            activity_id,
+            from_status: from.map(str::to_string),
+            to_status: to.to_string(),
+            reason: reason.map(str::to_string),
+            transitioned_by: Some(user_id),
+            transitioned_by_name: self.user_name(Some(user_id)),
+            transitioned_at: Utc::now(),
+        };
+        self.activity_transitions.push(transition.clone());
+        transition
+    }
+
+    fn activity_patient(&self, activity_id: i32) -> Option<i32> {
+        self.activities.iter().find(|row| row.activity_id == activity_id).map(|row| row.patient_id)
+    }
+
+    fn comment(&self, comment_id: i32) -> Option<ProcedureComment> {
+        let comment = self.comments.iter().find(|comment| comment.comment_id == comment_id)?;
+        Some(ProcedureComment {
+            patient_id: self.activity_patient(comment.activity_id).unwrap_or_default(),
+            body: comment.body.clone().filter(|_| comment.deleted_at.is_none()),
+            created_by_name: self.user_name(comment.created_by),
+            ..comment.clone()
+        })
+    }
+
+    // Comments of an activity that were not deleted, as get_patient_procedures shows them
+    fn joined_comments(&self, activity_id: i32) -> Option<String> {
+        let bodies: Vec<&str> = self
+            .comments
+            .iter()
+            .filter(|comment| comment.activity_id == activity_id && comment.deleted_at.is_none())
+            .filter_map(|comment| comment.body.as_deref())
+            .collect();
+        (!bodies.is_empty()).then(|| bodies.join("\n"))
+    }
+
+    fn record_comment_change(&mut self, user_id: i32, action: AuditAction, comment_id: i32, body: &str) {
+        let change = CommentChange {
+            change_id: self.next_id(),
+            comment_id,
+            action: action.as_str().to_string(),
+            body: Some(body.to_string()),
+            changed_by: Some(user_id),
+            changed_by_name: self.user_name(Some(user_id)),
+            changed_at: Utc::now(),
+        };
+        self.comment_changes.push(change);
+    }
+
+    fn conversation_view(&self, conversation: &Conversation) -> Conversation {
+        let last = self
+            .messages
+            .iter()
+            .find(|message| Some(message.message_id) == conversation.last_message);
+
+        Conversation {
+            last_message_sender_id: last.and_then(|message| message.sender_id),
+            last_message_content: last.and_then(|message| message.content.clone()),
+            last_message_created_at: last.and_then(|message| message.created_at),
+            ..conversation.clone()
+        }
+    }
+
+    fn user_mut(&mut self, user_id: i32) -> Option<&mut UserProfile> {
+        self.users.iter_mut().find(|user| user.user_id == user_id)
+    }
+
+    fn thumbnail(&self, patient_id: i32) -> Option<String> {
+        self.thumbnails.iter().find(|thumbnail| thumbnail.patient_id == patient_id).map(|thumbnail| thumbnail.data_url.clone())
+    }
+
+    fn email_taken(&self, email: &str, except: Option<i32>) -> bool {
+        self.users.iter().any(|user| user.email == email && Some(user.user_id) != except)
+    }
+
+    // Rows of a patient-owned table belonging to either patient, keyed like the unique constraints
+    fn owned_rows(&self, table: &str, patient_ids: [i32; 2]) -> Vec<OwnedRow> {
+        let row = |row_id, patient_id: Option<i32>, conflict_key: Option<String>, changed_at| {
+            patient_id.filter(|patient_id| patient_ids.contains(patient_id)).map(|patient_id| OwnedRow {
+                row_id,
+                patient_id,
+                conflict_key,
+                changed_at,
+            })
+        };
+
+        match table {
+            "patient_activity" => self
+                .activities
+                .iter()
+                .filter_map(|a| row(a.activity_id, Some(a.patient_id), None, Some(a.created_at)))
+                .collect(),
+            "patient_history" => self
+                .histories
+                .iter()
+                .filter_map(|h| row(h.history_id, h.patient_id, Some(String::new()), h.last_visit))
+                .collect(),
+            "vision" => self
+                .visions
+                .iter()
+                .filter_map(|v| row(v.vision_id, v.patient_id, Some(format!("{}|{}", v.side, v.value_type)), v.updated_at.or(v.created_at)))
+                .collect(),
+            "refraction" => self
+                .refractions
+                .iter()
+                .filter_map(|r| {
+                    let key = format!("{}|{}|{}", r.side, r.value_type, r.vision_type);
+                    row(r.refraction_id, r.patient_id, Some(key), r.updated_at.or(r.created_at))
+                })
+                .collect(),
+            "eye_measurement" => self
+                .eye_measurements
+                .iter()
+                .filter_map(|m| row(m.measurement_id, m.patient_id, Some(m.side.clone()), m.updated_at.or(m.created_at)))
+                .collect(),
+            "medical_history_entries" => self
+                .history_entries
+                .iter()
+                .filter_map(|e| row(e.entry_id, Some(e.patient_id), None, Some(e.updated_at.unwrap_or(e.created_at))))
+                .collect(),
+            "medical_history_changes" => self
+                .history_changes
+                .iter()
+                .filter_map(|(patient_id, c)| row(c.change_id, Some(*patient_id), None, Some(c.changed_at)))
+                .collect(),
+            "appointments" => self
+                .appointments
+                .iter()
+                .filter_map(|(a, _)| row(a.appointment_id, a.patient_id, None, a.created_at))
+                .collect(),
+            "alerts" => self.alerts.iter().filter_map(|a| row(a.alert_id, a.patient_id, None, a.created_at)).collect(),
+            _ => vec![],
+        }
+    }
+
+    // Appends the current version of an entry to its changes
+    fn record_history_change(&mut self, user_id: i32, action: AuditAction, entry: &HistoryEntry) {
+        let change = HistoryChange {
+            change_id: self.next_id(),
+            entry_id: entry.entry_id,
+            action: action.as_str().to_string(),
+            details: entry.details.clone(),
+            changed_by: Some(user_id),
+            changed_by_name: self.user_name(Some(user_id)),
+            changed_at: Utc::now(),
+        };
+        self.history_changes.push((entry.patient_id, change));
+    }
+
+    // Hands rows of a patient-owned table to another patient
+    fn reassign(&mut self, table: &str, row_ids: &[i32], patient_id: i32) {
+        match table {
+            "patient_activity" => self.activities.iter_mut().filter(|a| row_ids.contains(&a.activity_id)).for_each(|a| a.patient_id = patient_id),
+            "patient_history" => self.histories.iter_mut().filter(|h| row_ids.contains(&h.history_id)).for_each(|h| h.patient_id = Some(patient_id)),
+            "vision" => self.visions.iter_mut().filter(|v| row_ids.contains(&v.vision_id)).for_each(|v| v.patient_id = Some(patient_id)),
+            "refraction" => self
+                .refractions
+                .iter_mut()
+                .filter(|r| row_ids.contains(&r.refraction_id))
+                .for_each(|r| r.patient_id = Some(patient_id)),
+            "eye_measurement" => self
+                .eye_measurements
+                .iter_mut()
+                .filter(|m| row_ids.contains(&m.measurement_id))
+                .for_each(|m| m.patient_id = Some(patient_id)),
+            "medical_history_entries" => self
+                .history_entries
+                .iter_mut()
+                .filter(|e| row_ids.contains(&e.entry_id))
+                .for_each(|e| e.patient_id = patient_id),
+            "medical_history_changes" => self
+                .history_changes
+                .iter_mut()
+                .filter(|(_, c)| row_ids.contains(&c.change_id))
+                .for_each(|(owner, _)| *owner = patient_id),
+            "appointments" => self
+                .appointments
+                .iter_mut()
+                .filter(|(a, _)| row_ids.contains(&a.appointment_id))
+                .for_each(|(a, _)| a.patient_id = Some(patient_id)),
+            "alerts" => self.alerts.iter_mut().filter(|a| row_ids.contains(&a.alert_id)).for_each(|a| a.patient_id = Some(patient_id)),
+            _ => {}
+        }
+    }
+}
+
+// Copies the editable demographics onto a patient row
+fn with_demographics(patient: PatientData, input: &PatientInput) -> PatientData {
+    PatientData {
+        first_name: input.first_name.clone(),
+        last_name: input.last_name.clone(),
+        date_of_birth: input.date_of_birth,
+        gender: input.gender.clone(),
+        phone: input.phone.clone(),
+        email: input.email.clone(),
+        address: input.address.clone(),
+        emergency_contact_name: input.emergency_contact_name.clone(),
+        emergency_contact_phone: input.emergency_contact_phone.clone(),
+        preferred_language: input.preferred_language.clone(),
+        national_id: input.national_id.clone(),
+        ..patient
+    }
+}
+
+// Same error Postgres reports for a foreign key violation
+fn missing_reference() -> AppError {
+    AppError::conflict("The record is still referenced or refers to a missing record.")
+}
+
+fn email_conflict() -> AppError {
+    AppError::conflict("A user with this email already exists.")
+}
+
+#[derive(Default)]
+pub struct MemoryRepository {
+    store: Mutex<MemoryStore>,
+}
+
+impl MemoryRepository {
+    fn store(&self) -> MutexGuard<'_, MemoryStore> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Function to add a patient and return its id
+    pub fn add_patient(&self, first_name: &str, last_name: &str) -> i32 {
+        let mut store = self.store();
+        let patient_id = store.next_id();
+        store.patients.push(PatientData {
+            patient_id,
+            mr_number: format!("MR{:06}", patient_id),
+            first_name: first_name.to_string(),
+            last_name: last_name.to_string(),
+            date_of_birth: NaiveDate::from_ymd_opt(1980, 1, 1).unwrap_or_default(),
+            gender: "FEMALE".to_string(),
+            patient_photo: None,
+            phone: None,
+            email: None,
+            address: None,
+            emergency_contact_name: None,
+            emergency_contact_phone: None,
+            preferred_language: None,
+            national_id: None,
+            active: true,
+            created_at: Some(Utc::now()),
+            updated_at: None,
+            deactivated_at: None,
+        });
+        patient_id
+    }
+
+    // Function to add an active diagnostic catalog procedure and return its id
+    pub fn add_procedure(&self, procedure_name: &str, description: &str) -> i32 {
+        let mut store = self.store();
+        let procedure_id = store.next_id();
+        store.procedures.push(Procedure {
+            procedure_id,
+            procedure_name: Some(procedure_name.to_string()),
+            description: Some(description.to_string()),
+            category: "DIAGNOSTIC".to_string(),
+            default_duration_minutes: None,
+            default_cost_cents: None,
+            cpt_code: None,
+            snomed_code: None,
+            active: true,
+            created_at: Some(Utc::now()),
+            updated_at: None,
+        });
+        procedure_id
+    }
+
+    // Function to add a patient's medical history and return its id
+    pub fn add_history(&self, patient_id: i32, medical_conditions: &str, medications: &str, allergies: &str) -> i32 {
+        let mut store = self.store();
+        let history_id = store.next_id();
+        store.histories.push(PatientHistoryData {
+            history_id,
+            patient_id: Some(patient_id),
+            medical_conditions: Some(medical_conditions.to_string()),
+            medications: Some(medications.to_string()),
+            allergies: Some(allergies.to_string()),
+            last_visit: None,
+        });
+        history_id
+    }
+
+    // Function to add an active user and return it
+    pub fn add_user(&self, role: &str, first_name: &str, last_name: &str, email: &str) -> UserProfile {
+        let mut store = self.store();
+        let user = UserProfile {
+            user_id: store.next_id(),
+            role: role.to_string(),
+            first_name: first_name.to_string(),
+            last_name: last_name.to_string(),
+            email: email.to_string(),
+            status: "ACTIVE".to_string(),
+            must_reset_password: false,
+            locked_until: None,
+            created_at: Some(Utc::now()),
+        };
+        store.users.push(user.clone());
+        user
+    }
+
+    // Audit events recorded so far, oldest first
+    pub fn audit_events(&self) -> Vec<AuditEvent> {
+        self.store().audit.clone()
+    }
+
+    // Sessions are not modelled; revocations are kept as (user_id, reason) for assertions
+    pub fn revoked_sessions(&self) -> Vec<(i32, String)> {
+        self.store().revoked_sessions.clone()
+    }
+}
+
+// Function to add an active user with the given role and sign it in
+pub fn test_user(repo: &MemoryRepository, role: &str) -> AuthUser {
+    let count = repo.store().users.len() + 1;
+    let user = repo.add_user(role, "Test", &format!("User{}", count), &format!("user{}@example.com", count));
+
+    AuthUser { user_id: user.user_id, role: user.role, session_id: 0 }
+}
+
+// Function to build an activity in progress now, for tests to override what they care about
+pub fn new_activity(patient_id: i32, procedure_id: i32) -> NewPatientActivity {
+    NewPatientActivity {
+        patient_id,
+        procedure_id,
+        status: "IN_PROGRESS".to_string(),
+        patient_complaint: "Blurred vision".to_string(),
+        activity_time: Utc::now(),
+        ..Default::default()
+    }
+}
+
+impl AuditRepo for MemoryRepository {
+    async fn record_audit(&self, event: AuditEvent) -> Result<(), AppError> {
+        self.store().audit.push(event);
+        Ok(())
+    }
+
+    async fn record_patient_reads(&self, user_id: i32, entity: &'static str, patient_ids: &[i32]) -> Result<(), AppError> {
+        let mut unique_ids = patient_ids.to_vec();
+        unique_ids.sort_unstable();
+        unique_ids.dedup();
+
+        let mut store = self.store();
+        for patient_id in unique_ids {
+            store.audit.push(AuditEvent::new(user_id, AuditAction::Read, entity).patient(patient_id));
+        }
+        Ok(())
+    }
+}
+
+impl PatientRepo for MemoryRepository {
+    async fn find_patient(&self, patient_id: i32) -> Result<Option<PatientData>, AppError> {
+        Ok(self.store().patients.iter().find(|patient| patient.patient_id == patient_id).cloned())
+    }
+
+    async fn list_patients(&self) -> Result<Vec<PatientData>, AppError> {
+        let store = self.store();
+        Ok(store
+            .patients
+            .iter()
+            .filter(|patient| patient.active)
+            .map(|patient| PatientData { patient_photo: store.thumbnail(patient.patient_id), ..patient.clone() })
+            .collect())
+    }
+
+    async fn next_mr_sequence(&self) -> Result<i64, AppError> {
+        let mut store = self.store();
+        store.next_mr_sequence += 1;
+        Ok(store.next_mr_sequence)
+    }
+
+    async fn create_patient(&self, user_id: i32, mr_number: &str, patient: &PatientInput) -> Result<PatientData, AppError> {
+        let mut store = self.store();
+        if store.patients.iter().any(|existing| existing.mr_number == mr_number) {
+            return Err(AppError::conflict("A record with the same values already exists."));
+        }
+
+        let patient_id = store.next_id();
+        let created = with_demographics(
+            PatientData {
+                patient_id,
+                mr_number: mr_number.to_string(),
+                first_name: String::new(),
+                last_name: String::new(),
+                date_of_birth: patient.date_of_birth,
+                gender: String::new(),
+                patient_photo: None,
+                phone: None,
+                email: None,
+                address: None,
+                emergency_contact_name: None,
+                emergency_contact_phone: None,
+                preferred_language: None,
+                national_id: None,
+                active: true,
+                created_at: Some(Utc::now()),
+                updated_at: None,
+                deactivated_at: None,
+            },
+            patient,
+        );
+        store.patients.push(created.clone());
+        store.audit.push(patient_changed_event(user_id, AuditAction::Create, None, &created));
+        Ok(created)
+    }
+
+    async fn update_patient(&self, user_id: i32, patient_id: i32, patient: &PatientInput) -> Result<Option<PatientData>, AppError> {
+        let mut store = self.store();
+        let Some(existing) = store.patients.iter_mut().find(|existing| existing.patient_id == patient_id && existing.active) else {
+            return Ok(None);
+        };
+
+        let before = existing.clone();
+        *existing = PatientData { updated_at: Some(Utc::now()), ..with_demographics(before.clone(), patient) };
+        let after = existing.clone();
+        store.audit.push(patient_changed_event(user_id, AuditAction::Update, Some(&before), &after));
+        Ok(Some(after))
+    }
+
+    async fn deactivate_patient(&self, user_id: i32, patient_id: i32) -> Result<Option<PatientData>, AppError> {
+        let mut store = self.store();
+        let Some(existing) = store.patients.iter_mut().find(|existing| existing.patient_id == patient_id && existing.active) else {
+            return Ok(None);
+        };
+
+        existing.active = false;
+        existing.updated_at = Some(Utc::now());
+        existing.deactivated_at = existing.updated_at;
+        let deactivated = existing.clone();
+        store.audit.push(patient_changed_event(user_id, AuditAction::Update, None, &deactivated));
+        Ok(Some(deactivated))
+    }
+
+    async fn list_activities(&self, patient_id: i32) -> Result<Vec<PatientActivityData>, AppError> {
+        let store = self.store();
+        let mut activities: Vec<&ActivityRow> = store.activities.iter().filter(|row| row.patient_id == patient_id).collect();
+        activities.sort_by_key(|row| Reverse(row.activity_time));
+
+        Ok(activities
+            .into_iter()
+            .map(|row| PatientActivityData {
+                activity_id: row.activity_id,
+                activity: store.procedure(row.procedure_id).and_then(|procedure| procedure.procedure_name.clone()),
+                activity_time: Some(row.activity_time),
+                status: Some(row.status.clone()),
+                doctor_id: row.doctor_id,
+                doctor_name: store.user_name(row.doctor_id),
+                nurse_id: row.nurse_id,
+                nurse_name: store.user_name(row.nurse_id),
+            })
+            .collect())
+    }
+
+    async fn list_todays_appointments(&self) -> Result<Vec<AppointmentData>, AppError> {
+        let store = self.store();
+        let today = Utc::now().date_naive();
+        let mut activities: Vec<&ActivityRow> = store
+            .activities
+            .iter()
+            .filter(|row| row.activity_time.date_naive() == today)
+            .collect();
+        activities.sort_by_key(|row| row.activity_time);
+
+        Ok(activities.into_iter().filter_map(|row| store.appointment(row)).collect())
+    }
+
+    async fn find_history(&self, patient_id: i32) -> Result<Option<PatientHistoryData>, AppError> {
+        let store = self.store();
+        let last_visit = store
+            .activities
+            .iter()
+            .filter(|row| row.patient_id == patient_id)
+            .map(|row| row.activity_time)
+            .max();
+
+        Ok(store
+            .histories
+            .iter()
+            .find(|history| history.patient_id == Some(patient_id))
+            .map(|history| PatientHistoryData { last_visit, ..history.clone() }))
+    }
+
+    async fn list_doctor_notes(&self, patient_id: i32) -> Result<Vec<PatientDoctorData>, AppError> {
+        Ok(self
+            .store()
+            .activities
+            .iter()
+            .filter(|row| row.patient_id == patient_id)
+            .map(|row| PatientDoctorData {
+                doctors_note: row.doctors_note.clone(),
+                patient_complaint: Some(row.patient_complaint.clone()),
+                activity_time: Some(row.activity_time),
+            })
+            .collect())
+    }
+
+    async fn list_patient_procedures(&self, patient_id: i32) -> Result<Vec<PatientProcedureData>, AppError> {
+        let store = self.store();
+        let mut activities: Vec<&ActivityRow> = store.activities.iter().filter(|row| row.patient_id == patient_id).collect();
+        activities.sort_by_key(|row| Reverse(row.activity_time));
+
+        Ok(activities
+            .into_iter()
+            .map(|row| {
+                let procedure = store.procedure(row.procedure_id);
+                PatientProcedureData {
+                    activity_id: row.activity_id,
+                    status: row.status.clone(),
+                    procedure_name: procedure.and_then(|procedure| procedure.procedure_name.clone()),
+                    procedure_description: procedure.and_then(|procedure| procedure.description.clone()),
+                    doctors_note: row.doctors_note.clone(),
+                    patient_complaint: Some(row.patient_complaint.clone()),
+                    comments: store.joined_comments(row.activity_id),
+                    activity_time: Some(row.activity_time),
+                }
+            })
+            .collect())
+    }
+
+    async fn list_complaints(&self, patient_id: i32) -> Result<Vec<String>, AppError> {
+        Ok(self
+            .store()
+            .activities
+            .iter()
+            .filter(|row| row.patient_id == patient_id)
+            .map(|row| row.patient_complaint.clone())
+            .collect())
+    }
+
+    async fn create_activity(&self, user_id: i32, activity: &NewPatientActivity) -> Result<i32, AppError> {
+        let mut store = self.store();
+        if !store.has_patient(activity.patient_id) || store.procedure(activity.procedure_id).is_none() {
+            return Err(missing_reference());
+        }
+
+        let activity_id = store.next_id();
+        store.activities.push(ActivityRow {
+            activity_id,
+            patient_id: activity.patient_id,
+            procedure_id: activity.procedure_id,
+            status: activity.status.clone(),
+            doctors_note: Some(activity.doctors_note.clone()),
+            patient_complaint: activity.patient_complaint.clone(),
+            activity_time: activity.activity_time,
+            created_at: Utc::now(),
+            doctor_id: activity.doctor_id,
+            nurse_id: activity.nurse_id,
+        });
+        store.record_transition(user_id, activity_id, None, &activity.status, None);
+        store.audit.push(activity_created_event(user_id, activity_id, activity));
+        Ok(activity_id)
+    }
+}
+
+impl ProcedureCatalogRepo for MemoryRepository {
+    async fn list_procedures(&self, include_inactive: bool) -> Result<Vec<Procedure>, AppError> {
+        let mut procedures: Vec<Procedure> =
+            self.store().procedures.iter().filter(|procedure| procedure.active || include_inactive).cloned().collect();
+        procedures.sort_by_key(|procedure| (procedure.procedure_name.as_deref().map(str::to_lowercase), procedure.procedure_id));
+        Ok(procedures)
+    }
+
+    async fn find_procedure(&self, procedure_id: i32) -> Result<Option<Procedure>, AppError> {
+        Ok(self.store().procedure(procedure_id).cloned())
+    }
+
+    async fn count_procedure_activities(&self, procedure_id: i32) -> Result<i64, AppError> {
+        Ok(self.store().activities.iter().filter(|row| row.procedure_id == procedure_id).count() as i64)
+    }
+
+    async fn create_procedure(&self, user_id: i32, input: &ProcedureInput) -> Result<Procedure, AppError> {
+        let mut store = self.store();
+        let procedure = Procedure {
+            procedure_id: store.next_id(),
+            procedure_name: Some(input.procedure_name.clone()),
+            description: input.description.clone(),
+            category: input.category.clone(),
+            default_duration_minutes: input.default_duration_minutes,
+            default_cost_cents: input.default_cost_cents,
+            cpt_code: input.cpt_code.clone(),
+            snomed_code: input.snomed_code.clone(),
+            active: true,
+            created_at: Some(Utc::now()),
+            updated_at: None,
+        };
+        store.procedures.push(procedure.clone());
+        store.audit.push(procedure_changed_event(user_id, AuditAction::Create, None, &procedure));
+        Ok(procedure)
+    }
+
+    async fn update_procedure(&self, user_id: i32, procedure_id: i32, input: &ProcedureInput) -> Result<Option<Procedure>, AppError> {
+        let mut store = self.store();
+        let Some(procedure) = store.procedures.iter_mut().find(|procedure| procedure.procedure_id == procedure_id) else {
+            return Ok(None);
+        };
+        let before = procedure.clone();
+        procedure.procedure_name = Some(input.procedure_name.clone());
+        procedure.description = input.description.clone();
+        procedure.category = input.category.clone();
+        procedure.default_duration_minutes = input.default_duration_minutes;
+        procedure.default_cost_cents = input.default_cost_cents;
+        procedure.cpt_code = input.cpt_code.clone();
+        procedure.snomed_code = input.snomed_code.clone();
+        procedure.updated_at = Some(Utc::now());
+
+        let updated = procedure.clone();
+        store.audit.push(procedure_changed_event(user_id, AuditAction::Update, Some(&before), &updated));
+        Ok(Some(updated))
+    }
+
+    async fn set_procedure_active(&self, user_id: i32, procedure_id: i32, active: bool) -> Result<Option<Procedure>, AppError> {
+        let mut store = self.store();
+        let Some(procedure) = store.procedures.iter_mut().find(|procedure| procedure.procedure_id == procedure_id) else {
+            return Ok(None);
+        };
+        let before = procedure.clone();
+        procedure.active = active;
+        procedure.updated_at = Some(Utc::now());
+
+        let updated = procedure.clone();
+        store.audit.push(procedure_changed_event(user_id, AuditAction::Update, Some(&before), &updated));
+        Ok(Some(updated))
+    }
+
+    async fn delete_procedure(&self, user_id: i32, procedure_id: i32) -> Result<Option<Procedure>, AppError> {
+        let mut store = self.store();
+        let Some(deleted) = store.procedure(procedure_id).cloned() else {
+            return Ok(None);
+        };
+        if store.activities.iter().any(|row| row.procedure_id == procedure_id) {
+            return Err(missing_reference());
+        }
+
+        store.procedures.retain(|procedure| procedure.procedure_id != procedure_id);
+        store.audit.push(procedure_changed_event(user_id, AuditAction::Delete, Some(&deleted), &deleted));
+        Ok(Some(deleted))
+    }
+}
+
+impl ActivityAssignmentRepo for MemoryRepository {
+    async fn find_active_role(&self, user_id: i32) -> Result<Option<String>, AppError> {
+        Ok(self
+            .store()
+            .users
+            .iter()
+            .find(|user| user.user_id == user_id && user.status == "ACTIVE")
+            .map(|user| user.role.clone()))
+    }
+
+    async fn find_assignment(&self, activity_id: i32) -> Result<Option<ActivityAssignment>, AppError> {
+        Ok(self.store().assignment(activity_id))
+    }
+
+    async fn assign_clinicians(
+        &self,
+        user_id: i32,
+        activity_id: i32,
+        doctor_id: i32,
+        nurse_id: Option<i32>
+    ) -> Result<Option<ActivityAssignment>, AppError> {
+        let mut store = self.store();
+        let Some(before) = store.assignment(activity_id) else {
+            return Ok(None);
+        };
+        check_reassignable(&before.status)?;
+        if let Some(row) = store.activities.iter_mut().find(|row| row.activity_id == activity_id) {
+            row.doctor_id = Some(doctor_id);
+            row.nurse_id = nurse_id;
+        }
+
+        let after = store.assignment(activity_id).ok_or_else(|| AppError::not_found("Activity does not exist."))?;
+        store.audit.push(assignment_changed_event(user_id, &before, &after));
+        Ok(Some(after))
+    }
+
+    async fn list_worklist(&self, user_id: i32, worklist: Worklist) -> Result<Vec<AppointmentData>, AppError> {
+        let store = self.store();
+        let mut activities: Vec<&ActivityRow> = store
+            .activities
+            .iter()
+            .filter(|row| match worklist {
+                Worklist::Today { start, end } => {
+                    row.activity_time >= start
+                        && row.activity_time < end
+                        && row.status != "CANCELLED"
+                        && (row.doctor_id == Some(user_id) || row.nurse_id == Some(user_id))
+                }
+                Worklist::AwaitingReview => row.status == "TO_BE_REVIEWED" && row.doctor_id == Some(user_id),
+            })
+            .collect();
+        activities.sort_by_key(|row| row.activity_time);
+
+        Ok(activities.into_iter().filter_map(|row| store.appointment(row)).collect())
+    }
+}
+
+impl ActivityWorkflowRepo for MemoryRepository {
+    async fn find_activity_status(&self, activity_id: i32) -> Result<Option<String>, AppError> {
+        Ok(self.store().activities.iter().find(|row| row.activity_id == activity_id).map(|row| row.status.clone()))
+    }
+
+    async fn transition_activity(
+        &self,
+        user_id: i32,
+        activity_id: i32,
+        from: ActivityStatus,
+        to: ActivityStatus,
+        reason: Option<&str>
+    ) -> Result<Option<ActivityTransition>, AppError> {
+        let mut store = self.store();
+        let Some(row) = store
+            .activities
+            .iter_mut()
+            .find(|row| row.activity_id == activity_id && row.status == from.as_str())
+        else {
+            return Ok(None);
+        };
+        row.status = to.as_str().to_string();
+        let patient_id = row.patient_id;
+
+        let transition = store.record_transition(user_id, activity_id, Some(from.as_str()), to.as_str(), reason);
+        store.audit.push(activity_transition_event(user_id, patient_id, &transition));
+        Ok(Some(transition))
+    }
+
+    async fn list_activity_transitions(&self, activity_id: i32) -> Result<Vec<ActivityTransition>, AppError> {
+        Ok(self
+            .store()
+            .activity_transitions
+            .iter()
+            .filter(|transition| transition.activity_id == activity_id)
+            .cloned()
+            .collect())
+    }
+}
+
+impl ProcedureCommentRepo for MemoryRepository {
+    async fn find_activity_patient(&self, activity_id: i32) -> Result<Option<i32>, AppError> {
+        Ok(self.store().activity_patient(activity_id))
+    }
+
+    async fn list_comments(&self, activity_id: i32) -> Result<Vec<ProcedureComment>, AppError> {
+        let store = self.store();
+        Ok(store
+            .comments
+            .iter()
+            .filter(|comment| comment.activity_id == activity_id)
+            .filter_map(|comment| store.comment(comment.comment_id))
+            .collect())
+    }
+
+    async fn find_comment(&self, comment_id: i32) -> Result<Option<ProcedureComment>, AppError> {
+        Ok(self.store().comment(comment_id))
+    }
+
+    async fn list_comment_changes(&self, comment_id: i32) -> Result<Vec<CommentChange>, AppError> {
+        Ok(self.store().comment_changes.iter().filter(|change| change.comment_id == comment_id).cloned().collect())
+    }
+
+    async fn create_comment(&self, user_id: i32, activity_id: i32, parent_id: Option<i32>, body: &str) -> Result<ProcedureComment, AppError> {
+        let mut store = self.store();
+        let parent_missing = parent_id.is_some_and(|parent_id| store.comment(parent_id).is_none());
+        let Some(patient_id) = store.activity_patient(activity_id).filter(|_| !parent_missing) else {
+            return Err(missing_reference());
+        };
+
+        let comment = ProcedureComment {
+            comment_id: store.next_id(),
+            activity_id,
+            patient_id,
+            parent_id,
+            body: Some(body.to_string()),
+            created_at: Utc::now(),
+            created_by: Some(user_id),
+            created_by_name: store.user_name(Some(user_id)),
+            updated_at: None,
+            updated_by: None,
+            deleted_at: None,
+            deleted_by: None,
+            replies: vec![],
+        };
+        store.comments.push(comment.clone());
+        store.record_comment_change(user_id, AuditAction::Create, comment.comment_id, body);
+        store.audit.push(comment_changed_event(user_id, AuditAction::Create, None, &comment));
+        Ok(comment)
+    }
+
+    async fn update_comment(&self, user_id: i32, comment_id: i32, body: &str) -> Result<Option<ProcedureComment>, AppError> {
+        let mut store = self.store();
+        let Some(comment) = store.comments.iter_mut().find(|comment| comment.comment_id == comment_id && comment.deleted_at.is_none()) else {
+            return Ok(None);
+        };
+        let before = comment.body.replace(body.to_string());
+        comment.updated_at = Some(Utc::now());
+        comment.updated_by = Some(user_id);
+
+        let updated = store.comment(comment_id).ok_or_else(|| AppError::not_found("Comment does not exist."))?;
+        store.record_comment_change(user_id, AuditAction::Update, comment_id, body);
+        store.audit.push(comment_changed_event(user_id, AuditAction::Update, before.as_deref(), &updated));
+        Ok(Some(updated))
+    }
+
+    async fn delete_comment(&self, user_id: i32, comment_id: i32) -> Result<Option<ProcedureComment>, AppError> {
+        let mut store = self.store();
+        let Some(comment) = store.comments.iter_mut().find(|comment| comment.comment_id == comment_id && comment.deleted_at.is_none()) else {
+            return Ok(None);
+        };
+        comment.deleted_at = Some(Utc::now());
+        comment.deleted_by = Some(user_id);
+        let body = comment.body.clone().unwrap_or_default();
+
+        let deleted = store.comment(comment_id).ok_or_else(|| AppError::not_found("Comment does not exist."))?;
+        store.record_comment_change(user_id, AuditAction::Delete, comment_id, &body);
+        store.audit.push(comment_changed_event(user_id, AuditAction::Delete, Some(&body), &deleted));
+        Ok(Some(deleted))
+    }
+}
+
+impl PatientSearchRepo for MemoryRepository {
+    async fn search_patients(&self, filter: &PatientFilter) -> Result<Vec<PatientSearchRow>, AppError> {
+        let store = self.store();
+        let now = Utc::now();
+        let prefixed = |value: &str, prefix: &Option<String>| prefix.as_deref().is_some_and(|prefix| value.to_lowercase().starts_with(prefix));
+
+        let mut rows: Vec<PatientSearchRow> = store
+            .patients
+            .iter()
+            .filter(|patient| filter.include_inactive || patient.active)
+            .filter(|patient| {
+                filter.name.is_none()
+                    || match filter.name_rest {
+                        None => prefixed(&patient.first_name, &filter.name) || prefixed(&patient.last_name, &filter.name),
+                        Some(_) => {
+                            (prefixed(&patient.first_name, &filter.name) && prefixed(&patient.last_name, &filter.name_rest))
+                                || (prefixed(&patient.last_name, &filter.name) && prefixed(&patient.first_name, &filter.name_rest))
+                        }
+                    }
+            })
+            .filter(|patient| filter.mr_number.as_deref().is_none_or(|prefix| patient.mr_number.starts_with(prefix)))
+            .filter(|patient| {
+                filter.phone_digits.as_deref().is_none_or(|digits| {
+                    patient.phone.as_deref().is_some_and(|phone| phone_digits(phone).starts_with(digits))
+                })
+            })
+            .filter(|patient| filter.born_from.is_none_or(|from| patient.date_of_birth >= from))
+            .filter(|patient| filter.born_before.is_none_or(|before| patient.date_of_birth < before))
+            .filter(|patient| filter.gender.as_deref().is_none_or(|gender| patient.gender == gender))
+            .map(|patient| {
+                let activity_times = store.activities.iter().filter(|row| row.patient_id == patient.patient_id).map(|row| row.activity_time);
+                let mut row = PatientSearchRow {
+                    patient_id: patient.patient_id,
+                    mr_number: patient.mr_number.clone(),
+                    first_name: patient.first_name.clone(),
+                    last_name: patient.last_name.clone(),
+                    date_of_birth: patient.date_of_birth,
+                    gender: patient.gender.clone(),
+                    active: patient.active,
+                    last_visit: activity_times.clone().filter(|time| *time <= now).max(),
+                    has_appointment_today: activity_times.clone().any(|time| time.date_naive() == now.date_naive()),
+                    sort_key: String::new(),
+                };
+                row.sort_key = sort_key(filter.sort, &row, patient.created_at);
+                row
+            })
+            .filter(|row| filter.has_appointment_today.is_none_or(|today| row.has_appointment_today == today))
+            .filter(|row| filter.last_visit_from.is_none_or(|from| row.last_visit.is_some_and(|visit| visit >= from)))
+            .filter(|row| filter.last_visit_to.is_none_or(|to| row.last_visit.is_some_and(|visit| visit < to)))
+            .filter(|row| {
+                filter.after.as_ref().is_none_or(|(key, id)| {
+                    let position = (row.sort_key.as_str(), row.patient_id).cmp(&(key.as_str(), *id));
+                    position == if filter.descending { Ordering::Less } else { Ordering::Greater }
+                })
+            })
+            .collect();
+
+        rows.sort_by(|a, b| (a.sort_key.as_str(), a.patient_id).cmp(&(b.sort_key.as_str(), b.patient_id)));
+        if filter.descending {
+            rows.reverse();
+        }
+        rows.truncate(filter.limit as usize);
+        Ok(rows)
+    }
+}
+
+impl PatientMergeRepo for MemoryRepository {
+    async fn list_duplicate_candidates(&self, patient: &PatientData) -> Result<Vec<DuplicateCandidateRow>, AppError> {
+        let digits = patient.phone.as_deref().map(phone_digits).filter(|digits| digits.len() >= 4);
+
+        Ok(self
+            .store()
+            .patients
+            .iter()
+            .filter(|candidate| candidate.active && candidate.patient_id != patient.patient_id)
+            .map(|candidate| DuplicateCandidateRow {
+                patient_id: candidate.patient_id,
+                mr_number: candidate.mr_number.clone(),
+                first_name: candidate.first_name.clone(),
+                last_name: candidate.last_name.clone(),
+                date_of_birth: candidate.date_of_birth,
+                gender: candidate.gender.clone(),
+                phone_matches: digits.as_deref().is_some_and(|digits| {
+                    candidate.phone.as_deref().is_some_and(|phone| phone_digits(phone).starts_with(digits))
+                }),
+            })
+            .collect())
+    }
+
+    async fn merge_patients(&self, user_id: i32, surviving_patient_id: i32, merged_patient_id: i32) -> Result<Option<PatientMerge>, AppError> {
+        let mut store = self.store();
+        let active = |store: &MemoryStore, patient_id| store.patients.iter().any(|p| p.patient_id == patient_id && p.active);
+        if !active(&store, surviving_patient_id) || !active(&store, merged_patient_id) {
+            return Ok(None);
+        }
+
+        let mut tables = vec![];
+        for table in PATIENT_TABLES {
+            let rows = store.owned_rows(table.table, [surviving_patient_id, merged_patient_id]);
+            let plan = plan_table_merge(table.table, surviving_patient_id, &rows);
+            store.reassign(table.table, &plan.moved, surviving_patient_id);
+            store.reassign(table.table, &plan.displaced, merged_patient_id);
+            tables.push(plan);
+        }
+
+        if let Some(merged) = store.patients.iter_mut().find(|p| p.patient_id == merged_patient_id) {
+            merged.active = false;
+            merged.updated_at = Some(Utc::now());
+            merged.deactivated_at = merged.updated_at;
+        }
+
+        let merge = PatientMerge {
+            merge_id: store.next_id(),
+            surviving_patient_id,
+            merged_patient_id,
+            merged_by: user_id,
+            merged_at: Utc::now(),
+            undone_by: None,
+            undone_at: None,
+            tables,
+        };
+        store.merges.push(merge.clone());
+        store.audit.extend(merge_events(user_id, AuditAction::Create, &merge));
+        store.audit.push(AuditEvent::new(user_id, AuditAction::Update, ENTITY_PATIENT).patient(merged_patient_id).entity_id(merged_patient_id));
+        Ok(Some(merge))
+    }
+
+    async fn undo_merge(&self, user_id: i32, merge_id: i32) -> Result<Option<PatientMerge>, AppError> {
+        let mut store = self.store();
+        let Some(index) = store.merges.iter().position(|merge| merge.merge_id == merge_id && merge.undone_at.is_none()) else {
+            return Ok(None);
+        };
+
+        let pair = [store.merges[index].surviving_patient_id, store.merges[index].merged_patient_id];
+        if !store.patients.iter().any(|p| p.patient_id == pair[0] && p.active) {
+            return Err(AppError::conflict("The surviving patient has since been merged or deactivated. Undo that first."));
+        }
+        let later = store.merges.iter().any(|later| {
+            later.merge_id > merge_id
+                && later.undone_at.is_none()
+                && (pair.contains(&later.surviving_patient_id) || pair.contains(&later.merged_patient_id))
+        });
+        if later {
+            return Err(AppError::conflict("A later merge involves one of these patients. Undo that first."));
+        }
+        for TableMove { table_name, moved, displaced } in &store.merges[index].tables {
+            let owners: HashMap<i32, i32> = store.owned_rows(table_name, pair).into_iter().map(|row| (row.row_id, row.patient_id)).collect();
+            let misplaced = |row_ids: &[i32], owner| row_ids.iter().any(|row_id| owners.get(row_id).is_some_and(|current| *current != owner));
+            if misplaced(moved, pair[0]) || misplaced(displaced, pair[1]) {
+                return Err(AppError::conflict(&format!("Some {} rows no longer belong to the patient they were merged into.", table_name)));
+            }
+        }
+
+        let merge = &mut store.merges[index];
+        merge.undone_by = Some(user_id);
+        merge.undone_at = Some(Utc::now());
+        let merge = merge.clone();
+        for TableMove { table_name, moved, displaced } in &merge.tables {
+            store.reassign(table_name, moved, merge.merged_patient_id);
+            store.reassign(table_name, displaced, merge.surviving_patient_id);
+        }
+
+        if let Some(merged) = store.patients.iter_mut().find(|p| p.patient_id == merge.merged_patient_id) {
+            merged.active = true;
+            merged.updated_at = Some(Utc::now());
+            merged.deactivated_at = None;
+        }
+        store.audit.extend(merge_events(user_id, AuditAction::Update, &merge));
+        Ok(Some(merge))
+    }
+}
+
+impl PatientPhotoRepo for MemoryRepository {
+    async fn save_photo(&self, user_id: i32, patient_id: i32, photo: &ProcessedPhoto) -> Result<Option<DateTime<Utc>>, AppError> {
+        let mut store = self.store();
+        let Some(patient) = store.patients.iter_mut().find(|patient| patient.patient_id == patient_id && patient.active) else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        patient.patient_photo = Some(photo.photo.clone());
+        patient.updated_at = Some(now);
+        store.thumbnails.retain(|thumbnail| thumbnail.patient_id != patient_id);
+        store.thumbnails.push(PatientPhoto { patient_id, data_url: photo.thumbnail.clone(), updated_at: Some(now) });
+        store.audit.push(photo_saved_event(user_id, patient_id, photo));
+        Ok(Some(now))
+    }
+
+    async fn find_photo(&self, patient_id: i32) -> Result<Option<PatientPhoto>, AppError> {
+        let store = self.store();
+        let updated_at = store.thumbnails.iter().find(|thumbnail| thumbnail.patient_id == patient_id).and_then(|thumbnail| thumbnail.updated_at);
+
+        Ok(store
+            .patients
+            .iter()
+            .find(|patient| patient.patient_id == patient_id)
+            .and_then(|patient| patient.patient_photo.clone())
+            .map(|data_url| PatientPhoto { patient_id, data_url, updated_at }))
+    }
+
+    async fn list_thumbnails(&self, patient_ids: &[i32]) -> Result<Vec<PatientPhoto>, AppError> {
+        let mut thumbnails: Vec<PatientPhoto> = self
+            .store()
+            .thumbnails
+            .iter()
+            .filter(|thumbnail| patient_ids.contains(&thumbnail.patient_id))
+            .cloned()
+            .collect();
+        thumbnails.sort_by_key(|thumbnail| thumbnail.patient_id);
+        Ok(thumbnails)
+    }
+}
+
+impl MedicalHistoryRepo for MemoryRepository {
+    async fn list_history_entries(&self, patient_id: i32) -> Result<Vec<HistoryEntry>, AppError> {
+        Ok(self
+            .store()
+            .history_entries
+            .iter()
+            .filter(|entry| entry.patient_id == patient_id && entry.removed_at.is_none())
+            .cloned()
+            .collect())
+    }
+
+    async fn list_history_changes(&self, patient_id: i32) -> Result<Vec<HistoryChange>, AppError> {
+        let mut changes: Vec<HistoryChange> = self
+            .store()
+            .history_changes
+            .iter()
+            .filter(|(owner, _)| *owner == patient_id)
+            .map(|(_, change)| change.clone())
+            .collect();
+        changes.sort_by_key(|change| Reverse((change.changed_at, change.change_id)));
+        Ok(changes)
+    }
+
+    async fn create_history_entry(&self, user_id: i32, patient_id: i32, details: &HistoryDetails) -> Result<HistoryEntry, AppError> {
+        let mut store = self.store();
+        if !store.has_patient(patient_id) {
+            return Err(missing_reference());
+        }
+
+        let entry = HistoryEntry {
+            entry_id: store.next_id(),
+            patient_id,
+            details: details.clone(),
+            created_at: Utc::now(),
+            created_by: Some(user_id),
+            created_by_name: store.user_name(Some(user_id)),
+            updated_at: None,
+            updated_by: None,
+            updated_by_name: None,
+            removed_at: None,
+            removed_by: None,
+        };
+        store.history_entries.push(entry.clone());
+        store.record_history_change(user_id, AuditAction::Create, &entry);
+        store.audit.push(history_changed_event(user_id, AuditAction::Create, None, &entry));
+        Ok(entry)
+    }
+
+    async fn update_history_entry(&self, user_id: i32, entry_id: i32, details: &HistoryDetails) -> Result<Option<HistoryEntry>, AppError> {
+        let mut store = self.store();
+        let updated_by_name = store.user_name(Some(user_id));
+        let Some(entry) = store
+            .history_entries
+            .iter_mut()
+            .find(|entry| entry.entry_id == entry_id && entry.details.kind() == details.kind() && entry.removed_at.is_none())
+        else {
+            return Ok(None);
+        };
+
+        let before = entry.clone();
+        entry.details = details.clone();
+        entry.updated_at = Some(Utc::now());
+        entry.updated_by = Some(user_id);
+        entry.updated_by_name = updated_by_name;
+        let after = entry.clone();
+        store.record_history_change(user_id, AuditAction::Update, &after);
+        store.audit.push(history_changed_event(user_id, AuditAction::Update, Some(&before), &after));
+        Ok(Some(after))
+    }
+
+    async fn remove_history_entry(&self, user_id: i32, entry_id: i32, kind: HistoryKind) -> Result<Option<HistoryEntry>, AppError> {
+        let mut store = self.store();
+        let Some(entry) = store
+            .history_entries
+            .iter_mut()
+            .find(|entry| entry.entry_id == entry_id && entry.details.kind() == kind && entry.removed_at.is_none())
+        else {
+            return Ok(None);
+        };
+
+        entry.removed_at = Some(Utc::now());
+        entry.removed_by = Some(user_id);
+        let removed = entry.clone();
+        store.record_history_change(user_id, AuditAction::Delete, &removed);
+        store.audit.push(history_changed_event(user_id, AuditAction::Delete, Some(&removed), &removed));
+        Ok(Some(removed))
+    }
+}
+
+impl TimelineRepo for MemoryRepository {
+    async fn patient_exists(&self, patient_id: i32) -> Result<bool, AppError> {
+        Ok(self.store().patients.iter().any(|patient| patient.patient_id == patient_id))
+    }
+
+    async fn list_timeline_events(&self, filter: &TimelineFilter) -> Result<Vec<TimelineEvent>, AppError> {
+        let patient_id = filter.patient_id;
+        let mut events = activity_events(self.list_patient_procedures(patient_id).await?);
+        let changes = self.list_history_changes(patient_id).await?;
+
+        let store = self.store();
+        events.extend(comment_events(
+            store
+                .comments
+                .iter()
+                .filter(|comment| store.activity_patient(comment.activity_id) == Some(patient_id))
+                .filter_map(|comment| store.comment(comment.comment_id))
+                .collect(),
+        ));
+        let owned = |owner: Option<i32>| owner == Some(patient_id);
+        events.extend(measurement_events(
+            store.visions.iter().filter(|row| owned(row.patient_id)).cloned().collect(),
+            store.refractions.iter().filter(|row| owned(row.patient_id)).cloned().collect(),
+            store.eye_measurements.iter().filter(|row| owned(row.patient_id)).cloned().collect(),
+        ));
+        events.extend(record_events(
+            store.appointments.iter().map(|(appointment, _)| appointment).filter(|row| owned(row.patient_id)).cloned().collect(),
+            store
+                .alerts
+                .iter()
+                .filter(|row| owned(row.patient_id))
+                .map(|alert| Alert {
+                    issued_for_name: store.user_name(alert.issued_for),
+                    issued_by_name: store.user_name(alert.issued_by),
+                    ..alert.clone()
+                })
+                .collect(),
+            changes,
+        ));
+
+        filter.retain(&mut events);
+        Ok(events)
+    }
+}
+
+impl VisionRepo for MemoryRepository {
+    async fn find_vision(&self, patient_id: i32, side: &str, value_type: &str) -> Result<Option<VisionData>, AppError> {
+        Ok(self
+            .store()
+            .visions
+            .iter()
+            .find(|row| row.patient_id == Some(patient_id) && row.side == side && row.value_type == value_type)
+            .cloned())
+    }
+
+    async fn find_refraction(
+        &self,
+        patient_id: i32,
+        side: &str,
+        value_type: &str,
+        vision_type: &str
+    ) -> Result<Option<RefractionData>, AppError> {
+        Ok(self
+            .store()
+            .refractions
+            .iter()
+            .find(|row| {
+                row.patient_id == Some(patient_id)
+                    && row.side == side
+                    && row.value_type == value_type
+                    && row.vision_type == vision_type
+            })
+            .cloned())
+    }
+
+    async fn find_eye_measurement(&self, patient_id: i32, side: &str) -> Result<Option<EyeMeasurementData>, AppError> {
+        Ok(self
+            .store()
+            .eye_measurements
+            .iter()
+            .find(|row| row.patient_id == Some(patient_id) && row.side == side)
+            .cloned())
+    }
+
+    async fn upsert_vision(&self, user_id: i32, input: &VisionInput) -> Result<VisionData, AppError> {
+        let mut store = self.store();
+        if !store.has_patient(input.patient_id) {
+            return Err(missing_reference());
+        }
+
+        let existing = store.visions.iter().position(|row| {
+            row.patient_id == Some(input.patient_id) && row.side == input.side && row.value_type == input.value_type
+        });
+        let before = existing.map(|index| store.visions[index].clone());
+        let after = match existing {
+            Some(index) => {
+                let row = &mut store.visions[index];
+                row.near_vision = Some(input.near_vision.clone());
+                row.distant_vision = Some(input.distant_vision.clone());
+                row.updated_at = Some(Utc::now());
+                row.updated_by = Some(user_id);
+                row.clone()
+            }
+            None => {
+                let row = VisionData {
+                    vision_id: store.next_id(),
+                    patient_id: Some(input.patient_id),
+                    near_vision: Some(input.near_vision.clone()),
+                    distant_vision: Some(input.distant_vision.clone()),
+                    side: input.side.clone(),
+                    value_type: input.value_type.clone(),
+                    created_at: Some(Utc::now()),
+                    created_by: Some(user_id),
+                    updated_at: None,
+                    updated_by: None,
+                };
+                store.visions.push(row.clone());
+                row
+            }
+        };
+
+        let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+        store.audit.push(
+            AuditEvent::new(user_id, action, ENTITY_VISION)
+                .patient(input.patient_id)
+                .entity_id(after.vision_id)
+                .before(before.as_ref())
+                .after(&after),
+        );
+        Ok(after)
+    }
+
+    async fn upsert_refraction(&self, user_id: i32, input: &RefractionInput) -> Result<RefractionData, AppError> {
+        let mut store = self.store();
+        if !store.has_patient(input.patient_id) {
+            return Err(missing_reference());
+        }
+
+        let existing = store.refractions.iter().position(|row| {
+            row.patient_id == Some(input.patient_id)
+                && row.side == input.side
+                && row.value_type == input.value_type
+                && row.vision_type == input.vision_type
+        });
+        let before = existing.map(|index| store.refractions[index].clone());
+        let after = match existing {
+            Some(index) => {
+                let row = &mut store.refractions[index];
+                row.spherical = Some(input.spherical.clone());
+                row.cylindrical = Some(input.cylindrical.clone());
+                row.axis = Some(input.axis.clone());
+                row.updated_at = Some(Utc::now());
+                row.updated_by = Some(user_id);
+                row.clone()
+            }
+            None => {
+                let row = RefractionData {
+                    refraction_id: store.next_id(),
+                    patient_id: Some(input.patient_id),
+                    spherical: Some(input.spherical.clone()),
+                    cylindrical: Some(input.cylindrical.clone()),
+                    axis: Some(input.axis.clone()),
+                    side: input.side.clone(),
+                    value_type: input.value_type.clone(),
+                    vision_type: input.vision_type.clone(),
+                    created_at: Some(Utc::now()),
+                    created_by: Some(user_id),
+                    updated_at: None,
+                    updated_by: None,
+                };
+                store.refractions.push(row.clone());
+                row
+            }
+        };
+
+        let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+        store.audit.push(
+            AuditEvent::new(user_id, action, ENTITY_REFRACTION)
+                .patient(input.patient_id)
+                .entity_id(after.refraction_id)
+                .before(before.as_ref())
+                .after(&after),
+        );
+        Ok(after)
+    }
+
+    async fn upsert_eye_measurement(&self, user_id: i32, input: &EyeMeasurementInput) -> Result<EyeMeasurementData, AppError> {
+        let mut store = self.store();
+        if !store.has_patient(input.patient_id) {
+            return Err(missing_reference());
+        }
+
+        let existing = store
+            .eye_measurements
+            .iter()
+            .position(|row| row.patient_id == Some(input.patient_id) && row.side == input.side);
+        let before = existing.map(|index| store.eye_measurements[index].clone());
+        let after = match existing {
+            Some(index) => {
+                let row = &mut store.eye_measurements[index];
+                row.iop_at = Some(input.iop_at.clone());
+                row.iop_nct = Some(input.iop_nct.clone());
+                row.cct = Some(input.cct.clone());
+                row.tond = Some(input.tond.clone());
+                row.updated_at = Some(Utc::now());
+                row.updated_by = Some(user_id);
+                row.clone()
+            }
+            None => {
+                let row = EyeMeasurementData {
+                    measurement_id: store.next_id(),
+                    patient_id: Some(input.patient_id),
+                    iop_at: Some(input.iop_at.clone()),
+                    iop_nct: Some(input.iop_nct.clone()),
+                    cct: Some(input.cct.clone()),
+                    tond: Some(input.tond.clone()),
+                    side: input.side.clone(),
+                    created_at: Some(Utc::now()),
+                    created_by: Some(user_id),
+                    updated_at: None,
+                    updated_by: None,
+                };
+                store.eye_measurements.push(row.clone());
+                row
+            }
+        };
+
+        let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+        store.audit.push(
+            AuditEvent::new(user_id, action, ENTITY_EYE_MEASUREMENT)
+                .patient(input.patient_id)
+                .entity_id(after.measurement_id)
+                .before(before.as_ref())
+                .after(&after),
+        );
+        Ok(after)
+    }
+}
+
+impl MessagingRepo for MemoryRepository {
+    async fn list_unread(&self, user_id: i32) -> Result<Vec<MessageData>, AppError> {
+        let store = self.store();
+
+        Ok(store
+            .message_statuses
+            .iter()
+            .filter(|status| status.status == "delivered" && status.recipient_id == user_id)
+            .filter_map(|status| {
+                let message = store.messages.iter().find(|message| message.message_id == status.message_id)?;
+                Some(MessageData {
+                    message_id: Some(message.message_id),
+                    conversation_id: message.conversation_id,
+                    sender_id: message.sender_id,
+                    recipient_id: Some(status.recipient_id),
+                    content: message.content.clone(),
+                    status: Some(status.status.clone()),
+                    created_at: message.created_at,
+                })
+            })
+            .collect())
+    }
+
+    async fn is_participant(&self, conversation_id: i32, user_id: i32) -> Result<bool, AppError> {
+        Ok(self.store().conversations.iter().any(|conversation| {
+            conversation.conversation_id == conversation_id
+                && (conversation.user1 == Some(user_id) || conversation.user2 == Some(user_id))
+        }))
+    }
+
+    async fn send_message(&self, conversation_id: i32, sender_id: i32, content: &str) -> Result<Message, AppError> {
+        let mut store = self.store();
+        let (user1, user2) = store
+            .conversations
+            .iter()
+            .find(|conversation| conversation.conversation_id == conversation_id)
+            .map(|conversation| (conversation.user1, conversation.user2))
+            .ok_or_else(missing_reference)?;
+        let recipient_id = if user1 == Some(sender_id) { user2 } else { user1 }.ok_or_else(missing_reference)?;
+
+        let message = Message {
+            message_id: store.next_id(),
+            conversation_id: Some(conversation_id),
+            sender_id: Some(sender_id),
+            content: Some(content.to_string()),
+            created_at: Some(Utc::now()),
+        };
+        store.messages.push(message.clone());
+        if let Some(conversation) = store
+            .conversations
+            .iter_mut()
+            .find(|conversation| conversation.conversation_id == conversation_id)
+        {
+            conversation.last_message = Some(message.message_id);
+        }
+        store.message_statuses.push(MessageStatusRow {
+            message_id: message.message_id,
+            recipient_id,
+            status: "delivered".to_string(),
+        });
+        Ok(message)
+    }
+
+    async fn list_messages(&self, conversation_id: i32) -> Result<Vec<MessageData>, AppError> {
+        let store = self.store();
+        let mut messages: Vec<&Message> = store
+            .messages
+            .iter()
+            .filter(|message| message.conversation_id == Some(conversation_id))
+            .collect();
+        messages.sort_by_key(|message| Reverse((message.created_at, message.message_id)));
+
+        Ok(messages
+            .into_iter()
+            .map(|message| {
+                let status = store.message_statuses.iter().find(|status| status.message_id == message.message_id);
+                MessageData {
+                    message_id: Some(message.message_id),
+                    conversation_id: message.conversation_id,
+                    sender_id: message.sender_id,
+                    recipient_id: Some(status.map_or(-1, |status| status.recipient_id)),
+                    content: message.content.clone(),
+                    status: Some(status.map_or("delivered".to_string(), |status| status.status.clone())),
+                    created_at: message.created_at,
+                }
+            })
+            .collect())
+    }
+
+    async fn open_conversation(&self, user1: i32, user2: i32) -> Result<Conversation, AppError> {
+        let mut store = self.store();
+        if user1 >= user2 {
+            return Err(AppError::validation("recipient_id", "Conversation users must be ordered."));
+        }
+        if !store.has_user(user1) || !store.has_user(user2) {
+            return Err(missing_reference());
+        }
+
+        let existing = store
+            .conversations
+            .iter()
+            .find(|conversation| conversation.user1 == Some(user1) && conversation.user2 == Some(user2))
+            .cloned();
+        let conversation = match existing {
+            Some(conversation) => conversation,
+            None => {
+                let conversation = Conversation {
+                    conversation_id: store.next_id(),
+                    user1: Some(user1),
+                    user2: Some(user2),
+                    last_message: None,
+                    created_at: Some(Utc::now()),
+                    last_message_sender_id: None,
+                    last_message_content: None,
+                    last_message_created_at: None,
+                };
+                store.conversations.push(conversation.clone());
+                conversation
+            }
+        };
+
+        Ok(store.conversation_view(&conversation))
+    }
+
+    async fn list_conversations(&self, user_id: i32) -> Result<Vec<Conversation>, AppError> {
+        let store = self.store();
+        let mut conversations: Vec<Conversation> = store
+            .conversations
+            .iter()
+            .filter(|conversation| conversation.user1 == Some(user_id) || conversation.user2 == Some(user_id))
+            .map(|conversation| store.conversation_view(conversation))
+            .collect();
+        // ORDER BY ... DESC puts conversations without messages first
+        conversations.sort_by(|a, b| match (a.last_message_created_at, b.last_message_created_at) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a), Some(b)) => b.cmp(&a),
+        });
+
+        Ok(conversations)
+    }
+}
+
+impl AppointmentRepo for MemoryRepository {
+    async fn list_appointments(&self, user_id: i32) -> Result<Vec<Appointment>, AppError> {
+        let mut appointments: Vec<Appointment> = self
+            .store()
+            .appointments
+            .iter()
+            .filter(|(_, users)| users.contains(&user_id))
+            .map(|(appointment, _)| appointment.clone())
+            .collect();
+        appointments.sort_by_key(|appointment| Reverse(appointment.appointment_time));
+
+        Ok(appointments)
+    }
+
+    async fn create_appointment(&self, created_by: i32, appointment: &NewAppointment) -> Result<Appointment, AppError> {
+        let mut store = self.store();
+        let known_patient = appointment.patient_id.is_none_or(|patient_id| store.has_patient(patient_id));
+        if !store.has_user(created_by) || !appointment.users.iter().all(|user_id| store.has_user(*user_id)) || !known_patient {
+            return Err(missing_reference());
+        }
+
+        let created = Appointment {
+            appointment_id: store.next_id(),
+            description: Some(appointment.description.clone()),
+            appointment_time: Some(appointment.appointment_time),
+            appointment_duration: Some(appointment.appointment_duration * 1_000_000),
+            created_by: Some(created_by),
+            created_at: Some(Utc::now()),
+            patient_id: appointment.patient_id,
+        };
+        store.appointments.push((created.clone(), appointment.users.clone()));
+        Ok(created)
+    }
+}
+
+impl AlertRepo for MemoryRepository {
+    async fn list_alerts(&self, user_id: i32) -> Result<Vec<Alert>, AppError> {
+        let store = self.store();
+
+        Ok(store
+            .alerts
+            .iter()
+            .filter(|alert| alert.issued_for == Some(user_id) || alert.issued_by == Some(user_id))
+            .map(|alert| Alert {
+                issued_for_name: store.user_name(alert.issued_for),
+                issued_by_name: store.user_name(alert.issued_by),
+                ..alert.clone()
+            })
+            .collect())
+    }
+
+    async fn create_alert(&self, issued_by: i32, alert: &NewAlert) -> Result<Alert, AppError> {
+        let mut store = self.store();
+        let known_patient = alert.patient_id.is_none_or(|patient_id| store.has_patient(patient_id));
+        if !store.has_user(issued_by) || !store.has_user(alert.issued_for) || !known_patient {
+            return Err(missing_reference());
+        }
+
+        let created = Alert {
+            alert_id: store.next_id(),
+            priority_level: Some(alert.priority_level.clone()),
+            title: Some(alert.title.clone()),
+            message: Some(alert.message.clone()),
+            issued_for: Some(alert.issued_for),
+            issued_for_name: None,
+            issued_by: Some(issued_by),
+            issued_by_name: None,
+            status: Some("delivered".to_string()),
+            created_at: Some(Utc::now()),
+            patient_id: alert.patient_id,
+        };
+        store.alerts.push(created.clone());
+        Ok(created)
+    }
+}
+
+impl UserRepo for MemoryRepository {
+    async fn list_users(&self, role: Option<&str>, status: Option<&str>) -> Result<Vec<UserProfile>, AppError> {
+        let mut users: Vec<UserProfile> = self
+            .store()
+            .users
+            .iter()
+            .filter(|user| role.is_none_or(|role| user.role == role))
+            .filter(|user| status.is_none_or(|status| user.status == status))
+            .cloned()
+            .collect();
+        users.sort_by(|a, b| (&a.last_name, &a.first_name, a.user_id).cmp(&(&b.last_name, &b.first_name, b.user_id)));
+
+        Ok(users)
+    }
+
+    async fn list_active_doctors(&self) -> Result<Vec<UserProfile>, AppError> {
+        Ok(self
+            .store()
+            .users
+            .iter()
+            .filter(|user| user.role == "DOCTOR" && user.status == "ACTIVE")
+            .cloned()
+            .collect())
+    }
+
+    async fn create_user(&self, user: &NewUser) -> Result<UserProfile, AppError> {
+        let mut store = self.store();
+        if store.email_taken(&user.email, None) {
+            return Err(email_conflict());
+        }
+
+        let created = UserProfile {
+            user_id: store.next_id(),
+            role: user.role.clone(),
+            first_name: user.first_name.clone(),
+            last_name: user.last_name.clone(),
+            email: user.email.clone(),
+            status: user.status.clone(),
+            must_reset_password: user.must_reset_password,
+            locked_until: None,
+            created_at: Some(Utc::now()),
+        };
+        store.users.push(created.clone());
+        Ok(created)
+    }
+
+    async fn set_status(&self, user_id: i32, from: &[&str], to: &str) -> Result<Option<UserProfile>, AppError> {
+        let mut store = self.store();
+
+        Ok(store.user_mut(user_id).filter(|user| from.contains(&user.status.as_str())).map(|user| {
+            user.status = to.to_string();
+            user.clone()
+        }))
+    }
+
+    async fn set_role(&self, user_id: i32, role: &str) -> Result<Option<UserProfile>, AppError> {
+        Ok(self.store().user_mut(user_id).map(|user| {
+            user.role = role.to_string();
+            user.clone()
+        }))
+    }
+
+    async fn reset_password(&self, user_id: i32, _password_hash: &str) -> Result<Option<UserProfile>, AppError> {
+        Ok(self.store().user_mut(user_id).map(|user| {
+            user.must_reset_password = true;
+            user.locked_until = None;
+            user.clone()
+        }))
+    }
+
+    async fn update_user(&self, user_id: i32, first_name: &str, last_name: &str, email: &str) -> Result<Option<UserProfile>, AppError> {
+        let mut store = self.store();
+        if store.email_taken(email, Some(user_id)) {
+            return Err(email_conflict());
+        }
+
+        Ok(store.user_mut(user_id).map(|user| {
+            user.first_name = first_name.to_string();
+            user.last_name = last_name.to_string();
+            user.email = email.to_string();
+            user.clone()
+        }))
+    }
+
+    async fn unlock_user(&self, user_id: i32) -> Result<Option<UserProfile>, AppError> {
+        Ok(self.store().user_mut(user_id).map(|user| {
+            user.locked_until = None;
+            user.clone()
+        }))
+    }
+
+    async fn revoke_sessions(&self, user_id: i32, reason: &str) -> Result<u64, AppError> {
+        self.store().revoked_sessions.push((user_id, reason.to_string()));
+        Ok(0)
+    }
+}
@@ -3,169 +3,338 @@
 // Dependencies
 use std::time::Duration;
 use chrono::{DateTime, Utc};
-use sqlx::{Postgres, Pool};
 use serde::{Deserialize, Serialize};
+use tauri::Manager;
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
 use crate::db::DatabaseState;
-use crate::auth::get_user_from_token;
+use crate::error::AppError;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{MessagingRepo, PgRepository};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct MessageData {
-    message_id: Option<i32>,
-    conversation_id: Option<i32>,
-    sender_id: Option<i32>,
-    recipient_id: Option<i32>,
-    content: Option<String>,
-    status: Option<String>,
-    created_at: Option<DateTime<Utc>>
+    pub message_id: Option<i32>,
+    pub conversation_id: Option<i32>,
+    pub sender_id: Option<i32>,
+    pub recipient_id: Option<i32>,
+    pub content: Option<String>,
+    pub status: Option<String>,
+    pub created_at: Option<DateTime<Utc>>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Conversation {
-    conversation_id: i32,
-    user1: Option<i32>,
-    user2: Option<i32>,
-    last_message: Option<i32>,
-    created_at: Option<DateTime<Utc>>,
-    last_message_sender_id: Option<i32>,
-    last_message_content: Option<String>,
-    last_message_created_at: Option<DateTime<Utc>>
+    pub conversation_id: i32,
+    pub user1: Option<i32>,
+    pub user2: Option<i32>,
+    pub last_message: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_message_sender_id: Option<i32>,
+    pub last_message_content: Option<String>,
+    pub last_message_created_at: Option<DateTime<Utc>>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Message {
-    message_id: i32,
-    conversation_id: Option<i32>, 
-    sender_id: Option<i32>,
-    content: Option<String>,
-    created_at: Option<DateTime<Utc>>
+    pub message_id: i32,
+    pub conversation_id: Option<i32>,
+    pub sender_id: Option<i32>,
+    pub content: Option<String>,
+    pub created_at: Option<DateTime<Utc>>
+}
+
+
+impl MessagingRepo for PgRepository<'_> {
+    async fn list_unread(&self, user_id: i32) -> Result<Vec<MessageData>, AppError> {
+        sqlx::query_as!(
+            MessageData,
+            r#"
+            SELECT
+                m.message_id,
+                m.conversation_id,
+                m.sender_id,
+                ms.recipient_id,
+                m.content,
+                ms.status,
+                m.created_at
+            FROM
+                message_status ms
+            LEFT JOIN
+                messages m
+            ON
+                ms.message_id = m.message_id
+            WHERE
+                ms.status = 'delivered'
+            AND
+                ms.recipient_id = $1
+            "#,
+            &user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching unread messages", e))
+    }
+
+    async fn is_participant(&self, conversation_id: i32, user_id: i32) -> Result<bool, AppError> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM conversations
+                WHERE conversation_id = $1
+                AND (user1 = $2 OR user2 = $2)
+            ) as "is_participant!"
+            "#,
+            conversation_id,
+            user_id
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while checking conversation access", e))
+    }
+
+    async fn send_message(&self, conversation_id: i32, sender_id: i32, content: &str) -> Result<Message, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let message: Message = sqlx::query_as!(
+            Message,
+            r#"
+            INSERT INTO
+                messages (conversation_id, sender_id, content)
+            VALUES
+                ($1, $2, $3)
+            RETURNING
+                *
+            "#,
+            conversation_id,
+            sender_id,
+            content
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while sending messages", e))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE
+                conversations
+            SET
+                last_message = $1
+            WHERE
+                conversation_id = $2
+            RETURNING
+                *
+            "#,
+            &message.message_id,
+            &conversation_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating conversation history", e))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO
+                message_status (message_id, recipient_id, status)
+            VALUES
+                ($1, (SELECT user2
+            FROM
+                conversations
+            WHERE
+                conversation_id = $2 AND user1 = $3
+            UNION
+            SELECT
+                user1
+            FROM
+                conversations
+            WHERE
+                conversation_id = $2 AND user2 = $3), 'delivered')
+            "#,
+            message.message_id,
+            conversation_id,
+            sender_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while delivering message", e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(message)
+    }
+
+    async fn list_messages(&self, conversation_id: i32) -> Result<Vec<MessageData>, AppError> {
+        sqlx::query_as!(
+            MessageData,
+            r#"
+            SELECT
+                m.message_id,
+                m.conversation_id,
+                m.sender_id,
+                COALESCE(ms.recipient_id, -1) as "recipient_id?",
+                m.content,
+                COALESCE(ms.status, 'delivered') as "status?",
+                m.created_at
+            FROM messages m
+            LEFT JOIN message_status ms
+                ON m.message_id = ms.message_id
+            WHERE m.conversation_id = $1
+            ORDER BY m.created_at DESC
+            "#,
+            conversation_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Message fetch error", e))
+    }
+
+    async fn open_conversation(&self, user1: i32, user2: i32) -> Result<Conversation, AppError> {
+        sqlx::query_as!(
+            Conversation,
+            r#"
+            WITH inserted_or_updated AS (
+                INSERT INTO conversations (user1, user2)
+                VALUES ($1, $2)
+                ON CONFLICT (user1, user2)
+                DO UPDATE SET user1 = EXCLUDED.user1  -- No-op update
+                RETURNING *
+            )
+            SELECT
+                c.conversation_id,
+                c.user1,
+                c.user2,
+                c.last_message,
+                c.created_at,
+                m.sender_id AS "last_message_sender_id?",
+                m.content AS "last_message_content?",
+                m.created_at AS "last_message_created_at?"
+            FROM inserted_or_updated c
+            LEFT JOIN messages m ON c.last_message = m.message_id
+            "#,
+            user1,
+            user2
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| AppError::database("Database error", e))
+    }
+
+    async fn list_conversations(&self, user_id: i32) -> Result<Vec<Conversation>, AppError> {
+        sqlx::query_as!(
+            Conversation,
+            r#"
+            SELECT
+                c.conversation_id,
+                c.user1,
+                c.user2,
+                c.last_message,
+                c.created_at,
+                m.sender_id AS "last_message_sender_id?",
+                m.content AS "last_message_content?",
+                m.created_at AS "last_message_created_at?"
+            FROM
+                conversations c
+            LEFT JOIN
+                messages m
+            ON
+                c.last_message = m.message_id
+            WHERE
+                c.user1 = $1
+            OR
+                c.user2 = $1
+            ORDER BY
+                m.created_at DESC
+            "#,
+            &user_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while getting conversations", e))
+    }
+}
+
+// Check that the user is one of the two participants of a conversation
+async fn ensure_participant<R: MessagingRepo>(repo: &R, conversation_id: i32, user_id: i32) -> Result<(), AppError> {
+    if !repo.is_participant(conversation_id, user_id).await? {
+        return Err(AppError::forbidden());
+    }
+
+    Ok(())
+}
+
+// Get unread messages for a user
+pub async fn list_unread_messages<R: MessagingRepo>(repo: &R, user: &AuthUser) -> Result<Vec<MessageData>, AppError> {
+    require(user, Permission::Messaging)?;
+
+    repo.list_unread(user.user_id).await
+}
+
+// Send a message to the other participant of a conversation
+pub async fn post_message<R: MessagingRepo>(
+    repo: &R,
+    user: &AuthUser,
+    conversation_id: i32,
+    content: &str
+) -> Result<Message, AppError> {
+    require(user, Permission::Messaging)?;
+    ensure_participant(repo, conversation_id, user.user_id).await?;
+
+    repo.send_message(conversation_id, user.user_id, content).await
+}
+
+// Get the messages of a conversation, newest first
+pub async fn list_conversation_messages<R: MessagingRepo>(
+    repo: &R,
+    user: &AuthUser,
+    conversation_id: i32
+) -> Result<Vec<MessageData>, AppError> {
+    require(user, Permission::Messaging)?;
+    ensure_participant(repo, conversation_id, user.user_id).await?;
+
+    repo.list_messages(conversation_id).await
 }
 
-// Get unread messages for currently logged in user
-async fn get_unread_messages(pool: tokio::sync::MutexGuard<'_, Pool<Postgres>>, token: String) -> Result<Vec<MessageData>, String> {
-    let user = match get_user_from_token(token) {
-        Ok(user) => user,
-        Err(err) => return Err(format!("Forbidden."))
-    };
-
-    match sqlx::query_as!(
-        MessageData,
-        r#"
-        SELECT
-            m.message_id,
-            m.conversation_id,
-            m.sender_id,
-            ms.recipient_id,
-            m.content,
-            ms.status,
-            m.created_at
-        FROM
-            message_status ms
-        LEFT JOIN
-            messages m
-        ON
-            ms.message_id = m.message_id
-        WHERE
-            ms.status = 'delivered'
-        "#
-    )
-    .fetch_all(&*pool)
-    .await {
-        Ok(unread_messages) => Ok(unread_messages),
-        Err(err) => Err(format!("Error while fetching unread messages: {}", err))
+// Get the conversation with another user, starting it on first use
+pub async fn open_conversation<R: MessagingRepo>(repo: &R, user: &AuthUser, recipient_id: i32) -> Result<Conversation, AppError> {
+    require(user, Permission::Messaging)?;
+
+    if user.user_id == recipient_id {
+        return Err(AppError::validation("recipient_id", "Cannot start a conversation with self."));
     }
+
+    let smaller_id = std::cmp::min(user.user_id, recipient_id);
+    let larger_id = std::cmp::max(user.user_id, recipient_id);
+
+    repo.open_conversation(smaller_id, larger_id).await
+}
+
+// Get all conversations of a user
+pub async fn list_conversations<R: MessagingRepo>(repo: &R, user: &AuthUser) -> Result<Vec<Conversation>, AppError> {
+    require(user, Permission::Messaging)?;
+
+    repo.list_conversations(user.user_id).await
 }
 
 // Send message
 #[tauri::command]
 pub async fn send_message(
-    state: tauri::State<'_, DatabaseState>, 
-    token: String, 
-    conversation_id: i32, 
+    state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
+    conversation_id: i32,
     content: String
-) -> Result<Message, String> {
-    let user = match get_user_from_token(token) {
-        Ok(user) => user,
-        Err(err) => return Err(format!("Forbidden."))
-    };
-
-    let pool = state.pool.lock().await;
-
-    eprintln!("Test.");
-
-    let message: Message = sqlx::query_as!(
-        Message,
-        r#"
-        INSERT INTO
-            messages (conversation_id, sender_id, content)
-        VALUES 
-            ($1, $2, $3)
-        RETURNING
-            *
-        "#,
-        conversation_id,
-        user.user_id,
-        content
-    )
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| format!("Error while sending messages: {}", e)).unwrap();
-
-    eprintln!("message: ");
-
-    match sqlx::query!(
-        r#"
-        UPDATE
-            conversations
-        SET 
-            last_message = $1
-        WHERE
-            conversation_id = $2
-        RETURNING
-            *
-        "#,
-        &message.message_id,
-        &conversation_id
-    )
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| format!("Error while updating conversation history: {}", e)) {
-        Ok(_) => {},
-        Err(err) => {
-            return Err(err);
-        }
-    };
-
-    eprintln!("Second query done.");
-
-    sqlx::query!(
-        r#"
-        INSERT INTO 
-            message_status (message_id, recipient_id, status)
-        VALUES
-            ($1, (SELECT user2 
-        FROM 
-            conversations 
-        WHERE
-            conversation_id = $2 AND user1 = $3
-        UNION
-        SELECT 
-            user1 
-        FROM 
-            conversations 
-        WHERE 
-            conversation_id = $2 AND user2 = $3), 'delivered')
-        "#,
-        message.message_id,
-        conversation_id,
-        user.user_id
-    )
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| format!(""));
-
-    eprintln!("Third query done.");
-
-    Ok(message)
+) -> Result<Message, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    post_message(&PgRepository::new(pool, &config.keyring), &user, conversation_id, &content).await
 }
 
 // For real time notifications
@@ -173,17 +342,27 @@ pub async fn send_message(
 pub async fn poll_messages(
     app: tauri::AppHandle,
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String
-) -> Result<(), String> {
-    let user = get_user_from_token(token.clone())?;
-    let app_clone = app.clone();  
+) -> Result<(), AppError> {
     let pool = state.pool.clone();
+    let user = authenticate(&pool, &config, &token).await?;
+    require(&user, Permission::Messaging)?;
+    let app_clone = app.clone();
 
     tokio::spawn(async move {
+        let config = app_clone.state::<AppConfig>();
         loop {
-            let locked_pool = pool.lock().await;
-            let messages = match get_unread_messages(locked_pool, token.clone()).await {
+            // The token is checked on every poll so that polling stops once the session is
+            // logged out, revoked or expired
+            let unread = match authenticate(&pool, &config, &token).await {
+                Ok(user) => list_unread_messages(&PgRepository::new(&pool, &config.keyring), &user).await,
+                Err(err) => Err(err),
+            };
+
+            let messages = match unread {
                 Ok(data) => data,
+                Err(AppError::Unauthorized(_)) => break,
                 Err(err) => {
                     eprintln!("Error while getting unread messages: {}", err);
                     vec![]
@@ -203,120 +382,92 @@ pub async fn poll_messages(
 #[tauri::command]
 pub async fn get_messages_for_conversation(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String,
     conversation_id: i32
-) -> Result<Vec<MessageData>, String> {
-    let pool = state.pool.lock().await;
-    let _user = get_user_from_token(token)?; // Validate token first
-
-    sqlx::query_as!(
-        MessageData,
-        r#"
-        SELECT
-            m.message_id,
-            m.conversation_id,
-            m.sender_id,
-            COALESCE(ms.recipient_id, -1) as "recipient_id?",
-            m.content,
-            COALESCE(ms.status, 'delivered') as "status?",
-            m.created_at
-        FROM messages m
-        LEFT JOIN message_status ms 
-            ON m.message_id = ms.message_id
-        WHERE m.conversation_id = $1
-        ORDER BY m.created_at DESC
-        "#,
-        conversation_id
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| format!("Message fetch error: {}", e))
+) -> Result<Vec<MessageData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_conversation_messages(&PgRepository::new(pool, &config.keyring), &user, conversation_id).await
 }
 
 // Get conversation id
 #[tauri::command]
 pub async fn get_conversation(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String,
     recipient_id: i32
-) -> Result<Conversation, String> {
-    let pool = state.pool.lock().await;
-    let user = get_user_from_token(token)?;
-
-    if user.user_id == recipient_id {
-        return Err(format!("Cannot start a conversation with self."));
-    }
-
-    let smaller_id = std::cmp::min(user.user_id, recipient_id);
-    let larger_id = std::cmp::max(user.user_id, recipient_id);
+) -> Result<Conversation, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    sqlx::query_as!(
-        Conversation,
-        r#"
-        WITH inserted_or_updated AS (
-            INSERT INTO conversations (user1, user2)
-            VALUES ($1, $2)
-            ON CONFLICT (user1, user2) 
-            DO UPDATE SET user1 = EXCLUDED.user1  -- No-op update
-            RETURNING *
-        )
-        SELECT 
-            c.conversation_id, 
-            c.user1, 
-            c.user2, 
-            c.last_message, 
-            c.created_at,
-            m.sender_id AS "last_message_sender_id?",
-            m.content AS "last_message_content?",
-            m.created_at AS "last_message_created_at?"
-        FROM inserted_or_updated c
-        LEFT JOIN messages m ON c.last_message = m.message_id
-        "#,
-        smaller_id,
-        larger_id
-    )
-    .fetch_one(&*pool)
-    .await
-    .map_err(|e| format!("Database error: {}", e))
+    open_conversation(&PgRepository::new(pool, &config.keyring), &user, recipient_id).await
 }
 
 // Get all conversations
 #[tauri::command]
 pub async fn get_all_conversations(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
     token: String
-) -> Result<Vec<Conversation>, String> {
-    let pool = state.pool.lock().await;
-    let user = get_user_from_token(token)?;
-
-    sqlx::query_as!(
-        Conversation,
-        r#"
-        SELECT 
-            c.conversation_id, 
-            c.user1, 
-            c.user2, 
-            c.last_message, 
-            c.created_at,
-            m.sender_id AS "last_message_sender_id?",
-            m.content AS "last_message_content?",
-            m.created_at AS "last_message_created_at?"
-        FROM 
-            conversations c
-        LEFT JOIN 
-            messages m 
-        ON 
-            c.last_message = m.message_id
-        WHERE
-            c.user1 = $1
-        OR
-            c.user2 = $1
-        ORDER BY
-            m.created_at DESC
-        "#,
-        &user.user_id
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| format!("Error while getting conversations: {}", e))
-}
\ No newline at end of file
+) -> Result<Vec<Conversation>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_conversations(&PgRepository::new(pool, &config.keyring), &user).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{test_user, MemoryRepository};
+
+    #[tokio::test]
+    async fn messages_are_delivered_to_the_other_participant() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+
+        let conversation = open_conversation(&repo, &nurse, doctor.user_id).await.unwrap();
+        assert_eq!(conversation.user1, Some(doctor.user_id.min(nurse.user_id)));
+        post_message(&repo, &doctor, conversation.conversation_id, "Patient is ready").await.unwrap();
+
+        let unread = list_unread_messages(&repo, &nurse).await.unwrap();
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].content.as_deref(), Some("Patient is ready"));
+        assert!(list_unread_messages(&repo, &doctor).await.unwrap().is_empty());
+
+        let conversations = list_conversations(&repo, &nurse).await.unwrap();
+        assert_eq!(conversations[0].last_message_content.as_deref(), Some("Patient is ready"));
+    }
+
+    #[tokio::test]
+    async fn opening_a_conversation_twice_returns_the_same_one() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+
+        let first = open_conversation(&repo, &doctor, nurse.user_id).await.unwrap();
+        let second = open_conversation(&repo, &nurse, doctor.user_id).await.unwrap();
+        assert_eq!(first.conversation_id, second.conversation_id);
+
+        let err = open_conversation(&repo, &doctor, doctor.user_id).await.unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+    }
+
+    #[tokio::test]
+    async fn outsiders_cannot_read_or_post() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let outsider = test_user(&repo, "NURSE");
+        let conversation = open_conversation(&repo, &doctor, nurse.user_id).await.unwrap();
+
+        let err = post_message(&repo, &outsider, conversation.conversation_id, "Hello").await.unwrap_err();
+        assert_eq!(err, AppError::forbidden());
+        let err = list_conversation_messages(&repo, &outsider, conversation.conversation_id).await.unwrap_err();
+        assert_eq!(err, AppError::forbidden());
+    }
+}
@@ -0,0 +1,375 @@
+// src-tauri/src/mfa.rs
+
+// Dependencies
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use tauri::State;
+use crate::config::AppConfig;
+use crate::auth::{
+    create_session, decode_mfa_token, find_active_user, get_user_from_token, revoke_all_sessions, LoginResponse,
+    MFA_PURPOSE_ENROLL, MFA_PURPOSE_VERIFY
+};
+use crate::crypto::{random_token, sha256_hex};
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::keys::KeyRing;
+use crate::permissions::{authorize, Permission};
+use crate::security::{
+    clear_failed_logins, record_login_attempt, register_failed_login, LOGIN_FAILED,
+    REASON_BAD_SECOND_FACTOR, REASON_LOCKED, REASON_RECOVERY_CODE, REASON_SUCCESS
+};
+use crate::totp;
+
+const TOTP_ISSUER: &str = "EHR Portal";
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    secret: String,
+    provisioning_uri: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TotpEnrollmentResult {
+    recovery_codes: Vec<String>,
+    // Present when enrollment was the last step of a login
+    session: Option<LoginResponse>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TotpStatus {
+    enabled: bool,
+    required: bool,
+    recovery_codes_remaining: i64
+}
+
+// Which second factor satisfied a check
+#[derive(Debug, PartialEq, Eq)]
+pub enum SecondFactor {
+    Totp,
+    RecoveryCode
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+fn unix_time_now() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+// Function to replace a user's recovery codes. Returns the plain codes, which are only shown once.
+async fn replace_recovery_codes(pool: &Pool<Postgres>, user_id: i32) -> Result<Vec<String>, AppError> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = random_token(5);
+            format!("{}-{}", &raw[..5], &raw[5..])
+        })
+        .collect();
+    let hashes: Vec<String> = codes.iter().map(|code| sha256_hex(normalize_recovery_code(code).as_bytes())).collect();
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::database("Error while starting transaction", e))?;
+    sqlx::query!("DELETE FROM user_recovery_codes WHERE user_id = $1", &user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while removing recovery codes", e))?;
+    sqlx::query!(
+        r#"
+        INSERT INTO user_recovery_codes (user_id, code_hash)
+        SELECT $1, code_hash FROM UNNEST($2::TEXT[]) AS t(code_hash)
+        "#,
+        &user_id,
+        &hashes
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::database("Error while storing recovery codes", e))?;
+    tx.commit().await.map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+    Ok(codes)
+}
+
+// Function to check a TOTP code at the given unix time against the user's secret.
+// `enabled` picks the secret's state: pending secrets only confirm enrollment, enabled ones sign in.
+// Taking the time as a parameter keeps the check deterministic for a fixed clock.
+pub async fn check_totp_code(
+    pool: &Pool<Postgres>,
+    keyring: &KeyRing,
+    user_id: i32,
+    code: &str,
+    unix_time: u64,
+    enabled: bool
+) -> Result<bool, AppError> {
+    let record = sqlx::query!(
+        r#"
+        SELECT
+            pgp_sym_decrypt(secret, ($2::TEXT[])[key_id]) as "secret!",
+            last_used_step
+        FROM user_totp
+        WHERE user_id = $1 AND enabled = $3
+        "#,
+        &user_id,
+        &keyring.sql_keys(),
+        enabled
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::database("Error while loading second factor", e))?;
+
+    let Some(record) = record else {
+        return Ok(false);
+    };
+
+    let last_used_step = record.last_used_step.map(|step| step as u64);
+    let Some(step) = totp::verify_code(&record.secret, code, unix_time, last_used_step).map_err(|e| AppError::crypto("Error while checking verification code", e))? else {
+        return Ok(false);
+    };
+
+    // Only one use per time step, so an observed code cannot be reused
+    let updated = sqlx::query!(
+        r#"
+        UPDATE user_totp
+        SET last_used_step = $2
+        WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        &user_id,
+        step as i64
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::database("Error while recording second factor use", e))?;
+
+    Ok(updated.rows_affected() == 1)
+}
+
+// Function to check a code from an enabled TOTP factor at the given unix time, or else consume a recovery code
+pub async fn check_second_factor(
+    pool: &Pool<Postgres>,
+    keyring: &KeyRing,
+    user_id: i32,
+    code: &str,
+    unix_time: u64
+) -> Result<Option<SecondFactor>, AppError> {
+    if check_totp_code(pool, keyring, user_id, code, unix_time, true).await? {
+        return Ok(Some(SecondFactor::Totp));
+    }
+
+    let used = sqlx::query!(
+        r#"
+        UPDATE user_recovery_codes
+        SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        &user_id,
+        sha256_hex(normalize_recovery_code(code).as_bytes())
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::database("Error while checking recovery code", e))?;
+
+    if used.rows_affected() == 1 {
+        Ok(Some(SecondFactor::RecoveryCode))
+    } else {
+        Ok(None)
+    }
+}
+
+// Resolves the user for enrollment commands, either from a session or from an enrollment token issued by login
+async fn enrolling_user(pool: &Pool<Postgres>, config: &AppConfig, token: &str) -> Result<(i32, bool), AppError> {
+    match get_user_from_token(pool, config, token).await {
+        Ok(user) => Ok((user.user_id, false)),
+        Err(_) => decode_mfa_token(config, token, MFA_PURPOSE_ENROLL).map(|user_id| (user_id, true))
+    }
+}
+
+// Endpoint to complete a login with a TOTP or recovery code
+#[tauri::command]
+pub async fn verify_login_totp(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, mfa_token: String, code: String) -> Result<LoginResponse, AppError> {
+    let pool = &state.pool;
+    let user_id = decode_mfa_token(&config, &mfa_token, MFA_PURPOSE_VERIFY)?;
+    let user = find_active_user(pool, user_id).await?.ok_or_else(|| AppError::Unauthorized(LOGIN_FAILED.to_string()))?;
+
+    if user.locked_until.is_some_and(|locked_until| locked_until > Utc::now()) {
+        record_login_attempt(pool, Some(user.user_id), &user.email, false, REASON_LOCKED).await;
+        return Err(AppError::Unauthorized(LOGIN_FAILED.to_string()));
+    }
+
+    let reason = match check_second_factor(pool, &config.keyring, user.user_id, &code, unix_time_now()).await? {
+        Some(SecondFactor::Totp) => REASON_SUCCESS,
+        Some(SecondFactor::RecoveryCode) => REASON_RECOVERY_CODE,
+        None => {
+            register_failed_login(pool, user.user_id, &config.lockout_policy).await?;
+            record_login_attempt(pool, Some(user.user_id), &user.email, false, REASON_BAD_SECOND_FACTOR).await;
+            return Err(AppError::validation("code", "Invalid verification code."));
+        }
+    };
+
+    clear_failed_logins(pool, user.user_id).await?;
+    record_login_attempt(pool, Some(user.user_id), &user.email, true, reason).await;
+
+    create_session(pool, &config, user).await
+}
+
+// Endpoint to start TOTP enrollment. Returns the secret and the URI to render as a QR code.
+// The factor stays disabled until a code is confirmed.
+#[tauri::command]
+pub async fn begin_totp_enrollment(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String) -> Result<TotpEnrollment, AppError> {
+    let pool = &state.pool;
+    let (user_id, _) = enrolling_user(pool, &config, &token).await?;
+    let user = find_active_user(pool, user_id).await?.ok_or_else(AppError::unauthorized)?;
+    let keyring = &config.keyring;
+    let secret = totp::generate_secret();
+
+    let updated = sqlx::query!(
+        r#"
+        INSERT INTO user_totp (user_id, secret, enabled, key_id)
+        VALUES ($1, pgp_sym_encrypt($2, $3), FALSE, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, key_id = EXCLUDED.key_id, last_used_step = NULL, created_at = NOW()
+        WHERE user_totp.enabled = FALSE
+        "#,
+        &user_id,
+        &secret,
+        &keyring.current().secret,
+        keyring.current().id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::database("Error while starting enrollment", e))?;
+
+    if updated.rows_affected() == 0 {
+        return Err(AppError::conflict("Two-factor authentication is already enabled."));
+    }
+
+    Ok(TotpEnrollment {
+        provisioning_uri: totp::provisioning_uri(TOTP_ISSUER, &user.email, &secret),
+        secret
+    })
+}
+
+// Endpoint to confirm enrollment with a first code. Enables the factor and issues recovery codes.
+// When called with an enrollment token from login, the login is completed as well.
+#[tauri::command]
+pub async fn confirm_totp_enrollment(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    code: String
+) -> Result<TotpEnrollmentResult, AppError> {
+    let pool = &state.pool;
+    let (user_id, during_login) = enrolling_user(pool, &config, &token).await?;
+
+    // Recovery codes are not accepted here: they belong to the factor being enrolled
+    if !check_totp_code(pool, &config.keyring, user_id, &code, unix_time_now(), false).await? {
+        return Err(AppError::validation("code", "Invalid verification code."));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE user_totp
+        SET enabled = TRUE, confirmed_at = NOW()
+        WHERE user_id = $1 AND enabled = FALSE
+        "#,
+        &user_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::database("Error while enabling second factor", e))?;
+
+    let recovery_codes = replace_recovery_codes(pool, user_id).await?;
+
+    let session = if during_login {
+        let user = find_active_user(pool, user_id).await?.ok_or_else(|| AppError::Unauthorized(LOGIN_FAILED.to_string()))?;
+        clear_failed_logins(pool, user_id).await?;
+        record_login_attempt(pool, Some(user_id), &user.email, true, REASON_SUCCESS).await;
+        Some(create_session(pool, &config, user).await?)
+    } else {
+        None
+    };
+
+    Ok(TotpEnrollmentResult { recovery_codes, session })
+}
+
+// Endpoint to show whether the caller has a second factor and how many recovery codes are left
+#[tauri::command]
+pub async fn get_totp_status(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String) -> Result<TotpStatus, AppError> {
+    let pool = &state.pool;
+    let user = get_user_from_token(pool, &config, &token).await.map_err(|_| AppError::unauthorized())?;
+
+    let record = sqlx::query!(
+        r#"
+        SELECT
+            COALESCE((SELECT enabled FROM user_totp WHERE user_id = $1), FALSE) as "enabled!",
+            (SELECT COUNT(*) FROM user_recovery_codes WHERE user_id = $1 AND used_at IS NULL) as "remaining!"
+        "#,
+        &user.user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::database("Error while loading second factor status", e))?;
+
+    Ok(TotpStatus {
+        enabled: record.enabled,
+        required: config.totp_required_for(&user.role),
+        recovery_codes_remaining: record.remaining
+    })
+}
+
+// Endpoint to replace the caller's recovery codes, confirmed with a current code
+#[tauri::command]
+pub async fn regenerate_recovery_codes(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, code: String) -> Result<Vec<String>, AppError> {
+    let pool = &state.pool;
+    let user = get_user_from_token(pool, &config, &token).await.map_err(|_| AppError::unauthorized())?;
+
+    if check_second_factor(pool, &config.keyring, user.user_id, &code, unix_time_now()).await?.is_none() {
+        return Err(AppError::validation("code", "Invalid verification code."));
+    }
+
+    replace_recovery_codes(pool, user.user_id).await
+}
+
+// Endpoint for the caller to turn off their second factor, unless their role requires it
+#[tauri::command]
+pub async fn disable_totp(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, code: String) -> Result<String, AppError> {
+    let pool = &state.pool;
+    let user = get_user_from_token(pool, &config, &token).await.map_err(|_| AppError::unauthorized())?;
+
+    if config.totp_required_for(&user.role) {
+        return Err(AppError::Forbidden("Two-factor authentication is required for your role.".to_string()));
+    }
+    if check_second_factor(pool, &config.keyring, user.user_id, &code, unix_time_now()).await?.is_none() {
+        return Err(AppError::validation("code", "Invalid verification code."));
+    }
+
+    remove_second_factor(pool, user.user_id).await?;
+
+    Ok("Two-factor authentication disabled".to_string())
+}
+
+// Function to delete a user's TOTP secret and recovery codes
+async fn remove_second_factor(pool: &Pool<Postgres>, user_id: i32) -> Result<(), AppError> {
+    let mut tx = pool.begin().await.map_err(|e| AppError::database("Error while starting transaction", e))?;
+    sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", &user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while removing second factor", e))?;
+    sqlx::query!("DELETE FROM user_recovery_codes WHERE user_id = $1", &user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while removing recovery codes", e))?;
+    tx.commit().await.map_err(|e| AppError::database("Error while committing transaction", e))
+}
+
+// Endpoint for admins to clear a user's second factor, e.g. after a lost phone.
+// The user is signed out and must enroll again on next login if their role requires it.
+#[tauri::command]
+pub async fn reset_user_totp(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, user_id: i32) -> Result<String, AppError> {
+    let pool = &state.pool;
+    let _user = authorize(pool, &config, &token, Permission::ManageUsers).await?;
+
+    remove_second_factor(pool, user_id).await?;
+    revoke_all_sessions(pool, user_id, "MFA_RESET").await?;
+
+    Ok("Second factor reset".to_string())
+}
@@ -0,0 +1,262 @@
+// src-tauri/src/migrations.rs
+
+// Dependencies
+use crate::crypto::sha256_hex;
+use sqlx::{Connection, Executor, Pool, Postgres, Row};
+
+// Arbitrary key for the advisory lock that serializes concurrent migration runs
+const MIGRATION_LOCK_KEY: i64 = 0x4548_525f_4d49_4752;
+
+// Struct describing a single forward-only schema migration
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    // Hex encoded SHA-256 of the migration body, used to detect edits to applied migrations
+    pub fn checksum(&self) -> String {
+        sha256_hex(self.sql.as_bytes())
+    }
+}
+
+// All migrations in the order they must be applied.
+// Migrations are append-only: never edit or reorder an entry once it has shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_users_table",
+        sql: include_str!("../migrations/0001_create_users_table.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "create_patient_tables",
+        sql: include_str!("../migrations/0002_create_patient_tables.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "create_vision_tables",
+        sql: include_str!("../migrations/0003_create_vision_tables.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "create_messaging_tables",
+        sql: include_str!("../migrations/0004_create_messaging_tables.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "create_alerts_table",
+        sql: include_str!("../migrations/0005_create_alerts_table.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "create_appointment_tables",
+        sql: include_str!("../migrations/0006_create_appointment_tables.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "create_sessions_table",
+        sql: include_str!("../migrations/0007_create_sessions_table.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "add_user_status",
+        sql: include_str!("../migrations/0008_add_user_status.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "add_login_security",
+        sql: include_str!("../migrations/0009_add_login_security.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "create_totp_tables",
+        sql: include_str!("../migrations/0010_create_totp_tables.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "create_audit_log",
+        sql: include_str!("../migrations/0011_create_audit_log.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "add_encryption_key_ids",
+        sql: include_str!("../migrations/0012_add_encryption_key_ids.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "add_patient_demographics",
+        sql: include_str!("../migrations/0013_add_patient_demographics.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "add_patient_search_indexes",
+        sql: include_str!("../migrations/0014_add_patient_search_indexes.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "create_patient_merges",
+        sql: include_str!("../migrations/0015_create_patient_merges.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "add_patient_photo_thumbnails",
+        sql: include_str!("../migrations/0016_add_patient_photo_thumbnails.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "create_medical_history_entries",
+        sql: include_str!("../migrations/0017_create_medical_history_entries.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "link_appointments_and_alerts_to_patients",
+        sql: include_str!("../migrations/0018_link_appointments_and_alerts_to_patients.sql"),
+    },
+    Migration {
+        version: 19,
+        name: "create_activity_workflow",
+        sql: include_str!("../migrations/0019_create_activity_workflow.sql"),
+    },
+    Migration {
+        version: 20,
+        name: "create_procedure_comments",
+        sql: include_str!("../migrations/0020_create_procedure_comments.sql"),
+    },
+    Migration {
+        version: 21,
+        name: "add_procedure_catalog_fields",
+        sql: include_str!("../migrations/0021_add_procedure_catalog_fields.sql"),
+    },
+    Migration {
+        version: 22,
+        name: "add_activity_clinicians",
+        sql: include_str!("../migrations/0022_add_activity_clinicians.sql"),
+    },
+    Migration {
+        version: 23,
+        name: "backfill_activity_transitions",
+        sql: include_str!("../migrations/0023_backfill_activity_transitions.sql"),
+    },
+];
+
+// Function to check that migration versions are strictly increasing
+fn validate_migration_order(migrations: &[Migration]) -> Result<(), String> {
+    for pair in migrations.windows(2) {
+        if pair[1].version <= pair[0].version {
+            return Err(format!(
+                "Migration {} ({}) is out of order after migration {} ({})",
+                pair[1].version, pair[1].name, pair[0].version, pair[0].name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Function to apply all pending migrations, returns the versions that were applied
+pub async fn run_migrations(pool: &Pool<Postgres>) -> Result<Vec<i64>, String> {
+    validate_migration_order(MIGRATIONS)?;
+
+    // Advisory locks are per session, so every step runs on the same connection
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Error while acquiring connection for migrations: {}", e))?;
+
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Error while acquiring migration lock: {}", e))?;
+
+    let result = apply_pending_migrations(&mut conn).await;
+
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Error while releasing migration lock: {}", e))?;
+
+    result
+}
+
+async fn apply_pending_migrations(conn: &mut sqlx::PgConnection) -> Result<Vec<i64>, String> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )
+    .await
+    .map_err(|e| format!("Error while creating schema_migrations table: {}", e))?;
+
+    let applied = sqlx::query("SELECT version, checksum FROM schema_migrations ORDER BY version")
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| format!("Error while reading applied migrations: {}", e))?;
+
+    // Refuse to run against a database migrated by a newer build
+    if let Some(row) = applied.last() {
+        let latest: i64 = row.get("version");
+        let known = MIGRATIONS.last().map(|m| m.version).unwrap_or(0);
+        if latest > known {
+            return Err(format!(
+                "Database schema version {} is newer than this build supports ({})",
+                latest, known
+            ));
+        }
+    }
+
+    let mut newly_applied = vec![];
+    for migration in MIGRATIONS {
+        let checksum = migration.checksum();
+
+        if let Some(row) = applied
+            .iter()
+            .find(|row| row.get::<i64, _>("version") == migration.version)
+        {
+            let recorded: String = row.get("checksum");
+            if recorded != checksum {
+                return Err(format!(
+                    "Checksum mismatch for applied migration {} ({})",
+                    migration.version, migration.name
+                ));
+            }
+            continue;
+        }
+
+        let mut tx = conn
+            .begin()
+            .await
+            .map_err(|e| format!("Error while starting migration transaction: {}", e))?;
+
+        (&mut *tx).execute(migration.sql).await.map_err(|e| {
+            format!(
+                "Error while applying migration {} ({}): {}",
+                migration.version, migration.name, e
+            )
+        })?;
+
+        sqlx::query("INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(&checksum)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Error while recording migration {}: {}", migration.version, e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Error while committing migration {}: {}", migration.version, e))?;
+
+        newly_applied.push(migration.version);
+    }
+
+    Ok(newly_applied)
+}
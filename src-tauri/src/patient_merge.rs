@@ -0,0 +1,782 @@
+// src-tauri/src/patient_merge.rs
+
+// Dependencies
+use std::collections::HashMap;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tauri::State;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_PATIENT, ENTITY_PATIENT_MERGE};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::{is_unique_violation, DatabaseState};
+use crate::error::AppError;
+use crate::patient_search::{phone_digits, phone_search_token, MAX_PHONE_DIGITS, MIN_PHONE_DIGITS};
+use crate::patients::PatientData;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AuditRepo, PatientMergeRepo, PatientRepo, PgRepository};
+
+// Candidates scoring below this are not reported
+pub const DUPLICATE_THRESHOLD: f64 = 0.6;
+const MAX_DUPLICATE_CANDIDATES: usize = 20;
+
+// A table whose rows belong to a patient and move with a merge
+pub struct PatientTable {
+    pub table: &'static str,
+    pub id_column: &'static str,
+    // Columns that together with patient_id are unique. None when a patient can have any number of rows.
+    pub unique_by: Option<&'static [&'static str]>,
+    // When a row last changed, to decide which side of a unique key conflict is kept
+    pub changed_at: &'static str,
+}
+
+// Every patient-owned table. Add new ones here so merges carry them over.
+pub const PATIENT_TABLES: &[PatientTable] = &[
+    PatientTable { table: "patient_activity", id_column: "activity_id", unique_by: None, changed_at: "created_at" },
+    PatientTable { table: "patient_history", id_column: "history_id", unique_by: Some(&[]), changed_at: "created_at" },
+    PatientTable {
+        table: "medical_history_entries",
+        id_column: "entry_id",
+        unique_by: None,
+        changed_at: "COALESCE(updated_at, created_at)",
+    },
+    PatientTable { table: "medical_history_changes", id_column: "change_id", unique_by: None, changed_at: "changed_at" },
+    PatientTable {
+        table: "vision",
+        id_column: "vision_id",
+        unique_by: Some(&["side", "value_type"]),
+        changed_at: "COALESCE(updated_at, created_at)",
+    },
+    PatientTable {
+        table: "refraction",
+        id_column: "refraction_id",
+        unique_by: Some(&["side", "value_type", "vision_type"]),
+        changed_at: "COALESCE(updated_at, created_at)",
+    },
+    PatientTable {
+        table: "eye_measurement",
+        id_column: "measurement_id",
+        unique_by: Some(&["side"]),
+        changed_at: "COALESCE(updated_at, created_at)",
+    },
+    PatientTable { table: "appointments", id_column: "appointment_id", unique_by: None, changed_at: "created_at" },
+    PatientTable { table: "alerts", id_column: "alert_id", unique_by: None, changed_at: "created_at" },
+];
+
+// One row of a patient-owned table as the merge planner sees it
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct OwnedRow {
+    pub row_id: i32,
+    pub patient_id: i32,
+    // None when the table has no unique key besides the row id
+    pub conflict_key: Option<String>,
+    pub changed_at: Option<DateTime<Utc>>,
+}
+
+// Rows of one table whose owner a merge changed
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct TableMove {
+    pub table_name: String,
+    // From the merged patient to the surviving one
+    pub moved: Vec<i32>,
+    // Older rows of the surviving patient replaced by a moved row, handed to the merged patient
+    pub displaced: Vec<i32>,
+}
+
+// Struct to store a merge and its undo record
+#[derive(Serialize, Clone, Debug)]
+pub struct PatientMerge {
+    pub merge_id: i32,
+    pub surviving_patient_id: i32,
+    pub merged_patient_id: i32,
+    pub merged_by: i32,
+    pub merged_at: DateTime<Utc>,
+    pub undone_by: Option<i32>,
+    pub undone_at: Option<DateTime<Utc>>,
+    pub tables: Vec<TableMove>,
+}
+
+// A patient that may be the same person, as returned by the repositories
+#[derive(Clone, Debug)]
+pub struct DuplicateCandidateRow {
+    pub patient_id: i32,
+    pub mr_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    pub gender: String,
+    // The candidate's phone starts with every digit of the patient's phone
+    pub phone_matches: bool,
+}
+
+// Struct to store result of find_duplicate_patients
+#[derive(Serialize, Clone, Debug)]
+pub struct DuplicateCandidate {
+    pub patient_id: i32,
+    pub mr_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    pub gender: String,
+    // Between 0 and 1
+    pub score: f64,
+    // NAME, DATE_OF_BIRTH and PHONE for the parts that matched
+    pub reasons: Vec<&'static str>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MergeRequest {
+    pub surviving_patient_id: i32,
+    pub merged_patient_id: i32,
+}
+
+// Function to decide which of the merged patient's rows move to the surviving patient. On a unique
+// key conflict the more recently changed row is kept and the other one stays with the merged patient.
+pub fn plan_table_merge(table: &str, surviving_patient_id: i32, rows: &[OwnedRow]) -> TableMove {
+    let surviving: HashMap<&str, &OwnedRow> = rows
+        .iter()
+        .filter(|row| row.patient_id == surviving_patient_id)
+        .filter_map(|row| row.conflict_key.as_deref().map(|key| (key, row)))
+        .collect();
+
+    let mut plan = TableMove { table_name: table.to_string(), ..TableMove::default() };
+    for row in rows.iter().filter(|row| row.patient_id != surviving_patient_id) {
+        match row.conflict_key.as_deref().and_then(|key| surviving.get(key)) {
+            None => plan.moved.push(row.row_id),
+            Some(existing) if row.changed_at > existing.changed_at => {
+                plan.moved.push(row.row_id);
+                plan.displaced.push(existing.row_id);
+            }
+            Some(_) => {}
+        }
+    }
+    plan
+}
+
+// Token for the full phone number, matched against the candidates' blind index
+pub fn full_phone_token(search_key: &str, phone: Option<&str>) -> Option<String> {
+    let digits = phone.map(phone_digits).unwrap_or_default();
+    let digits = &digits[..digits.len().min(MAX_PHONE_DIGITS)];
+
+    (digits.len() >= MIN_PHONE_DIGITS).then(|| phone_search_token(search_key, digits))
+}
+
+// Function to score how likely a candidate is the same person: 0.45 for the name, 0.3 for the date
+// of birth (half for a swapped day and month or a different day in the same month) and 0.25 for the phone
+pub fn score_candidate(patient: &PatientData, candidate: &DuplicateCandidateRow) -> (f64, Vec<&'static str>) {
+    let similarity = |a: &str, b: &str| strsim::jaro_winkler(&a.trim().to_lowercase(), &b.trim().to_lowercase());
+    let in_order = similarity(&patient.first_name, &candidate.first_name) + similarity(&patient.last_name, &candidate.last_name);
+    let swapped = similarity(&patient.first_name, &candidate.last_name) + similarity(&patient.last_name, &candidate.first_name);
+    let name = in_order.max(swapped) / 2.0;
+
+    let (born, other) = (patient.date_of_birth, candidate.date_of_birth);
+    let date_of_birth = if born == other {
+        0.3
+    } else if born.year() == other.year()
+        && (born.month() == other.month() || (born.month() == other.day() && born.day() == other.month()))
+    {
+        0.15
+    } else {
+        0.0
+    };
+
+    let phone = if candidate.phone_matches { 0.25 } else { 0.0 };
+
+    let mut reasons = vec![];
+    if name >= 0.9 {
+        reasons.push("NAME");
+    }
+    if born == other {
+        reasons.push("DATE_OF_BIRTH");
+    }
+    if candidate.phone_matches {
+        reasons.push("PHONE");
+    }
+
+    ((0.45 * name + date_of_birth + phone).min(1.0), reasons)
+}
+
+// SQL listing both patients' rows of one table, locked until the merge commits.
+// Table and column names come from PATIENT_TABLES only, never from input.
+fn owned_rows_sql(table: &PatientTable) -> String {
+    let conflict_key = match table.unique_by {
+        None => "NULL::TEXT".to_string(),
+        Some(columns) => format!("CONCAT_WS('|', ''{})", columns.iter().map(|column| format!(", {}", column)).collect::<String>()),
+    };
+
+    format!(
+        "SELECT {id} AS row_id, patient_id, {conflict_key} AS conflict_key, {changed_at} AS changed_at \
+         FROM {table} WHERE patient_id IN ($1, $2) FOR UPDATE",
+        id = table.id_column,
+        conflict_key = conflict_key,
+        changed_at = table.changed_at,
+        table = table.table
+    )
+}
+
+// Function to hand rows to new owners. Every row is detached first, so rows trading places
+// never hold the same unique key at once.
+async fn reassign_rows(
+    conn: &mut PgConnection,
+    table: &PatientTable,
+    to_surviving: (i32, &[i32]),
+    to_merged: (i32, &[i32])
+) -> Result<(), AppError> {
+    let error = |e| AppError::database(&format!("Error while moving rows of {}", table.table), e);
+    let existing = format!("SELECT COUNT(*) FROM {} WHERE {} = ANY($1)", table.table, table.id_column);
+    let detach = format!("UPDATE {} SET patient_id = NULL WHERE {} = ANY($1) AND patient_id = $2", table.table, table.id_column);
+    let attach = format!("UPDATE {} SET patient_id = $1 WHERE {} = ANY($2)", table.table, table.id_column);
+
+    // Each row is only taken from the patient the other side of the move left it with, so rows
+    // that have been moved again since are never pulled out from under their new owner
+    for ((_, row_ids), (owner, _)) in [(to_surviving, to_merged), (to_merged, to_surviving)] {
+        if row_ids.is_empty() {
+            continue;
+        }
+        let expected: i64 = sqlx::query_scalar(&existing).bind(row_ids).fetch_one(&mut *conn).await.map_err(error)?;
+        let detached = sqlx::query(&detach).bind(row_ids).bind(owner).execute(&mut *conn).await.map_err(error)?;
+        if detached.rows_affected() != expected as u64 {
+            return Err(AppError::conflict(&format!(
+                "Some {} rows no longer belong to the patient they were merged into.",
+                table.table
+            )));
+        }
+    }
+
+    for (patient_id, row_ids) in [to_surviving, to_merged] {
+        if row_ids.is_empty() {
+            continue;
+        }
+        sqlx::query(&attach).bind(patient_id).bind(row_ids).execute(&mut *conn).await.map_err(|e| {
+            if is_unique_violation(&e) {
+                AppError::conflict(&format!(
+                    "The patient now has {} rows that clash with the ones being handed back.",
+                    table.table
+                ))
+            } else {
+                error(e)
+            }
+        })?;
+    }
+    Ok(())
+}
+
+// Audit events for a merge or its undo, one on each patient's record
+pub fn merge_events(user_id: i32, action: AuditAction, merge: &PatientMerge) -> Vec<AuditEvent> {
+    [merge.surviving_patient_id, merge.merged_patient_id]
+        .into_iter()
+        .map(|patient_id| {
+            AuditEvent::new(user_id, action, ENTITY_PATIENT_MERGE)
+                .patient(patient_id)
+                .entity_id(merge.merge_id)
+                .after(&(merge.surviving_patient_id, merge.merged_patient_id, &merge.tables))
+        })
+        .collect()
+}
+
+impl PatientMergeRepo for PgRepository<'_> {
+    async fn list_duplicate_candidates(&self, patient: &PatientData) -> Result<Vec<DuplicateCandidateRow>, AppError> {
+        // Only rows that share the date of birth, the phone or the start of a name are scored
+        let name_start = |name: &str| {
+            let start: String = name.trim().to_lowercase().chars().take(3).collect();
+            format!("{}%", start.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+        };
+        let phone_token = full_phone_token(self.keyring.search_key(), patient.phone.as_deref());
+
+        sqlx::query_as!(
+            DuplicateCandidateRow,
+            r#"
+            SELECT
+                patient_id,
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                COALESCE($5::TEXT IS NOT NULL AND phone_search_tokens @> ARRAY[$5::TEXT], FALSE) as "phone_matches!"
+            FROM
+                patients
+            WHERE active AND patient_id <> $1
+            AND (date_of_birth = $2
+                OR LOWER(first_name) LIKE $3
+                OR LOWER(last_name) LIKE $4
+                OR LOWER(first_name) LIKE $4
+                OR LOWER(last_name) LIKE $3
+                OR ($5::TEXT IS NOT NULL AND phone_search_tokens @> ARRAY[$5::TEXT]))
+            ORDER BY patient_id
+            LIMIT 500
+            "#,
+            patient.patient_id,
+            patient.date_of_birth,
+            name_start(&patient.first_name),
+            name_start(&patient.last_name),
+            phone_token
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while looking for duplicate patients", e))
+    }
+
+    async fn merge_patients(&self, user_id: i32, surviving_patient_id: i32, merged_patient_id: i32) -> Result<Option<PatientMerge>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let locked = sqlx::query_scalar!(
+            r#"
+            SELECT patient_id
+            FROM patients
+            WHERE patient_id IN ($1, $2) AND active
+            ORDER BY patient_id
+            FOR UPDATE
+            "#,
+            surviving_patient_id,
+            merged_patient_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while locking patients", e))?;
+        if locked.len() != 2 {
+            return Ok(None);
+        }
+
+        let created = sqlx::query!(
+            r#"
+            INSERT INTO patient_merges (surviving_patient_id, merged_patient_id, merged_by)
+            VALUES ($1, $2, $3)
+            RETURNING merge_id, merged_at
+            "#,
+            surviving_patient_id,
+            merged_patient_id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while recording merge", e))?;
+
+        let mut tables = vec![];
+        for table in PATIENT_TABLES {
+            let rows = sqlx::query_as::<_, OwnedRow>(&owned_rows_sql(table))
+                .bind(surviving_patient_id)
+                .bind(merged_patient_id)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|e| AppError::database(&format!("Error while reading rows of {}", table.table), e))?;
+            let plan = plan_table_merge(table.table, surviving_patient_id, &rows);
+
+            reassign_rows(&mut tx, table, (surviving_patient_id, &plan.moved), (merged_patient_id, &plan.displaced)).await?;
+            for (direction, row_ids) in [("MOVED", &plan.moved), ("DISPLACED", &plan.displaced)] {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO patient_merge_rows (merge_id, table_name, row_id, direction)
+                    SELECT $1, $2, UNNEST($3::INT[]), $4
+                    "#,
+                    created.merge_id,
+                    table.table,
+                    row_ids,
+                    direction
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::database("Error while recording merge", e))?;
+            }
+            tables.push(plan);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE patients
+            SET active = FALSE, merged_into = $2, updated_at = NOW(), deactivated_at = NOW()
+            WHERE patient_id = $1
+            "#,
+            merged_patient_id,
+            surviving_patient_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while deactivating merged patient", e))?;
+
+        let merge = PatientMerge {
+            merge_id: created.merge_id,
+            surviving_patient_id,
+            merged_patient_id,
+            merged_by: user_id,
+            merged_at: created.merged_at,
+            undone_by: None,
+            undone_at: None,
+            tables,
+        };
+        for event in merge_events(user_id, AuditAction::Create, &merge) {
+            record_audit(&mut *tx, self.keyring, event).await?;
+        }
+        record_audit(
+            &mut *tx,
+            self.keyring,
+            AuditEvent::new(user_id, AuditAction::Update, ENTITY_PATIENT).patient(merged_patient_id).entity_id(merged_patient_id),
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(merge))
+    }
+
+    async fn undo_merge(&self, user_id: i32, merge_id: i32) -> Result<Option<PatientMerge>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let Some(undone) = sqlx::query!(
+            r#"
+            UPDATE patient_merges
+            SET undone_by = $2, undone_at = NOW()
+            WHERE merge_id = $1 AND undone_at IS NULL
+            RETURNING surviving_patient_id, merged_patient_id, merged_by, merged_at, undone_at
+            "#,
+            merge_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while undoing merge", e))?
+        else {
+            return Ok(None);
+        };
+
+        let patients = sqlx::query!(
+            r#"
+            SELECT patient_id, active, merged_into
+            FROM patients
+            WHERE patient_id IN ($1, $2)
+            ORDER BY patient_id
+            FOR UPDATE
+            "#,
+            undone.surviving_patient_id,
+            undone.merged_patient_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while locking patients", e))?;
+        let surviving = patients.iter().find(|patient| patient.patient_id == undone.surviving_patient_id);
+        if !surviving.is_some_and(|patient| patient.active && patient.merged_into.is_none()) {
+            return Err(AppError::conflict("The surviving patient has since been merged or deactivated. Undo that first."));
+        }
+
+        let later = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1
+                FROM patient_merges
+                WHERE merge_id > $1
+                  AND undone_at IS NULL
+                  AND (surviving_patient_id IN ($2, $3) OR merged_patient_id IN ($2, $3))
+            ) AS "later!"
+            "#,
+            merge_id,
+            undone.surviving_patient_id,
+            undone.merged_patient_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while reading merge", e))?;
+        if later {
+            return Err(AppError::conflict("A later merge involves one of these patients. Undo that first."));
+        }
+
+        let recorded = sqlx::query!(
+            r#"
+            SELECT table_name, row_id, direction
+            FROM patient_merge_rows
+            WHERE merge_id = $1
+            ORDER BY table_name, row_id
+            "#,
+            merge_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while reading merge", e))?;
+
+        let mut tables = vec![];
+        for table in PATIENT_TABLES {
+            let mut plan = TableMove { table_name: table.table.to_string(), ..TableMove::default() };
+            for row in recorded.iter().filter(|row| row.table_name == table.table) {
+                if row.direction == "MOVED" {
+                    plan.moved.push(row.row_id);
+                } else {
+                    plan.displaced.push(row.row_id);
+                }
+            }
+
+            reassign_rows(&mut tx, table, (undone.surviving_patient_id, &plan.displaced), (undone.merged_patient_id, &plan.moved)).await?;
+            tables.push(plan);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE patients
+            SET active = TRUE, merged_into = NULL, updated_at = NOW(), deactivated_at = NULL
+            WHERE patient_id = $1
+            "#,
+            undone.merged_patient_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while reactivating merged patient", e))?;
+
+        let merge = PatientMerge {
+            merge_id,
+            surviving_patient_id: undone.surviving_patient_id,
+            merged_patient_id: undone.merged_patient_id,
+            merged_by: undone.merged_by,
+            merged_at: undone.merged_at,
+            undone_by: Some(user_id),
+            undone_at: undone.undone_at,
+            tables,
+        };
+        for event in merge_events(user_id, AuditAction::Update, &merge) {
+            record_audit(&mut *tx, self.keyring, event).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(merge))
+    }
+}
+
+// Function to list patients that may be the same person as the given one, most likely first
+pub async fn find_duplicate_candidates<R: PatientRepo + PatientMergeRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<Vec<DuplicateCandidate>, AppError> {
+    require(user, Permission::ReadPatient)?;
+
+    let patient = repo
+        .find_patient(patient_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Patient does not exist."))?;
+
+    let mut candidates: Vec<DuplicateCandidate> = repo
+        .list_duplicate_candidates(&patient)
+        .await?
+        .into_iter()
+        .filter_map(|row| {
+            let (score, reasons) = score_candidate(&patient, &row);
+            (score >= DUPLICATE_THRESHOLD).then_some(DuplicateCandidate {
+                patient_id: row.patient_id,
+                mr_number: row.mr_number,
+                first_name: row.first_name,
+                last_name: row.last_name,
+                date_of_birth: row.date_of_birth,
+                gender: row.gender,
+                score,
+                reasons,
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.patient_id.cmp(&b.patient_id)));
+    candidates.truncate(MAX_DUPLICATE_CANDIDATES);
+
+    let patient_ids: Vec<i32> = std::iter::once(patient_id).chain(candidates.iter().map(|candidate| candidate.patient_id)).collect();
+    repo.record_patient_reads(user.user_id, ENTITY_PATIENT, &patient_ids).await?;
+
+    Ok(candidates)
+}
+
+// Function to fold a duplicate into the surviving patient and deactivate it
+pub async fn merge_patient_records<R: PatientMergeRepo>(
+    repo: &R,
+    user: &AuthUser,
+    request: &MergeRequest
+) -> Result<PatientMerge, AppError> {
+    require(user, Permission::MergePatients)?;
+
+    if request.surviving_patient_id == request.merged_patient_id {
+        return Err(AppError::validation("merged_patient_id", "A patient cannot be merged into itself."));
+    }
+
+    repo.merge_patients(user.user_id, request.surviving_patient_id, request.merged_patient_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Both patients must exist and be active."))
+}
+
+// Function to give a merged patient back its rows and reactivate it
+pub async fn undo_patient_merge_record<R: PatientMergeRepo>(repo: &R, user: &AuthUser, merge_id: i32) -> Result<PatientMerge, AppError> {
+    require(user, Permission::MergePatients)?;
+
+    repo.undo_merge(user.user_id, merge_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Merge does not exist or was already undone."))
+}
+
+// Endpoint to list likely duplicates of a patient
+#[tauri::command]
+pub async fn find_duplicate_patients(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<Vec<DuplicateCandidate>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    find_duplicate_candidates(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
+}
+
+// Endpoint to merge a duplicate patient into the surviving record
+#[tauri::command]
+pub async fn merge_patients(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    request: MergeRequest,
+) -> Result<PatientMerge, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    merge_patient_records(&PgRepository::new(pool, &config.keyring), &user, &request).await
+}
+
+// Endpoint to undo a patient merge
+#[tauri::command]
+pub async fn undo_patient_merge(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    merge_id: i32,
+) -> Result<PatientMerge, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    undo_patient_merge_record(&PgRepository::new(pool, &config.keyring), &user, merge_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::memory_repository::{test_user, MemoryRepository};
+    use crate::vision::{find_vision, save_vision, VisionInput, VisionQuery};
+
+    fn owned(row_id: i32, patient_id: i32, conflict_key: Option<&str>, minutes_ago: i64) -> OwnedRow {
+        OwnedRow {
+            row_id,
+            patient_id,
+            conflict_key: conflict_key.map(str::to_string),
+            changed_at: Some(Utc::now() - Duration::minutes(minutes_ago)),
+        }
+    }
+
+    fn vision_input(patient_id: i32, near_vision: &str) -> VisionInput {
+        VisionInput {
+            patient_id,
+            near_vision: near_vision.to_string(),
+            distant_vision: "6/6".to_string(),
+            side: "LEFT".to_string(),
+            value_type: "BCVA".to_string(),
+        }
+    }
+
+    #[test]
+    fn newer_rows_win_unique_key_conflicts() {
+        let rows = vec![
+            owned(1, 10, Some("LEFT|BCVA"), 30),
+            owned(2, 10, Some("RIGHT|BCVA"), 5),
+            owned(3, 20, Some("LEFT|BCVA"), 10),
+            owned(4, 20, Some("RIGHT|BCVA"), 60),
+            owned(5, 20, Some("LEFT|UC"), 60),
+        ];
+
+        let plan = plan_table_merge("vision", 10, &rows);
+
+        assert_eq!(plan.moved, vec![3, 5]);
+        assert_eq!(plan.displaced, vec![1]);
+
+        let activities = vec![owned(6, 10, None, 1), owned(7, 20, None, 1)];
+        assert_eq!(plan_table_merge("patient_activity", 10, &activities).moved, vec![7]);
+    }
+
+    #[tokio::test]
+    async fn likely_duplicates_are_scored_and_ranked() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+        let patient_id = repo.add_patient("Katherine", "Johnson");
+        let typo = repo.add_patient("Katherina", "Jonson");
+        let swapped = repo.add_patient("Johnson", "Katherine");
+        repo.add_patient("Dorothy", "Vaughan");
+
+        let candidates = find_duplicate_candidates(&repo, &nurse, patient_id).await.unwrap();
+
+        let ids: Vec<i32> = candidates.iter().map(|candidate| candidate.patient_id).collect();
+        assert_eq!(ids, vec![swapped, typo]);
+        assert_eq!(candidates[0].reasons, vec!["NAME", "DATE_OF_BIRTH"]);
+        assert!(candidates[1].score >= DUPLICATE_THRESHOLD && candidates[1].score < candidates[0].score);
+    }
+
+    #[tokio::test]
+    async fn merge_moves_rows_and_undo_restores_them() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let nurse = test_user(&repo, "NURSE");
+        let surviving = repo.add_patient("Katherine", "Johnson");
+        let merged = repo.add_patient("Katherine", "Jonson");
+        let history_id = repo.add_history(merged, "Hypertension", "Amlodipine", "None");
+        save_vision(&repo, &nurse, &vision_input(surviving, "N8")).await.unwrap();
+        let newer = save_vision(&repo, &nurse, &vision_input(merged, "N5")).await.unwrap();
+
+        let request = MergeRequest { surviving_patient_id: surviving, merged_patient_id: merged };
+        let error = merge_patient_records(&repo, &nurse, &request).await.unwrap_err();
+        assert_eq!(error.code(), "FORBIDDEN");
+        let merge = merge_patient_records(&repo, &admin, &request).await.unwrap();
+
+        let vision = merge.tables.iter().find(|table| table.table_name == "vision").unwrap();
+        assert_eq!((vision.moved.len(), vision.displaced.len()), (1, 1));
+        let history = merge.tables.iter().find(|table| table.table_name == "patient_history").unwrap();
+        assert_eq!(history.moved, vec![history_id]);
+        let query = VisionQuery { patient_id: surviving, side: "LEFT".to_string(), value_type: "BCVA".to_string() };
+        let kept = find_vision(&repo, &nurse, &query).await.unwrap().unwrap();
+        assert_eq!((kept.vision_id, kept.near_vision.as_deref()), (newer.vision_id, Some("N5")));
+        assert!(merge_patient_records(&repo, &admin, &request).await.is_err());
+        let error = save_vision(&repo, &nurse, &vision_input(merged, "N6")).await.unwrap_err();
+        assert_eq!(error.code(), "VALIDATION");
+
+        undo_patient_merge_record(&repo, &admin, merge.merge_id).await.unwrap();
+
+        let restored = find_vision(&repo, &nurse, &query).await.unwrap().unwrap();
+        assert_eq!(restored.near_vision.as_deref(), Some("N8"));
+        assert!(undo_patient_merge_record(&repo, &admin, merge.merge_id).await.is_err());
+        let merge_events = repo.audit_events().iter().filter(|event| event.entity == ENTITY_PATIENT_MERGE).count();
+        assert_eq!(merge_events, 4);
+    }
+
+    #[tokio::test]
+    async fn undo_waits_for_later_merges_of_the_same_patients() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let first = repo.add_patient("Katherine", "Johnson");
+        let second = repo.add_patient("Katherine", "Jonson");
+        let third = repo.add_patient("Katherine", "Johnsen");
+        let history_id = repo.add_history(first, "Glaucoma", "Latanoprost", "None");
+
+        let request = MergeRequest { surviving_patient_id: second, merged_patient_id: first };
+        let first_merge = merge_patient_records(&repo, &admin, &request).await.unwrap();
+        let request = MergeRequest { surviving_patient_id: third, merged_patient_id: second };
+        let second_merge = merge_patient_records(&repo, &admin, &request).await.unwrap();
+
+        let error = undo_patient_merge_record(&repo, &admin, first_merge.merge_id).await.unwrap_err();
+        assert_eq!(error.code(), "CONFLICT");
+
+        undo_patient_merge_record(&repo, &admin, second_merge.merge_id).await.unwrap();
+        let undone = undo_patient_merge_record(&repo, &admin, first_merge.merge_id).await.unwrap();
+        let history = undone.tables.iter().find(|table| table.table_name == "patient_history").unwrap();
+        assert_eq!(history.moved, vec![history_id]);
+    }
+}
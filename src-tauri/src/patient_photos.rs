@@ -0,0 +1,376 @@
+// src-tauri/src/patient_photos.rs
+
+// Dependencies
+use std::io::Cursor;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use serde::Serialize;
+use tauri::State;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_PATIENT_PHOTO};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AuditRepo, PatientPhotoRepo, PgRepository};
+
+// Largest upload accepted, after base64 decoding
+pub const MAX_PHOTO_BYTES: usize = 10 * 1024 * 1024;
+// Uploads wider or taller than this are rejected before decoding
+const MAX_SOURCE_DIMENSION: u32 = 10_000;
+// Longest side of the stored full-size photo
+pub const PHOTO_MAX_DIMENSION: u32 = 1024;
+// Side of the square thumbnail
+pub const THUMBNAIL_SIZE: u32 = 160;
+const JPEG_QUALITY: u8 = 85;
+
+// Re-encoded photo ready to be stored
+#[derive(Clone, Debug)]
+pub struct ProcessedPhoto {
+    // JPEG data URLs
+    pub photo: String,
+    pub thumbnail: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Struct to store result of upload_patient_photo, get_patient_photo and get_patient_thumbnails
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PatientPhoto {
+    pub patient_id: i32,
+    // JPEG data URL, usable as an img src
+    pub data_url: String,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+fn invalid_photo(message: &str) -> AppError {
+    AppError::validation("photo", message)
+}
+
+fn jpeg_data_url(image: &DynamicImage) -> Result<String, AppError> {
+    let mut bytes = vec![];
+    // JPEG has no alpha channel, and the encoder writes no metadata
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))
+        .map_err(|_| invalid_photo("The photo could not be encoded."))?;
+
+    Ok(format!("data:image/jpeg;base64,{}", STANDARD.encode(bytes)))
+}
+
+// Function to decode an uploaded photo, given as base64 or a data URL
+pub fn decode_upload(data: &str) -> Result<Vec<u8>, AppError> {
+    let encoded = match data.split_once(";base64,") {
+        Some((prefix, encoded)) if prefix.starts_with("data:") => encoded,
+        _ => data,
+    };
+
+    let bytes = STANDARD.decode(encoded.trim()).map_err(|_| invalid_photo("The photo is not valid base64."))?;
+    if bytes.len() > MAX_PHOTO_BYTES {
+        return Err(invalid_photo("The photo must be 10 MB or smaller."));
+    }
+    Ok(bytes)
+}
+
+// Function to validate a JPEG, PNG or WebP image and re-encode it as a full-size photo and a
+// thumbnail. The EXIF orientation is applied to the pixels; EXIF and every other kind of metadata,
+// such as GPS position and camera serial numbers, is dropped by decoding and re-encoding.
+pub fn process_photo(bytes: &[u8]) -> Result<ProcessedPhoto, AppError> {
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|_| invalid_photo("The photo could not be read."))?;
+    if !matches!(reader.format(), Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP)) {
+        return Err(invalid_photo("Upload a JPEG, PNG or WebP image."));
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(|_| invalid_photo("The photo is damaged or too large."))?;
+    let orientation = decoder.orientation().map_err(|_| invalid_photo("The photo is damaged."))?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|_| invalid_photo("The photo is damaged or too large."))?;
+    image.apply_orientation(orientation);
+
+    if image.width() > PHOTO_MAX_DIMENSION || image.height() > PHOTO_MAX_DIMENSION {
+        image = image.resize(PHOTO_MAX_DIMENSION, PHOTO_MAX_DIMENSION, FilterType::Lanczos3);
+    }
+    let thumbnail = image.resize_to_fill(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle);
+
+    Ok(ProcessedPhoto {
+        photo: jpeg_data_url(&image)?,
+        thumbnail: jpeg_data_url(&thumbnail)?,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+// Audit event for a new photo, shared by every PatientPhotoRepo implementation
+pub fn photo_saved_event(user_id: i32, patient_id: i32, photo: &ProcessedPhoto) -> AuditEvent {
+    AuditEvent::new(user_id, AuditAction::Update, ENTITY_PATIENT_PHOTO)
+        .patient(patient_id)
+        .entity_id(patient_id)
+        .after(&photo.photo)
+}
+
+impl PatientPhotoRepo for PgRepository<'_> {
+    async fn save_photo(&self, user_id: i32, patient_id: i32, photo: &ProcessedPhoto) -> Result<Option<DateTime<Utc>>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        // Encrypted under the row's own key, which key_id records for every column of the row
+        let saved = sqlx::query_scalar!(
+            r#"
+            UPDATE patients
+            SET
+                patient_photo = pgp_sym_encrypt($2, ($4::TEXT[])[key_id]),
+                patient_photo_thumbnail = pgp_sym_encrypt($3, ($4::TEXT[])[key_id]),
+                photo_updated_at = NOW(),
+                updated_at = NOW()
+            WHERE patient_id = $1 AND active
+            RETURNING photo_updated_at as "photo_updated_at!"
+            "#,
+            patient_id,
+            &photo.photo,
+            &photo.thumbnail,
+            &self.keyring.sql_keys()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while saving patient photo", e))?;
+
+        if saved.is_some() {
+            record_audit(&mut *tx, self.keyring, photo_saved_event(user_id, patient_id, photo)).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(saved)
+    }
+
+    async fn find_photo(&self, patient_id: i32) -> Result<Option<PatientPhoto>, AppError> {
+        sqlx::query_as!(
+            PatientPhoto,
+            r#"
+            SELECT
+                patient_id,
+                pgp_sym_decrypt(patient_photo::bytea, ($1::TEXT[])[key_id]) as "data_url!",
+                photo_updated_at as updated_at
+            FROM patients
+            WHERE patient_id = $2 AND patient_photo IS NOT NULL
+            "#,
+            &self.keyring.sql_keys(),
+            patient_id
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching patient photo", e))
+    }
+
+    async fn list_thumbnails(&self, patient_ids: &[i32]) -> Result<Vec<PatientPhoto>, AppError> {
+        sqlx::query_as!(
+            PatientPhoto,
+            r#"
+            SELECT
+                patient_id,
+                pgp_sym_decrypt(patient_photo_thumbnail::bytea, ($1::TEXT[])[key_id]) as "data_url!",
+                photo_updated_at as updated_at
+            FROM patients
+            WHERE patient_id = ANY($2) AND patient_photo_thumbnail IS NOT NULL
+            ORDER BY patient_id
+            "#,
+            &self.keyring.sql_keys(),
+            patient_ids
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching patient thumbnails", e))
+    }
+}
+
+// Function to replace a patient's photo. Returns the new thumbnail.
+pub async fn save_patient_photo<R: PatientPhotoRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32,
+    data: &str
+) -> Result<PatientPhoto, AppError> {
+    require(user, Permission::WritePatient)?;
+
+    let photo = process_photo(&decode_upload(data)?)?;
+    let updated_at = repo
+        .save_photo(user.user_id, patient_id, &photo)
+        .await?
+        .ok_or_else(|| AppError::not_found("Patient does not exist or is deactivated."))?;
+
+    Ok(PatientPhoto { patient_id, data_url: photo.thumbnail, updated_at: Some(updated_at) })
+}
+
+// Function to read a patient's full-size photo, if one was uploaded
+pub async fn find_patient_photo<R: PatientPhotoRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<Option<PatientPhoto>, AppError> {
+    require(user, Permission::ReadPatient)?;
+
+    let photo = repo.find_photo(patient_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_PHOTO).patient(patient_id))
+        .await?;
+
+    Ok(photo)
+}
+
+// Function to read the thumbnails of several patients at once. Patients without a photo are left out.
+pub async fn list_patient_thumbnails<R: PatientPhotoRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_ids: &[i32]
+) -> Result<Vec<PatientPhoto>, AppError> {
+    require(user, Permission::ReadPatient)?;
+
+    let thumbnails = repo.list_thumbnails(patient_ids).await?;
+    let found: Vec<i32> = thumbnails.iter().map(|thumbnail| thumbnail.patient_id).collect();
+    repo.record_patient_reads(user.user_id, ENTITY_PATIENT_PHOTO, &found).await?;
+
+    Ok(thumbnails)
+}
+
+// Endpoint to upload a patient's photo as base64 or a data URL
+#[tauri::command]
+pub async fn upload_patient_photo(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+    data: String,
+) -> Result<PatientPhoto, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    save_patient_photo(&PgRepository::new(pool, &config.keyring), &user, patient_id, &data).await
+}
+
+// Endpoint to fetch a patient's full-size photo
+#[tauri::command]
+pub async fn get_patient_photo(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<Option<PatientPhoto>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    find_patient_photo(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
+}
+
+// Endpoint to fetch photo thumbnails for a list of patients
+#[tauri::command]
+pub async fn get_patient_thumbnails(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_ids: Vec<i32>,
+) -> Result<Vec<PatientPhoto>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_patient_thumbnails(&PgRepository::new(pool, &config.keyring), &user, &patient_ids).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use crate::memory_repository::{test_user, MemoryRepository};
+    use crate::patients::list_patients;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = vec![];
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([200, 120, 40])))
+            .write_with_encoder(JpegEncoder::new(&mut bytes))
+            .unwrap();
+        bytes
+    }
+
+    // JPEG with an APP1 EXIF segment holding only an orientation tag of 6 (rotate 90° clockwise)
+    fn jpeg_with_exif_orientation(width: u32, height: u32) -> Vec<u8> {
+        let tiff: &[u8] = &[
+            b'M', b'M', 0, 42, 0, 0, 0, 8, // big-endian header, first IFD at offset 8
+            0, 1, // one entry
+            0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, // orientation, SHORT, 1 value, 6
+            0, 0, 0, 0, // no next IFD
+        ];
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(tiff);
+        let length = (segment.len() + 2) as u16;
+
+        let plain = jpeg(width, height);
+        let mut bytes = plain[..2].to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xE1]);
+        bytes.extend_from_slice(&length.to_be_bytes());
+        bytes.extend_from_slice(&segment);
+        bytes.extend_from_slice(&plain[2..]);
+        bytes
+    }
+
+    fn decode_data_url(data_url: &str) -> (Vec<u8>, DynamicImage) {
+        let bytes = decode_upload(data_url).unwrap();
+        let image = image::load_from_memory(&bytes).unwrap();
+        (bytes, image)
+    }
+
+    #[test]
+    fn exif_is_applied_then_stripped() {
+        let upload = jpeg_with_exif_orientation(40, 20);
+        assert!(upload.windows(4).any(|window| window == b"Exif"));
+
+        let processed = process_photo(&upload).unwrap();
+
+        let (bytes, image) = decode_data_url(&processed.photo);
+        assert_eq!((image.width(), image.height()), (20, 40));
+        assert!(!bytes.windows(4).any(|window| window == b"Exif"));
+        let (_, thumbnail) = decode_data_url(&processed.thumbnail);
+        assert_eq!((thumbnail.width(), thumbnail.height()), (THUMBNAIL_SIZE, THUMBNAIL_SIZE));
+    }
+
+    #[test]
+    fn large_photos_are_scaled_down_and_other_files_rejected() {
+        let processed = process_photo(&jpeg(3000, 1500)).unwrap();
+        assert_eq!((processed.width, processed.height), (PHOTO_MAX_DIMENSION, PHOTO_MAX_DIMENSION / 2));
+
+        for upload in [b"GIF89a\x01\x00\x01\x00".to_vec(), b"%PDF-1.7".to_vec(), jpeg(10, 10)[..40].to_vec()] {
+            assert_eq!(process_photo(&upload).unwrap_err().code(), "VALIDATION");
+        }
+        assert_eq!(decode_upload("not base64!").unwrap_err().code(), "VALIDATION");
+    }
+
+    #[tokio::test]
+    async fn lists_carry_thumbnails_and_the_full_photo_is_fetched_separately() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+        let patient_id = repo.add_patient("Grace", "Hopper");
+        let upload = format!("data:image/jpeg;base64,{}", STANDARD.encode(jpeg(600, 800)));
+
+        let thumbnail = save_patient_photo(&repo, &nurse, patient_id, &upload).await.unwrap();
+
+        let listed = list_patients(&repo, &nurse).await.unwrap();
+        assert_eq!(listed[0].patient_photo.as_deref(), Some(thumbnail.data_url.as_str()));
+        let photo = find_patient_photo(&repo, &nurse, patient_id).await.unwrap().unwrap();
+        assert_eq!(decode_data_url(&photo.data_url).1.height(), 800);
+        assert_eq!(list_patient_thumbnails(&repo, &nurse, &[patient_id, 999]).await.unwrap(), vec![thumbnail]);
+
+        let error = save_patient_photo(&repo, &nurse, 999, &upload).await.unwrap_err();
+        assert_eq!(error.code(), "NOT_FOUND");
+    }
+}
@@ -0,0 +1,535 @@
+// src-tauri/src/patient_search.rs
+
+// Dependencies
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::audit::ENTITY_PATIENT;
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::crypto::hmac_sha256_hex;
+use crate::db::DatabaseState;
+use crate::error::{AppError, FieldError};
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AuditRepo, PatientSearchRepo, PgRepository};
+
+// Phone prefixes shorter than this are not indexed, so they cannot be searched
+pub const MIN_PHONE_DIGITS: usize = 4;
+// Longest phone number allowed by E.164
+pub const MAX_PHONE_DIGITS: usize = 15;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+// Order of search results. Ties are broken by patient_id.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PatientSort {
+    // Last name, then first name
+    #[default]
+    Name,
+    MrNumber,
+    DateOfBirth,
+    // Patients without a visit come first
+    LastVisit,
+    CreatedAt,
+}
+
+impl PatientSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatientSort::Name => "NAME",
+            PatientSort::MrNumber => "MR_NUMBER",
+            PatientSort::DateOfBirth => "DATE_OF_BIRTH",
+            PatientSort::LastVisit => "LAST_VISIT",
+            PatientSort::CreatedAt => "CREATED_AT",
+        }
+    }
+}
+
+// Struct to store input of search_patients. Every field is optional and text fields match prefixes.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PatientSearchQuery {
+    // First name, last name, or both in either order
+    pub name: Option<String>,
+    pub mr_number: Option<String>,
+    // At least 4 digits; spaces, dashes and other formatting are ignored
+    pub phone: Option<String>,
+    // YYYY, YYYY-MM or YYYY-MM-DD
+    pub date_of_birth: Option<String>,
+    pub gender: Option<String>,
+    pub min_age: Option<i32>,
+    pub max_age: Option<i32>,
+    pub has_appointment_today: Option<bool>,
+    // Inclusive start and exclusive end
+    pub last_visit_from: Option<DateTime<Utc>>,
+    pub last_visit_to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub include_inactive: bool,
+    #[serde(default)]
+    pub sort: PatientSort,
+    #[serde(default)]
+    pub descending: bool,
+    pub limit: Option<i64>,
+    // next_cursor of the previous page, with the same filters and sort
+    pub cursor: Option<String>,
+}
+
+// Struct to store one search result. Photos and encrypted demographics are left out to keep pages small.
+#[derive(Serialize, Clone, Debug)]
+pub struct PatientSearchRow {
+    pub patient_id: i32,
+    pub mr_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    pub gender: String,
+    pub active: bool,
+    pub last_visit: Option<DateTime<Utc>>,
+    pub has_appointment_today: bool,
+    // Value the rows are ordered by, kept for the cursor
+    #[serde(skip)]
+    pub sort_key: String,
+}
+
+// Struct to store result of search_patients
+#[derive(Serialize, Clone, Debug)]
+pub struct PatientSearchPage {
+    pub patients: Vec<PatientSearchRow>,
+    // None on the last page
+    pub next_cursor: Option<String>,
+}
+
+// A validated search as the repositories run it
+#[derive(Clone, Debug, Default)]
+pub struct PatientFilter {
+    // Lower case prefixes. With both set, one must match the first name and the other the last name.
+    pub name: Option<String>,
+    pub name_rest: Option<String>,
+    // Upper case prefix
+    pub mr_number: Option<String>,
+    pub phone_digits: Option<String>,
+    pub born_from: Option<NaiveDate>,
+    pub born_before: Option<NaiveDate>,
+    pub gender: Option<String>,
+    pub has_appointment_today: Option<bool>,
+    pub last_visit_from: Option<DateTime<Utc>>,
+    pub last_visit_to: Option<DateTime<Utc>>,
+    pub include_inactive: bool,
+    pub sort: PatientSort,
+    pub descending: bool,
+    // Sort key and patient_id of the last row already returned
+    pub after: Option<(String, i32)>,
+    // One more than the page size, so the service can tell whether another page follows
+    pub limit: i64,
+}
+
+// Position after the last row of a page, handed to the frontend as an opaque string
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: PatientSort,
+    descending: bool,
+    key: String,
+    id: i32,
+}
+
+// Function to reduce a phone number to its digits
+pub fn phone_digits(phone: &str) -> String {
+    phone.chars().filter(char::is_ascii_digit).collect()
+}
+
+// Blind index token for one run of leading phone digits
+pub fn phone_search_token(search_key: &str, digits: &str) -> String {
+    hmac_sha256_hex(search_key.as_bytes(), format!("phone:{}", digits).as_bytes())
+}
+
+// Function to compute the tokens stored in patients.phone_search_tokens: one per leading run of
+// at least MIN_PHONE_DIGITS digits, so a prefix search is a single token lookup
+pub fn phone_search_tokens(search_key: &str, phone: Option<&str>) -> Vec<String> {
+    let digits = phone.map(phone_digits).unwrap_or_default();
+    let indexed = digits.len().min(MAX_PHONE_DIGITS);
+
+    (MIN_PHONE_DIGITS..=indexed).map(|length| phone_search_token(search_key, &digits[..length])).collect()
+}
+
+// Function to format the value rows are ordered by. Postgres builds the same strings in SQL.
+pub fn sort_key(sort: PatientSort, row: &PatientSearchRow, created_at: Option<DateTime<Utc>>) -> String {
+    let timestamp = |value: Option<DateTime<Utc>>| {
+        value.map(|value| value.format("%Y-%m-%dT%H:%M:%S%.6f").to_string()).unwrap_or_default()
+    };
+
+    match sort {
+        PatientSort::Name => format!("{}\u{1}{}", row.last_name.to_lowercase(), row.first_name.to_lowercase()),
+        PatientSort::MrNumber => row.mr_number.clone(),
+        PatientSort::DateOfBirth => row.date_of_birth.format("%Y-%m-%d").to_string(),
+        PatientSort::LastVisit => timestamp(row.last_visit),
+        PatientSort::CreatedAt => timestamp(created_at),
+    }
+}
+
+// Function to turn a date of birth prefix into the range of dates it covers
+fn date_prefix_range(prefix: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let parts: Vec<&str> = prefix.split('-').collect();
+    match parts.as_slice() {
+        [year] if year.len() == 4 => {
+            let year = year.parse().ok()?;
+            Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?))
+        }
+        [year, month] if year.len() == 4 && month.len() == 2 => {
+            let start = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)?;
+            Some((start, start.checked_add_months(Months::new(1))?))
+        }
+        [_, _, _] => {
+            let day = NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok()?;
+            Some((day, day.succ_opt()?))
+        }
+        _ => None,
+    }
+}
+
+// Function to check a search and turn it into a filter. Every problem is reported at once.
+pub fn build_filter(query: &PatientSearchQuery, today: NaiveDate) -> Result<PatientFilter, AppError> {
+    let mut errors = vec![];
+    let mut error = |field: &str, message: &str| {
+        errors.push(FieldError { field: field.to_string(), message: message.to_string() })
+    };
+    let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+
+    let mut filter = PatientFilter {
+        mr_number: text(&query.mr_number).map(|mr_number| mr_number.to_uppercase()),
+        gender: text(&query.gender).map(|gender| gender.to_uppercase()),
+        has_appointment_today: query.has_appointment_today,
+        last_visit_from: query.last_visit_from,
+        last_visit_to: query.last_visit_to,
+        include_inactive: query.include_inactive,
+        sort: query.sort,
+        descending: query.descending,
+        limit: query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) + 1,
+        ..PatientFilter::default()
+    };
+
+    if let Some(name) = text(&query.name) {
+        let name = name.to_lowercase();
+        let mut words = name.splitn(2, char::is_whitespace);
+        filter.name = words.next().map(str::to_string);
+        filter.name_rest = words.next().map(str::trim).map(str::to_string);
+    }
+
+    if let Some(phone) = text(&query.phone) {
+        let digits = phone_digits(&phone);
+        if (MIN_PHONE_DIGITS..=MAX_PHONE_DIGITS).contains(&digits.len()) {
+            filter.phone_digits = Some(digits);
+        } else {
+            error("phone", "Enter between 4 and 15 digits of the phone number.");
+        }
+    }
+
+    if filter.gender.as_deref().is_some_and(|gender| !["MALE", "FEMALE", "OTHERS"].contains(&gender)) {
+        error("gender", "Gender must be MALE, FEMALE or OTHERS.");
+    }
+
+    if let Some(date_of_birth) = text(&query.date_of_birth) {
+        match date_prefix_range(&date_of_birth) {
+            Some((from, before)) => {
+                filter.born_from = Some(from);
+                filter.born_before = Some(before);
+            }
+            None => error("date_of_birth", "Use YYYY, YYYY-MM or YYYY-MM-DD."),
+        }
+    }
+
+    // Someone aged n was born on or before today minus n years, and after today minus n + 1 years
+    let years_ago = |years: i32| today.checked_sub_months(Months::new(years as u32 * 12));
+    match (query.min_age, query.max_age) {
+        (Some(min), _) if !(0..=150).contains(&min) => error("min_age", "Age must be between 0 and 150."),
+        (_, Some(max)) if !(0..=150).contains(&max) => error("max_age", "Age must be between 0 and 150."),
+        (Some(min), Some(max)) if min > max => error("min_age", "Minimum age is above the maximum age."),
+        (min_age, max_age) => {
+            if let Some(born_by) = min_age.and_then(years_ago).and_then(|date| date.succ_opt()) {
+                filter.born_before = Some(filter.born_before.map_or(born_by, |before| before.min(born_by)));
+            }
+            if let Some(born_after) = max_age.and_then(|max| years_ago(max + 1)).and_then(|date| date.succ_opt()) {
+                filter.born_from = Some(filter.born_from.map_or(born_after, |from| from.max(born_after)));
+            }
+        }
+    }
+
+    if let (Some(from), Some(to)) = (query.last_visit_from, query.last_visit_to) {
+        if from > to {
+            error("last_visit_from", "The start of the range is after its end.");
+        }
+    }
+
+    if let Some(cursor) = text(&query.cursor) {
+        match decode_cursor(&cursor) {
+            Some(cursor) if cursor.sort == query.sort && cursor.descending == query.descending => filter.after = Some((cursor.key, cursor.id)),
+            _ => error("cursor", "The cursor does not belong to this search. Start again from the first page."),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(filter)
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+fn encode_cursor(sort: PatientSort, descending: bool, row: &PatientSearchRow) -> String {
+    let cursor = Cursor { sort, descending, key: row.sort_key.clone(), id: row.patient_id };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str) -> Option<Cursor> {
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()
+}
+
+impl PatientSearchRepo for PgRepository<'_> {
+    async fn search_patients(&self, filter: &PatientFilter) -> Result<Vec<PatientSearchRow>, AppError> {
+        let like_prefix = |value: &Option<String>| {
+            value.as_deref().map(|value| {
+                format!("{}%", value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+            })
+        };
+        let phone_token = filter
+            .phone_digits
+            .as_deref()
+            .map(|digits| phone_search_token(self.keyring.search_key(), digits));
+        let (after_key, after_id) = match &filter.after {
+            Some((key, id)) => (Some(key.as_str()), Some(*id)),
+            None => (None, None),
+        };
+
+        sqlx::query_as!(
+            PatientSearchRow,
+            r#"
+            SELECT
+                patient_id as "patient_id!",
+                mr_number as "mr_number!",
+                first_name as "first_name!",
+                last_name as "last_name!",
+                date_of_birth as "date_of_birth!",
+                gender as "gender!",
+                active as "active!",
+                last_visit,
+                has_appointment_today as "has_appointment_today!",
+                sort_key as "sort_key!"
+            FROM (
+                SELECT
+                    p.patient_id,
+                    p.mr_number,
+                    p.first_name,
+                    p.last_name,
+                    p.date_of_birth,
+                    p.gender,
+                    p.active,
+                    v.last_visit,
+                    COALESCE(v.has_appointment_today, FALSE) as has_appointment_today,
+                    (CASE $12::TEXT
+                        WHEN 'NAME' THEN LOWER(p.last_name) || chr(1) || LOWER(p.first_name)
+                        WHEN 'MR_NUMBER' THEN p.mr_number
+                        WHEN 'DATE_OF_BIRTH' THEN to_char(p.date_of_birth, 'YYYY-MM-DD')
+                        WHEN 'LAST_VISIT' THEN COALESCE(to_char(v.last_visit AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US'), '')
+                        ELSE COALESCE(to_char(p.created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US'), '')
+                    END) COLLATE "C" as sort_key
+                FROM
+                    patients p
+                LEFT JOIN LATERAL (
+                    SELECT
+                        MAX(a.activity_time) FILTER (WHERE a.activity_time <= NOW()) as last_visit,
+                        BOOL_OR(DATE(a.activity_time) = CURRENT_DATE) as has_appointment_today
+                    FROM patient_activity a
+                    WHERE a.patient_id = p.patient_id
+                ) v ON TRUE
+                WHERE ($11 OR p.active)
+                AND ($1::TEXT IS NULL
+                    OR ($2::TEXT IS NULL AND (LOWER(p.first_name) LIKE $1 OR LOWER(p.last_name) LIKE $1))
+                    OR (LOWER(p.first_name) LIKE $1 AND LOWER(p.last_name) LIKE $2)
+                    OR (LOWER(p.last_name) LIKE $1 AND LOWER(p.first_name) LIKE $2))
+                AND ($3::TEXT IS NULL OR p.mr_number LIKE $3)
+                AND ($4::TEXT IS NULL OR p.phone_search_tokens @> ARRAY[$4::TEXT])
+                AND ($5::DATE IS NULL OR p.date_of_birth >= $5)
+                AND ($6::DATE IS NULL OR p.date_of_birth < $6)
+                AND ($7::TEXT IS NULL OR p.gender = $7)
+                AND ($8::BOOL IS NULL OR COALESCE(v.has_appointment_today, FALSE) = $8)
+                AND ($9::TIMESTAMPTZ IS NULL OR v.last_visit >= $9)
+                AND ($10::TIMESTAMPTZ IS NULL OR v.last_visit < $10)
+            ) matches
+            WHERE ($14::TEXT IS NULL
+                OR (NOT $13 AND (sort_key, patient_id) > ($14 COLLATE "C", $15))
+                OR ($13 AND (sort_key, patient_id) < ($14 COLLATE "C", $15)))
+            ORDER BY
+                CASE WHEN $13 THEN NULL ELSE sort_key END ASC,
+                CASE WHEN $13 THEN sort_key END DESC,
+                CASE WHEN $13 THEN NULL ELSE patient_id END ASC,
+                CASE WHEN $13 THEN patient_id END DESC
+            LIMIT $16
+            "#,
+            like_prefix(&filter.name),
+            like_prefix(&filter.name_rest),
+            like_prefix(&filter.mr_number),
+            phone_token,
+            filter.born_from,
+            filter.born_before,
+            filter.gender,
+            filter.has_appointment_today,
+            filter.last_visit_from,
+            filter.last_visit_to,
+            filter.include_inactive,
+            filter.sort.as_str(),
+            filter.descending,
+            after_key,
+            after_id,
+            filter.limit
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while searching patients", e))
+    }
+}
+
+// Function to search patients one page at a time
+pub async fn find_patients<R: PatientSearchRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    query: &PatientSearchQuery
+) -> Result<PatientSearchPage, AppError> {
+    require(user, Permission::ReadPatient)?;
+
+    let filter = build_filter(query, Utc::now().date_naive())?;
+    let mut patients = repo.search_patients(&filter).await?;
+
+    let page_size = (filter.limit - 1) as usize;
+    let next_cursor = if patients.len() > page_size {
+        patients.truncate(page_size);
+        patients.last().map(|row| encode_cursor(filter.sort, filter.descending, row))
+    } else {
+        None
+    };
+
+    let patient_ids: Vec<i32> = patients.iter().map(|patient| patient.patient_id).collect();
+    repo.record_patient_reads(user.user_id, ENTITY_PATIENT, &patient_ids).await?;
+
+    Ok(PatientSearchPage { patients, next_cursor })
+}
+
+// Endpoint to search patients by name, MR number, phone or date of birth, one page at a time
+#[tauri::command]
+pub async fn search_patients(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    query: PatientSearchQuery,
+) -> Result<PatientSearchPage, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    find_patients(&PgRepository::new(pool, &config.keyring), &user, &query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{test_user, MemoryRepository};
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn field_errors(error: AppError) -> Vec<String> {
+        match error {
+            AppError::Validation(errors) => errors.into_iter().map(|error| error.field).collect(),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn phone_tokens_cover_every_searchable_prefix() {
+        let tokens = phone_search_tokens("key", Some("+1 (555) 010-99"));
+
+        assert_eq!(tokens.len(), 9 - MIN_PHONE_DIGITS + 1);
+        assert_eq!(tokens[0], phone_search_token("key", "1555"));
+        assert!(tokens.contains(&phone_search_token("key", "1555010")));
+        assert_ne!(tokens[0], phone_search_token("other key", "1555"));
+        assert!(phone_search_tokens("key", Some("12")).is_empty());
+        assert!(phone_search_tokens("key", None).is_empty());
+    }
+
+    #[test]
+    fn date_of_birth_prefixes_and_ages_become_date_ranges() {
+        let today = day(2026, 3, 15);
+        let query = PatientSearchQuery { date_of_birth: Some("1980-02".to_string()), ..Default::default() };
+        let filter = build_filter(&query, today).unwrap();
+        assert_eq!((filter.born_from, filter.born_before), (Some(day(1980, 2, 1)), Some(day(1980, 3, 1))));
+
+        // Aged 40 to 45 today
+        let query = PatientSearchQuery { min_age: Some(40), max_age: Some(45), ..Default::default() };
+        let filter = build_filter(&query, today).unwrap();
+        assert_eq!((filter.born_from, filter.born_before), (Some(day(1980, 3, 16)), Some(day(1986, 3, 16))));
+
+        let query = PatientSearchQuery {
+            date_of_birth: Some("80".to_string()),
+            phone: Some("12".to_string()),
+            gender: Some("x".to_string()),
+            min_age: Some(50),
+            max_age: Some(40),
+            ..Default::default()
+        };
+        assert_eq!(field_errors(build_filter(&query, today).unwrap_err()), vec!["phone", "gender", "date_of_birth", "min_age"]);
+    }
+
+    #[test]
+    fn cursors_only_continue_the_same_sort_and_direction() {
+        let row = PatientSearchRow {
+            patient_id: 7,
+            mr_number: "MR1".to_string(),
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            date_of_birth: day(1980, 1, 1),
+            gender: "FEMALE".to_string(),
+            active: true,
+            last_visit: None,
+            has_appointment_today: false,
+            sort_key: "lovelace\u{1}ada".to_string(),
+        };
+        let cursor = encode_cursor(PatientSort::Name, false, &row);
+
+        let query = PatientSearchQuery { cursor: Some(cursor.clone()), ..Default::default() };
+        assert_eq!(build_filter(&query, day(2026, 1, 1)).unwrap().after, Some(("lovelace\u{1}ada".to_string(), 7)));
+
+        let query = PatientSearchQuery { cursor: Some(cursor.clone()), sort: PatientSort::MrNumber, ..Default::default() };
+        assert_eq!(field_errors(build_filter(&query, day(2026, 1, 1)).unwrap_err()), vec!["cursor"]);
+
+        let query = PatientSearchQuery { cursor: Some(cursor), descending: true, ..Default::default() };
+        assert_eq!(field_errors(build_filter(&query, day(2026, 1, 1)).unwrap_err()), vec!["cursor"]);
+    }
+
+    #[tokio::test]
+    async fn pages_follow_the_sort_order_without_gaps() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+        for (first_name, last_name) in [("Ada", "Lovelace"), ("Alan", "Turing"), ("Grace", "Hopper"), ("Adele", "Goldberg")] {
+            repo.add_patient(first_name, last_name);
+        }
+
+        let mut query = PatientSearchQuery { limit: Some(2), ..Default::default() };
+        let first = find_patients(&repo, &nurse, &query).await.unwrap();
+        query.cursor = first.next_cursor.clone();
+        let second = find_patients(&repo, &nurse, &query).await.unwrap();
+
+        let names: Vec<&str> = first.patients.iter().chain(&second.patients).map(|row| row.last_name.as_str()).collect();
+        assert_eq!(names, vec!["Goldberg", "Hopper", "Lovelace", "Turing"]);
+        assert!(second.next_cursor.is_none());
+
+        // A cursor from an ascending page cannot continue a descending search
+        query.cursor = first.next_cursor.clone();
+        query.descending = true;
+        assert!(matches!(find_patients(&repo, &nurse, &query).await, Err(AppError::Validation(_))));
+
+        let query = PatientSearchQuery { name: Some("ad".to_string()), descending: true, ..Default::default() };
+        let found = find_patients(&repo, &nurse, &query).await.unwrap();
+        let names: Vec<&str> = found.patients.iter().map(|row| row.first_name.as_str()).collect();
+        assert_eq!(names, vec!["Ada", "Adele"]);
+        assert_eq!(repo.audit_events().len(), 6);
+    }
+}
@@ -0,0 +1,529 @@
+// src-tauri/src/patient_summary.rs
+
+// Dependencies
+use std::future::Future;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use tauri::State;
+use crate::audit::{AuditAction, AuditEvent, ENTITY_PATIENT_SUMMARY};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::medical_history::{HistoryDetails, HistoryEntry};
+use crate::patients::{PatientData, PatientProcedureData};
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AuditRepo, MedicalHistoryRepo, PatientRepo, PgRepository, VisionRepo};
+use crate::vision::{EyeMeasurementData, RefractionData, VisionData};
+
+const SIDES: [&str; 2] = ["RIGHT", "LEFT"];
+const VISION_VALUE_TYPES: [&str; 3] = ["UC", "BCVA", "PH"];
+const REFRACTION_VALUE_TYPES: [&str; 2] = ["UD", "DL"];
+const REFRACTION_VISION_TYPES: [&str; 2] = ["DV", "NV"];
+// How many complaints and procedures the summary mentions
+const RECENT_LIMIT: usize = 3;
+
+// Struct to store everything a summary is built from
+#[derive(Clone, Debug)]
+pub struct SummaryInput {
+    pub patient: PatientData,
+    // Newest first
+    pub procedures: Vec<PatientProcedureData>,
+    // Entries that have not been removed
+    pub history: Vec<HistoryEntry>,
+    pub visions: Vec<VisionData>,
+    pub refractions: Vec<RefractionData>,
+    pub eye_measurements: Vec<EyeMeasurementData>,
+    // Date ages and current medications are judged against
+    pub today: NaiveDate,
+}
+
+// Turns a patient's record into the sentences shown on the summary card.
+// RuleBasedSummary is the default; a local summarizer can implement this to replace it.
+pub trait SummaryBuilder {
+    fn build(&self, input: &SummaryInput) -> impl Future<Output = Result<Vec<String>, AppError>> + Send;
+}
+
+// Builds the summary from fixed sentence templates. The same record always gives the same text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RuleBasedSummary;
+
+impl SummaryBuilder for RuleBasedSummary {
+    async fn build(&self, input: &SummaryInput) -> Result<Vec<String>, AppError> {
+        Ok(rule_based_summary(input))
+    }
+}
+
+fn format_date(time: Option<DateTime<Utc>>) -> Option<String> {
+    time.map(|time| time.format("%-d %b %Y").to_string())
+}
+
+fn with_date(text: &str, time: Option<DateTime<Utc>>) -> String {
+    match format_date(time) {
+        Some(date) => format!("{} ({})", text, date),
+        None => text.to_string(),
+    }
+}
+
+// Words of an upper-case code, e.g. TO_BE_REVIEWED -> "to be reviewed"
+fn humanize(code: &str) -> String {
+    code.replace('_', " ").to_lowercase()
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+}
+
+fn join_sides(parts: Vec<(String, String)>) -> Option<String> {
+    (!parts.is_empty()).then(|| {
+        parts
+            .into_iter()
+            .map(|(side, value)| format!("{} {}", side, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+fn side_label(side: &str) -> String {
+    match side {
+        "RIGHT" => "right eye (OD)".to_string(),
+        "LEFT" => "left eye (OS)".to_string(),
+        other => humanize(other),
+    }
+}
+
+fn age_on(date_of_birth: NaiveDate, today: NaiveDate) -> i32 {
+    let had_birthday = (today.month(), today.day()) >= (date_of_birth.month(), date_of_birth.day());
+    today.year() - date_of_birth.year() - if had_birthday { 0 } else { 1 }
+}
+
+fn introduction(patient: &PatientData, today: NaiveDate) -> String {
+    let gender = match patient.gender.as_str() {
+        "MALE" => "male",
+        "FEMALE" => "female",
+        _ => "patient",
+    };
+    format!(
+        "{} {} is a {}-year-old {} (MR {}).",
+        patient.first_name,
+        patient.last_name,
+        age_on(patient.date_of_birth, today),
+        gender,
+        patient.mr_number
+    )
+}
+
+fn complaints(procedures: &[PatientProcedureData]) -> Option<String> {
+    let mut seen: Vec<String> = vec![];
+    let recent: Vec<String> = procedures
+        .iter()
+        .filter_map(|procedure| non_empty(&procedure.patient_complaint).map(|complaint| (complaint, procedure.activity_time)))
+        .filter(|(complaint, _)| {
+            let key = complaint.to_lowercase();
+            let new = !seen.contains(&key);
+            seen.push(key);
+            new
+        })
+        .take(RECENT_LIMIT)
+        .map(|(complaint, time)| with_date(complaint, time))
+        .collect();
+
+    match recent.as_slice() {
+        [] => None,
+        [only] => Some(format!("Presenting complaint: {}.", only)),
+        _ => Some(format!("Recent complaints: {}.", recent.join("; "))),
+    }
+}
+
+fn doctors_note(procedures: &[PatientProcedureData]) -> Option<String> {
+    procedures
+        .iter()
+        .find_map(|procedure| non_empty(&procedure.doctors_note).map(|note| (note, procedure.activity_time)))
+        .map(|(note, time)| match format_date(time) {
+            Some(date) => format!("Latest doctor's note ({}): {}", date, note),
+            None => format!("Latest doctor's note: {}", note),
+        })
+}
+
+fn procedures(procedures: &[PatientProcedureData]) -> Option<String> {
+    let recent: Vec<String> = procedures
+        .iter()
+        .take(RECENT_LIMIT)
+        .map(|procedure| {
+            let name = non_empty(&procedure.procedure_name).unwrap_or("Unnamed procedure");
+            let mut details = vec![humanize(&procedure.status)];
+            details.extend(format_date(procedure.activity_time));
+            format!("{} ({})", name, details.join(", "))
+        })
+        .collect();
+
+    (!recent.is_empty()).then(|| {
+        let more = procedures.len().saturating_sub(RECENT_LIMIT);
+        let suffix = if more > 0 { format!(", and {} earlier", more) } else { String::new() };
+        format!("Recent procedures: {}{}.", recent.join("; "), suffix)
+    })
+}
+
+fn conditions(history: &[HistoryEntry]) -> Option<String> {
+    let listed: Vec<String> = history
+        .iter()
+        .filter_map(|entry| match &entry.details {
+            HistoryDetails::Condition(condition) if condition.status != "RESOLVED" => {
+                let mut details = vec![];
+                if condition.status == "IN_REMISSION" {
+                    details.push("in remission".to_string());
+                }
+                if let Some(onset) = condition.onset_date {
+                    details.push(format!("since {}", onset.format("%b %Y")));
+                }
+                Some(if details.is_empty() {
+                    condition.name.clone()
+                } else {
+                    format!("{} ({})", condition.name, details.join(", "))
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    (!listed.is_empty()).then(|| format!("Active conditions: {}.", listed.join(", ")))
+}
+
+fn medications(history: &[HistoryEntry], today: NaiveDate) -> Option<String> {
+    let listed: Vec<String> = history
+        .iter()
+        .filter_map(|entry| match &entry.details {
+            HistoryDetails::Medication(medication) if medication.stop_date.is_none_or(|stop| stop >= today) => {
+                let mut parts = vec![medication.name.clone()];
+                parts.extend(medication.dose.clone());
+                parts.extend(medication.frequency.as_deref().map(str::to_lowercase));
+                Some(parts.join(" "))
+            }
+            _ => None,
+        })
+        .collect();
+
+    (!listed.is_empty()).then(|| format!("Current medications: {}.", listed.join(", ")))
+}
+
+fn allergies(history: &[HistoryEntry]) -> Option<String> {
+    let listed: Vec<String> = history
+        .iter()
+        .filter_map(|entry| match &entry.details {
+            HistoryDetails::Allergy(allergy) => {
+                let mut details = vec![humanize(&allergy.severity)];
+                details.extend(allergy.reaction.as_deref().map(str::to_lowercase));
+                Some(format!("{} ({})", allergy.substance, details.join(", ")))
+            }
+            _ => None,
+        })
+        .collect();
+
+    (!listed.is_empty()).then(|| format!("Allergies: {}.", listed.join(", ")))
+}
+
+fn visual_acuity(visions: &[VisionData]) -> Option<String> {
+    let parts = SIDES
+        .iter()
+        .filter_map(|side| {
+            let vision = visions
+                .iter()
+                .filter(|vision| vision.side == *side && (non_empty(&vision.distant_vision).is_some() || non_empty(&vision.near_vision).is_some()))
+                .max_by_key(|vision| vision.updated_at.or(vision.created_at))?;
+            let mut values = vec![];
+            values.extend(non_empty(&vision.distant_vision).map(|value| format!("{} distance", value)));
+            values.extend(non_empty(&vision.near_vision).map(|value| format!("{} near", value)));
+            Some((side_label(side), format!("{} {}", vision.value_type, values.join(" and "))))
+        })
+        .collect();
+
+    join_sides(parts).map(|sides| format!("Most recent visual acuity: {}.", sides))
+}
+
+fn refraction(refractions: &[RefractionData]) -> Option<String> {
+    let parts = SIDES
+        .iter()
+        .filter_map(|side| {
+            let refraction = refractions
+                .iter()
+                .filter(|refraction| refraction.side == *side && non_empty(&refraction.spherical).is_some())
+                .max_by_key(|refraction| refraction.updated_at.or(refraction.created_at))?;
+            let mut value = non_empty(&refraction.spherical).unwrap_or_default().to_string();
+            if let Some(cylindrical) = non_empty(&refraction.cylindrical) {
+                value.push_str(&format!(" / {}", cylindrical));
+                if let Some(axis) = non_empty(&refraction.axis) {
+                    value.push_str(&format!(" x {}", axis));
+                }
+            }
+            Some((side_label(side), format!("{} ({} {})", value, refraction.value_type, refraction.vision_type)))
+        })
+        .collect();
+
+    join_sides(parts).map(|sides| format!("Most recent refraction: {}.", sides))
+}
+
+fn intraocular_pressure(measurements: &[EyeMeasurementData]) -> Option<String> {
+    let parts = SIDES
+        .iter()
+        .filter_map(|side| {
+            let measurement = measurements
+                .iter()
+                .filter(|measurement| measurement.side == *side)
+                .max_by_key(|measurement| measurement.updated_at.or(measurement.created_at))?;
+            let value = match (non_empty(&measurement.iop_at), non_empty(&measurement.iop_nct)) {
+                (Some(applanation), _) => format!("{} mmHg (applanation)", applanation),
+                (None, Some(non_contact)) => format!("{} mmHg (non-contact)", non_contact),
+                (None, None) => return None,
+            };
+            Some((side_label(side), value))
+        })
+        .collect();
+
+    join_sides(parts).map(|sides| format!("Most recent IOP: {}.", sides))
+}
+
+// Function to compose the summary sentences in a fixed order, leaving out anything not recorded
+pub fn rule_based_summary(input: &SummaryInput) -> Vec<String> {
+    [
+        Some(introduction(&input.patient, input.today)),
+        complaints(&input.procedures),
+        doctors_note(&input.procedures),
+        procedures(&input.procedures),
+        conditions(&input.history),
+        medications(&input.history, input.today),
+        allergies(&input.history),
+        visual_acuity(&input.visions),
+        refraction(&input.refractions),
+        intraocular_pressure(&input.eye_measurements),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+// Function to gather what a summary is built from
+async fn summary_input<R: PatientRepo + MedicalHistoryRepo + VisionRepo>(repo: &R, patient_id: i32) -> Result<SummaryInput, AppError> {
+    let patient = repo
+        .find_patient(patient_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("No patient with this id."))?;
+
+    let mut visions = vec![];
+    let mut refractions = vec![];
+    let mut eye_measurements = vec![];
+    for side in SIDES {
+        for value_type in VISION_VALUE_TYPES {
+            visions.extend(repo.find_vision(patient_id, side, value_type).await?);
+        }
+        for value_type in REFRACTION_VALUE_TYPES {
+            for vision_type in REFRACTION_VISION_TYPES {
+                refractions.extend(repo.find_refraction(patient_id, side, value_type, vision_type).await?);
+            }
+        }
+        eye_measurements.extend(repo.find_eye_measurement(patient_id, side).await?);
+    }
+
+    Ok(SummaryInput {
+        patient,
+        procedures: repo.list_patient_procedures(patient_id).await?,
+        history: repo.list_history_entries(patient_id).await?,
+        visions,
+        refractions,
+        eye_measurements,
+        today: Utc::now().date_naive(),
+    })
+}
+
+// Function to build a patient's summary with the given builder
+pub async fn patient_summary<R, B>(repo: &R, builder: &B, user: &AuthUser, patient_id: i32) -> Result<Vec<String>, AppError>
+where
+    R: PatientRepo + MedicalHistoryRepo + VisionRepo + AuditRepo,
+    B: SummaryBuilder,
+{
+    require(user, Permission::ReadClinical)?;
+
+    let input = summary_input(repo, patient_id).await?;
+    let summary = builder.build(&input).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_SUMMARY).patient(patient_id))
+        .await?;
+
+    Ok(summary)
+}
+
+// Endpoint to get patient summary data
+#[tauri::command]
+pub async fn get_patient_summary_data(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<Vec<String>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    patient_summary(&PgRepository::new(pool, &config.keyring), &RuleBasedSummary, &user, patient_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::medical_history::{add_history_entry, Allergy, Condition, Medication};
+    use crate::memory_repository::{new_activity, test_user, MemoryRepository};
+    use crate::patients::NewPatientActivity;
+    use crate::vision::{EyeMeasurementInput, VisionInput};
+
+    fn procedure(name: &str, status: &str, complaint: &str, note: Option<&str>, day: u32) -> PatientProcedureData {
+        PatientProcedureData {
+            activity_id: day as i32,
+            status: status.to_string(),
+            procedure_name: Some(name.to_string()),
+            procedure_description: None,
+            doctors_note: note.map(str::to_string),
+            patient_complaint: Some(complaint.to_string()),
+            comments: None,
+            activity_time: Some(Utc.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap()),
+        }
+    }
+
+    fn entry(details: HistoryDetails) -> HistoryEntry {
+        HistoryEntry {
+            entry_id: 1,
+            patient_id: 1,
+            details,
+            created_at: Utc::now(),
+            created_by: None,
+            created_by_name: None,
+            updated_at: None,
+            updated_by: None,
+            updated_by_name: None,
+            removed_at: None,
+            removed_by: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn summary_is_composed_from_the_record() {
+        let repo = MemoryRepository::default();
+        let patient_id = repo.add_patient("Grace", "Hopper");
+        let patient = repo.find_patient(patient_id).await.unwrap().unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let input = SummaryInput {
+            patient,
+            procedures: vec![
+                procedure("Cataract surgery", "TO_BE_REVIEWED", "Blurred vision", Some("Plan surgery on the right eye."), 20),
+                procedure("Slit lamp exam", "COMPLETED", "blurred vision", None, 10),
+                procedure("Refraction", "COMPLETED", "Headaches when reading", Some("Glasses prescribed."), 2),
+                procedure("Tonometry", "COMPLETED", "Routine check", None, 1),
+            ],
+            history: vec![
+                entry(HistoryDetails::Condition(Condition {
+                    name: "Glaucoma".to_string(),
+                    onset_date: NaiveDate::from_ymd_opt(2021, 3, 15),
+                    status: "ACTIVE".to_string(),
+                    notes: None,
+                })),
+                entry(HistoryDetails::Condition(Condition {
+                    name: "Conjunctivitis".to_string(),
+                    onset_date: None,
+                    status: "RESOLVED".to_string(),
+                    notes: None,
+                })),
+                entry(HistoryDetails::Medication(Medication {
+                    name: "Timolol".to_string(),
+                    dose: Some("0.5%".to_string()),
+                    frequency: Some("Twice daily".to_string()),
+                    start_date: None,
+                    stop_date: None,
+                    notes: None,
+                })),
+                entry(HistoryDetails::Medication(Medication {
+                    name: "Prednisolone".to_string(),
+                    dose: None,
+                    frequency: None,
+                    start_date: None,
+                    stop_date: NaiveDate::from_ymd_opt(2024, 5, 1),
+                    notes: None,
+                })),
+                entry(HistoryDetails::Allergy(Allergy {
+                    substance: "Penicillin".to_string(),
+                    reaction: Some("Hives".to_string()),
+                    severity: "SEVERE".to_string(),
+                    notes: None,
+                })),
+            ],
+            visions: vec![],
+            refractions: vec![],
+            eye_measurements: vec![],
+            today,
+        };
+
+        let summary = RuleBasedSummary.build(&input).await.unwrap();
+
+        assert_eq!(
+            summary,
+            vec![
+                format!("Grace Hopper is a 44-year-old female (MR MR{:06}).", patient_id),
+                "Recent complaints: Blurred vision (20 May 2024); Headaches when reading (2 May 2024); Routine check (1 May 2024).".to_string(),
+                "Latest doctor's note (20 May 2024): Plan surgery on the right eye.".to_string(),
+                "Recent procedures: Cataract surgery (to be reviewed, 20 May 2024); Slit lamp exam (completed, 10 May 2024); Refraction (completed, 2 May 2024), and 1 earlier.".to_string(),
+                "Active conditions: Glaucoma (since Mar 2021).".to_string(),
+                "Current medications: Timolol 0.5% twice daily.".to_string(),
+                "Allergies: Penicillin (severe, hives).".to_string(),
+            ]
+        );
+        assert_eq!(rule_based_summary(&input), summary);
+    }
+
+    #[tokio::test]
+    async fn summary_reads_the_latest_measurements_and_is_audited() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let patient_id = repo.add_patient("Grace", "Hopper");
+        let procedure_id = repo.add_procedure("OCT scan", "Optical coherence tomography");
+        repo.create_activity(doctor.user_id, &NewPatientActivity {
+            patient_complaint: "Red and watery eyes".to_string(),
+            ..new_activity(patient_id, procedure_id)
+        })
+        .await
+        .unwrap();
+        let vision = |side: &str, value_type: &str, distant: &str| VisionInput {
+            patient_id,
+            near_vision: "".to_string(),
+            distant_vision: distant.to_string(),
+            side: side.to_string(),
+            value_type: value_type.to_string(),
+        };
+        repo.upsert_vision(doctor.user_id, &vision("RIGHT", "UC", "6/18")).await.unwrap();
+        repo.upsert_vision(doctor.user_id, &vision("RIGHT", "BCVA", "6/6")).await.unwrap();
+        repo.upsert_eye_measurement(doctor.user_id, &EyeMeasurementInput {
+            patient_id,
+            iop_at: "".to_string(),
+            iop_nct: "21".to_string(),
+            cct: "".to_string(),
+            tond: "".to_string(),
+            side: "LEFT".to_string(),
+        })
+        .await
+        .unwrap();
+        add_history_entry(&repo, &doctor, patient_id, &HistoryDetails::Allergy(Allergy {
+            substance: "Latex".to_string(),
+            reaction: None,
+            severity: "MILD".to_string(),
+            notes: None,
+        }))
+        .await
+        .unwrap();
+
+        let summary = patient_summary(&repo, &RuleBasedSummary, &doctor, patient_id).await.unwrap();
+
+        assert!(summary.contains(&"Allergies: Latex (mild).".to_string()));
+        assert!(summary.iter().any(|line| line.starts_with("Presenting complaint: Red and watery eyes (")));
+        assert!(summary.contains(&"Most recent visual acuity: right eye (OD) BCVA 6/6 distance.".to_string()));
+        assert!(summary.contains(&"Most recent IOP: left eye (OS) 21 mmHg (non-contact).".to_string()));
+        assert!(!summary.iter().any(|line| line.contains("refraction") || line.contains("doctor's note")));
+        assert!(repo.audit_events().iter().any(|event| event.entity == ENTITY_PATIENT_SUMMARY && event.patient_id == Some(patient_id)));
+
+        let error = patient_summary(&repo, &RuleBasedSummary, &doctor, 999).await.unwrap_err();
+        assert_eq!(error.code(), "NOT_FOUND");
+    }
+}
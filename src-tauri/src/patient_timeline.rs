@@ -0,0 +1,852 @@
+// src-tauri/src/patient_timeline.rs
+
+// Dependencies
+use std::cmp::{Ordering, Reverse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::alert::Alert;
+use crate::appointment::Appointment;
+use crate::audit::{AuditAction, AuditEvent, ENTITY_PATIENT_TIMELINE};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::{AppError, FieldError};
+use crate::medical_history::{HistoryChange, HistoryChangeRow};
+use crate::patients::PatientProcedureData;
+use crate::permissions::{authenticate, require, Permission};
+use crate::procedure_comments::{CommentRow, ProcedureComment};
+use crate::repository::{AuditRepo, PgRepository, TimelineRepo};
+use crate::vision::{EyeMeasurementData, RefractionData, VisionData};
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+
+// Kinds of timeline events. Events at the same moment are listed in this order.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimelineKind {
+    Activity,
+    Note,
+    Comment,
+    Vision,
+    Refraction,
+    EyeMeasurement,
+    Appointment,
+    Alert,
+    HistoryChange,
+}
+
+pub const TIMELINE_KINDS: [TimelineKind; 9] = [
+    TimelineKind::Activity,
+    TimelineKind::Note,
+    TimelineKind::Comment,
+    TimelineKind::Vision,
+    TimelineKind::Refraction,
+    TimelineKind::EyeMeasurement,
+    TimelineKind::Appointment,
+    TimelineKind::Alert,
+    TimelineKind::HistoryChange,
+];
+
+// Struct to store a procedure performed or planned for the patient
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TimelineActivity {
+    pub activity_id: i32,
+    pub procedure_name: Option<String>,
+    pub status: String,
+    pub patient_complaint: Option<String>,
+}
+
+// Struct to store the doctor's note of an activity
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TimelineNote {
+    pub activity_id: i32,
+    pub procedure_name: Option<String>,
+    pub doctors_note: String,
+}
+
+// What happened, tagged with its kind
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", content = "data", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimelineData {
+    Activity(TimelineActivity),
+    Note(TimelineNote),
+    Comment(ProcedureComment),
+    Vision(VisionData),
+    Refraction(RefractionData),
+    EyeMeasurement(EyeMeasurementData),
+    Appointment(Appointment),
+    Alert(Alert),
+    HistoryChange(HistoryChange),
+}
+
+impl TimelineData {
+    pub fn kind(&self) -> TimelineKind {
+        match self {
+            TimelineData::Activity(_) => TimelineKind::Activity,
+            TimelineData::Note(_) => TimelineKind::Note,
+            TimelineData::Comment(_) => TimelineKind::Comment,
+            TimelineData::Vision(_) => TimelineKind::Vision,
+            TimelineData::Refraction(_) => TimelineKind::Refraction,
+            TimelineData::EyeMeasurement(_) => TimelineKind::EyeMeasurement,
+            TimelineData::Appointment(_) => TimelineKind::Appointment,
+            TimelineData::Alert(_) => TimelineKind::Alert,
+            TimelineData::HistoryChange(_) => TimelineKind::HistoryChange,
+        }
+    }
+}
+
+// Struct to store one event of get_patient_timeline
+#[derive(Serialize, Clone, Debug)]
+pub struct TimelineEvent {
+    pub occurred_at: DateTime<Utc>,
+    // Id of the row the event comes from, unique within its kind
+    pub source_id: i32,
+    #[serde(flatten)]
+    pub data: TimelineData,
+}
+
+impl TimelineEvent {
+    fn new(occurred_at: DateTime<Utc>, source_id: i32, data: TimelineData) -> Self {
+        TimelineEvent { occurred_at, source_id, data }
+    }
+
+    pub fn kind(&self) -> TimelineKind {
+        self.data.kind()
+    }
+
+    // Newest first; events at the same moment by kind, then newest row first
+    fn sort_key(&self) -> (Reverse<DateTime<Utc>>, TimelineKind, Reverse<i32>) {
+        (Reverse(self.occurred_at), self.kind(), Reverse(self.source_id))
+    }
+}
+
+// Struct to store the filters of get_patient_timeline
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct TimelineQuery {
+    // Every kind when empty
+    #[serde(default)]
+    pub kinds: Vec<TimelineKind>,
+    // Inclusive start and exclusive end
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    // next_cursor of the previous page, with the same filters
+    pub cursor: Option<String>,
+}
+
+// Struct to store result of get_patient_timeline
+#[derive(Serialize, Clone, Debug)]
+pub struct TimelinePage {
+    pub events: Vec<TimelineEvent>,
+    // None on the last page
+    pub next_cursor: Option<String>,
+}
+
+// A validated timeline query as the repositories run it
+#[derive(Clone, Debug)]
+pub struct TimelineFilter {
+    pub patient_id: i32,
+    pub kinds: Vec<TimelineKind>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    // Last event already returned; only events after it in timeline order are wanted
+    pub after: Option<TimelinePosition>,
+    // Most events of one kind a page can use: one more than the page size, to tell if another page follows
+    pub limit: i64,
+}
+
+// Time, kind and source row of a timeline event
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TimelinePosition {
+    pub occurred_at: DateTime<Utc>,
+    pub kind: TimelineKind,
+    pub source_id: i32,
+}
+
+impl TimelineFilter {
+    pub fn wants(&self, kind: TimelineKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| time >= from) && self.to.is_none_or(|to| time < to)
+    }
+
+    // Keyset bound for rows of one kind: rows whose (time, id) sorts below it come after the cursor.
+    // At the cursor's moment, kinds listed before the cursor's were all on earlier pages and later kinds on none.
+    pub fn before(&self, kind: TimelineKind) -> Option<(DateTime<Utc>, i32)> {
+        self.after.map(|after| {
+            let source_id = match kind.cmp(&after.kind) {
+                Ordering::Less => i32::MIN,
+                Ordering::Equal => after.source_id,
+                Ordering::Greater => i32::MAX,
+            };
+            (after.occurred_at, source_id)
+        })
+    }
+
+    // Keeps the events of the wanted kinds inside the window and after the cursor
+    pub fn retain(&self, events: &mut Vec<TimelineEvent>) {
+        events.retain(|event| {
+            self.wants(event.kind())
+                && self.contains(event.occurred_at)
+                && self.before(event.kind()).is_none_or(|bound| (event.occurred_at, event.source_id) < bound)
+        });
+    }
+}
+
+// Position after the last event of a page, handed to the frontend as an opaque string
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Cursor {
+    #[serde(flatten)]
+    position: TimelinePosition,
+    // Kinds of the query the cursor was made for
+    kinds: Vec<TimelineKind>,
+}
+
+fn encode_cursor(event: &TimelineEvent, kinds: &[TimelineKind]) -> String {
+    let position = TimelinePosition { occurred_at: event.occurred_at, kind: event.kind(), source_id: event.source_id };
+    let cursor = Cursor { position, kinds: kinds.to_vec() };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str) -> Option<Cursor> {
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()
+}
+
+// Function to check a timeline query. Returns the filter and the page size.
+fn build_filter(patient_id: i32, query: &TimelineQuery) -> Result<(TimelineFilter, usize), AppError> {
+    let mut errors = vec![];
+    let mut error = |field: &str, message: &str| errors.push(FieldError { field: field.to_string(), message: message.to_string() });
+
+    let mut kinds = if query.kinds.is_empty() { TIMELINE_KINDS.to_vec() } else { query.kinds.clone() };
+    kinds.sort();
+    kinds.dedup();
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            error("from", "The start of the range is after its end.");
+        }
+    }
+
+    let page_size = match query.limit {
+        None => DEFAULT_PAGE_SIZE,
+        Some(limit) if (1..=MAX_PAGE_SIZE as i64).contains(&limit) => limit as usize,
+        Some(_) => {
+            error("limit", &format!("Limit must be between 1 and {}.", MAX_PAGE_SIZE));
+            DEFAULT_PAGE_SIZE
+        }
+    };
+
+    let cursor = match query.cursor.as_deref().map(str::trim).filter(|cursor| !cursor.is_empty()) {
+        None => None,
+        Some(cursor) => match decode_cursor(cursor) {
+            Some(cursor) if cursor.kinds == kinds => Some(cursor),
+            _ => {
+                error("cursor", "The cursor does not belong to this timeline. Start again from the first page.");
+                None
+            }
+        },
+    };
+
+    if !errors.is_empty() {
+        return Err(AppError::Validation(errors));
+    }
+
+    let after = cursor.map(|cursor| cursor.position);
+    let limit = page_size as i64 + 1;
+    Ok((TimelineFilter { patient_id, kinds, from: query.from, to: query.to, after, limit }, page_size))
+}
+
+// Function to turn activities into their activity and note events
+pub fn activity_events(procedures: Vec<PatientProcedureData>) -> Vec<TimelineEvent> {
+    let mut events = vec![];
+    for procedure in procedures {
+        // Notes carry no time of their own, so they sit at their activity's time
+        let Some(occurred_at) = procedure.activity_time else {
+            continue;
+        };
+        let activity_id = procedure.activity_id;
+
+        if let Some(doctors_note) = procedure.doctors_note.filter(|note| !note.trim().is_empty()) {
+            let note = TimelineNote { activity_id, procedure_name: procedure.procedure_name.clone(), doctors_note };
+            events.push(TimelineEvent::new(occurred_at, activity_id, TimelineData::Note(note)));
+        }
+
+        let activity = TimelineActivity {
+            activity_id,
+            procedure_name: procedure.procedure_name,
+            status: procedure.status,
+            patient_complaint: procedure.patient_complaint,
+        };
+        events.push(TimelineEvent::new(occurred_at, activity_id, TimelineData::Activity(activity)));
+    }
+    events
+}
+
+// Function to turn comments and replies into events at the time they were written. Deleted ones are left out.
+pub fn comment_events(comments: Vec<ProcedureComment>) -> Vec<TimelineEvent> {
+    comments
+        .into_iter()
+        .filter(|comment| comment.deleted_at.is_none())
+        .map(|comment| TimelineEvent::new(comment.created_at, comment.comment_id, TimelineData::Comment(comment)))
+        .collect()
+}
+
+// Function to turn the recorded vision, refraction and IOP values into events at their last change
+pub fn measurement_events(
+    visions: Vec<VisionData>,
+    refractions: Vec<RefractionData>,
+    eye_measurements: Vec<EyeMeasurementData>
+) -> Vec<TimelineEvent> {
+    let visions = visions.into_iter().filter_map(|vision| {
+        let occurred_at = vision.updated_at.or(vision.created_at)?;
+        Some(TimelineEvent::new(occurred_at, vision.vision_id, TimelineData::Vision(vision)))
+    });
+    let refractions = refractions.into_iter().filter_map(|refraction| {
+        let occurred_at = refraction.updated_at.or(refraction.created_at)?;
+        Some(TimelineEvent::new(occurred_at, refraction.refraction_id, TimelineData::Refraction(refraction)))
+    });
+    let eye_measurements = eye_measurements.into_iter().filter_map(|measurement| {
+        let occurred_at = measurement.updated_at.or(measurement.created_at)?;
+        Some(TimelineEvent::new(occurred_at, measurement.measurement_id, TimelineData::EyeMeasurement(measurement)))
+    });
+
+    visions.chain(refractions).chain(eye_measurements).collect()
+}
+
+// Function to turn appointments, alerts and history changes into events
+pub fn record_events(appointments: Vec<Appointment>, alerts: Vec<Alert>, changes: Vec<HistoryChange>) -> Vec<TimelineEvent> {
+    let appointments = appointments.into_iter().filter_map(|appointment| {
+        let occurred_at = appointment.appointment_time?;
+        Some(TimelineEvent::new(occurred_at, appointment.appointment_id, TimelineData::Appointment(appointment)))
+    });
+    let alerts = alerts.into_iter().filter_map(|alert| {
+        let occurred_at = alert.created_at?;
+        Some(TimelineEvent::new(occurred_at, alert.alert_id, TimelineData::Alert(alert)))
+    });
+    let changes = changes
+        .into_iter()
+        .map(|change| TimelineEvent::new(change.changed_at, change.change_id, TimelineData::HistoryChange(change)));
+
+    appointments.chain(alerts).chain(changes).collect()
+}
+
+impl TimelineRepo for PgRepository<'_> {
+    async fn patient_exists(&self, patient_id: i32) -> Result<bool, AppError> {
+        sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM patients WHERE patient_id = $1) as "exists!""#, patient_id)
+            .fetch_one(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while checking patient", e))
+    }
+
+    // Each source returns at most filter.limit rows after the cursor, so a page never reads a whole history
+    async fn list_timeline_events(&self, filter: &TimelineFilter) -> Result<Vec<TimelineEvent>, AppError> {
+        let keys = self.keyring.sql_keys();
+        let patient_id = filter.patient_id;
+        let mut events = vec![];
+
+        // Notes come from the same rows as activities but page on their own
+        for (kind, every_row) in [(TimelineKind::Activity, true), (TimelineKind::Note, false)] {
+            if !filter.wants(kind) {
+                continue;
+            }
+            let (before_time, before_id) = filter.before(kind).unzip();
+            let procedures = sqlx::query_as!(
+                PatientProcedureData,
+                r#"
+                SELECT
+                    pa.activity_id,
+                    pa.status,
+                    pgp_sym_decrypt(pr.procedure_name::bytea, ($1::TEXT[])[pr.key_id]) as procedure_name,
+                    NULL::TEXT as procedure_description,
+                    pgp_sym_decrypt(pa.doctors_note::bytea, ($1::TEXT[])[pa.key_id]) as doctors_note,
+                    pgp_sym_decrypt(pa.patient_complaint::bytea, ($1::TEXT[])[pa.key_id]) as patient_complaint,
+                    NULL::TEXT as comments,
+                    pa.activity_time
+                FROM patient_activity pa
+                LEFT JOIN procedures pr ON pa.procedure_id = pr.procedure_id
+                WHERE pa.patient_id = $2 AND pa.activity_time IS NOT NULL
+                AND ($3::TIMESTAMPTZ IS NULL OR pa.activity_time >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR pa.activity_time < $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR (pa.activity_time, pa.activity_id) < ($5, $6::INT))
+                AND ($7::BOOLEAN OR TRIM(pgp_sym_decrypt(pa.doctors_note::bytea, ($1::TEXT[])[pa.key_id])) <> '')
+                ORDER BY pa.activity_time DESC, pa.activity_id DESC
+                LIMIT $8
+                "#,
+                &keys,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                every_row,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline activities", e))?;
+            events.extend(activity_events(procedures).into_iter().filter(|event| event.kind() == kind));
+        }
+
+        if filter.wants(TimelineKind::Comment) {
+            let (before_time, before_id) = filter.before(TimelineKind::Comment).unzip();
+            let comments = sqlx::query_as!(
+                CommentRow,
+                r#"
+                SELECT
+                    c.comment_id,
+                    c.activity_id,
+                    pa.patient_id,
+                    c.parent_id,
+                    pgp_sym_decrypt(c.body::bytea, ($1::TEXT[])[c.key_id]) as body,
+                    c.created_at,
+                    c.created_by,
+                    u.first_name || ' ' || u.last_name as created_by_name,
+                    c.updated_at,
+                    c.updated_by,
+                    c.deleted_at,
+                    c.deleted_by
+                FROM procedure_comments c
+                JOIN patient_activity pa ON pa.activity_id = c.activity_id
+                LEFT JOIN users u ON u.user_id = c.created_by
+                WHERE pa.patient_id = $2 AND c.deleted_at IS NULL
+                AND ($3::TIMESTAMPTZ IS NULL OR c.created_at >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR c.created_at < $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR (c.created_at, c.comment_id) < ($5, $6::INT))
+                ORDER BY c.created_at DESC, c.comment_id DESC
+                LIMIT $7
+                "#,
+                &keys,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline comments", e))?;
+            events.extend(comment_events(comments.into_iter().map(ProcedureComment::from).collect()));
+        }
+
+        let visions = if filter.wants(TimelineKind::Vision) {
+            let (before_time, before_id) = filter.before(TimelineKind::Vision).unzip();
+            sqlx::query_as!(
+                VisionData,
+                r#"
+                SELECT
+                    vision_id,
+                    patient_id,
+                    pgp_sym_decrypt(near_vision::bytea, ($1::TEXT[])[key_id]) as near_vision,
+                    pgp_sym_decrypt(distant_vision::bytea, ($1::TEXT[])[key_id]) as distant_vision,
+                    side,
+                    value_type,
+                    created_at,
+                    created_by,
+                    updated_at,
+                    updated_by
+                FROM vision
+                WHERE patient_id = $2 AND COALESCE(updated_at, created_at) IS NOT NULL
+                AND ($3::TIMESTAMPTZ IS NULL OR COALESCE(updated_at, created_at) >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR COALESCE(updated_at, created_at) < $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR (COALESCE(updated_at, created_at), vision_id) < ($5, $6::INT))
+                ORDER BY COALESCE(updated_at, created_at) DESC, vision_id DESC
+                LIMIT $7
+                "#,
+                &keys,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline vision values", e))?
+        } else {
+            vec![]
+        };
+        let refractions = if filter.wants(TimelineKind::Refraction) {
+            let (before_time, before_id) = filter.before(TimelineKind::Refraction).unzip();
+            sqlx::query_as!(
+                RefractionData,
+                r#"
+                SELECT
+                    refraction_id,
+                    patient_id,
+                    pgp_sym_decrypt(spherical::bytea, ($1::TEXT[])[key_id]) as spherical,
+                    pgp_sym_decrypt(cylindrical::bytea, ($1::TEXT[])[key_id]) as cylindrical,
+                    pgp_sym_decrypt(axis::bytea, ($1::TEXT[])[key_id]) as axis,
+                    side,
+                    value_type,
+                    vision_type,
+                    created_at,
+                    created_by,
+                    updated_at,
+                    updated_by
+                FROM refraction
+                WHERE patient_id = $2 AND COALESCE(updated_at, created_at) IS NOT NULL
+                AND ($3::TIMESTAMPTZ IS NULL OR COALESCE(updated_at, created_at) >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR COALESCE(updated_at, created_at) < $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR (COALESCE(updated_at, created_at), refraction_id) < ($5, $6::INT))
+                ORDER BY COALESCE(updated_at, created_at) DESC, refraction_id DESC
+                LIMIT $7
+                "#,
+                &keys,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline refraction values", e))?
+        } else {
+            vec![]
+        };
+        let eye_measurements = if filter.wants(TimelineKind::EyeMeasurement) {
+            let (before_time, before_id) = filter.before(TimelineKind::EyeMeasurement).unzip();
+            sqlx::query_as!(
+                EyeMeasurementData,
+                r#"
+                SELECT
+                    measurement_id,
+                    patient_id,
+                    pgp_sym_decrypt(iop_at::bytea, ($1::TEXT[])[key_id]) as iop_at,
+                    pgp_sym_decrypt(iop_nct::bytea, ($1::TEXT[])[key_id]) as iop_nct,
+                    pgp_sym_decrypt(cct::bytea, ($1::TEXT[])[key_id]) as cct,
+                    pgp_sym_decrypt(tond::bytea, ($1::TEXT[])[key_id]) as tond,
+                    side,
+                    created_at,
+                    created_by,
+                    updated_at,
+                    updated_by
+                FROM eye_measurement
+                WHERE patient_id = $2 AND COALESCE(updated_at, created_at) IS NOT NULL
+                AND ($3::TIMESTAMPTZ IS NULL OR COALESCE(updated_at, created_at) >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR COALESCE(updated_at, created_at) < $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR (COALESCE(updated_at, created_at), measurement_id) < ($5, $6::INT))
+                ORDER BY COALESCE(updated_at, created_at) DESC, measurement_id DESC
+                LIMIT $7
+                "#,
+                &keys,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline eye measurements", e))?
+        } else {
+            vec![]
+        };
+        events.extend(measurement_events(visions, refractions, eye_measurements));
+
+        let appointments = if filter.wants(TimelineKind::Appointment) {
+            let (before_time, before_id) = filter.before(TimelineKind::Appointment).unzip();
+            sqlx::query_as!(
+                Appointment,
+                r#"
+                SELECT
+                    appointment_id,
+                    description,
+                    appointment_time,
+                    (EXTRACT(EPOCH FROM appointment_duration) * 1000000)::BIGINT as "appointment_duration?",
+                    created_by,
+                    created_at,
+                    patient_id
+                FROM appointments
+                WHERE patient_id = $1 AND appointment_time IS NOT NULL
+                AND ($2::TIMESTAMPTZ IS NULL OR appointment_time >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR appointment_time < $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR (appointment_time, appointment_id) < ($4, $5::INT))
+                ORDER BY appointment_time DESC, appointment_id DESC
+                LIMIT $6
+                "#,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline appointments", e))?
+        } else {
+            vec![]
+        };
+        let alerts = if filter.wants(TimelineKind::Alert) {
+            let (before_time, before_id) = filter.before(TimelineKind::Alert).unzip();
+            sqlx::query_as!(
+                Alert,
+                r#"
+                SELECT
+                    a.alert_id, a.priority_level, a.title, a.message, a.issued_for,
+                    uf.first_name || ' ' || uf.last_name AS issued_for_name,
+                    a.issued_by,
+                    ub.first_name || ' ' || ub.last_name AS issued_by_name,
+                    a.status, a.created_at, a.patient_id
+                FROM alerts a
+                LEFT JOIN users uf ON a.issued_for = uf.user_id
+                LEFT JOIN users ub ON a.issued_by = ub.user_id
+                WHERE a.patient_id = $1 AND a.created_at IS NOT NULL
+                AND ($2::TIMESTAMPTZ IS NULL OR a.created_at >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR a.created_at < $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR (a.created_at, a.alert_id) < ($4, $5::INT))
+                ORDER BY a.created_at DESC, a.alert_id DESC
+                LIMIT $6
+                "#,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline alerts", e))?
+        } else {
+            vec![]
+        };
+        let changes = if filter.wants(TimelineKind::HistoryChange) {
+            let (before_time, before_id) = filter.before(TimelineKind::HistoryChange).unzip();
+            sqlx::query_as!(
+                HistoryChangeRow,
+                r#"
+                SELECT
+                    h.change_id,
+                    h.entry_id,
+                    h.action,
+                    pgp_sym_decrypt(h.details::bytea, ($1::TEXT[])[h.key_id]) as details,
+                    h.changed_by,
+                    u.first_name || ' ' || u.last_name as changed_by_name,
+                    h.changed_at
+                FROM medical_history_changes h
+                LEFT JOIN users u ON u.user_id = h.changed_by
+                WHERE h.patient_id = $2
+                AND ($3::TIMESTAMPTZ IS NULL OR h.changed_at >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR h.changed_at < $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR (h.changed_at, h.change_id) < ($5, $6::INT))
+                ORDER BY h.changed_at DESC, h.change_id DESC
+                LIMIT $7
+                "#,
+                &keys,
+                patient_id,
+                filter.from,
+                filter.to,
+                before_time,
+                before_id,
+                filter.limit
+            )
+            .fetch_all(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while fetching timeline history changes", e))?
+            .into_iter()
+            .map(HistoryChange::try_from)
+            .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![]
+        };
+        events.extend(record_events(appointments, alerts, changes));
+
+        filter.retain(&mut events);
+        Ok(events)
+    }
+}
+
+// Function to read one page of a patient's timeline, newest first
+pub async fn find_patient_timeline<R: TimelineRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32,
+    query: &TimelineQuery
+) -> Result<TimelinePage, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let (filter, page_size) = build_filter(patient_id, query)?;
+    if !repo.patient_exists(patient_id).await? {
+        return Err(AppError::not_found("Patient does not exist."));
+    }
+    let mut events = repo.list_timeline_events(&filter).await?;
+    events.sort_by_key(TimelineEvent::sort_key);
+
+    let next_cursor = if events.len() > page_size {
+        events.truncate(page_size);
+        events.last().map(|event| encode_cursor(event, &filter.kinds))
+    } else {
+        None
+    };
+
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_TIMELINE).patient(patient_id))
+        .await?;
+
+    Ok(TimelinePage { events, next_cursor })
+}
+
+// Endpoint to get a patient's activities, notes, measurements, appointments, alerts and history changes as one stream
+#[tauri::command]
+pub async fn get_patient_timeline(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+    query: Option<TimelineQuery>,
+) -> Result<TimelinePage, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    find_patient_timeline(&PgRepository::new(pool, &config.keyring), &user, patient_id, &query.unwrap_or_default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::alert::{issue_alert, NewAlert};
+    use crate::appointment::{schedule_appointment, NewAppointment};
+    use crate::medical_history::{add_history_entry, Allergy, HistoryDetails};
+    use crate::memory_repository::{new_activity, test_user, MemoryRepository};
+    use crate::patients::NewPatientActivity;
+    use crate::repository::{PatientRepo, ProcedureCommentRepo, VisionRepo};
+    use crate::vision::VisionInput;
+
+    fn field_errors(error: AppError) -> Vec<String> {
+        match error {
+            AppError::Validation(errors) => errors.into_iter().map(|error| error.field).collect(),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn queries_are_checked_and_cursors_stay_with_their_kinds() {
+        let now = Utc::now();
+        let query = TimelineQuery { from: Some(now), to: Some(now - Duration::days(1)), limit: Some(0), ..Default::default() };
+        assert_eq!(field_errors(build_filter(1, &query).unwrap_err()), vec!["from", "limit"]);
+
+        let (filter, page_size) = build_filter(1, &TimelineQuery::default()).unwrap();
+        assert_eq!((filter.kinds, page_size, filter.limit), (TIMELINE_KINDS.to_vec(), DEFAULT_PAGE_SIZE, DEFAULT_PAGE_SIZE as i64 + 1));
+
+        let event = TimelineEvent::new(now, 7, TimelineData::Note(TimelineNote {
+            activity_id: 7,
+            procedure_name: None,
+            doctors_note: "Review in a week".to_string(),
+        }));
+        let cursor = encode_cursor(&event, &[TimelineKind::Note, TimelineKind::Vision]);
+        let query = TimelineQuery {
+            kinds: vec![TimelineKind::Vision, TimelineKind::Note, TimelineKind::Note],
+            cursor: Some(cursor.clone()),
+            ..Default::default()
+        };
+        let (filter, _) = build_filter(1, &query).unwrap();
+        assert_eq!(filter.after.map(|after| (after.occurred_at, after.kind, after.source_id)), Some((now, TimelineKind::Note, 7)));
+        // At the cursor's moment the earlier kinds were all listed, the later ones not yet
+        assert_eq!(filter.before(TimelineKind::Activity), Some((now, i32::MIN)));
+        assert_eq!(filter.before(TimelineKind::Note), Some((now, 7)));
+        assert_eq!(filter.before(TimelineKind::Vision), Some((now, i32::MAX)));
+
+        let query = TimelineQuery { cursor: Some(cursor), ..Default::default() };
+        assert_eq!(field_errors(build_filter(1, &query).unwrap_err()), vec!["cursor"]);
+    }
+
+    #[tokio::test]
+    async fn timeline_merges_sources_newest_first_across_pages() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let patient_id = repo.add_patient("Grace", "Hopper");
+        let other_patient_id = repo.add_patient("Alan", "Turing");
+        let procedure_id = repo.add_procedure("OCT scan", "Optical coherence tomography");
+        let now = Utc::now();
+
+        let activity_id = repo
+            .create_activity(doctor.user_id, &NewPatientActivity {
+                doctors_note: "Macula looks normal.".to_string(),
+                activity_time: now - Duration::days(3),
+                ..new_activity(patient_id, procedure_id)
+            })
+            .await
+            .unwrap();
+        repo.create_comment(nurse.user_id, activity_id, None, "Drops given").await.unwrap();
+        repo.upsert_vision(doctor.user_id, &VisionInput {
+            patient_id,
+            near_vision: "N6".to_string(),
+            distant_vision: "6/9".to_string(),
+            side: "RIGHT".to_string(),
+            value_type: "UC".to_string(),
+        })
+        .await
+        .unwrap();
+        schedule_appointment(&repo, &doctor, &NewAppointment {
+            description: "Follow-up".to_string(),
+            appointment_time: now + Duration::days(7),
+            appointment_duration: 1800,
+            users: vec![doctor.user_id],
+            patient_id: Some(patient_id),
+        })
+        .await
+        .unwrap();
+        for (alert_patient_id, title) in [(Some(patient_id), "Pressure high"), (Some(other_patient_id), "Other patient"), (None, "Staff meeting")] {
+            issue_alert(&repo, &nurse, &NewAlert {
+                priority_level: "NORMAL".to_string(),
+                title: title.to_string(),
+                message: "".to_string(),
+                issued_for: doctor.user_id,
+                patient_id: alert_patient_id,
+            })
+            .await
+            .unwrap();
+        }
+        add_history_entry(&repo, &doctor, patient_id, &HistoryDetails::Allergy(Allergy {
+            substance: "Latex".to_string(),
+            reaction: None,
+            severity: "MILD".to_string(),
+            notes: None,
+        }))
+        .await
+        .unwrap();
+
+        let mut query = TimelineQuery { limit: Some(3), ..Default::default() };
+        let mut kinds = vec![];
+        loop {
+            let page = find_patient_timeline(&repo, &doctor, patient_id, &query).await.unwrap();
+            kinds.extend(page.events.iter().map(TimelineEvent::kind));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(kinds[0], TimelineKind::Appointment);
+        assert_eq!(kinds[kinds.len() - 2..], [TimelineKind::Activity, TimelineKind::Note]);
+        let mut sorted = kinds.clone();
+        sorted.sort();
+        assert_eq!(sorted, TIMELINE_KINDS.iter().filter(|kind| !matches!(kind, TimelineKind::Refraction | TimelineKind::EyeMeasurement)).copied().collect::<Vec<_>>());
+
+        let query = TimelineQuery {
+            kinds: vec![TimelineKind::Note, TimelineKind::Alert],
+            to: Some(now),
+            ..Default::default()
+        };
+        let page = find_patient_timeline(&repo, &doctor, patient_id, &query).await.unwrap();
+        let kinds: Vec<TimelineKind> = page.events.iter().map(TimelineEvent::kind).collect();
+        assert_eq!(kinds, vec![TimelineKind::Note]);
+        assert!(page.next_cursor.is_none());
+
+        // Three pages of seven events, then the filtered read
+        let reads = repo.audit_events().iter().filter(|event| event.entity == ENTITY_PATIENT_TIMELINE).count();
+        assert_eq!(reads, 4);
+    }
+}
@@ -1,628 +1,1491 @@
 // src-tauri/src/patients.rs
 
 // Dependencies
+use crate::activity_assignments::validate_clinicians;
+use crate::activity_workflow::{insert_transition, validate_initial_status};
+use crate::config::AppConfig;
+use crate::audit::{
+    record_audit, AuditAction, AuditEvent, ENTITY_PATIENT, ENTITY_PATIENT_ACTIVITY, ENTITY_PATIENT_HISTORY
+};
+use crate::auth::AuthUser;
 use crate::db::DatabaseState;
+use crate::error::{AppError, FieldError};
+use crate::patient_search::phone_search_tokens;
+use crate::permissions::{authenticate, require, Permission, Role};
+use crate::procedure_catalog::require_active_procedure;
+use crate::repository::{ActivityAssignmentRepo, AuditRepo, PatientRepo, PgRepository, ProcedureCatalogRepo};
 use chrono;
-use chrono::{DateTime, NaiveDate, Utc};
-use serde::Serialize;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 use tauri::State;
 
 // Struct to store result of get_patient_data
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, Debug)]
 pub struct PatientData {
-    patient_id: i32,
-    mr_number: String,
-    first_name: String,
-    last_name: String,
-    date_of_birth: NaiveDate,
-    gender: String,
-    patient_photo: Option<String>,
-    created_at: Option<DateTime<Utc>>,
+    pub patient_id: i32,
+    pub mr_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    pub gender: String,
+    // Full-size data URL, or the thumbnail in list views
+    pub patient_photo: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub emergency_contact_name: Option<String>,
+    pub emergency_contact_phone: Option<String>,
+    pub preferred_language: Option<String>,
+    pub national_id: Option<String>,
+    pub active: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub deactivated_at: Option<DateTime<Utc>>,
+}
+
+// Struct to store input of create_patient and update_patient
+#[derive(Deserialize, Clone, Debug)]
+pub struct PatientInput {
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    pub gender: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub emergency_contact_name: Option<String>,
+    pub emergency_contact_phone: Option<String>,
+    pub preferred_language: Option<String>,
+    pub national_id: Option<String>,
+}
+
+// Pattern for generated MR numbers, set with MR_NUMBER_PATTERN. {SEQ} is the next value of
+// patient_mr_number_seq and {SEQ:n} pads it with zeros to n digits; {YYYY}, {YY} and {MM} come
+// from the registration date. Everything else is copied as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MrNumberPattern {
+    parts: Vec<MrNumberPart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MrNumberPart {
+    Literal(String),
+    Sequence(usize),
+    Year,
+    ShortYear,
+    Month,
+}
+
+pub const DEFAULT_MR_NUMBER_PATTERN: &str = "MR-{YYYY}-{SEQ:6}";
+
+impl MrNumberPattern {
+    pub fn parse(pattern: &str) -> Result<MrNumberPattern, String> {
+        let mut parts = vec![];
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(MrNumberPart::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or("has an unclosed {")? + start;
+            parts.push(match &rest[start + 1..end] {
+                "SEQ" => MrNumberPart::Sequence(0),
+                "YYYY" => MrNumberPart::Year,
+                "YY" => MrNumberPart::ShortYear,
+                "MM" => MrNumberPart::Month,
+                token => match token.strip_prefix("SEQ:").map(str::parse::<usize>) {
+                    Some(Ok(width)) if (1..=18).contains(&width) => MrNumberPart::Sequence(width),
+                    _ => return Err(format!("has an unknown placeholder {{{}}}", token)),
+                },
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(MrNumberPart::Literal(rest.to_string()));
+        }
+
+        match parts.iter().filter(|part| matches!(part, MrNumberPart::Sequence(_))).count() {
+            1 => Ok(MrNumberPattern { parts }),
+            _ => Err("must contain {SEQ} exactly once".to_string()),
+        }
+    }
+
+    pub fn format(&self, sequence: i64, date: NaiveDate) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                MrNumberPart::Literal(text) => text.clone(),
+                MrNumberPart::Sequence(width) => format!("{:0width$}", sequence, width = *width),
+                MrNumberPart::Year => format!("{:04}", date.year()),
+                MrNumberPart::ShortYear => format!("{:02}", date.year() % 100),
+                MrNumberPart::Month => format!("{:02}", date.month()),
+            })
+            .collect()
+    }
+}
+
+impl Default for MrNumberPattern {
+    fn default() -> Self {
+        MrNumberPattern::parse(DEFAULT_MR_NUMBER_PATTERN).expect("default MR number pattern is valid")
+    }
 }
 
 // Struct to store result of get_patient_activity_data
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, Debug)]
 pub struct PatientActivityData {
-    activity_id: i32,
-    activity: Option<String>,
-    activity_time: Option<DateTime<Utc>>,
-    status: Option<String>,
+    pub activity_id: i32,
+    pub activity: Option<String>,
+    pub activity_time: Option<DateTime<Utc>>,
+    pub status: Option<String>,
+    // Attending doctor and assisting nurse
+    pub doctor_id: Option<i32>,
+    pub doctor_name: Option<String>,
+    pub nurse_id: Option<i32>,
+    pub nurse_name: Option<String>,
 }
 
 // Struct to store result of get_patient_history_data
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, Debug)]
 pub struct PatientHistoryData {
-    history_id: i32,
-    patient_id: Option<i32>,
-    medical_conditions: Option<String>,
-    medications: Option<String>,
-    allergies: Option<String>,
-    last_visit: Option<DateTime<Utc>>,
+    pub history_id: i32,
+    pub patient_id: Option<i32>,
+    pub medical_conditions: Option<String>,
+    pub medications: Option<String>,
+    pub allergies: Option<String>,
+    pub last_visit: Option<DateTime<Utc>>,
 }
 
 // Struct to store result of get_appointment_data
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Debug)]
 pub struct AppointmentData {
-    patient_id: i32,
-    mr_number: String,
-    first_name: String,
-    last_name: String,
-    date_of_birth: NaiveDate,
-    gender: String,
-    patient_photo: Option<String>,
-    created_at: Option<DateTime<Utc>>,
-    activity_id: Option<i32>,
-    status: Option<String>,
-    activity: Option<String>,
-    doctors_note: Option<String>,
-    patient_complaint: Option<String>,
-    activity_time: Option<DateTime<Utc>>,
-    activity_created_at: Option<DateTime<Utc>>,
+    pub patient_id: i32,
+    pub mr_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    pub gender: String,
+    // Thumbnail data URL
+    pub patient_photo: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub activity_id: Option<i32>,
+    pub status: Option<String>,
+    pub activity: Option<String>,
+    pub doctors_note: Option<String>,
+    pub patient_complaint: Option<String>,
+    pub activity_time: Option<DateTime<Utc>>,
+    pub activity_created_at: Option<DateTime<Utc>>,
+    pub doctor_id: Option<i32>,
+    pub doctor_name: Option<String>,
+    pub nurse_id: Option<i32>,
+    pub nurse_name: Option<String>,
 }
 
 // Struct to store result of get_patient_doctor_data
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Debug)]
 pub struct PatientDoctorData {
-    doctors_note: Option<String>,
-    patient_complaint: Option<String>,
-    activity_time: Option<DateTime<Utc>>,
-}
-
-// Struct to store result of get_all_procedures
-#[derive(Serialize)]
-pub struct Procedure {
-    procedure_id: i32,
-    procedure_name: Option<String>,
-    description: Option<String>,
-    created_at: Option<DateTime<Utc>>,
+    pub doctors_note: Option<String>,
+    pub patient_complaint: Option<String>,
+    pub activity_time: Option<DateTime<Utc>>,
 }
 
 // Struct to store result of get_patient_procedures
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Debug)]
 pub struct PatientProcedureData {
-    activity_id: i32,
-    status: String,
-    procedure_name: Option<String>,
-    procedure_description: Option<String>,
-    doctors_note: Option<String>,
-    patient_complaint: Option<String>,
-    comments: Option<String>,
-    activity_time: Option<DateTime<Utc>>,
+    pub activity_id: i32,
+    pub status: String,
+    pub procedure_name: Option<String>,
+    pub procedure_description: Option<String>,
+    pub doctors_note: Option<String>,
+    pub patient_complaint: Option<String>,
+    // Comments that were not deleted, oldest first, one per line. get_procedure_comments has the threads.
+    pub comments: Option<String>,
+    pub activity_time: Option<DateTime<Utc>>,
 }
 
-// Endpoint to get data of specific patient
-#[tauri::command]
-pub async fn get_patient_data(
-    state: tauri::State<'_, DatabaseState>,
-    patient_id: i32,
-) -> Result<PatientData, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
-        }
-    };
+// Struct to store input of create_patient_activity
+#[derive(Clone, Debug, Default)]
+pub struct NewPatientActivity {
+    pub patient_id: i32,
+    pub procedure_id: i32,
+    pub status: String,
+    pub doctors_note: String,
+    pub patient_complaint: String,
+    pub activity_time: DateTime<Utc>,
+    // Attending doctor, defaulting to the doctor who records the activity
+    pub doctor_id: Option<i32>,
+    // Assisting nurse
+    pub nurse_id: Option<i32>,
+}
 
-    match sqlx::query_as!(
-        PatientData,
-        r#"
-        SELECT 
-            patient_id,
+impl PatientRepo for PgRepository<'_> {
+    async fn find_patient(&self, patient_id: i32) -> Result<Option<PatientData>, AppError> {
+        sqlx::query_as!(
+            PatientData,
+            r#"
+            SELECT
+                patient_id,
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                pgp_sym_decrypt(patient_photo::bytea, ($1::TEXT[])[key_id]) as patient_photo,
+                pgp_sym_decrypt(phone::bytea, ($1::TEXT[])[key_id]) as phone,
+                pgp_sym_decrypt(email::bytea, ($1::TEXT[])[key_id]) as email,
+                pgp_sym_decrypt(address::bytea, ($1::TEXT[])[key_id]) as address,
+                pgp_sym_decrypt(emergency_contact_name::bytea, ($1::TEXT[])[key_id]) as emergency_contact_name,
+                pgp_sym_decrypt(emergency_contact_phone::bytea, ($1::TEXT[])[key_id]) as emergency_contact_phone,
+                pgp_sym_decrypt(preferred_language::bytea, ($1::TEXT[])[key_id]) as preferred_language,
+                pgp_sym_decrypt(national_id::bytea, ($1::TEXT[])[key_id]) as national_id,
+                active,
+                created_at,
+                updated_at,
+                deactivated_at
+            FROM patients
+            WHERE patient_id = $2
+            "#,
+            &self.keyring.sql_keys(),
+            patient_id
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| AppError::database("Failed to fetch patient data", e))
+    }
+
+    async fn list_patients(&self) -> Result<Vec<PatientData>, AppError> {
+        sqlx::query_as!(
+            PatientData,
+            r#"
+            SELECT
+                patient_id,
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                pgp_sym_decrypt(patient_photo_thumbnail::bytea, ($1::TEXT[])[key_id]) as patient_photo,
+                pgp_sym_decrypt(phone::bytea, ($1::TEXT[])[key_id]) as phone,
+                pgp_sym_decrypt(email::bytea, ($1::TEXT[])[key_id]) as email,
+                pgp_sym_decrypt(address::bytea, ($1::TEXT[])[key_id]) as address,
+                pgp_sym_decrypt(emergency_contact_name::bytea, ($1::TEXT[])[key_id]) as emergency_contact_name,
+                pgp_sym_decrypt(emergency_contact_phone::bytea, ($1::TEXT[])[key_id]) as emergency_contact_phone,
+                pgp_sym_decrypt(preferred_language::bytea, ($1::TEXT[])[key_id]) as preferred_language,
+                pgp_sym_decrypt(national_id::bytea, ($1::TEXT[])[key_id]) as national_id,
+                active,
+                created_at,
+                updated_at,
+                deactivated_at
+            FROM patients
+            WHERE active
+            ORDER BY patient_id
+            "#,
+            &self.keyring.sql_keys()
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Failed to fetch patient data", e))
+    }
+
+    async fn list_activities(&self, patient_id: i32) -> Result<Vec<PatientActivityData>, AppError> {
+        sqlx::query_as!(
+            PatientActivityData,
+            r#"
+            SELECT
+                pa.activity_id,
+                pgp_sym_decrypt(p.procedure_name::bytea, ($1::TEXT[])[p.key_id]) as activity,
+                pa.activity_time,
+                pa.status,
+                pa.doctor_id,
+                d.first_name || ' ' || d.last_name as doctor_name,
+                pa.nurse_id,
+                n.first_name || ' ' || n.last_name as nurse_name
+            FROM
+                patient_activity pa
+            LEFT JOIN
+                procedures p
+            ON
+                pa.procedure_id = p.procedure_id
+            LEFT JOIN users d ON d.user_id = pa.doctor_id
+            LEFT JOIN users n ON n.user_id = pa.nurse_id
+            WHERE
+                patient_id = $2
+            ORDER BY
+                activity_time DESC
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching patient activity data", e))
+    }
+
+    async fn list_todays_appointments(&self) -> Result<Vec<AppointmentData>, AppError> {
+        sqlx::query_as!(
+            AppointmentData,
+            r#"
+            SELECT
+                p.patient_id,
+                p.mr_number,
+                p.first_name,
+                p.last_name,
+                p.date_of_birth,
+                p.gender,
+                pgp_sym_decrypt(p.patient_photo_thumbnail::bytea, ($1::TEXT[])[p.key_id]) as patient_photo,
+                p.created_at,
+                pa.activity_id,
+                pa.status,
+                pgp_sym_decrypt(pr.procedure_name::bytea, ($1::TEXT[])[pr.key_id]) as activity,
+                pgp_sym_decrypt(pa.doctors_note::bytea, ($1::TEXT[])[pa.key_id]) as doctors_note,
+                pgp_sym_decrypt(pa.patient_complaint::bytea, ($1::TEXT[])[pa.key_id]) as patient_complaint,
+                pa.activity_time,
+                pa.created_at as activity_created_at,
+                pa.doctor_id,
+                d.first_name || ' ' || d.last_name as doctor_name,
+                pa.nurse_id,
+                n.first_name || ' ' || n.last_name as nurse_name
+            FROM patient_activity pa
+            LEFT JOIN patients p ON p.patient_id = pa.patient_id
+            LEFT JOIN procedures pr ON pr.procedure_id = pa.procedure_id
+            LEFT JOIN users d ON d.user_id = pa.doctor_id
+            LEFT JOIN users n ON n.user_id = pa.nurse_id
+            WHERE DATE(pa.activity_time) = CURRENT_DATE
+            ORDER BY pa.activity_time ASC
+            "#,
+            &self.keyring.sql_keys()
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Failed to fetch appointment data", e))
+    }
+
+    async fn find_history(&self, patient_id: i32) -> Result<Option<PatientHistoryData>, AppError> {
+        sqlx::query_as!(
+            PatientHistoryData,
+            r#"
+            SELECT
+                history_id,
+                patient_id,
+                pgp_sym_decrypt(medical_conditions::bytea, ($1::TEXT[])[key_id]) as medical_conditions,
+                pgp_sym_decrypt(medications::bytea, ($1::TEXT[])[key_id]) as medications,
+                pgp_sym_decrypt(allergies::bytea, ($1::TEXT[])[key_id]) as allergies,
+                (
+                    SELECT MAX(activity_time)
+                    FROM patient_activity
+                    WHERE patient_id = $2
+                ) as last_visit
+            FROM
+                patient_history
+            WHERE
+                patient_id = $2
+            LIMIT 1
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching patient history data", e))
+    }
+
+    async fn list_doctor_notes(&self, patient_id: i32) -> Result<Vec<PatientDoctorData>, AppError> {
+        sqlx::query_as!(
+            PatientDoctorData,
+            r#"
+            SELECT
+                pgp_sym_decrypt(doctors_note::bytea, ($1::TEXT[])[key_id]) as doctors_note,
+                pgp_sym_decrypt(patient_complaint::bytea, ($1::TEXT[])[key_id]) as patient_complaint,
+                activity_time
+            FROM
+                patient_activity
+            WHERE
+                patient_id = $2
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while getting patient doctor data", e))
+    }
+
+    async fn list_patient_procedures(&self, patient_id: i32) -> Result<Vec<PatientProcedureData>, AppError> {
+        sqlx::query_as!(
+            PatientProcedureData,
+            r#"
+            SELECT
+                pa.activity_id,
+                pa.status,
+                pgp_sym_decrypt(pr.procedure_name::bytea, ($1::TEXT[])[pr.key_id]) as procedure_name,
+                pgp_sym_decrypt(pr.description::bytea, ($1::TEXT[])[pr.key_id]) as procedure_description,
+                pgp_sym_decrypt(pa.doctors_note::bytea, ($1::TEXT[])[pa.key_id]) as doctors_note,
+                pgp_sym_decrypt(pa.patient_complaint::bytea, ($1::TEXT[])[pa.key_id]) as patient_complaint,
+                (
+                    SELECT string_agg(pgp_sym_decrypt(c.body::bytea, ($1::TEXT[])[c.key_id]), E'\n' ORDER BY c.created_at, c.comment_id)
+                    FROM procedure_comments c
+                    WHERE c.activity_id = pa.activity_id AND c.deleted_at IS NULL
+                ) as comments,
+                pa.activity_time
+            FROM
+                patient_activity pa
+            LEFT JOIN
+                procedures pr
+            ON
+                pa.procedure_id = pr.procedure_id
+            WHERE
+                pa.patient_id = $2
+            ORDER BY
+                pa.activity_time DESC
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching patient procedure data", e))
+    }
+
+    async fn list_complaints(&self, patient_id: i32) -> Result<Vec<String>, AppError> {
+        let data = sqlx::query!(
+            r#"
+            SELECT
+                pgp_sym_decrypt(patient_complaint::bytea, ($1::TEXT[])[key_id]) as patient_complaint
+            FROM
+                patient_activity
+            WHERE
+                patient_id = $2
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching patient complaints", e))?;
+
+        Ok(data.into_iter().filter_map(|entry| entry.patient_complaint).collect())
+    }
+
+    async fn create_activity(&self, user_id: i32, activity: &NewPatientActivity) -> Result<i32, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let activity_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO patient_activity (
+                patient_id,
+                procedure_id,
+                status,
+                doctors_note,
+                patient_complaint,
+                activity_time,
+                doctor_id,
+                nurse_id,
+                key_id,
+                created_at
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                pgp_sym_encrypt($4, $7),
+                pgp_sym_encrypt($5, $7),
+                $6,
+                $9,
+                $10,
+                $8,
+                NOW()
+            )
+            RETURNING activity_id
+            "#,
+            activity.patient_id,
+            activity.procedure_id,
+            &activity.status,
+            &activity.doctors_note,
+            &activity.patient_complaint,
+            activity.activity_time,
+            &keyring.current().secret,
+            keyring.current().id,
+            activity.doctor_id,
+            activity.nurse_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while creating patient activity", e))?;
+
+        insert_transition(&mut tx, keyring, user_id, activity_id, None, &activity.status, None).await?;
+        record_audit(&mut *tx, self.keyring, activity_created_event(user_id, activity_id, activity)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(activity_id)
+    }
+
+    async fn next_mr_sequence(&self) -> Result<i64, AppError> {
+        sqlx::query_scalar!(r#"SELECT nextval('patient_mr_number_seq') as "sequence!""#)
+            .fetch_one(self.pool)
+            .await
+            .map_err(|e| AppError::database("Error while generating MR number", e))
+    }
+
+    async fn create_patient(&self, user_id: i32, mr_number: &str, patient: &PatientInput) -> Result<PatientData, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let created = sqlx::query_as!(
+            PatientData,
+            r#"
+            INSERT INTO patients (
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                phone,
+                email,
+                address,
+                emergency_contact_name,
+                emergency_contact_phone,
+                preferred_language,
+                national_id,
+                phone_search_tokens,
+                key_id
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                pgp_sym_encrypt($6, $13),
+                pgp_sym_encrypt($7, $13),
+                pgp_sym_encrypt($8, $13),
+                pgp_sym_encrypt($9, $13),
+                pgp_sym_encrypt($10, $13),
+                pgp_sym_encrypt($11, $13),
+                pgp_sym_encrypt($12, $13),
+                $15,
+                $14
+            )
+            RETURNING
+                patient_id,
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                pgp_sym_decrypt(patient_photo::bytea, $13) as patient_photo,
+                pgp_sym_decrypt(phone::bytea, $13) as phone,
+                pgp_sym_decrypt(email::bytea, $13) as email,
+                pgp_sym_decrypt(address::bytea, $13) as address,
+                pgp_sym_decrypt(emergency_contact_name::bytea, $13) as emergency_contact_name,
+                pgp_sym_decrypt(emergency_contact_phone::bytea, $13) as emergency_contact_phone,
+                pgp_sym_decrypt(preferred_language::bytea, $13) as preferred_language,
+                pgp_sym_decrypt(national_id::bytea, $13) as national_id,
+                active,
+                created_at,
+                updated_at,
+                deactivated_at
+            "#,
             mr_number,
-            first_name,
-            last_name,
-            date_of_birth,
-            gender,
-            pgp_sym_decrypt(patient_photo::bytea, $1) as patient_photo,
-            created_at
-        FROM patients
-        WHERE patient_id = $2
-        "#,
-        &encryption_key,
-        patient_id
+            &patient.first_name,
+            &patient.last_name,
+            patient.date_of_birth,
+            &patient.gender,
+            patient.phone.as_deref(),
+            patient.email.as_deref(),
+            patient.address.as_deref(),
+            patient.emergency_contact_name.as_deref(),
+            patient.emergency_contact_phone.as_deref(),
+            patient.preferred_language.as_deref(),
+            patient.national_id.as_deref(),
+            &keyring.current().secret,
+            keyring.current().id,
+            &phone_search_tokens(keyring.search_key(), patient.phone.as_deref())
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while creating patient", e))?;
+
+        record_audit(&mut *tx, self.keyring, patient_changed_event(user_id, AuditAction::Create, None, &created)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(created)
+    }
+
+    async fn update_patient(&self, user_id: i32, patient_id: i32, patient: &PatientInput) -> Result<Option<PatientData>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        // Current values, for the audit trail
+        let before = sqlx::query_as!(
+            PatientData,
+            r#"
+            SELECT
+                patient_id,
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                pgp_sym_decrypt(patient_photo::bytea, ($1::TEXT[])[key_id]) as patient_photo,
+                pgp_sym_decrypt(phone::bytea, ($1::TEXT[])[key_id]) as phone,
+                pgp_sym_decrypt(email::bytea, ($1::TEXT[])[key_id]) as email,
+                pgp_sym_decrypt(address::bytea, ($1::TEXT[])[key_id]) as address,
+                pgp_sym_decrypt(emergency_contact_name::bytea, ($1::TEXT[])[key_id]) as emergency_contact_name,
+                pgp_sym_decrypt(emergency_contact_phone::bytea, ($1::TEXT[])[key_id]) as emergency_contact_phone,
+                pgp_sym_decrypt(preferred_language::bytea, ($1::TEXT[])[key_id]) as preferred_language,
+                pgp_sym_decrypt(national_id::bytea, ($1::TEXT[])[key_id]) as national_id,
+                active,
+                created_at,
+                updated_at,
+                deactivated_at
+            FROM patients
+            WHERE patient_id = $2 AND active
+            FOR UPDATE
+            "#,
+            &keyring.sql_keys(),
+            patient_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating patient", e))?;
+
+        let before = match before {
+            Some(before) => before,
+            None => return Ok(None),
+        };
+
+        // The photo is re-encrypted too, since key_id covers every encrypted column of the row
+        let after = sqlx::query_as!(
+            PatientData,
+            r#"
+            UPDATE patients
+            SET
+                first_name = $2,
+                last_name = $3,
+                date_of_birth = $4,
+                gender = $5,
+                phone = pgp_sym_encrypt($6, $13),
+                email = pgp_sym_encrypt($7, $13),
+                address = pgp_sym_encrypt($8, $13),
+                emergency_contact_name = pgp_sym_encrypt($9, $13),
+                emergency_contact_phone = pgp_sym_encrypt($10, $13),
+                preferred_language = pgp_sym_encrypt($11, $13),
+                national_id = pgp_sym_encrypt($12, $13),
+                patient_photo = pgp_sym_encrypt(pgp_sym_decrypt(patient_photo::bytea, ($15::TEXT[])[key_id]), $13),
+                patient_photo_thumbnail = pgp_sym_encrypt(pgp_sym_decrypt(patient_photo_thumbnail::bytea, ($15::TEXT[])[key_id]), $13),
+                phone_search_tokens = $16,
+                key_id = $14,
+                updated_at = NOW()
+            WHERE patient_id = $1
+            RETURNING
+                patient_id,
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                pgp_sym_decrypt(patient_photo::bytea, $13) as patient_photo,
+                pgp_sym_decrypt(phone::bytea, $13) as phone,
+                pgp_sym_decrypt(email::bytea, $13) as email,
+                pgp_sym_decrypt(address::bytea, $13) as address,
+                pgp_sym_decrypt(emergency_contact_name::bytea, $13) as emergency_contact_name,
+                pgp_sym_decrypt(emergency_contact_phone::bytea, $13) as emergency_contact_phone,
+                pgp_sym_decrypt(preferred_language::bytea, $13) as preferred_language,
+                pgp_sym_decrypt(national_id::bytea, $13) as national_id,
+                active,
+                created_at,
+                updated_at,
+                deactivated_at
+            "#,
+            patient_id,
+            &patient.first_name,
+            &patient.last_name,
+            patient.date_of_birth,
+            &patient.gender,
+            patient.phone.as_deref(),
+            patient.email.as_deref(),
+            patient.address.as_deref(),
+            patient.emergency_contact_name.as_deref(),
+            patient.emergency_contact_phone.as_deref(),
+            patient.preferred_language.as_deref(),
+            patient.national_id.as_deref(),
+            &keyring.current().secret,
+            keyring.current().id,
+            &keyring.sql_keys(),
+            &phone_search_tokens(keyring.search_key(), patient.phone.as_deref())
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating patient", e))?;
+
+        record_audit(&mut *tx, self.keyring, patient_changed_event(user_id, AuditAction::Update, Some(&before), &after)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(after))
+    }
+
+    async fn deactivate_patient(&self, user_id: i32, patient_id: i32) -> Result<Option<PatientData>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let deactivated = sqlx::query_as!(
+            PatientData,
+            r#"
+            UPDATE patients
+            SET
+                active = FALSE,
+                updated_at = NOW(),
+                deactivated_at = NOW()
+            WHERE patient_id = $2 AND active
+            RETURNING
+                patient_id,
+                mr_number,
+                first_name,
+                last_name,
+                date_of_birth,
+                gender,
+                pgp_sym_decrypt(patient_photo::bytea, ($1::TEXT[])[key_id]) as patient_photo,
+                pgp_sym_decrypt(phone::bytea, ($1::TEXT[])[key_id]) as phone,
+                pgp_sym_decrypt(email::bytea, ($1::TEXT[])[key_id]) as email,
+                pgp_sym_decrypt(address::bytea, ($1::TEXT[])[key_id]) as address,
+                pgp_sym_decrypt(emergency_contact_name::bytea, ($1::TEXT[])[key_id]) as emergency_contact_name,
+                pgp_sym_decrypt(emergency_contact_phone::bytea, ($1::TEXT[])[key_id]) as emergency_contact_phone,
+                pgp_sym_decrypt(preferred_language::bytea, ($1::TEXT[])[key_id]) as preferred_language,
+                pgp_sym_decrypt(national_id::bytea, ($1::TEXT[])[key_id]) as national_id,
+                active,
+                created_at,
+                updated_at,
+                deactivated_at
+            "#,
+            &self.keyring.sql_keys(),
+            patient_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while deactivating patient", e))?;
+
+        if let Some(patient) = &deactivated {
+            record_audit(&mut *tx, self.keyring, patient_changed_event(user_id, AuditAction::Update, None, patient)).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(deactivated)
+    }
+}
+
+// Audit event for a new or changed patient, shared by every PatientRepo implementation
+pub fn patient_changed_event(user_id: i32, action: AuditAction, before: Option<&PatientData>, after: &PatientData) -> AuditEvent {
+    AuditEvent::new(user_id, action, ENTITY_PATIENT)
+        .patient(after.patient_id)
+        .entity_id(after.patient_id)
+        .before(before)
+        .after(after)
+}
+
+// Audit event for a new activity, shared by every PatientRepo implementation
+pub fn activity_created_event(user_id: i32, activity_id: i32, activity: &NewPatientActivity) -> AuditEvent {
+    AuditEvent::new(user_id, AuditAction::Create, ENTITY_PATIENT_ACTIVITY)
+        .patient(activity.patient_id)
+        .entity_id(activity_id)
+        .after(&(
+            activity.procedure_id,
+            &activity.status,
+            &activity.doctors_note,
+            &activity.patient_complaint,
+            activity.activity_time,
+            activity.doctor_id,
+            activity.nurse_id,
+        ))
+}
+
+// Function to read one patient's demographics
+pub async fn find_patient<R: PatientRepo + AuditRepo>(repo: &R, user: &AuthUser, patient_id: i32) -> Result<PatientData, AppError> {
+    require(user, Permission::ReadPatient)?;
+
+    let patient = repo
+        .find_patient(patient_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Patient does not exist."))?;
+
+    repo.record_audit(
+        AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT).patient(patient_id).entity_id(patient_id),
     )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(patients) => {
-            if patients.len() != 1 {
-                Err(format!("Patient does not exists").as_str().to_string())
-            } else {
-                Ok(patients[0].clone())
-            }
+    .await?;
+
+    Ok(patient)
+}
+
+// Function to list every active patient
+pub async fn list_patients<R: PatientRepo + AuditRepo>(repo: &R, user: &AuthUser) -> Result<Vec<PatientData>, AppError> {
+    require(user, Permission::ReadPatient)?;
+
+    let patients = repo.list_patients().await?;
+    let patient_ids: Vec<i32> = patients.iter().map(|patient| patient.patient_id).collect();
+    repo.record_patient_reads(user.user_id, ENTITY_PATIENT, &patient_ids).await?;
+
+    Ok(patients)
+}
+
+// Attempts at a free MR number before giving up. Only numbers entered outside the pattern can collide.
+const MR_NUMBER_ATTEMPTS: usize = 5;
+
+// Function to check and normalize registration input: trims text, upper-cases the gender and
+// drops empty optional fields. Every problem is reported at once.
+pub fn validate_patient(patient: &PatientInput, today: NaiveDate) -> Result<PatientInput, AppError> {
+    let mut errors = vec![];
+    let optional = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+
+    let normalized = PatientInput {
+        first_name: patient.first_name.trim().to_string(),
+        last_name: patient.last_name.trim().to_string(),
+        date_of_birth: patient.date_of_birth,
+        gender: patient.gender.trim().to_uppercase(),
+        phone: optional(&patient.phone),
+        email: optional(&patient.email),
+        address: optional(&patient.address),
+        emergency_contact_name: optional(&patient.emergency_contact_name),
+        emergency_contact_phone: optional(&patient.emergency_contact_phone),
+        preferred_language: optional(&patient.preferred_language),
+        national_id: optional(&patient.national_id),
+    };
+
+    for (field, value) in [("first_name", &normalized.first_name), ("last_name", &normalized.last_name)] {
+        if value.is_empty() || value.chars().count() > 255 {
+            errors.push(FieldError { field: field.to_string(), message: "Enter between 1 and 255 characters.".to_string() });
+        }
+    }
+    if !["MALE", "FEMALE", "OTHERS"].contains(&normalized.gender.as_str()) {
+        errors.push(FieldError { field: "gender".to_string(), message: "Gender must be MALE, FEMALE or OTHERS.".to_string() });
+    }
+    if normalized.date_of_birth > today {
+        errors.push(FieldError { field: "date_of_birth".to_string(), message: "Date of birth cannot be in the future.".to_string() });
+    }
+    for (field, value) in [("phone", &normalized.phone), ("emergency_contact_phone", &normalized.emergency_contact_phone)] {
+        if value.as_deref().is_some_and(|phone| !is_phone_number(phone)) {
+            errors.push(FieldError { field: field.to_string(), message: "Enter a valid phone number.".to_string() });
         }
-        Err(err) => Err(format!("Failed to fetch patient data: {}", err)
-            .as_str()
-            .to_string()),
+    }
+    if normalized.email.as_deref().is_some_and(|email| !is_email(email)) {
+        errors.push(FieldError { field: "email".to_string(), message: "Enter a valid email address.".to_string() });
+    }
+
+    if errors.is_empty() {
+        Ok(normalized)
+    } else {
+        Err(AppError::Validation(errors))
     }
 }
 
-// Endpoint to get all patients data
-#[tauri::command]
-pub async fn get_patients_data(
-    state: State<'_, DatabaseState>,
-) -> Result<Vec<PatientData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
+// Digits with optional +, spaces, dashes, dots and brackets
+fn is_phone_number(phone: &str) -> bool {
+    let digits = phone.chars().filter(char::is_ascii_digit).count();
+    (7..=15).contains(&digits) && phone.chars().all(|c| c.is_ascii_digit() || " +-.()".contains(c))
+}
+
+fn is_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+                && !domain.contains('@') && !email.chars().any(char::is_whitespace)
         }
-    };
+        None => false,
+    }
+}
 
-    match sqlx::query_as!(
-        PatientData,
-        r#"
-        SELECT 
-            patient_id,
-            mr_number,
-            first_name,
-            last_name,
-            date_of_birth,
-            gender,
-            pgp_sym_decrypt(patient_photo::bytea, $1) as patient_photo,
-            created_at
-        FROM patients
-        "#,
-        &encryption_key
+// Function to register a patient under a newly generated MR number
+pub async fn register_patient<R: PatientRepo>(
+    repo: &R,
+    user: &AuthUser,
+    pattern: &MrNumberPattern,
+    patient: &PatientInput
+) -> Result<PatientData, AppError> {
+    require(user, Permission::WritePatient)?;
+
+    let today = Utc::now().date_naive();
+    let patient = validate_patient(patient, today)?;
+
+    for _ in 0..MR_NUMBER_ATTEMPTS {
+        let mr_number = pattern.format(repo.next_mr_sequence().await?, today);
+        match repo.create_patient(user.user_id, &mr_number, &patient).await {
+            Err(AppError::Conflict(_)) => continue,
+            result => return result,
+        }
+    }
+
+    Err(AppError::conflict("Could not find a free MR number. Check MR_NUMBER_PATTERN."))
+}
+
+// Function to edit an active patient's demographics
+pub async fn edit_patient<R: PatientRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32,
+    patient: &PatientInput
+) -> Result<PatientData, AppError> {
+    require(user, Permission::WritePatient)?;
+
+    let patient = validate_patient(patient, Utc::now().date_naive())?;
+    repo.update_patient(user.user_id, patient_id, &patient)
+        .await?
+        .ok_or_else(|| AppError::not_found("Patient does not exist or is deactivated."))
+}
+
+// Function to deactivate a patient. The record and its clinical data are kept.
+pub async fn deactivate_patient_record<R: PatientRepo>(repo: &R, user: &AuthUser, patient_id: i32) -> Result<PatientData, AppError> {
+    require(user, Permission::WritePatient)?;
+
+    repo.deactivate_patient(user.user_id, patient_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Patient does not exist or is already deactivated."))
+}
+
+// Function to list a patient's activities, newest first
+pub async fn list_patient_activity<R: PatientRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<Vec<PatientActivityData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let activities = repo.list_activities(patient_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_ACTIVITY).patient(patient_id))
+        .await?;
+
+    Ok(activities)
+}
+
+// Function to list today's appointments across all patients
+pub async fn list_todays_appointments<R: PatientRepo + AuditRepo>(repo: &R, user: &AuthUser) -> Result<Vec<AppointmentData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let appointments = repo.list_todays_appointments().await?;
+    let patient_ids: Vec<i32> = appointments.iter().map(|appointment| appointment.patient_id).collect();
+    repo.record_patient_reads(user.user_id, ENTITY_PATIENT_ACTIVITY, &patient_ids).await?;
+
+    Ok(appointments)
+}
+
+// Function to read a patient's medical history
+pub async fn find_patient_history<R: PatientRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<PatientHistoryData, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let history = repo
+        .find_history(patient_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("No history found for this patient."))?;
+
+    repo.record_audit(
+        AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_HISTORY)
+            .patient(patient_id)
+            .entity_id(history.history_id),
     )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(patients) => Ok(patients),
-        Err(err) => Err(format!("Failed to fetch appointment data: {}", err)
-            .as_str()
-            .to_string()),
+    .await?;
+
+    Ok(history)
+}
+
+// Function to list previous doctor's notes and complaints for a patient
+pub async fn list_doctor_notes<R: PatientRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<Vec<PatientDoctorData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let notes = repo.list_doctor_notes(patient_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_ACTIVITY).patient(patient_id))
+        .await?;
+
+    Ok(notes)
+}
+
+// Function to list a patient's procedures, newest first
+pub async fn list_patient_procedures<R: PatientRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<Vec<PatientProcedureData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let procedures = repo.list_patient_procedures(patient_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_ACTIVITY).patient(patient_id))
+        .await?;
+
+    Ok(procedures)
+}
+
+// Function to record a new activity with the doctor's note
+pub async fn add_patient_activity<R: PatientRepo + ProcedureCatalogRepo + ActivityAssignmentRepo>(
+    repo: &R,
+    user: &AuthUser,
+    activity: &NewPatientActivity
+) -> Result<String, AppError> {
+    require(user, Permission::WriteActivity)?;
+
+    validate_initial_status(&activity.status)?;
+    reject_inactive_patient(repo, activity.patient_id).await?;
+    require_active_procedure(repo, activity.procedure_id).await?;
+    let doctor_id = activity.doctor_id.or_else(|| (user.role == Role::Doctor.as_str()).then_some(user.user_id));
+    validate_clinicians(repo, doctor_id, activity.nurse_id).await?;
+    let activity = NewPatientActivity { doctor_id, ..activity.clone() };
+    let activity_id = repo.create_activity(user.user_id, &activity).await?;
+
+    Ok(format!(
+        "Successfully created patient activity: {}",
+        activity_id
+    ))
+}
+
+// Function to refuse new clinical data for a deactivated patient. Unknown ids are left to the write itself.
+// Merged patients are deactivated, so their records only change through the surviving patient.
+pub async fn reject_inactive_patient<R: PatientRepo>(repo: &R, patient_id: i32) -> Result<(), AppError> {
+    match repo.find_patient(patient_id).await? {
+        Some(patient) if !patient.active => {
+            Err(AppError::validation("patient_id", "This patient is deactivated or was merged into another record."))
+        }
+        _ => Ok(()),
     }
 }
 
-// Endpoint to get all activity for a particular patient
+// Function to list every complaint recorded for a patient
+pub async fn list_patient_complaints<R: PatientRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32
+) -> Result<Vec<String>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let complaints = repo.list_complaints(patient_id).await?;
+    repo.record_audit(AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PATIENT_ACTIVITY).patient(patient_id))
+        .await?;
+
+    Ok(complaints)
+}
+
+// Endpoint to get data of specific patient
 #[tauri::command]
-pub async fn get_patient_activity_data(
+pub async fn get_patient_data(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
-) -> Result<Vec<PatientActivityData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
+) -> Result<PatientData, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden."));
-    }
-
-    match sqlx::query_as!(
-        PatientActivityData,
-        r#"
-        SELECT 
-            pa.activity_id,
-            pgp_sym_decrypt(p.procedure_name::bytea, $1) as activity,
-            pa.activity_time,
-            pa.status
-        FROM
-            patient_activity pa
-        LEFT JOIN
-            procedures p
-        ON
-            pa.procedure_id = p.procedure_id
-        WHERE
-            patient_id = $2
-        ORDER BY
-            activity_time DESC
-        "#,
-        &encryption_key,
-        &patient_id
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(patient_activity_data) => Ok(patient_activity_data),
-        Err(err) => Err(format!(
-            "Error while fetching patient activity data: {}",
-            err
-        )),
-    }
+    find_patient(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
 }
 
-// Endpoint to get today's appointment data
+// Endpoint to get all patients data
 #[tauri::command]
-pub async fn get_appointment_data(
+pub async fn get_patients_data(
     state: State<'_, DatabaseState>,
-) -> Result<Vec<AppointmentData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
+    config: State<'_, AppConfig>,
+    token: String,
+) -> Result<Vec<PatientData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden."));
-    }
-
-    match sqlx::query_as!(
-        AppointmentData,
-        r#"
-        SELECT 
-            p.patient_id,
-            p.mr_number,
-            p.first_name,
-            p.last_name,
-            p.date_of_birth,
-            p.gender,
-            pgp_sym_decrypt(p.patient_photo::bytea, $1) as patient_photo,
-            p.created_at,
-            pa.activity_id,
-            pa.status,
-            pgp_sym_decrypt(pr.procedure_name::bytea, $1) as activity,
-            pgp_sym_decrypt(pa.doctors_note::bytea, $1) as doctors_note,
-            pgp_sym_decrypt(pa.patient_complaint::bytea, $1) as patient_complaint,
-            pa.activity_time,
-            pa.created_at as activity_created_at
-        FROM patient_activity pa
-        LEFT JOIN patients p ON p.patient_id = pa.patient_id
-        LEFT JOIN procedures pr ON pr.procedure_id = pa.procedure_id
-        WHERE DATE(pa.activity_time) = CURRENT_DATE
-        ORDER BY pa.activity_time ASC
-        "#,
-        &encryption_key
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(patients) => Ok(patients),
-        Err(err) => Err(format!("Failed to fetch appointment data: {}", err)),
-    }
+    list_patients(&PgRepository::new(pool, &config.keyring), &user).await
 }
 
-// Endpoint to get patient summary data
+// Endpoint to register a new patient. The MR number is generated from MR_NUMBER_PATTERN.
 #[tauri::command]
-pub async fn get_patient_summary_data(
-    _state: State<'_, DatabaseState>,
-    _patient_id: i32,
-) -> Result<Vec<String>, String> {
-    Ok(vec![
-        "Parvon experienced red and watery eyes.".to_string(),
-        "He mentioned that he had been playing in a pool the day before the symptoms started."
-            .to_string(),
-        "Scans were performed to assess the condition of his eyes.".to_string(),
-        "The results revealed findings that require surgical intervention.".to_string(),
-        "A follow-up visit was scheduled to discuss the surgery and next steps.".to_string(),
-    ])
+pub async fn create_patient(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient: PatientInput,
+) -> Result<PatientData, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    register_patient(&PgRepository::new(pool, &config.keyring), &user, &config.mr_number_pattern, &patient).await
 }
 
-// Endpoint to get patient history data
+// Endpoint to edit a patient's demographics
 #[tauri::command]
-pub async fn get_patient_history_data(
+pub async fn update_patient(
     state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
-) -> Result<PatientHistoryData, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
-
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden"));
-    }
+    patient: PatientInput,
+) -> Result<PatientData, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    match sqlx::query_as!(
-        PatientHistoryData,
-        r#"
-        SELECT 
-            history_id,
-            patient_id,
-            pgp_sym_decrypt(medical_conditions::bytea, $1) as medical_conditions,
-            pgp_sym_decrypt(medications::bytea, $1) as medications,
-            pgp_sym_decrypt(allergies::bytea, $1) as allergies,
-            (
-                SELECT MAX(activity_time)
-                FROM patient_activity
-                WHERE patient_id = $2
-            ) as last_visit
-        FROM
-            patient_history
-        WHERE
-            patient_id = $2
-        "#,
-        &encryption_key,
-        &patient_id
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(patient_history_data) => {
-            if patient_history_data.len() == 0 {
-                Err(format!(
-                    "Error while fetching patient history data: No data found."
-                ))
-            } else {
-                Ok(patient_history_data[0].clone())
-            }
-        }
-        Err(err) => Err(format!(
-            "Error while fetching patient history data: {}",
-            err
-        )),
-    }
+    edit_patient(&PgRepository::new(pool, &config.keyring), &user, patient_id, &patient).await
 }
 
-// Endpoint to get previous doctors notes and patient complaints for a patient
+// Endpoint to deactivate a patient
 #[tauri::command]
-pub async fn get_patient_doctor_data(
+pub async fn deactivate_patient(
     state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
-) -> Result<Vec<PatientDoctorData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
+) -> Result<PatientData, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden."));
-    }
-
-    match sqlx::query_as!(
-        PatientDoctorData,
-        r#"
-        SELECT 
-            pgp_sym_decrypt(doctors_note::bytea, $1) as doctors_note,
-            pgp_sym_decrypt(patient_complaint::bytea, $1) as patient_complaint,
-            activity_time
-        FROM
-            patient_activity
-        WHERE
-            patient_id = $2
-        "#,
-        &encryption_key,
-        &patient_id
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(patient_doctor_data) => Ok(patient_doctor_data),
-        Err(err) => Err(format!("Error while getting patient doctor data: {}", err)),
-    }
+    deactivate_patient_record(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
 }
 
-// Endpoint to get all procedures
+// Endpoint to get all activity for a particular patient
 #[tauri::command]
-pub async fn get_all_procedures(state: State<'_, DatabaseState>) -> Result<Vec<Procedure>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
+pub async fn get_patient_activity_data(
+    state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<Vec<PatientActivityData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden"));
-    }
-
-    match sqlx::query_as!(
-        Procedure,
-        r#"
-        SELECT
-            procedure_id,
-            pgp_sym_decrypt(procedure_name::bytea, $1) as procedure_name,
-            pgp_sym_decrypt(description::bytea, $1) as description,
-            created_at
-        FROM
-            procedures
-        "#,
-        &encryption_key
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(procedures) => Ok(procedures),
-        Err(err) => Err(format!("Error while fetching procedures: {}", err)),
-    }
+    list_patient_activity(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
 }
 
-// Endpoint to get procedures data for a patient
+// Endpoint to get today's appointment data
 #[tauri::command]
-pub async fn get_patient_procedures(
+pub async fn get_appointment_data(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+) -> Result<Vec<AppointmentData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_todays_appointments(&PgRepository::new(pool, &config.keyring), &user).await
+}
+
+// Endpoint to get patient history data
+#[tauri::command]
+pub async fn get_patient_history_data(
     state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
-) -> Result<Vec<PatientProcedureData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
+) -> Result<PatientHistoryData, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden"));
-    }
-
-    match sqlx::query_as!(
-        PatientProcedureData,
-        r#"
-        SELECT
-            pa.activity_id,
-            pa.status,
-            pgp_sym_decrypt(pr.procedure_name::bytea, $1) as procedure_name,
-            pgp_sym_decrypt(pr.description::bytea, $1) as procedure_description,
-            pgp_sym_decrypt(pa.doctors_note::bytea, $1) as doctors_note,
-            pgp_sym_decrypt(pa.patient_complaint::bytea, $1) as patient_complaint,
-            pgp_sym_decrypt(pa.comments::bytea, $1) as comments,
-            pa.activity_time
-        FROM
-            patient_activity pa
-        LEFT JOIN
-            procedures pr
-        ON
-            pa.procedure_id = pr.procedure_id
-        WHERE
-            pa.patient_id = $2
-        ORDER BY
-            pa.activity_time DESC
-        "#,
-        &encryption_key,
-        &patient_id
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(patient_procedure_data) => Ok(patient_procedure_data),
-        Err(err) => Err(format!(
-            "Error while fetching patient procedure data: {}",
-            err
-        )),
-    }
+    find_patient_history(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
 }
 
-// Endpoint to add comments to a particular activity
+// Endpoint to get previous doctors notes and patient complaints for a patient
 #[tauri::command]
-pub async fn add_comment_to_procedure(
+pub async fn get_patient_doctor_data(
     state: State<'_, DatabaseState>,
-    activity_id: i32,
-    comment: String,
-) -> Result<String, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<Vec<PatientDoctorData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden"));
-    }
+    list_doctor_notes(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
+}
 
-    // Update the comments field by decrypting, appending, and re-encrypting
-    match sqlx::query!(
-        r#"
-        UPDATE patient_activity
-        SET comments = pgp_sym_encrypt(
-            COALESCE(pgp_sym_decrypt(comments::bytea, $1), '') || '\n' || $2,
-            $1
-        )
-        WHERE activity_id = $3
-        RETURNING *;
-        "#,
-        &encryption_key,
-        comment,
-        activity_id
-    )
-    .fetch_one(&*pool)
-    .await
-    {
-        Ok(_) => Ok(format!("Successfully added comment to activity")),
-        Err(err) => Err(format!("Error while adding comment to procedure: {}", err)),
-    }
+// Endpoint to get procedures data for a patient
+#[tauri::command]
+pub async fn get_patient_procedures(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    patient_id: i32,
+) -> Result<Vec<PatientProcedureData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_patient_procedures(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
 }
 
 // Endpoint to create new patient activity
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_patient_activity(
     state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
     procedure_id: i32,
     status: String,
     doctors_note: String,
     patient_complaint: String,
     activity_time: String,
-) -> Result<String, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
-
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden"));
-    }
+    doctor_id: Option<i32>,
+    nurse_id: Option<i32>,
+) -> Result<String, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
     // Convert activity_time from String to DateTime<Utc>
     let activity_time = match activity_time.parse::<DateTime<Utc>>() {
         Ok(dt) => dt,
-        Err(_) => return Err("Invalid datetime format".to_string()),
+        Err(_) => return Err(AppError::validation("activity_time", "Invalid datetime format.")),
     };
 
-    match sqlx::query!(
-        r#"
-        INSERT INTO patient_activity (
-            patient_id, 
-            procedure_id, 
-            status, 
-            doctors_note, 
-            patient_complaint, 
-            activity_time, 
-            created_at
-        ) VALUES (
-            $1, 
-            $2, 
-            $3, 
-            pgp_sym_encrypt($4, $7), 
-            pgp_sym_encrypt($5, $7), 
-            $6, 
-            NOW()
-        ) 
-        RETURNING activity_id
-        "#,
+    let activity = NewPatientActivity {
         patient_id,
         procedure_id,
         status,
         doctors_note,
         patient_complaint,
         activity_time,
-        encryption_key
-    )
-    .fetch_one(&*pool)
-    .await
-    {
-        Ok(record) => Ok(format!(
-            "Successfully created patient activity: {}",
-            record.activity_id
-        )),
-        Err(err) => Err(format!("Error while creating patient activity: {}", err)),
-    }
+        doctor_id,
+        nurse_id,
+    };
+    add_patient_activity(&PgRepository::new(pool, &config.keyring), &user, &activity).await
 }
 
 // Endpoint to fetch all complaints for a patient
 #[tauri::command]
 pub async fn get_patient_complaints(
     state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
-) -> Result<Vec<String>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => "".to_string(),
-    };
+) -> Result<Vec<String>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    if encryption_key.len() == 0 {
-        return Err(format!("Forbidden"));
-    }
-
-    match sqlx::query!(
-        r#"
-        SELECT
-            pgp_sym_decrypt(patient_complaint::bytea, $1) as patient_complaint
-        FROM
-            patient_activity
-        WHERE 
-            patient_id = $2
-        "#,
-        &encryption_key,
-        &patient_id
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(data) => {
-            let mut res = vec![];
-            for entry in data.iter() {
-                if let Some(val) = entry.patient_complaint.clone() {
-                    res.push(val);
-                }
-            }
+    list_patient_complaints(&PgRepository::new(pool, &config.keyring), &user, patient_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{new_activity, test_user, MemoryRepository};
+    use crate::procedure_comments::add_procedure_comment;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn find_patient_records_a_read() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+
+        let patient = find_patient(&repo, &doctor, patient_id).await.unwrap();
+        assert_eq!(patient.first_name, "Ada");
+
+        let events = repo.audit_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, AuditAction::Read);
+        assert_eq!(events[0].patient_id, Some(patient_id));
+    }
+
+    #[tokio::test]
+    async fn find_patient_reports_missing_patient() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+
+        let err = find_patient(&repo, &doctor, 42).await.unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+        assert!(repo.audit_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn admins_cannot_read_clinical_data() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
 
-            Ok(res)
+        assert_eq!(list_patient_activity(&repo, &admin, patient_id).await.unwrap_err(), AppError::forbidden());
+        assert_eq!(list_patient_complaints(&repo, &admin, patient_id).await.unwrap_err(), AppError::forbidden());
+        assert!(find_patient(&repo, &admin, patient_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn activities_are_listed_newest_first() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+        let exam = repo.add_procedure("Eye exam", "Routine exam");
+        let surgery = repo.add_procedure("Cataract surgery", "Lens replacement");
+        let now = Utc::now();
+
+        add_patient_activity(&repo, &doctor, &NewPatientActivity { activity_time: now - Duration::days(7), ..new_activity(patient_id, exam) }).await.unwrap();
+        add_patient_activity(&repo, &doctor, &NewPatientActivity { activity_time: now, ..new_activity(patient_id, surgery) }).await.unwrap();
+
+        let activities = list_patient_activity(&repo, &doctor, patient_id).await.unwrap();
+        let names: Vec<_> = activities.iter().map(|activity| activity.activity.as_deref()).collect();
+        assert_eq!(names, vec![Some("Cataract surgery"), Some("Eye exam")]);
+        assert_eq!(list_patient_complaints(&repo, &doctor, patient_id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn nurses_comment_but_cannot_create_activities() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+        let exam = repo.add_procedure("Eye exam", "Routine exam");
+        let activity = new_activity(patient_id, exam);
+
+        assert_eq!(add_patient_activity(&repo, &nurse, &activity).await.unwrap_err(), AppError::forbidden());
+
+        add_patient_activity(&repo, &doctor, &activity).await.unwrap();
+        let activity_id = list_patient_activity(&repo, &doctor, patient_id).await.unwrap()[0].activity_id;
+        add_procedure_comment(&repo, &nurse, activity_id, None, "Drops given").await.unwrap();
+        add_procedure_comment(&repo, &nurse, activity_id, None, "Patient discharged").await.unwrap();
+
+        let procedures = list_patient_procedures(&repo, &nurse, patient_id).await.unwrap();
+        assert_eq!(procedures[0].comments.as_deref(), Some("Drops given\nPatient discharged"));
+    }
+
+    #[tokio::test]
+    async fn commenting_on_a_missing_activity_fails() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+
+        let err = add_procedure_comment(&repo, &nurse, 7, None, "Drops given").await.unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn history_reports_the_last_visit() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+        let exam = repo.add_procedure("Eye exam", "Routine exam");
+
+        let err = find_patient_history(&repo, &doctor, patient_id).await.unwrap_err();
+        assert_eq!(err, AppError::not_found("No history found for this patient."));
+
+        let history_id = repo.add_history(patient_id, "Glaucoma", "Timolol", "Penicillin");
+        let visit = Utc::now() - Duration::days(3);
+        add_patient_activity(&repo, &doctor, &NewPatientActivity { activity_time: visit, ..new_activity(patient_id, exam) }).await.unwrap();
+
+        let history = find_patient_history(&repo, &doctor, patient_id).await.unwrap();
+        assert_eq!(history.history_id, history_id);
+        assert_eq!(history.last_visit, Some(visit));
+        assert_eq!(repo.audit_events().last().unwrap().entity_id, Some(history_id));
+    }
+
+    #[tokio::test]
+    async fn patient_list_records_one_read_per_patient() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+        repo.add_patient("Ada", "Lovelace");
+        repo.add_patient("Alan", "Turing");
+
+        assert_eq!(list_patients(&repo, &nurse).await.unwrap().len(), 2);
+        assert_eq!(repo.audit_events().len(), 2);
+    }
+
+    fn patient_input(first_name: &str) -> PatientInput {
+        PatientInput {
+            first_name: first_name.to_string(),
+            last_name: "Okafor".to_string(),
+            date_of_birth: NaiveDate::from_ymd_opt(1975, 6, 30).unwrap(),
+            gender: "female".to_string(),
+            phone: Some("+44 20 7946 0958".to_string()),
+            email: Some(" ".to_string()),
+            address: None,
+            emergency_contact_name: None,
+            emergency_contact_phone: None,
+            preferred_language: Some("Yoruba".to_string()),
+            national_id: None,
         }
-        Err(err) => Err(format!("Error while fetching patient complaints: {}", err)),
+    }
+
+    #[test]
+    fn mr_number_patterns_are_parsed_and_formatted() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+
+        assert_eq!(MrNumberPattern::default().format(42, date), "MR-2026-000042");
+        assert_eq!(MrNumberPattern::parse("{YY}{MM}/{SEQ}").unwrap().format(7, date), "2603/7");
+        assert!(MrNumberPattern::parse("MR-{YYYY}").is_err());
+        assert!(MrNumberPattern::parse("{SEQ}-{SEQ:4}").is_err());
+        assert!(MrNumberPattern::parse("MR-{SEQ:x}").is_err());
+        assert!(MrNumberPattern::parse("MR-{SEQ").is_err());
+    }
+
+    #[test]
+    fn patient_validation_reports_every_field() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let mut patient = patient_input(" ");
+        patient.gender = "unknown".to_string();
+        patient.date_of_birth = NaiveDate::from_ymd_opt(2026, 1, 2).unwrap();
+        patient.email = Some("not-an-email".to_string());
+        patient.emergency_contact_phone = Some("call me".to_string());
+
+        let fields: Vec<String> = match validate_patient(&patient, today).unwrap_err() {
+            AppError::Validation(errors) => errors.into_iter().map(|error| error.field).collect(),
+            other => panic!("unexpected error {:?}", other),
+        };
+        assert_eq!(fields, vec!["first_name", "gender", "date_of_birth", "emergency_contact_phone", "email"]);
+
+        let valid = validate_patient(&patient_input(" Bisi "), today).unwrap();
+        assert_eq!(valid.first_name, "Bisi");
+        assert_eq!(valid.gender, "FEMALE");
+        assert_eq!(valid.email, None);
+    }
+
+    #[tokio::test]
+    async fn registration_generates_mr_numbers_and_skips_taken_ones() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+        let pattern = MrNumberPattern::parse("MR{SEQ:6}").unwrap();
+        // Seeded patients are numbered MR + id; move the sequence up to just before that id
+        let taken = repo.add_patient("Ada", "Lovelace");
+        while repo.next_mr_sequence().await.unwrap() < i64::from(taken) - 1 {}
+
+        let patient = register_patient(&repo, &nurse, &pattern, &patient_input("Bisi")).await.unwrap();
+
+        assert_eq!(patient.mr_number, format!("MR{:06}", taken + 1));
+        assert_eq!(patient.preferred_language.as_deref(), Some("Yoruba"));
+        let event = repo.audit_events().pop().unwrap();
+        assert_eq!(event.action, AuditAction::Create);
+        assert_eq!(event.patient_id, Some(patient.patient_id));
+    }
+
+    #[tokio::test]
+    async fn deactivated_patients_leave_the_list_and_cannot_be_edited() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let patient = register_patient(&repo, &admin, &MrNumberPattern::default(), &patient_input("Bisi")).await.unwrap();
+
+        let edited = edit_patient(&repo, &admin, patient.patient_id, &patient_input("Abisola")).await.unwrap();
+        assert_eq!(edited.first_name, "Abisola");
+        assert!(edited.updated_at.is_some());
+
+        let deactivated = deactivate_patient_record(&repo, &admin, patient.patient_id).await.unwrap();
+        assert!(!deactivated.active);
+        assert!(list_patients(&repo, &admin).await.unwrap().is_empty());
+        let err = edit_patient(&repo, &admin, patient.patient_id, &patient_input("Bisi")).await.unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+        let err = deactivate_patient_record(&repo, &admin, patient.patient_id).await.unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
     }
 }
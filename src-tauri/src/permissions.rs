@@ -0,0 +1,167 @@
+// src-tauri/src/permissions.rs
+
+// Dependencies
+use crate::auth::{get_user_from_token, AuthUser};
+use crate::config::AppConfig;
+use crate::error::AppError;
+use sqlx::{Pool, Postgres};
+
+// Roles as stored in users.role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Doctor,
+    Nurse,
+    Admin,
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Result<Role, String> {
+        match role {
+            "DOCTOR" => Ok(Role::Doctor),
+            "NURSE" => Ok(Role::Nurse),
+            "ADMIN" => Ok(Role::Admin),
+            _ => Err(format!("Unknown role: {}", role)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Doctor => "DOCTOR",
+            Role::Nurse => "NURSE",
+            Role::Admin => "ADMIN",
+        }
+    }
+}
+
+// Actions a command can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    // Patient demographics
+    ReadPatient,
+    // Registering patients and editing or deactivating their demographics
+    WritePatient,
+    // Merging duplicate patient records and undoing merges
+    MergePatients,
+    // Activities, history, notes, complaints, vision/refraction/IOP
+    ReadClinical,
+    // Vision, refraction and eye measurement entries
+    WriteMeasurements,
+    // Conditions, medications and allergies in the medical history
+    WriteHistory,
+    // New patient activities with doctor's notes
+    WriteActivity,
+    // Moving activities through the clinical workflow, limited per transition by role
+    TransitionActivity,
+    // Choosing the attending doctor and assisting nurse of an activity
+    AssignActivity,
+    // Comments on an existing procedure
+    CommentOnProcedure,
+    // Procedure catalog
+    ReadProcedures,
+    // Adding, editing, deactivating and deleting catalog procedures
+    ManageProcedures,
+    // Exporting clinical documents to disk
+    ExportDocuments,
+    // Conversations and messages
+    Messaging,
+    // Alerts and appointments between staff
+    StaffCoordination,
+    // Staff directory
+    ReadStaff,
+    // User administration
+    ManageUsers,
+    // Patient data access history
+    ViewAuditLog,
+    // Re-encrypting stored data under a new key
+    ManageEncryptionKeys,
+}
+
+const DOCTOR_PERMISSIONS: &[Permission] = &[
+    Permission::ReadPatient,
+    Permission::WritePatient,
+    Permission::ReadClinical,
+    Permission::WriteMeasurements,
+    Permission::WriteHistory,
+    Permission::WriteActivity,
+    Permission::TransitionActivity,
+    Permission::AssignActivity,
+    Permission::CommentOnProcedure,
+    Permission::ReadProcedures,
+    Permission::ExportDocuments,
+    Permission::Messaging,
+    Permission::StaffCoordination,
+    Permission::ReadStaff,
+];
+
+const NURSE_PERMISSIONS: &[Permission] = &[
+    Permission::ReadPatient,
+    Permission::WritePatient,
+    Permission::ReadClinical,
+    Permission::WriteMeasurements,
+    Permission::WriteHistory,
+    Permission::TransitionActivity,
+    Permission::AssignActivity,
+    Permission::CommentOnProcedure,
+    Permission::ReadProcedures,
+    Permission::ExportDocuments,
+    Permission::Messaging,
+    Permission::StaffCoordination,
+    Permission::ReadStaff,
+];
+
+// Admins run the practice but do not see clinical data
+const ADMIN_PERMISSIONS: &[Permission] = &[
+    Permission::ReadPatient,
+    Permission::WritePatient,
+    Permission::MergePatients,
+    Permission::TransitionActivity,
+    Permission::AssignActivity,
+    Permission::ReadProcedures,
+    Permission::ManageProcedures,
+    Permission::Messaging,
+    Permission::StaffCoordination,
+    Permission::ReadStaff,
+    Permission::ManageUsers,
+    Permission::ViewAuditLog,
+    Permission::ManageEncryptionKeys,
+];
+
+pub fn role_permissions(role: Role) -> &'static [Permission] {
+    match role {
+        Role::Doctor => DOCTOR_PERMISSIONS,
+        Role::Nurse => NURSE_PERMISSIONS,
+        Role::Admin => ADMIN_PERMISSIONS,
+    }
+}
+
+pub fn role_has_permission(role: Role, permission: Permission) -> bool {
+    role_permissions(role).contains(&permission)
+}
+
+// Function to verify the token and its session. Services check permissions on the returned user.
+pub async fn authenticate(pool: &Pool<Postgres>, config: &AppConfig, token: &str) -> Result<AuthUser, AppError> {
+    get_user_from_token(pool, config, token).await.map_err(|err| match err {
+        AppError::PasswordChangeRequired(_) => err,
+        _ => AppError::unauthorized(),
+    })
+}
+
+// Function to check that an authenticated user's role grants the permission
+pub fn require(user: &AuthUser, permission: Permission) -> Result<(), AppError> {
+    let role = Role::parse(&user.role).map_err(|_| AppError::forbidden())?;
+
+    if !role_has_permission(role, permission) {
+        return Err(AppError::forbidden());
+    }
+
+    Ok(())
+}
+
+// Function to verify the token and its session, and check that the role grants the permission.
+// Returns the verified user so callers can record authorship from it.
+pub async fn authorize(pool: &Pool<Postgres>, config: &AppConfig, token: &str, permission: Permission) -> Result<AuthUser, AppError> {
+    let user = authenticate(pool, config, token).await?;
+    require(&user, permission)?;
+
+    Ok(user)
+}
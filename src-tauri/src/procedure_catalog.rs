@@ -0,0 +1,606 @@
+// src-tauri/src/procedure_catalog.rs
+
+// Dependencies
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tauri::State;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_PROCEDURE};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::{AppError, FieldError};
+use crate::keys::KeyRing;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{PgRepository, ProcedureCatalogRepo};
+
+pub const PROCEDURE_CATEGORIES: &[&str] = &["DIAGNOSTIC", "SURGICAL", "IMAGING", "OPTOMETRY"];
+const MAX_NAME_LENGTH: usize = 200;
+const MAX_DESCRIPTION_LENGTH: usize = 2000;
+// A procedure that takes longer than a day is booked as several
+const MAX_DURATION_MINUTES: i32 = 24 * 60;
+
+// Struct to store result of get_all_procedures
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Procedure {
+    pub procedure_id: i32,
+    pub procedure_name: Option<String>,
+    pub description: Option<String>,
+    // One of PROCEDURE_CATEGORIES
+    pub category: String,
+    pub default_duration_minutes: Option<i32>,
+    // In the smallest unit of the practice's currency
+    pub default_cost_cents: Option<i64>,
+    pub cpt_code: Option<String>,
+    pub snomed_code: Option<String>,
+    // Inactive procedures cannot be used for new activities
+    pub active: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+// Struct to store input of create_procedure and update_procedure
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ProcedureInput {
+    pub procedure_name: String,
+    pub description: Option<String>,
+    pub category: String,
+    pub default_duration_minutes: Option<i32>,
+    pub default_cost_cents: Option<i64>,
+    pub cpt_code: Option<String>,
+    pub snomed_code: Option<String>,
+}
+
+// Function to check a CPT code: four digits, then a digit or the F, T or U of category II/III codes
+fn is_cpt_code(code: &str) -> bool {
+    let bytes = code.as_bytes();
+    bytes.len() == 5 && bytes[..4].iter().all(u8::is_ascii_digit) && matches!(bytes[4], b'0'..=b'9' | b'F' | b'T' | b'U')
+}
+
+// Function to check the shape of a SNOMED CT concept id: 6 to 18 digits without a leading zero
+fn is_snomed_code(code: &str) -> bool {
+    (6..=18).contains(&code.len()) && code.bytes().all(|byte| byte.is_ascii_digit()) && !code.starts_with('0')
+}
+
+// Function to check a catalog entry and tidy its text. Every problem is reported at once.
+pub fn validate_procedure(input: &ProcedureInput) -> Result<ProcedureInput, AppError> {
+    let mut errors = vec![];
+    let mut error = |field: &str, message: String| errors.push(FieldError { field: field.to_string(), message });
+    let optional = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+    let code = |value: &Option<String>| optional(value).map(|code| code.to_uppercase());
+
+    let cleaned = ProcedureInput {
+        procedure_name: input.procedure_name.trim().to_string(),
+        description: optional(&input.description),
+        category: input.category.trim().to_uppercase(),
+        default_duration_minutes: input.default_duration_minutes,
+        default_cost_cents: input.default_cost_cents,
+        cpt_code: code(&input.cpt_code),
+        snomed_code: code(&input.snomed_code),
+    };
+
+    if cleaned.procedure_name.is_empty() {
+        error("procedure_name", "This field is required.".to_string());
+    } else if cleaned.procedure_name.chars().count() > MAX_NAME_LENGTH {
+        error("procedure_name", format!("Use at most {} characters.", MAX_NAME_LENGTH));
+    }
+    if cleaned.description.as_deref().is_some_and(|description| description.chars().count() > MAX_DESCRIPTION_LENGTH) {
+        error("description", format!("Use at most {} characters.", MAX_DESCRIPTION_LENGTH));
+    }
+    if !PROCEDURE_CATEGORIES.contains(&cleaned.category.as_str()) {
+        error("category", "Category must be DIAGNOSTIC, SURGICAL, IMAGING or OPTOMETRY.".to_string());
+    }
+    if cleaned.default_duration_minutes.is_some_and(|minutes| !(1..=MAX_DURATION_MINUTES).contains(&minutes)) {
+        error("default_duration_minutes", format!("Duration must be between 1 and {} minutes.", MAX_DURATION_MINUTES));
+    }
+    if cleaned.default_cost_cents.is_some_and(|cost| cost < 0) {
+        error("default_cost_cents", "Cost cannot be negative.".to_string());
+    }
+    if cleaned.cpt_code.as_deref().is_some_and(|code| !is_cpt_code(code)) {
+        error("cpt_code", "A CPT code is four digits followed by a digit, F, T or U.".to_string());
+    }
+    if cleaned.snomed_code.as_deref().is_some_and(|code| !is_snomed_code(code)) {
+        error("snomed_code", "A SNOMED CT code is 6 to 18 digits.".to_string());
+    }
+
+    if errors.is_empty() {
+        Ok(cleaned)
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+// Audit event for a change to the catalog, shared by every ProcedureCatalogRepo implementation
+pub fn procedure_changed_event(user_id: i32, action: AuditAction, before: Option<&Procedure>, after: &Procedure) -> AuditEvent {
+    AuditEvent::new(user_id, action, ENTITY_PROCEDURE)
+        .entity_id(after.procedure_id)
+        .before(before)
+        .after(after)
+}
+
+async fn fetch_procedure(conn: &mut PgConnection, keyring: &KeyRing, procedure_id: i32) -> Result<Option<Procedure>, AppError> {
+    sqlx::query_as!(
+        Procedure,
+        r#"
+        SELECT
+            procedure_id,
+            pgp_sym_decrypt(procedure_name::bytea, ($1::TEXT[])[key_id]) as procedure_name,
+            pgp_sym_decrypt(description::bytea, ($1::TEXT[])[key_id]) as description,
+            category,
+            default_duration_minutes,
+            default_cost_cents,
+            cpt_code,
+            snomed_code,
+            active,
+            created_at,
+            updated_at
+        FROM procedures
+        WHERE procedure_id = $2
+        "#,
+        &keyring.sql_keys(),
+        procedure_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| AppError::database("Error while fetching procedure", e))
+}
+
+impl ProcedureCatalogRepo for PgRepository<'_> {
+    async fn list_procedures(&self, include_inactive: bool) -> Result<Vec<Procedure>, AppError> {
+        let mut procedures = sqlx::query_as!(
+            Procedure,
+            r#"
+            SELECT
+                procedure_id,
+                pgp_sym_decrypt(procedure_name::bytea, ($1::TEXT[])[key_id]) as procedure_name,
+                pgp_sym_decrypt(description::bytea, ($1::TEXT[])[key_id]) as description,
+                category,
+                default_duration_minutes,
+                default_cost_cents,
+                cpt_code,
+                snomed_code,
+                active,
+                created_at,
+                updated_at
+            FROM procedures
+            WHERE active OR $2
+            "#,
+            &self.keyring.sql_keys(),
+            include_inactive
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching procedures", e))?;
+
+        // Names are encrypted, so they can only be ordered once decrypted
+        procedures.sort_by_key(|procedure| (procedure.procedure_name.as_deref().map(str::to_lowercase), procedure.procedure_id));
+        Ok(procedures)
+    }
+
+    async fn find_procedure(&self, procedure_id: i32) -> Result<Option<Procedure>, AppError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| AppError::database("Error while acquiring connection", e))?;
+
+        fetch_procedure(&mut conn, self.keyring, procedure_id).await
+    }
+
+    async fn count_procedure_activities(&self, procedure_id: i32) -> Result<i64, AppError> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM patient_activity WHERE procedure_id = $1"#,
+            procedure_id
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while counting procedure activities", e))
+    }
+
+    async fn create_procedure(&self, user_id: i32, input: &ProcedureInput) -> Result<Procedure, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let procedure_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO procedures (
+                procedure_name, description, category, default_duration_minutes, default_cost_cents,
+                cpt_code, snomed_code, key_id, created_by
+            )
+            VALUES (pgp_sym_encrypt($1, $8), pgp_sym_encrypt($2, $8), $3, $4, $5, $6, $7, $9, $10)
+            RETURNING procedure_id
+            "#,
+            &input.procedure_name,
+            input.description.as_deref(),
+            &input.category,
+            input.default_duration_minutes,
+            input.default_cost_cents,
+            input.cpt_code.as_deref(),
+            input.snomed_code.as_deref(),
+            &keyring.current().secret,
+            keyring.current().id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while creating procedure", e))?;
+
+        let created = fetch_procedure(&mut tx, keyring, procedure_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Procedure was not saved.".to_string()))?;
+        record_audit(&mut *tx, self.keyring, procedure_changed_event(user_id, AuditAction::Create, None, &created)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(created)
+    }
+
+    async fn update_procedure(&self, user_id: i32, procedure_id: i32, input: &ProcedureInput) -> Result<Option<Procedure>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let Some(before) = fetch_procedure(&mut tx, keyring, procedure_id).await? else {
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE procedures
+            SET
+                procedure_name = pgp_sym_encrypt($2, $8),
+                description = pgp_sym_encrypt($3, $8),
+                category = $4,
+                default_duration_minutes = $5,
+                default_cost_cents = $6,
+                cpt_code = $7,
+                snomed_code = $9,
+                key_id = $10,
+                updated_at = NOW(),
+                updated_by = $11
+            WHERE procedure_id = $1
+            "#,
+            procedure_id,
+            &input.procedure_name,
+            input.description.as_deref(),
+            &input.category,
+            input.default_duration_minutes,
+            input.default_cost_cents,
+            input.cpt_code.as_deref(),
+            &keyring.current().secret,
+            input.snomed_code.as_deref(),
+            keyring.current().id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating procedure", e))?;
+
+        let updated = fetch_procedure(&mut tx, keyring, procedure_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Procedure was not saved.".to_string()))?;
+        record_audit(&mut *tx, self.keyring, procedure_changed_event(user_id, AuditAction::Update, Some(&before), &updated)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(updated))
+    }
+
+    async fn set_procedure_active(&self, user_id: i32, procedure_id: i32, active: bool) -> Result<Option<Procedure>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let Some(before) = fetch_procedure(&mut tx, keyring, procedure_id).await? else {
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            "UPDATE procedures SET active = $2, updated_at = NOW(), updated_by = $3 WHERE procedure_id = $1",
+            procedure_id,
+            active,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating procedure", e))?;
+
+        let updated = fetch_procedure(&mut tx, keyring, procedure_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Procedure was not saved.".to_string()))?;
+        record_audit(&mut *tx, self.keyring, procedure_changed_event(user_id, AuditAction::Update, Some(&before), &updated)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(updated))
+    }
+
+    async fn delete_procedure(&self, user_id: i32, procedure_id: i32) -> Result<Option<Procedure>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let Some(deleted) = fetch_procedure(&mut tx, keyring, procedure_id).await? else {
+            return Ok(None);
+        };
+
+        // patient_activity references procedures with ON DELETE RESTRICT, so a procedure that
+        // gained an activity since the service checked is reported as a conflict
+        sqlx::query!("DELETE FROM procedures WHERE procedure_id = $1", procedure_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::database("Error while deleting procedure", e))?;
+        record_audit(&mut *tx, self.keyring, procedure_changed_event(user_id, AuditAction::Delete, Some(&deleted), &deleted)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(deleted))
+    }
+}
+
+// Function to list the procedure catalog by name. Inactive procedures are only listed on request.
+pub async fn list_procedures<R: ProcedureCatalogRepo>(
+    repo: &R,
+    user: &AuthUser,
+    include_inactive: bool
+) -> Result<Vec<Procedure>, AppError> {
+    require(user, Permission::ReadProcedures)?;
+
+    repo.list_procedures(include_inactive).await
+}
+
+// Function to add a procedure to the catalog
+pub async fn add_procedure<R: ProcedureCatalogRepo>(
+    repo: &R,
+    user: &AuthUser,
+    input: &ProcedureInput
+) -> Result<Procedure, AppError> {
+    require(user, Permission::ManageProcedures)?;
+
+    let input = validate_procedure(input)?;
+    repo.create_procedure(user.user_id, &input).await
+}
+
+// Function to replace the fields of a catalog procedure
+pub async fn edit_procedure<R: ProcedureCatalogRepo>(
+    repo: &R,
+    user: &AuthUser,
+    procedure_id: i32,
+    input: &ProcedureInput
+) -> Result<Procedure, AppError> {
+    require(user, Permission::ManageProcedures)?;
+
+    let input = validate_procedure(input)?;
+    repo.update_procedure(user.user_id, procedure_id, &input)
+        .await?
+        .ok_or_else(|| AppError::not_found("Procedure does not exist."))
+}
+
+// Function to take a procedure out of use, or bring it back
+pub async fn set_procedure_active<R: ProcedureCatalogRepo>(
+    repo: &R,
+    user: &AuthUser,
+    procedure_id: i32,
+    active: bool
+) -> Result<Procedure, AppError> {
+    require(user, Permission::ManageProcedures)?;
+
+    repo.set_procedure_active(user.user_id, procedure_id, active)
+        .await?
+        .ok_or_else(|| AppError::not_found("Procedure does not exist."))
+}
+
+// Function to delete a procedure that no activity uses. Used ones can only be deactivated.
+pub async fn remove_procedure<R: ProcedureCatalogRepo>(
+    repo: &R,
+    user: &AuthUser,
+    procedure_id: i32
+) -> Result<Procedure, AppError> {
+    require(user, Permission::ManageProcedures)?;
+
+    let uses = repo.count_procedure_activities(procedure_id).await?;
+    if uses > 0 {
+        return Err(AppError::conflict(&format!(
+            "This procedure is recorded on {} patient activit{}. Deactivate it instead.",
+            uses,
+            if uses == 1 { "y" } else { "ies" }
+        )));
+    }
+
+    repo.delete_procedure(user.user_id, procedure_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Procedure does not exist."))
+}
+
+// Function to check that a procedure can be used for a new activity
+pub async fn require_active_procedure<R: ProcedureCatalogRepo>(repo: &R, procedure_id: i32) -> Result<(), AppError> {
+    match repo.find_procedure(procedure_id).await? {
+        Some(procedure) if procedure.active => Ok(()),
+        Some(_) => Err(AppError::validation("procedure_id", "This procedure is no longer offered.")),
+        None => Err(AppError::validation("procedure_id", "Procedure does not exist.")),
+    }
+}
+
+// Endpoint to get the procedure catalog
+#[tauri::command]
+pub async fn get_all_procedures(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    include_inactive: Option<bool>,
+) -> Result<Vec<Procedure>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_procedures(&PgRepository::new(pool, &config.keyring), &user, include_inactive.unwrap_or(false)).await
+}
+
+// Endpoint to add a procedure to the catalog
+#[tauri::command]
+pub async fn create_procedure(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    procedure: ProcedureInput,
+) -> Result<Procedure, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    add_procedure(&PgRepository::new(pool, &config.keyring), &user, &procedure).await
+}
+
+// Endpoint to update a catalog procedure
+#[tauri::command]
+pub async fn update_procedure(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    procedure_id: i32,
+    procedure: ProcedureInput,
+) -> Result<Procedure, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    edit_procedure(&PgRepository::new(pool, &config.keyring), &user, procedure_id, &procedure).await
+}
+
+// Endpoint to stop offering a procedure
+#[tauri::command]
+pub async fn deactivate_procedure(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    procedure_id: i32,
+) -> Result<Procedure, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    set_procedure_active(&PgRepository::new(pool, &config.keyring), &user, procedure_id, false).await
+}
+
+// Endpoint to offer a deactivated procedure again
+#[tauri::command]
+pub async fn reactivate_procedure(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    procedure_id: i32,
+) -> Result<Procedure, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    set_procedure_active(&PgRepository::new(pool, &config.keyring), &user, procedure_id, true).await
+}
+
+// Endpoint to delete a procedure that was never used
+#[tauri::command]
+pub async fn delete_procedure(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    procedure_id: i32,
+) -> Result<Procedure, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    remove_procedure(&PgRepository::new(pool, &config.keyring), &user, procedure_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{new_activity, test_user, MemoryRepository};
+
+    fn input(procedure_name: &str, category: &str) -> ProcedureInput {
+        ProcedureInput {
+            procedure_name: procedure_name.to_string(),
+            description: None,
+            category: category.to_string(),
+            default_duration_minutes: Some(30),
+            default_cost_cents: Some(12_000),
+            cpt_code: None,
+            snomed_code: None,
+        }
+    }
+
+    #[test]
+    fn validation_reports_every_bad_field_and_normalizes_codes() {
+        let bad = ProcedureInput {
+            procedure_name: "  ".to_string(),
+            category: "dental".to_string(),
+            default_duration_minutes: Some(0),
+            default_cost_cents: Some(-1),
+            cpt_code: Some("9200".to_string()),
+            snomed_code: Some("012345".to_string()),
+            ..input("", "")
+        };
+        let Err(AppError::Validation(errors)) = validate_procedure(&bad) else {
+            panic!("expected validation errors");
+        };
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["procedure_name", "category", "default_duration_minutes", "default_cost_cents", "cpt_code", "snomed_code"]
+        );
+
+        let good = ProcedureInput {
+            description: Some("   ".to_string()),
+            cpt_code: Some(" 0001f ".to_string()),
+            snomed_code: Some("252886007".to_string()),
+            ..input(" Refraction ", "optometry")
+        };
+        let cleaned = validate_procedure(&good).unwrap();
+        assert_eq!(cleaned.procedure_name, "Refraction");
+        assert_eq!(cleaned.category, "OPTOMETRY");
+        assert_eq!(cleaned.description, None);
+        assert_eq!(cleaned.cpt_code.as_deref(), Some("0001F"));
+    }
+
+    #[tokio::test]
+    async fn only_admins_manage_and_used_procedures_can_only_be_deactivated() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let doctor = test_user(&repo, "DOCTOR");
+
+        let denied = add_procedure(&repo, &doctor, &input("Gonioscopy", "DIAGNOSTIC")).await;
+        assert!(matches!(denied, Err(AppError::Forbidden(_))));
+
+        let unused = add_procedure(&repo, &admin, &input("Gonioscopy", "DIAGNOSTIC")).await.unwrap();
+        let used = add_procedure(&repo, &admin, &input("Pachymetry", "DIAGNOSTIC")).await.unwrap();
+        let activity = new_activity(repo.add_patient("Ada", "Lovelace"), used.procedure_id);
+        crate::patients::add_patient_activity(&repo, &doctor, &activity).await.unwrap();
+
+        let in_use = remove_procedure(&repo, &admin, used.procedure_id).await;
+        assert!(matches!(in_use, Err(AppError::Conflict(_))));
+        set_procedure_active(&repo, &admin, used.procedure_id, false).await.unwrap();
+        remove_procedure(&repo, &admin, unused.procedure_id).await.unwrap();
+
+        assert!(list_procedures(&repo, &doctor, false).await.unwrap().is_empty());
+        let all = list_procedures(&repo, &doctor, true).await.unwrap();
+        assert_eq!((all.len(), all[0].active), (1, false));
+
+        let retired = crate::patients::add_patient_activity(&repo, &doctor, &activity).await;
+        assert!(matches!(retired, Err(AppError::Validation(_))));
+    }
+}
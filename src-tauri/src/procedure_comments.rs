@@ -0,0 +1,718 @@
+// src-tauri/src/procedure_comments.rs
+
+// Dependencies
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, Pool, Postgres};
+use tauri::State;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_PROCEDURE_COMMENT};
+use crate::auth::AuthUser;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::keys::KeyRing;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AuditRepo, PgRepository, ProcedureCommentRepo};
+
+const MAX_COMMENT_LENGTH: usize = 2000;
+
+// Struct to store one comment on an activity, with its replies when listed as a thread
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ProcedureComment {
+    pub comment_id: i32,
+    pub activity_id: i32,
+    pub patient_id: i32,
+    // None for a comment that starts a thread
+    pub parent_id: Option<i32>,
+    // None once the comment was deleted
+    pub body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    // None for comments moved over from the old comments field, which did not record authors
+    pub created_by: Option<i32>,
+    pub created_by_name: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub updated_by: Option<i32>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<i32>,
+    pub replies: Vec<ProcedureComment>,
+}
+
+// Struct to store one version of a comment, as returned by get_procedure_comment_history
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CommentChange {
+    pub change_id: i32,
+    pub comment_id: i32,
+    // CREATE, UPDATE or DELETE
+    pub action: String,
+    // The comment after the change, or as it was when deleted
+    pub body: Option<String>,
+    pub changed_by: Option<i32>,
+    pub changed_by_name: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+// Comment as read from Postgres, also by the timeline
+pub(crate) struct CommentRow {
+    pub(crate) comment_id: i32,
+    pub(crate) activity_id: i32,
+    pub(crate) patient_id: Option<i32>,
+    pub(crate) parent_id: Option<i32>,
+    pub(crate) body: Option<String>,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) created_by: Option<i32>,
+    pub(crate) created_by_name: Option<String>,
+    pub(crate) updated_at: Option<DateTime<Utc>>,
+    pub(crate) updated_by: Option<i32>,
+    pub(crate) deleted_at: Option<DateTime<Utc>>,
+    pub(crate) deleted_by: Option<i32>,
+}
+
+impl From<CommentRow> for ProcedureComment {
+    fn from(row: CommentRow) -> Self {
+        ProcedureComment {
+            comment_id: row.comment_id,
+            activity_id: row.activity_id,
+            patient_id: row.patient_id.unwrap_or_default(),
+            parent_id: row.parent_id,
+            // The stored text stays readable through get_procedure_comment_history only
+            body: row.body.filter(|_| row.deleted_at.is_none()),
+            created_at: row.created_at,
+            created_by: row.created_by,
+            created_by_name: row.created_by_name,
+            updated_at: row.updated_at,
+            updated_by: row.updated_by,
+            deleted_at: row.deleted_at,
+            deleted_by: row.deleted_by,
+            replies: vec![],
+        }
+    }
+}
+
+// Function to check a comment and tidy its text
+pub fn validate_comment(comment: &str) -> Result<String, AppError> {
+    let comment = comment.trim();
+
+    if comment.is_empty() {
+        return Err(AppError::validation("comment", "Comment cannot be empty."));
+    }
+    if comment.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(AppError::validation("comment", &format!("Use at most {} characters.", MAX_COMMENT_LENGTH)));
+    }
+
+    Ok(comment.to_string())
+}
+
+// Function to nest replies under their parents, oldest first at every level.
+// Deleted comments stay as placeholders only while they have replies.
+pub fn thread_comments(comments: Vec<ProcedureComment>) -> Vec<ProcedureComment> {
+    fn children(parent_id: Option<i32>, comments: &[ProcedureComment]) -> Vec<ProcedureComment> {
+        comments
+            .iter()
+            .filter(|comment| comment.parent_id == parent_id)
+            .map(|comment| ProcedureComment { replies: children(Some(comment.comment_id), comments), ..comment.clone() })
+            .filter(|comment| comment.deleted_at.is_none() || !comment.replies.is_empty())
+            .collect()
+    }
+
+    let mut comments = comments;
+    comments.sort_by_key(|comment| (comment.created_at, comment.comment_id));
+    children(None, &comments)
+}
+
+// Audit event for a change to a comment, shared by every ProcedureCommentRepo implementation
+pub fn comment_changed_event(user_id: i32, action: AuditAction, before: Option<&str>, after: &ProcedureComment) -> AuditEvent {
+    AuditEvent::new(user_id, action, ENTITY_PROCEDURE_COMMENT)
+        .patient(after.patient_id)
+        .entity_id(after.comment_id)
+        .before(before.as_ref())
+        .after(&after.body)
+}
+
+async fn fetch_comment(conn: &mut PgConnection, keyring: &KeyRing, comment_id: i32) -> Result<Option<ProcedureComment>, AppError> {
+    sqlx::query_as!(
+        CommentRow,
+        r#"
+        SELECT
+            c.comment_id,
+            c.activity_id,
+            pa.patient_id,
+            c.parent_id,
+            pgp_sym_decrypt(c.body::bytea, ($1::TEXT[])[c.key_id]) as body,
+            c.created_at,
+            c.created_by,
+            u.first_name || ' ' || u.last_name as created_by_name,
+            c.updated_at,
+            c.updated_by,
+            c.deleted_at,
+            c.deleted_by
+        FROM procedure_comments c
+        JOIN patient_activity pa ON pa.activity_id = c.activity_id
+        LEFT JOIN users u ON u.user_id = c.created_by
+        WHERE c.comment_id = $2
+        "#,
+        &keyring.sql_keys(),
+        comment_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map(|row| row.map(ProcedureComment::from))
+    .map_err(|e| AppError::database("Error while fetching comment", e))
+}
+
+// Function to append a version of a comment to procedure_comment_changes
+async fn insert_change(
+    conn: &mut PgConnection,
+    keyring: &KeyRing,
+    user_id: i32,
+    action: AuditAction,
+    comment_id: i32,
+    body: &str
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO procedure_comment_changes (comment_id, action, body, key_id, changed_by)
+        VALUES ($1, $2, pgp_sym_encrypt($3, $4), $5, $6)
+        "#,
+        comment_id,
+        action.as_str(),
+        body,
+        &keyring.current().secret,
+        keyring.current().id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map(|_| ())
+    .map_err(|e| AppError::database("Error while recording comment change", e))
+}
+
+impl ProcedureCommentRepo for PgRepository<'_> {
+    async fn find_activity_patient(&self, activity_id: i32) -> Result<Option<i32>, AppError> {
+        sqlx::query_scalar!("SELECT patient_id FROM patient_activity WHERE activity_id = $1", activity_id)
+            .fetch_optional(self.pool)
+            .await
+            .map(|patient_id| patient_id.map(Option::unwrap_or_default))
+            .map_err(|e| AppError::database("Error while fetching activity", e))
+    }
+
+    async fn list_comments(&self, activity_id: i32) -> Result<Vec<ProcedureComment>, AppError> {
+        sqlx::query_as!(
+            CommentRow,
+            r#"
+            SELECT
+                c.comment_id,
+                c.activity_id,
+                pa.patient_id,
+                c.parent_id,
+                pgp_sym_decrypt(c.body::bytea, ($1::TEXT[])[c.key_id]) as body,
+                c.created_at,
+                c.created_by,
+                u.first_name || ' ' || u.last_name as created_by_name,
+                c.updated_at,
+                c.updated_by,
+                c.deleted_at,
+                c.deleted_by
+            FROM procedure_comments c
+            JOIN patient_activity pa ON pa.activity_id = c.activity_id
+            LEFT JOIN users u ON u.user_id = c.created_by
+            WHERE c.activity_id = $2
+            ORDER BY c.created_at, c.comment_id
+            "#,
+            &self.keyring.sql_keys(),
+            activity_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map(|rows| rows.into_iter().map(ProcedureComment::from).collect())
+        .map_err(|e| AppError::database("Error while fetching comments", e))
+    }
+
+    async fn find_comment(&self, comment_id: i32) -> Result<Option<ProcedureComment>, AppError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| AppError::database("Error while acquiring connection", e))?;
+
+        fetch_comment(&mut conn, self.keyring, comment_id).await
+    }
+
+    async fn list_comment_changes(&self, comment_id: i32) -> Result<Vec<CommentChange>, AppError> {
+        sqlx::query_as!(
+            CommentChange,
+            r#"
+            SELECT
+                h.change_id,
+                h.comment_id,
+                h.action,
+                pgp_sym_decrypt(h.body::bytea, ($1::TEXT[])[h.key_id]) as body,
+                h.changed_by,
+                u.first_name || ' ' || u.last_name as changed_by_name,
+                h.changed_at
+            FROM procedure_comment_changes h
+            LEFT JOIN users u ON u.user_id = h.changed_by
+            WHERE h.comment_id = $2
+            ORDER BY h.changed_at, h.change_id
+            "#,
+            &self.keyring.sql_keys(),
+            comment_id
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching comment history", e))
+    }
+
+    async fn create_comment(&self, user_id: i32, activity_id: i32, parent_id: Option<i32>, body: &str) -> Result<ProcedureComment, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let comment_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO procedure_comments (activity_id, parent_id, body, key_id, created_by)
+            VALUES ($1, $2, pgp_sym_encrypt($3, $4), $5, $6)
+            RETURNING comment_id
+            "#,
+            activity_id,
+            parent_id,
+            body,
+            &keyring.current().secret,
+            keyring.current().id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while adding comment to procedure", e))?;
+
+        let created = fetch_comment(&mut tx, keyring, comment_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Comment was not saved.".to_string()))?;
+        insert_change(&mut tx, keyring, user_id, AuditAction::Create, comment_id, body).await?;
+        record_audit(&mut *tx, self.keyring, comment_changed_event(user_id, AuditAction::Create, None, &created)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(created)
+    }
+
+    async fn update_comment(&self, user_id: i32, comment_id: i32, body: &str) -> Result<Option<ProcedureComment>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let before = sqlx::query_scalar!(
+            r#"
+            SELECT pgp_sym_decrypt(body::bytea, ($1::TEXT[])[key_id]) as body
+            FROM procedure_comments
+            WHERE comment_id = $2 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+            &keyring.sql_keys(),
+            comment_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while fetching comment", e))?;
+        let Some(before) = before else {
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE procedure_comments
+            SET body = pgp_sym_encrypt($2, $3), key_id = $4, updated_at = NOW(), updated_by = $5
+            WHERE comment_id = $1
+            "#,
+            comment_id,
+            body,
+            &keyring.current().secret,
+            keyring.current().id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating comment", e))?;
+
+        let updated = fetch_comment(&mut tx, keyring, comment_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Comment was not saved.".to_string()))?;
+        insert_change(&mut tx, keyring, user_id, AuditAction::Update, comment_id, body).await?;
+        record_audit(&mut *tx, self.keyring, comment_changed_event(user_id, AuditAction::Update, before.as_deref(), &updated)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(updated))
+    }
+
+    async fn delete_comment(&self, user_id: i32, comment_id: i32) -> Result<Option<ProcedureComment>, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        let body = sqlx::query_scalar!(
+            r#"
+            UPDATE procedure_comments
+            SET deleted_at = NOW(), deleted_by = $3
+            WHERE comment_id = $2 AND deleted_at IS NULL
+            RETURNING pgp_sym_decrypt(body::bytea, ($1::TEXT[])[key_id]) as body
+            "#,
+            &keyring.sql_keys(),
+            comment_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while deleting comment", e))?;
+        let Some(body) = body else {
+            return Ok(None);
+        };
+
+        let deleted = fetch_comment(&mut tx, keyring, comment_id)
+            .await?
+            .ok_or_else(|| AppError::Database("Comment was not saved.".to_string()))?;
+        insert_change(&mut tx, keyring, user_id, AuditAction::Delete, comment_id, body.as_deref().unwrap_or_default()).await?;
+        record_audit(&mut *tx, self.keyring, comment_changed_event(user_id, AuditAction::Delete, body.as_deref(), &deleted)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(Some(deleted))
+    }
+}
+
+// Function to move comments from the old newline-joined patient_activity.comments field into
+// procedure_comments, one row per line. Runs at startup after the migrations and returns how many
+// activities were moved; once a blob is moved the field is cleared, so later runs find nothing.
+pub async fn migrate_legacy_comments(pool: &Pool<Postgres>, keyring: &KeyRing) -> Result<u64, AppError> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+    // Authors and times were never stored, so moved comments get none and sit at the activity's creation.
+    // Some older blobs were joined with a literal backslash-n instead of a line break.
+    sqlx::query!(
+        r#"
+        WITH legacy AS (
+            SELECT
+                activity_id,
+                COALESCE(created_at, activity_time) as created_at,
+                pgp_sym_decrypt(comments::bytea, ($1::TEXT[])[key_id]) as comments
+            FROM patient_activity
+            WHERE comments IS NOT NULL
+            FOR UPDATE
+        ),
+        lines AS (
+            SELECT legacy.activity_id, legacy.created_at, btrim(line.body) as body, line.position
+            FROM legacy,
+            LATERAL regexp_split_to_table(replace(legacy.comments, '\n', E'\n'), E'\n') WITH ORDINALITY AS line(body, position)
+            WHERE btrim(line.body) <> ''
+        ),
+        inserted AS (
+            INSERT INTO procedure_comments (activity_id, body, key_id, created_at)
+            SELECT activity_id, pgp_sym_encrypt(body, $2), $3, created_at
+            FROM lines
+            ORDER BY activity_id, position
+            RETURNING comment_id, body, key_id, created_at
+        )
+        INSERT INTO procedure_comment_changes (comment_id, action, body, key_id, changed_at)
+        SELECT comment_id, 'CREATE', body, key_id, created_at FROM inserted
+        "#,
+        &keyring.sql_keys(),
+        &keyring.current().secret,
+        keyring.current().id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::database("Error while moving comments", e))?;
+
+    let moved = sqlx::query!("UPDATE patient_activity SET comments = NULL WHERE comments IS NOT NULL")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while clearing moved comments", e))?
+        .rows_affected();
+
+    tx.commit()
+        .await
+        .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+    Ok(moved)
+}
+
+// Function to list the comments of an activity as threads
+pub async fn list_procedure_comments<R: ProcedureCommentRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    activity_id: i32
+) -> Result<Vec<ProcedureComment>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let patient_id = repo
+        .find_activity_patient(activity_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Activity does not exist."))?;
+    let comments = repo.list_comments(activity_id).await?;
+    repo.record_audit(
+        AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PROCEDURE_COMMENT)
+            .patient(patient_id)
+            .entity_id(activity_id),
+    )
+    .await?;
+
+    Ok(thread_comments(comments))
+}
+
+// Function to comment on an activity, or reply to one of its comments
+pub async fn add_procedure_comment<R: ProcedureCommentRepo>(
+    repo: &R,
+    user: &AuthUser,
+    activity_id: i32,
+    parent_id: Option<i32>,
+    comment: &str
+) -> Result<ProcedureComment, AppError> {
+    require(user, Permission::CommentOnProcedure)?;
+
+    let comment = validate_comment(comment)?;
+    repo.find_activity_patient(activity_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Activity does not exist."))?;
+    if let Some(parent_id) = parent_id {
+        let parent = repo.find_comment(parent_id).await?;
+        if !parent.is_some_and(|parent| parent.activity_id == activity_id && parent.deleted_at.is_none()) {
+            return Err(AppError::validation("parent_id", "You can only reply to a comment on the same procedure."));
+        }
+    }
+
+    repo.create_comment(user.user_id, activity_id, parent_id, &comment).await
+}
+
+// Function to find a comment the user may change. Only its author can edit or delete it.
+async fn own_comment<R: ProcedureCommentRepo>(repo: &R, user: &AuthUser, comment_id: i32) -> Result<ProcedureComment, AppError> {
+    let comment = repo
+        .find_comment(comment_id)
+        .await?
+        .filter(|comment| comment.deleted_at.is_none())
+        .ok_or_else(|| AppError::not_found("Comment does not exist."))?;
+
+    if comment.created_by != Some(user.user_id) {
+        return Err(AppError::Forbidden("Only the author can change a comment.".to_string()));
+    }
+
+    Ok(comment)
+}
+
+// Function to replace the text of a comment
+pub async fn edit_procedure_comment<R: ProcedureCommentRepo>(
+    repo: &R,
+    user: &AuthUser,
+    comment_id: i32,
+    comment: &str
+) -> Result<ProcedureComment, AppError> {
+    require(user, Permission::CommentOnProcedure)?;
+
+    let comment = validate_comment(comment)?;
+    own_comment(repo, user, comment_id).await?;
+
+    repo.update_comment(user.user_id, comment_id, &comment)
+        .await?
+        .ok_or_else(|| AppError::not_found("Comment does not exist."))
+}
+
+// Function to delete a comment. Its replies and history are kept.
+pub async fn delete_procedure_comment<R: ProcedureCommentRepo>(
+    repo: &R,
+    user: &AuthUser,
+    comment_id: i32
+) -> Result<ProcedureComment, AppError> {
+    require(user, Permission::CommentOnProcedure)?;
+
+    own_comment(repo, user, comment_id).await?;
+
+    repo.delete_comment(user.user_id, comment_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Comment does not exist."))
+}
+
+// Function to list every version of a comment, oldest first
+pub async fn list_comment_history<R: ProcedureCommentRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    comment_id: i32
+) -> Result<Vec<CommentChange>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let comment = repo
+        .find_comment(comment_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Comment does not exist."))?;
+    let changes = repo.list_comment_changes(comment_id).await?;
+    repo.record_audit(
+        AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_PROCEDURE_COMMENT)
+            .patient(comment.patient_id)
+            .entity_id(comment_id),
+    )
+    .await?;
+
+    Ok(changes)
+}
+
+// Endpoint to get the comment threads of an activity
+#[tauri::command]
+pub async fn get_procedure_comments(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    activity_id: i32,
+) -> Result<Vec<ProcedureComment>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_procedure_comments(&PgRepository::new(pool, &config.keyring), &user, activity_id).await
+}
+
+// Endpoint to add a comment to a procedure, or a reply when parent_id is given
+#[tauri::command]
+pub async fn add_comment_to_procedure(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    activity_id: i32,
+    comment: String,
+    parent_id: Option<i32>,
+) -> Result<ProcedureComment, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    add_procedure_comment(&PgRepository::new(pool, &config.keyring), &user, activity_id, parent_id, &comment).await
+}
+
+// Endpoint to edit one's own comment
+#[tauri::command]
+pub async fn update_procedure_comment(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    comment_id: i32,
+    comment: String,
+) -> Result<ProcedureComment, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    edit_procedure_comment(&PgRepository::new(pool, &config.keyring), &user, comment_id, &comment).await
+}
+
+// Endpoint to delete one's own comment
+#[tauri::command]
+pub async fn remove_procedure_comment(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    comment_id: i32,
+) -> Result<ProcedureComment, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    delete_procedure_comment(&PgRepository::new(pool, &config.keyring), &user, comment_id).await
+}
+
+// Endpoint to get who changed a comment and when
+#[tauri::command]
+pub async fn get_procedure_comment_history(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    comment_id: i32,
+) -> Result<Vec<CommentChange>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    list_comment_history(&PgRepository::new(pool, &config.keyring), &user, comment_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{new_activity, test_user, MemoryRepository};
+    use crate::repository::PatientRepo;
+
+    async fn activity(repo: &MemoryRepository, user: &AuthUser) -> i32 {
+        let activity = new_activity(repo.add_patient("Ada", "Lovelace"), repo.add_procedure("Tonometry", "Eye pressure"));
+        repo.create_activity(user.user_id, &activity).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn replies_nest_under_their_parents_and_deleted_leaves_drop_out() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let activity_id = activity(&repo, &doctor).await;
+
+        let question = add_procedure_comment(&repo, &doctor, activity_id, None, " Was the pupil dilated? ").await.unwrap();
+        assert_eq!(question.body.as_deref(), Some("Was the pupil dilated?"));
+        let answer = add_procedure_comment(&repo, &nurse, activity_id, Some(question.comment_id), "Yes").await.unwrap();
+        add_procedure_comment(&repo, &doctor, activity_id, Some(answer.comment_id), "Thanks").await.unwrap();
+        let aside = add_procedure_comment(&repo, &nurse, activity_id, None, "Typo").await.unwrap();
+
+        delete_procedure_comment(&repo, &nurse, aside.comment_id).await.unwrap();
+        delete_procedure_comment(&repo, &doctor, question.comment_id).await.unwrap();
+
+        let threads = list_procedure_comments(&repo, &doctor, activity_id).await.unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!((threads[0].comment_id, threads[0].body.as_deref()), (question.comment_id, None));
+        assert_eq!(threads[0].replies[0].body.as_deref(), Some("Yes"));
+        assert_eq!(threads[0].replies[0].replies[0].body.as_deref(), Some("Thanks"));
+
+        let reply_to_deleted = add_procedure_comment(&repo, &nurse, activity_id, Some(aside.comment_id), "Sorry").await;
+        assert!(matches!(reply_to_deleted, Err(AppError::Validation(ref errors)) if errors[0].field == "parent_id"));
+        let other_activity = activity(&repo, &doctor).await;
+        let elsewhere = add_procedure_comment(&repo, &nurse, other_activity, Some(answer.comment_id), "Wrong thread").await;
+        assert!(matches!(elsewhere, Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn only_authors_change_comments_and_every_version_is_kept() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let nurse = test_user(&repo, "NURSE");
+        let admin = test_user(&repo, "ADMIN");
+        let activity_id = activity(&repo, &doctor).await;
+
+        assert_eq!(
+            add_procedure_comment(&repo, &admin, activity_id, None, "Hello").await.unwrap_err(),
+            AppError::forbidden()
+        );
+        let empty = add_procedure_comment(&repo, &nurse, activity_id, None, "  ").await;
+        assert!(matches!(empty, Err(AppError::Validation(ref errors)) if errors[0].field == "comment"));
+
+        let comment = add_procedure_comment(&repo, &nurse, activity_id, None, "Drops given").await.unwrap();
+        let by_doctor = edit_procedure_comment(&repo, &doctor, comment.comment_id, "Drops not given").await;
+        assert!(matches!(by_doctor, Err(AppError::Forbidden(_))));
+        let edited = edit_procedure_comment(&repo, &nurse, comment.comment_id, "Drops given at 10:00").await.unwrap();
+        assert_eq!(edited.updated_by, Some(nurse.user_id));
+        delete_procedure_comment(&repo, &nurse, comment.comment_id).await.unwrap();
+        let again = delete_procedure_comment(&repo, &nurse, comment.comment_id).await;
+        assert!(matches!(again, Err(AppError::NotFound(_))));
+
+        let history = list_comment_history(&repo, &doctor, comment.comment_id).await.unwrap();
+        let versions: Vec<(&str, Option<&str>)> = history.iter().map(|change| (change.action.as_str(), change.body.as_deref())).collect();
+        assert_eq!(
+            versions,
+            vec![("CREATE", Some("Drops given")), ("UPDATE", Some("Drops given at 10:00")), ("DELETE", Some("Drops given at 10:00"))]
+        );
+        assert!(list_procedure_comments(&repo, &doctor, activity_id).await.unwrap().is_empty());
+    }
+}
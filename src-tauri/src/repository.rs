@@ -0,0 +1,391 @@
+// src-tauri/src/repository.rs
+//
+// Data access used by the service functions. Commands pass a PgRepository; tests pass a
+// memory_repository::MemoryRepository. The Postgres implementation of each trait lives in the
+// module that owns its types, next to the SQL.
+
+// Dependencies
+use std::future::Future;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+use crate::activity_assignments::{ActivityAssignment, Worklist};
+use crate::activity_workflow::{ActivityStatus, ActivityTransition};
+use crate::alert::{Alert, NewAlert};
+use crate::appointment::{Appointment, NewAppointment};
+use crate::audit::AuditEvent;
+use crate::auth::UserProfile;
+use crate::error::AppError;
+use crate::keys::KeyRing;
+use crate::medical_history::{HistoryChange, HistoryDetails, HistoryEntry, HistoryKind};
+use crate::messaging::{Conversation, Message, MessageData};
+use crate::patient_merge::{DuplicateCandidateRow, PatientMerge};
+use crate::patient_photos::{PatientPhoto, ProcessedPhoto};
+use crate::patient_search::{PatientFilter, PatientSearchRow};
+use crate::patient_timeline::{TimelineEvent, TimelineFilter};
+use crate::patients::{
+    AppointmentData, NewPatientActivity, PatientActivityData, PatientData, PatientDoctorData, PatientHistoryData, PatientInput,
+    PatientProcedureData
+};
+use crate::procedure_catalog::{Procedure, ProcedureInput};
+use crate::procedure_comments::{CommentChange, ProcedureComment};
+use crate::users::NewUser;
+use crate::vision::{
+    EyeMeasurementData, EyeMeasurementInput, RefractionData, RefractionInput, VisionData, VisionInput
+};
+
+// Postgres-backed repositories for one command call
+#[derive(Clone, Copy)]
+pub struct PgRepository<'a> {
+    pub pool: &'a Pool<Postgres>,
+    pub keyring: &'a KeyRing,
+}
+
+impl<'a> PgRepository<'a> {
+    pub fn new(pool: &'a Pool<Postgres>, keyring: &'a KeyRing) -> Self {
+        PgRepository { pool, keyring }
+    }
+}
+
+pub trait AuditRepo {
+    fn record_audit(&self, event: AuditEvent) -> impl Future<Output = Result<(), AppError>> + Send;
+
+    fn record_patient_reads(
+        &self,
+        user_id: i32,
+        entity: &'static str,
+        patient_ids: &[i32]
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+}
+
+pub trait PatientRepo {
+    fn find_patient(&self, patient_id: i32) -> impl Future<Output = Result<Option<PatientData>, AppError>> + Send;
+
+    // Active patients only, with the photo thumbnail in patient_photo
+    fn list_patients(&self) -> impl Future<Output = Result<Vec<PatientData>, AppError>> + Send;
+
+    // Next value for the {SEQ} part of a generated MR number
+    fn next_mr_sequence(&self) -> impl Future<Output = Result<i64, AppError>> + Send;
+
+    // Fails with a conflict when the MR number is taken. The audit event is recorded with the insert.
+    fn create_patient(
+        &self,
+        user_id: i32,
+        mr_number: &str,
+        patient: &PatientInput
+    ) -> impl Future<Output = Result<PatientData, AppError>> + Send;
+
+    // Returns None when the patient does not exist or is deactivated
+    fn update_patient(
+        &self,
+        user_id: i32,
+        patient_id: i32,
+        patient: &PatientInput
+    ) -> impl Future<Output = Result<Option<PatientData>, AppError>> + Send;
+
+    // Returns None when the patient does not exist or is already deactivated
+    fn deactivate_patient(&self, user_id: i32, patient_id: i32) -> impl Future<Output = Result<Option<PatientData>, AppError>> + Send;
+
+    // Newest first
+    fn list_activities(&self, patient_id: i32) -> impl Future<Output = Result<Vec<PatientActivityData>, AppError>> + Send;
+
+    // Activities scheduled for today, earliest first, with the photo thumbnail in patient_photo
+    fn list_todays_appointments(&self) -> impl Future<Output = Result<Vec<AppointmentData>, AppError>> + Send;
+
+    fn find_history(&self, patient_id: i32) -> impl Future<Output = Result<Option<PatientHistoryData>, AppError>> + Send;
+
+    fn list_doctor_notes(&self, patient_id: i32) -> impl Future<Output = Result<Vec<PatientDoctorData>, AppError>> + Send;
+
+    // Newest first
+    fn list_patient_procedures(&self, patient_id: i32) -> impl Future<Output = Result<Vec<PatientProcedureData>, AppError>> + Send;
+
+    fn list_complaints(&self, patient_id: i32) -> impl Future<Output = Result<Vec<String>, AppError>> + Send;
+
+    // Returns the new activity id. The audit event is recorded with the insert.
+    fn create_activity(&self, user_id: i32, activity: &NewPatientActivity) -> impl Future<Output = Result<i32, AppError>> + Send;
+}
+
+pub trait ProcedureCatalogRepo {
+    // Sorted by name
+    fn list_procedures(&self, include_inactive: bool) -> impl Future<Output = Result<Vec<Procedure>, AppError>> + Send;
+
+    fn find_procedure(&self, procedure_id: i32) -> impl Future<Output = Result<Option<Procedure>, AppError>> + Send;
+
+    // Number of patient activities recorded against the procedure
+    fn count_procedure_activities(&self, procedure_id: i32) -> impl Future<Output = Result<i64, AppError>> + Send;
+
+    // The audit event is recorded with each write. Writes return None when the procedure does not exist.
+    fn create_procedure(&self, user_id: i32, input: &ProcedureInput) -> impl Future<Output = Result<Procedure, AppError>> + Send;
+
+    fn update_procedure(&self, user_id: i32, procedure_id: i32, input: &ProcedureInput) -> impl Future<Output = Result<Option<Procedure>, AppError>> + Send;
+
+    fn set_procedure_active(&self, user_id: i32, procedure_id: i32, active: bool) -> impl Future<Output = Result<Option<Procedure>, AppError>> + Send;
+
+    // Conflict when an activity still references the procedure
+    fn delete_procedure(&self, user_id: i32, procedure_id: i32) -> impl Future<Output = Result<Option<Procedure>, AppError>> + Send;
+}
+
+pub trait ActivityAssignmentRepo {
+    // Role of an active user; None for unknown and inactive users
+    fn find_active_role(&self, user_id: i32) -> impl Future<Output = Result<Option<String>, AppError>> + Send;
+
+    fn find_assignment(&self, activity_id: i32) -> impl Future<Output = Result<Option<ActivityAssignment>, AppError>> + Send;
+
+    // Records the audit event with the change. None when the activity does not exist, Conflict when
+    // it is completed or cancelled by the time its row is locked.
+    fn assign_clinicians(
+        &self,
+        user_id: i32,
+        activity_id: i32,
+        doctor_id: i32,
+        nurse_id: Option<i32>
+    ) -> impl Future<Output = Result<Option<ActivityAssignment>, AppError>> + Send;
+
+    fn list_worklist(&self, user_id: i32, worklist: Worklist) -> impl Future<Output = Result<Vec<AppointmentData>, AppError>> + Send;
+}
+
+pub trait ActivityWorkflowRepo {
+    // None when the activity does not exist
+    fn find_activity_status(&self, activity_id: i32) -> impl Future<Output = Result<Option<String>, AppError>> + Send;
+
+    // Records the transition and its audit event with the status change.
+    // None when the activity no longer has the `from` status, so concurrent moves cannot both succeed.
+    fn transition_activity(
+        &self,
+        user_id: i32,
+        activity_id: i32,
+        from: ActivityStatus,
+        to: ActivityStatus,
+        reason: Option<&str>
+    ) -> impl Future<Output = Result<Option<ActivityTransition>, AppError>> + Send;
+
+    // Every status change of an activity, oldest first, including the status it was created with
+    fn list_activity_transitions(&self, activity_id: i32) -> impl Future<Output = Result<Vec<ActivityTransition>, AppError>> + Send;
+}
+
+pub trait ProcedureCommentRepo {
+    // Patient the activity belongs to; None when the activity does not exist
+    fn find_activity_patient(&self, activity_id: i32) -> impl Future<Output = Result<Option<i32>, AppError>> + Send;
+
+    // Every comment of an activity, deleted ones included, oldest first and not yet threaded
+    fn list_comments(&self, activity_id: i32) -> impl Future<Output = Result<Vec<ProcedureComment>, AppError>> + Send;
+
+    fn find_comment(&self, comment_id: i32) -> impl Future<Output = Result<Option<ProcedureComment>, AppError>> + Send;
+
+    // Every recorded version of a comment, oldest first
+    fn list_comment_changes(&self, comment_id: i32) -> impl Future<Output = Result<Vec<CommentChange>, AppError>> + Send;
+
+    // Each write below records a change row and the audit event with it
+    fn create_comment(
+        &self,
+        user_id: i32,
+        activity_id: i32,
+        parent_id: Option<i32>,
+        body: &str
+    ) -> impl Future<Output = Result<ProcedureComment, AppError>> + Send;
+
+    // None when the comment does not exist or was deleted
+    fn update_comment(&self, user_id: i32, comment_id: i32, body: &str) -> impl Future<Output = Result<Option<ProcedureComment>, AppError>> + Send;
+
+    // None when the comment does not exist or was already deleted
+    fn delete_comment(&self, user_id: i32, comment_id: i32) -> impl Future<Output = Result<Option<ProcedureComment>, AppError>> + Send;
+}
+
+pub trait PatientSearchRepo {
+    // Rows in filter order, at most filter.limit of them
+    fn search_patients(&self, filter: &PatientFilter) -> impl Future<Output = Result<Vec<PatientSearchRow>, AppError>> + Send;
+}
+
+pub trait PatientMergeRepo {
+    // Active patients worth scoring as duplicates of the given one
+    fn list_duplicate_candidates(&self, patient: &PatientData) -> impl Future<Output = Result<Vec<DuplicateCandidateRow>, AppError>> + Send;
+
+    // Moves the merged patient's rows to the surviving one and deactivates it, recording the undo
+    // record and audit events with the change. Returns None when either patient is missing or inactive.
+    fn merge_patients(
+        &self,
+        user_id: i32,
+        surviving_patient_id: i32,
+        merged_patient_id: i32
+    ) -> impl Future<Output = Result<Option<PatientMerge>, AppError>> + Send;
+
+    // Returns None when the merge does not exist or was already undone
+    fn undo_merge(&self, user_id: i32, merge_id: i32) -> impl Future<Output = Result<Option<PatientMerge>, AppError>> + Send;
+}
+
+pub trait PatientPhotoRepo {
+    // Replaces the photo and thumbnail of an active patient and records the audit event with it.
+    // Returns when the photo was saved, or None when the patient does not exist or is deactivated.
+    fn save_photo(
+        &self,
+        user_id: i32,
+        patient_id: i32,
+        photo: &ProcessedPhoto
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>, AppError>> + Send;
+
+    // Full-size photo; None when none was uploaded
+    fn find_photo(&self, patient_id: i32) -> impl Future<Output = Result<Option<PatientPhoto>, AppError>> + Send;
+
+    // Thumbnails of the patients that have a photo, by patient_id
+    fn list_thumbnails(&self, patient_ids: &[i32]) -> impl Future<Output = Result<Vec<PatientPhoto>, AppError>> + Send;
+}
+
+pub trait MedicalHistoryRepo {
+    // Entries of a patient that have not been removed, oldest first
+    fn list_history_entries(&self, patient_id: i32) -> impl Future<Output = Result<Vec<HistoryEntry>, AppError>> + Send;
+
+    // Every recorded version of a patient's entries, newest first
+    fn list_history_changes(&self, patient_id: i32) -> impl Future<Output = Result<Vec<HistoryChange>, AppError>> + Send;
+
+    // Each write below records a change row and the audit event with it
+    fn create_history_entry(
+        &self,
+        user_id: i32,
+        patient_id: i32,
+        details: &HistoryDetails
+    ) -> impl Future<Output = Result<HistoryEntry, AppError>> + Send;
+
+    // None when no entry of the same kind exists or it was removed
+    fn update_history_entry(
+        &self,
+        user_id: i32,
+        entry_id: i32,
+        details: &HistoryDetails
+    ) -> impl Future<Output = Result<Option<HistoryEntry>, AppError>> + Send;
+
+    // None when no entry of this kind exists or it was already removed
+    fn remove_history_entry(
+        &self,
+        user_id: i32,
+        entry_id: i32,
+        kind: HistoryKind
+    ) -> impl Future<Output = Result<Option<HistoryEntry>, AppError>> + Send;
+}
+
+pub trait TimelineRepo {
+    // Whether the patient exists, active or not
+    fn patient_exists(&self, patient_id: i32) -> impl Future<Output = Result<bool, AppError>> + Send;
+
+    // Events of the wanted kinds inside the filter's window and after its cursor, in any order.
+    // Each kind may be cut to its newest filter.limit events.
+    fn list_timeline_events(&self, filter: &TimelineFilter) -> impl Future<Output = Result<Vec<TimelineEvent>, AppError>> + Send;
+}
+
+pub trait VisionRepo {
+    fn find_vision(
+        &self,
+        patient_id: i32,
+        side: &str,
+        value_type: &str
+    ) -> impl Future<Output = Result<Option<VisionData>, AppError>> + Send;
+
+    fn find_refraction(
+        &self,
+        patient_id: i32,
+        side: &str,
+        value_type: &str,
+        vision_type: &str
+    ) -> impl Future<Output = Result<Option<RefractionData>, AppError>> + Send;
+
+    fn find_eye_measurement(
+        &self,
+        patient_id: i32,
+        side: &str
+    ) -> impl Future<Output = Result<Option<EyeMeasurementData>, AppError>> + Send;
+
+    // Upserts record a CREATE or UPDATE audit event with before and after hashes
+    fn upsert_vision(&self, user_id: i32, input: &VisionInput) -> impl Future<Output = Result<VisionData, AppError>> + Send;
+
+    fn upsert_refraction(&self, user_id: i32, input: &RefractionInput) -> impl Future<Output = Result<RefractionData, AppError>> + Send;
+
+    fn upsert_eye_measurement(
+        &self,
+        user_id: i32,
+        input: &EyeMeasurementInput
+    ) -> impl Future<Output = Result<EyeMeasurementData, AppError>> + Send;
+}
+
+pub trait MessagingRepo {
+    // Messages delivered to the user and not yet read
+    fn list_unread(&self, user_id: i32) -> impl Future<Output = Result<Vec<MessageData>, AppError>> + Send;
+
+    fn is_participant(&self, conversation_id: i32, user_id: i32) -> impl Future<Output = Result<bool, AppError>> + Send;
+
+    // Stores the message, makes it the conversation's last message and delivers it to the other participant
+    fn send_message(
+        &self,
+        conversation_id: i32,
+        sender_id: i32,
+        content: &str
+    ) -> impl Future<Output = Result<Message, AppError>> + Send;
+
+    // Newest first
+    fn list_messages(&self, conversation_id: i32) -> impl Future<Output = Result<Vec<MessageData>, AppError>> + Send;
+
+    // Returns the conversation between the two users, creating it on first use. Expects user1 < user2.
+    fn open_conversation(&self, user1: i32, user2: i32) -> impl Future<Output = Result<Conversation, AppError>> + Send;
+
+    // Most recent activity first
+    fn list_conversations(&self, user_id: i32) -> impl Future<Output = Result<Vec<Conversation>, AppError>> + Send;
+}
+
+pub trait AppointmentRepo {
+    // Appointments the user takes part in, latest first
+    fn list_appointments(&self, user_id: i32) -> impl Future<Output = Result<Vec<Appointment>, AppError>> + Send;
+
+    fn create_appointment(
+        &self,
+        created_by: i32,
+        appointment: &NewAppointment
+    ) -> impl Future<Output = Result<Appointment, AppError>> + Send;
+}
+
+pub trait AlertRepo {
+    // Alerts issued for or by the user
+    fn list_alerts(&self, user_id: i32) -> impl Future<Output = Result<Vec<Alert>, AppError>> + Send;
+
+    fn create_alert(&self, issued_by: i32, alert: &NewAlert) -> impl Future<Output = Result<Alert, AppError>> + Send;
+}
+
+// Update methods return None when the user does not exist or is not in an expected status
+pub trait UserRepo {
+    fn list_users(
+        &self,
+        role: Option<&str>,
+        status: Option<&str>
+    ) -> impl Future<Output = Result<Vec<UserProfile>, AppError>> + Send;
+
+    fn list_active_doctors(&self) -> impl Future<Output = Result<Vec<UserProfile>, AppError>> + Send;
+
+    // Fails with a conflict when the email is taken
+    fn create_user(&self, user: &NewUser) -> impl Future<Output = Result<UserProfile, AppError>> + Send;
+
+    fn set_status(
+        &self,
+        user_id: i32,
+        from: &[&str],
+        to: &str
+    ) -> impl Future<Output = Result<Option<UserProfile>, AppError>> + Send;
+
+    fn set_role(&self, user_id: i32, role: &str) -> impl Future<Output = Result<Option<UserProfile>, AppError>> + Send;
+
+    // Stores a temporary password, forces a change on next login and lifts any lockout
+    fn reset_password(
+        &self,
+        user_id: i32,
+        password_hash: &str
+    ) -> impl Future<Output = Result<Option<UserProfile>, AppError>> + Send;
+
+    // Fails with a conflict when the email is taken
+    fn update_user(
+        &self,
+        user_id: i32,
+        first_name: &str,
+        last_name: &str,
+        email: &str
+    ) -> impl Future<Output = Result<Option<UserProfile>, AppError>> + Send;
+
+    fn unlock_user(&self, user_id: i32) -> impl Future<Output = Result<Option<UserProfile>, AppError>> + Send;
+
+    // Returns the number of sessions revoked
+    fn revoke_sessions(&self, user_id: i32, reason: &str) -> impl Future<Output = Result<u64, AppError>> + Send;
+}
@@ -0,0 +1,194 @@
+// src-tauri/src/security.rs
+
+// Dependencies
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use tauri::State;
+use crate::config::AppConfig;
+use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::permissions::{authorize, Permission};
+
+// Single error for every failed login so callers cannot tell which emails exist
+pub const LOGIN_FAILED: &str = "Invalid email or password, or the account is not available. Try again later.";
+
+// Reasons recorded in login_attempts.reason
+pub const REASON_SUCCESS: &str = "SUCCESS";
+pub const REASON_UNKNOWN_EMAIL: &str = "UNKNOWN_EMAIL";
+pub const REASON_BAD_PASSWORD: &str = "BAD_PASSWORD";
+pub const REASON_LOCKED: &str = "LOCKED";
+pub const REASON_PENDING: &str = "ACCOUNT_PENDING";
+pub const REASON_DEACTIVATED: &str = "ACCOUNT_DEACTIVATED";
+pub const REASON_BAD_SECOND_FACTOR: &str = "BAD_SECOND_FACTOR";
+pub const REASON_RECOVERY_CODE: &str = "RECOVERY_CODE_USED";
+
+// Password strength rules applied when a user picks a password
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 12,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: false,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    // Returns every unmet rule in one message so the user can fix them at once
+    pub fn validate(&self, password: &str) -> Result<(), String> {
+        let mut problems = vec![];
+
+        if password.chars().count() < self.min_length {
+            problems.push(format!("be at least {} characters long", self.min_length));
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            problems.push("contain an uppercase letter".to_string());
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            problems.push("contain a lowercase letter".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            problems.push("contain a digit".to_string());
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+            problems.push("contain a symbol".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Password must {}.", problems.join(", ")))
+        }
+    }
+}
+
+// How many failed logins lock an account, and for how long
+#[derive(Debug, Clone)]
+pub struct LockoutPolicy {
+    pub max_failed_attempts: i32,
+    pub lockout_minutes: i64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        LockoutPolicy { max_failed_attempts: 5, lockout_minutes: 15 }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LoginAttempt {
+    attempt_id: i64,
+    user_id: Option<i32>,
+    email: String,
+    success: bool,
+    reason: String,
+    attempted_at: DateTime<Utc>
+}
+
+// Function to record a login attempt. Failures to record are logged but never block the login flow.
+pub async fn record_login_attempt(pool: &Pool<Postgres>, user_id: Option<i32>, email: &str, success: bool, reason: &str) {
+    if let Err(err) = sqlx::query!(
+        r#"
+        INSERT INTO login_attempts (user_id, email, success, reason)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        email,
+        success,
+        reason
+    )
+    .execute(pool)
+    .await {
+        eprintln!("Error while recording login attempt: {}", err);
+    }
+}
+
+// Function to count a failed password and lock the account once the limit is reached.
+// The counter restarts after a lockout so the next window gets the full number of attempts.
+pub async fn register_failed_login(pool: &Pool<Postgres>, user_id: i32, policy: &LockoutPolicy) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            failed_login_attempts = CASE
+                WHEN failed_login_attempts + 1 >= $2 THEN 0
+                ELSE failed_login_attempts + 1
+            END,
+            locked_until = CASE
+                WHEN failed_login_attempts + 1 >= $2 THEN NOW() + make_interval(mins => $3)
+                ELSE locked_until
+            END
+        WHERE user_id = $1
+        "#,
+        &user_id,
+        &policy.max_failed_attempts,
+        policy.lockout_minutes as i32
+    )
+    .execute(pool)
+    .await
+    .map(|_| ())
+    .map_err(|e| AppError::database("Error while updating failed login count", e))
+}
+
+// Function to clear the failed login counter and any lock
+pub async fn clear_failed_logins(pool: &Pool<Postgres>, user_id: i32) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET failed_login_attempts = 0, locked_until = NULL
+        WHERE user_id = $1
+        "#,
+        &user_id
+    )
+    .execute(pool)
+    .await
+    .map(|_| ())
+    .map_err(|e| AppError::database("Error while clearing failed login count", e))
+}
+
+// Endpoint for admins to review login attempts, newest first
+#[tauri::command]
+pub async fn get_login_attempts(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    user_id: Option<i32>,
+    email: Option<String>,
+    only_failures: Option<bool>,
+    limit: Option<i64>
+) -> Result<Vec<LoginAttempt>, AppError> {
+    let pool = &state.pool;
+    let _user = authorize(pool, &config, &token, Permission::ManageUsers).await?;
+
+    sqlx::query_as!(
+        LoginAttempt,
+        r#"
+        SELECT attempt_id, user_id, email, success, reason, attempted_at
+        FROM login_attempts
+        WHERE ($1::INT IS NULL OR user_id = $1)
+        AND ($2::VARCHAR IS NULL OR LOWER(email) = LOWER($2))
+        AND (NOT $3 OR success = FALSE)
+        ORDER BY attempted_at DESC, attempt_id DESC
+        LIMIT $4
+        "#,
+        user_id,
+        email,
+        only_failures.unwrap_or(false),
+        limit.unwrap_or(100).clamp(1, 1000)
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::database("Error while fetching login attempts", e))
+}
@@ -0,0 +1,806 @@
+// src-tauri/src/seed.rs
+
+// Dependencies
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use sqlx::{Pool, Postgres, Transaction};
+use crate::crypto::random_token;
+use crate::keys::EncryptionKey;
+
+// Bytes of entropy in the password generated for each seed run
+const PASSWORD_BYTES: usize = 12;
+
+// Rows per bulk insert statement
+const CHUNK_SIZE: usize = 1000;
+
+const FIRST_NAMES: &[&str] = &[
+    "Aarav", "Vivaan", "Aditya", "Arjun", "Rohan", "Ishaan", "Kabir", "Rahul", "Vikram", "Sanjay",
+    "Priya", "Ananya", "Diya", "Meera", "Kavya", "Neha", "Pooja", "Sneha", "Lakshmi", "Riya",
+    "James", "Olivia", "Liam", "Emma", "Noah", "Sophia", "Mateo", "Amara", "Yusuf", "Fatima",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Sharma", "Verma", "Patel", "Iyer", "Reddy", "Nair", "Gupta", "Singh", "Menon", "Das",
+    "Kulkarni", "Joshi", "Chatterjee", "Bose", "Rao", "Smith", "Johnson", "Garcia", "Khan", "Fernandes",
+];
+
+// Name, description, category, default duration in minutes and CPT code
+const PROCEDURES: &[(&str, &str, &str, i32, Option<&str>)] = &[
+    ("Comprehensive Eye Exam", "Visual acuity, refraction and slit lamp examination", "DIAGNOSTIC", 45, Some("92004")),
+    ("Refraction Test", "Subjective and objective refraction for spectacle prescription", "OPTOMETRY", 20, Some("92015")),
+    ("Tonometry", "Intraocular pressure measurement", "DIAGNOSTIC", 10, Some("92100")),
+    ("Dilated Fundus Exam", "Retinal examination after pupil dilation", "DIAGNOSTIC", 30, None),
+    ("OCT Scan", "Optical coherence tomography of macula and optic nerve", "IMAGING", 15, Some("92134")),
+    ("Visual Field Test", "Automated perimetry for glaucoma assessment", "DIAGNOSTIC", 30, Some("92083")),
+    ("Cataract Surgery", "Phacoemulsification with intraocular lens implant", "SURGICAL", 60, Some("66984")),
+    ("YAG Laser Capsulotomy", "Laser treatment for posterior capsule opacification", "SURGICAL", 20, Some("66821")),
+];
+
+const COMPLAINTS: &[&str] = &[
+    "Blurred vision while reading.",
+    "Red and watery eyes for three days.",
+    "Difficulty seeing road signs at night.",
+    "Frequent headaches after screen use.",
+    "Floaters in the right eye.",
+    "Itching and burning sensation in both eyes.",
+    "Gradual loss of distance vision.",
+    "Glare and halos around lights.",
+];
+
+const DOCTOR_NOTES: &[&str] = &[
+    "Refraction updated, new spectacles advised.",
+    "IOP within normal limits, review in six months.",
+    "Early cataract changes noted, monitor progression.",
+    "Allergic conjunctivitis, started on antihistamine drops.",
+    "Suspected glaucoma, visual field test ordered.",
+    "Dry eye disease, artificial tears prescribed.",
+    "Fundus normal, no diabetic retinopathy.",
+];
+
+const CONDITIONS: &[&str] = &[
+    "Hypertension", "Type 2 Diabetes", "Hypothyroidism", "Asthma", "Myopia", "Glaucoma suspect",
+];
+
+const MEDICATIONS: &[&str] = &[
+    "Metformin 500mg", "Amlodipine 5mg", "Levothyroxine 50mcg", "Timolol 0.5% drops", "Artificial tears",
+];
+
+const ALLERGIES: &[&str] = &["Penicillin", "Sulfa drugs", "Dust", "Pollen", "Latex"];
+
+const MESSAGES: &[&str] = &[
+    "Can you review the OCT for bed 4?",
+    "Patient in room 2 is ready for dilation.",
+    "IOP readings are uploaded.",
+    "Please sign off the discharge summary.",
+    "Running ten minutes late for rounds.",
+    "Thanks, looking at it now.",
+];
+
+const SNELLEN: &[&str] = &[
+    "20/15", "20/20", "20/25", "20/30", "20/40", "20/50", "20/60", "20/70", "20/100", "20/200",
+];
+
+// Struct to configure how many rows of each kind are generated
+#[derive(Debug, Clone)]
+pub struct SeedConfig {
+    pub doctors: usize,
+    pub nurses: usize,
+    pub admins: usize,
+    pub patients: usize,
+    pub activities_per_patient: usize,
+    pub conversations: usize,
+    pub messages_per_conversation: usize,
+    pub appointments: usize,
+    pub rng_seed: u64,
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        SeedConfig {
+            doctors: 3,
+            nurses: 3,
+            admins: 1,
+            patients: 25,
+            activities_per_patient: 3,
+            conversations: 4,
+            messages_per_conversation: 6,
+            appointments: 10,
+            rng_seed: 42,
+        }
+    }
+}
+
+// Struct to report how many rows were written
+#[derive(Debug, Default, Clone)]
+pub struct SeedSummary {
+    pub users: usize,
+    pub procedures: usize,
+    pub patients: usize,
+    pub activities: usize,
+    pub vision_rows: usize,
+    pub conversations: usize,
+    pub messages: usize,
+    pub appointments: usize,
+}
+
+// Function to check whether any table that seeding writes to already has rows
+pub async fn database_has_data(pool: &Pool<Postgres>) -> sqlx::Result<bool> {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT
+            EXISTS (SELECT 1 FROM users)
+            OR EXISTS (SELECT 1 FROM patients)
+            OR EXISTS (SELECT 1 FROM procedures)
+            OR EXISTS (SELECT 1 FROM patient_activity)
+            OR EXISTS (SELECT 1 FROM vision)
+            OR EXISTS (SELECT 1 FROM conversations)
+            OR EXISTS (SELECT 1 FROM appointments)
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+// Function to fill an empty, fully migrated database with generated rows.
+// Refuses to touch a database that already has data. Returns the summary and the one-time
+// password shared by the generated users, who must change it when they first sign in.
+pub async fn seed_database(
+    pool: &Pool<Postgres>,
+    key: &EncryptionKey,
+    config: &SeedConfig,
+) -> Result<(SeedSummary, String), String> {
+    if config.doctors == 0 {
+        return Err("At least one doctor is required to seed patient activity.".to_string());
+    }
+
+    if database_has_data(pool)
+        .await
+        .map_err(|e| format!("Error while checking database contents: {}", e))?
+    {
+        return Err("Refusing to seed a database that already has data.".to_string());
+    }
+
+    let mut rng = StdRng::seed_from_u64(config.rng_seed);
+    let mut summary = SeedSummary::default();
+
+    // Everything is written in one transaction so a failure leaves the database empty
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Error while starting seed transaction: {}", e))?;
+
+    // Drawn from the OS generator, not the seeded one, so it differs on every run
+    let password = random_token(PASSWORD_BYTES);
+    let staff = seed_users(&mut tx, &mut rng, config, &password).await?;
+    summary.users = staff.doctors.len() + staff.nurses.len() + staff.admins.len();
+
+    let procedure_ids = seed_procedures(&mut tx, key).await?;
+    summary.procedures = procedure_ids.len();
+
+    let patient_ids = seed_patients(&mut tx, &mut rng, key, config.patients).await?;
+    summary.patients = patient_ids.len();
+
+    summary.activities = seed_activities(
+        &mut tx,
+        &mut rng,
+        key,
+        &patient_ids,
+        &procedure_ids,
+        &staff,
+        config.activities_per_patient,
+    )
+    .await?;
+
+    let clinicians: Vec<i32> = staff.doctors.iter().chain(staff.nurses.iter()).copied().collect();
+    summary.vision_rows =
+        seed_eye_data(&mut tx, &mut rng, key, &patient_ids, &clinicians).await?;
+
+    let all_staff: Vec<i32> = clinicians.iter().chain(staff.admins.iter()).copied().collect();
+    let (conversations, messages) = seed_conversations(
+        &mut tx,
+        &mut rng,
+        &all_staff,
+        config.conversations,
+        config.messages_per_conversation,
+    )
+    .await?;
+    summary.conversations = conversations;
+    summary.messages = messages;
+
+    summary.appointments = seed_appointments(&mut tx, &mut rng, &all_staff, config.appointments).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Error while committing seed data: {}", e))?;
+
+    Ok((summary, password))
+}
+
+struct SeededStaff {
+    doctors: Vec<i32>,
+    nurses: Vec<i32>,
+    admins: Vec<i32>,
+}
+
+fn pick<'a>(rng: &mut StdRng, values: &'a [&'a str]) -> &'a str {
+    values.choose(rng).copied().unwrap_or_default()
+}
+
+fn pick_many(rng: &mut StdRng, values: &[&str], max: usize) -> String {
+    let count = rng.gen_range(0..=max);
+    let chosen: Vec<&str> = values.choose_multiple(rng, count).copied().collect();
+    serde_json::to_string(&chosen).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn random_time_within(rng: &mut StdRng, days_back: i64, days_forward: i64) -> DateTime<Utc> {
+    let offset_minutes = rng.gen_range(-days_back * 24 * 60..=days_forward * 24 * 60);
+    Utc::now() + Duration::minutes(offset_minutes)
+}
+
+async fn seed_users(
+    tx: &mut Transaction<'_, Postgres>,
+    rng: &mut StdRng,
+    config: &SeedConfig,
+    password: &str,
+) -> Result<SeededStaff, String> {
+    // Hashing once keeps large seeds fast; every demo user shares the run's password
+    let password_hash =
+        hash(password, DEFAULT_COST).map_err(|e| format!("Error while hashing demo password: {}", e))?;
+
+    let mut roles = vec![];
+    let mut first_names = vec![];
+    let mut last_names = vec![];
+    let mut emails = vec![];
+
+    let role_counts = [("DOCTOR", config.doctors), ("NURSE", config.nurses), ("ADMIN", config.admins)];
+    for (role, count) in role_counts {
+        for index in 0..count {
+            let first_name = pick(rng, FIRST_NAMES);
+            let last_name = pick(rng, LAST_NAMES);
+            roles.push(role.to_string());
+            first_names.push(first_name.to_string());
+            last_names.push(last_name.to_string());
+            emails.push(format!(
+                "{}.{}.{}{}@demo.ehrportal.local",
+                first_name.to_lowercase(),
+                last_name.to_lowercase(),
+                role.to_lowercase(),
+                index + 1
+            ));
+        }
+    }
+
+    let records = sqlx::query!(
+        r#"
+        INSERT INTO users (role, first_name, last_name, email, password, must_reset_password)
+        SELECT role, first_name, last_name, email, $5, TRUE
+        FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[])
+            AS t(role, first_name, last_name, email)
+        RETURNING user_id, role
+        "#,
+        &roles,
+        &first_names,
+        &last_names,
+        &emails,
+        &password_hash
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Error while seeding users: {}", e))?;
+
+    let ids_for = |role: &str| -> Vec<i32> {
+        records
+            .iter()
+            .filter(|record| record.role == role)
+            .map(|record| record.user_id)
+            .collect()
+    };
+
+    Ok(SeededStaff {
+        doctors: ids_for("DOCTOR"),
+        nurses: ids_for("NURSE"),
+        admins: ids_for("ADMIN"),
+    })
+}
+
+async fn seed_procedures(
+    tx: &mut Transaction<'_, Postgres>,
+    key: &EncryptionKey,
+) -> Result<Vec<i32>, String> {
+    let names: Vec<String> = PROCEDURES.iter().map(|(name, ..)| name.to_string()).collect();
+    let descriptions: Vec<String> = PROCEDURES.iter().map(|(_, desc, ..)| desc.to_string()).collect();
+    let categories: Vec<String> = PROCEDURES.iter().map(|(_, _, category, ..)| category.to_string()).collect();
+    let durations: Vec<i32> = PROCEDURES.iter().map(|(_, _, _, minutes, _)| *minutes).collect();
+    let cpt_codes: Vec<Option<String>> = PROCEDURES.iter().map(|(.., cpt)| cpt.map(str::to_string)).collect();
+
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO procedures (procedure_name, description, category, default_duration_minutes, cpt_code, key_id)
+        SELECT pgp_sym_encrypt(name, $6), pgp_sym_encrypt(description, $6), category, minutes, cpt, $7
+        FROM UNNEST($1::text[], $2::text[], $3::text[], $4::int[], $5::text[]) AS t(name, description, category, minutes, cpt)
+        RETURNING procedure_id
+        "#,
+        &names,
+        &descriptions,
+        &categories,
+        &durations,
+        &cpt_codes as &[Option<String>],
+        &key.secret,
+        key.id
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Error while seeding procedures: {}", e))
+}
+
+async fn seed_patients(
+    tx: &mut Transaction<'_, Postgres>,
+    rng: &mut StdRng,
+    key: &EncryptionKey,
+    count: usize,
+) -> Result<Vec<i32>, String> {
+    let mut patient_ids = Vec::with_capacity(count);
+    let earliest_birth = NaiveDate::from_ymd_opt(1940, 1, 1).unwrap_or_default();
+
+    for chunk_start in (0..count).step_by(CHUNK_SIZE) {
+        let chunk_len = CHUNK_SIZE.min(count - chunk_start);
+        let mut mr_numbers = vec![];
+        let mut first_names = vec![];
+        let mut last_names = vec![];
+        let mut dates_of_birth = vec![];
+        let mut genders = vec![];
+        let mut conditions = vec![];
+        let mut medications = vec![];
+        let mut allergies = vec![];
+
+        for index in chunk_start..chunk_start + chunk_len {
+            mr_numbers.push(format!("MR{:06}", index + 1));
+            first_names.push(pick(rng, FIRST_NAMES).to_string());
+            last_names.push(pick(rng, LAST_NAMES).to_string());
+            dates_of_birth.push(earliest_birth + Duration::days(rng.gen_range(0..27_000)));
+            genders.push(pick(rng, &["MALE", "FEMALE", "OTHERS"]).to_string());
+            conditions.push(pick_many(rng, CONDITIONS, 3));
+            medications.push(pick_many(rng, MEDICATIONS, 3));
+            allergies.push(pick_many(rng, ALLERGIES, 2));
+        }
+
+        let ids = sqlx::query_scalar!(
+            r#"
+            INSERT INTO patients (mr_number, first_name, last_name, date_of_birth, gender)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[], $4::date[], $5::text[])
+            RETURNING patient_id
+            "#,
+            &mr_numbers,
+            &first_names,
+            &last_names,
+            &dates_of_birth,
+            &genders
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding patients: {}", e))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO patient_history (patient_id, medical_conditions, medications, allergies, key_id)
+            SELECT
+                patient_id,
+                pgp_sym_encrypt(conditions, $5),
+                pgp_sym_encrypt(medications, $5),
+                pgp_sym_encrypt(allergies, $5),
+                $6
+            FROM UNNEST($1::int[], $2::text[], $3::text[], $4::text[])
+                AS t(patient_id, conditions, medications, allergies)
+            "#,
+            &ids,
+            &conditions,
+            &medications,
+            &allergies,
+            &key.secret,
+            key.id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding patient history: {}", e))?;
+
+        patient_ids.extend(ids);
+    }
+
+    Ok(patient_ids)
+}
+
+async fn seed_activities(
+    tx: &mut Transaction<'_, Postgres>,
+    rng: &mut StdRng,
+    key: &EncryptionKey,
+    patient_ids: &[i32],
+    procedure_ids: &[i32],
+    staff: &SeededStaff,
+    per_patient: usize,
+) -> Result<usize, String> {
+    let rows: Vec<(i32, i32, i32, Option<i32>)> = patient_ids
+        .iter()
+        .flat_map(|patient_id| std::iter::repeat_n(*patient_id, per_patient))
+        .map(|patient_id| {
+            let procedure_id = *procedure_ids.choose(rng).unwrap_or(&procedure_ids[0]);
+            let doctor_id = *staff.doctors.choose(rng).unwrap_or(&staff.doctors[0]);
+            // About half of the visits have a nurse assisting
+            let nurse_id = if rng.gen_bool(0.5) { staff.nurses.choose(rng).copied() } else { None };
+            (patient_id, procedure_id, doctor_id, nurse_id)
+        })
+        .collect();
+
+    for chunk in rows.chunks(CHUNK_SIZE) {
+        let mut patients = vec![];
+        let mut procedures = vec![];
+        let mut doctors = vec![];
+        let mut nurses: Vec<Option<i32>> = vec![];
+        let mut statuses = vec![];
+        let mut notes = vec![];
+        let mut complaints = vec![];
+        let mut times = vec![];
+
+        for (patient_id, procedure_id, doctor_id, nurse_id) in chunk {
+            // Past visits are mostly finished; a slice of them land on today for the dashboard
+            let activity_time = if rng.gen_bool(0.15) {
+                random_time_within(rng, 0, 0)
+            } else {
+                random_time_within(rng, 365, 0)
+            };
+            patients.push(*patient_id);
+            procedures.push(*procedure_id);
+            doctors.push(*doctor_id);
+            nurses.push(*nurse_id);
+            statuses.push(pick(rng, &["COMPLETED", "COMPLETED", "IN_PROGRESS", "TO_BE_REVIEWED"]).to_string());
+            notes.push(pick(rng, DOCTOR_NOTES).to_string());
+            complaints.push(pick(rng, COMPLAINTS).to_string());
+            times.push(activity_time);
+        }
+
+        // Each activity starts its workflow history in the status it was seeded with
+        sqlx::query!(
+            r#"
+            WITH inserted AS (
+                INSERT INTO patient_activity (
+                    patient_id, procedure_id, doctor_id, nurse_id, status, doctors_note, patient_complaint, activity_time, key_id
+                )
+                SELECT
+                    patient_id,
+                    procedure_id,
+                    doctor_id,
+                    nurse_id,
+                    status,
+                    pgp_sym_encrypt(note, $8),
+                    pgp_sym_encrypt(complaint, $8),
+                    activity_time,
+                    $9
+                FROM UNNEST($1::int[], $2::int[], $3::int[], $10::int[], $4::text[], $5::text[], $6::text[], $7::timestamptz[])
+                    AS t(patient_id, procedure_id, doctor_id, nurse_id, status, note, complaint, activity_time)
+                RETURNING activity_id, doctor_id, status, activity_time
+            )
+            INSERT INTO activity_transitions (activity_id, to_status, key_id, transitioned_by, transitioned_at)
+            SELECT activity_id, status, $9, doctor_id, activity_time FROM inserted
+            "#,
+            &patients,
+            &procedures,
+            &doctors,
+            &statuses,
+            &notes,
+            &complaints,
+            &times,
+            &key.secret,
+            key.id,
+            &nurses as &[Option<i32>]
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding patient activity: {}", e))?;
+    }
+
+    Ok(rows.len())
+}
+
+// Function to write vision, refraction and eye measurement rows for every patient and eye
+async fn seed_eye_data(
+    tx: &mut Transaction<'_, Postgres>,
+    rng: &mut StdRng,
+    key: &EncryptionKey,
+    patient_ids: &[i32],
+    clinician_ids: &[i32],
+) -> Result<usize, String> {
+    let mut written = 0;
+
+    for chunk in patient_ids.chunks(CHUNK_SIZE / 6) {
+        let mut vision = (vec![], vec![], vec![], vec![], vec![], vec![]);
+        let mut refraction = (vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]);
+        let mut measurement = (vec![], vec![], vec![], vec![], vec![], vec![], vec![]);
+
+        for patient_id in chunk {
+            for side in ["LEFT", "RIGHT"] {
+                let recorded_by = *clinician_ids.choose(rng).unwrap_or(&clinician_ids[0]);
+
+                // Corrected acuity is never worse than uncorrected
+                let uncorrected = rng.gen_range(0..SNELLEN.len());
+                let corrected = rng.gen_range(0..=uncorrected.min(3));
+                let pinhole = rng.gen_range(corrected..=uncorrected);
+                for (value_type, index) in [("UC", uncorrected), ("BCVA", corrected), ("PH", pinhole)] {
+                    vision.0.push(*patient_id);
+                    vision.1.push(SNELLEN[index.saturating_sub(1)].to_string());
+                    vision.2.push(SNELLEN[index].to_string());
+                    vision.3.push(side.to_string());
+                    vision.4.push(value_type.to_string());
+                    vision.5.push(recorded_by);
+                }
+
+                let spherical = rng.gen_range(-24..=16) as f64 * 0.25;
+                let cylindrical = rng.gen_range(-12..=0) as f64 * 0.25;
+                let axis = rng.gen_range(0..=180);
+                for value_type in ["DL", "UD"] {
+                    for vision_type in ["DV", "NV"] {
+                        let add = if vision_type == "NV" { 1.5 } else { 0.0 };
+                        refraction.0.push(*patient_id);
+                        refraction.1.push(format!("{:+.2}", spherical + add));
+                        refraction.2.push(format!("{:+.2}", cylindrical));
+                        refraction.3.push(axis.to_string());
+                        refraction.4.push(side.to_string());
+                        refraction.5.push(value_type.to_string());
+                        refraction.6.push(vision_type.to_string());
+                        refraction.7.push(recorded_by);
+                    }
+                }
+
+                let iop = rng.gen_range(10.0..24.0_f64);
+                measurement.0.push(*patient_id);
+                measurement.1.push(format!("{:.0}", iop));
+                measurement.2.push(format!("{:.0}", iop + rng.gen_range(-2.0..3.0)));
+                measurement.3.push(rng.gen_range(490..=590).to_string());
+                measurement.4.push(format!("{:.2}", rng.gen_range(0.2..0.7_f64)));
+                measurement.5.push(side.to_string());
+                measurement.6.push(recorded_by);
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO vision (patient_id, near_vision, distant_vision, side, value_type, created_by, key_id)
+            SELECT patient_id, pgp_sym_encrypt(near, $7), pgp_sym_encrypt(distant, $7), side, value_type, created_by, $8
+            FROM UNNEST($1::int[], $2::text[], $3::text[], $4::text[], $5::text[], $6::int[])
+                AS t(patient_id, near, distant, side, value_type, created_by)
+            "#,
+            &vision.0,
+            &vision.1,
+            &vision.2,
+            &vision.3,
+            &vision.4,
+            &vision.5,
+            &key.secret,
+            key.id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding vision: {}", e))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refraction (patient_id, spherical, cylindrical, axis, side, value_type, vision_type, created_by, key_id)
+            SELECT
+                patient_id,
+                pgp_sym_encrypt(spherical, $9),
+                pgp_sym_encrypt(cylindrical, $9),
+                pgp_sym_encrypt(axis, $9),
+                side,
+                value_type,
+                vision_type,
+                created_by,
+                $10
+            FROM UNNEST($1::int[], $2::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::text[], $8::int[])
+                AS t(patient_id, spherical, cylindrical, axis, side, value_type, vision_type, created_by)
+            "#,
+            &refraction.0,
+            &refraction.1,
+            &refraction.2,
+            &refraction.3,
+            &refraction.4,
+            &refraction.5,
+            &refraction.6,
+            &refraction.7,
+            &key.secret,
+            key.id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding refraction: {}", e))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO eye_measurement (patient_id, iop_at, iop_nct, cct, tond, side, created_by, key_id)
+            SELECT
+                patient_id,
+                pgp_sym_encrypt(iop_at, $8),
+                pgp_sym_encrypt(iop_nct, $8),
+                pgp_sym_encrypt(cct, $8),
+                pgp_sym_encrypt(tond, $8),
+                side,
+                created_by,
+                $9
+            FROM UNNEST($1::int[], $2::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::int[])
+                AS t(patient_id, iop_at, iop_nct, cct, tond, side, created_by)
+            "#,
+            &measurement.0,
+            &measurement.1,
+            &measurement.2,
+            &measurement.3,
+            &measurement.4,
+            &measurement.5,
+            &measurement.6,
+            &key.secret,
+            key.id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding eye measurements: {}", e))?;
+
+        written += vision.0.len() + refraction.0.len() + measurement.0.len();
+    }
+
+    Ok(written)
+}
+
+async fn seed_conversations(
+    tx: &mut Transaction<'_, Postgres>,
+    rng: &mut StdRng,
+    staff_ids: &[i32],
+    count: usize,
+    messages_per_conversation: usize,
+) -> Result<(usize, usize), String> {
+    // Conversations are unique per ordered pair of users
+    let mut pairs = vec![];
+    for (index, first) in staff_ids.iter().enumerate() {
+        for second in staff_ids.iter().skip(index + 1) {
+            pairs.push((*first.min(second), *first.max(second)));
+        }
+    }
+    pairs.shuffle(rng);
+    pairs.truncate(count);
+
+    let user1: Vec<i32> = pairs.iter().map(|(a, _)| *a).collect();
+    let user2: Vec<i32> = pairs.iter().map(|(_, b)| *b).collect();
+    let conversation_ids = sqlx::query_scalar!(
+        r#"
+        INSERT INTO conversations (user1, user2)
+        SELECT * FROM UNNEST($1::int[], $2::int[])
+        RETURNING conversation_id
+        "#,
+        &user1,
+        &user2
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Error while seeding conversations: {}", e))?;
+
+    let mut conversation_column = vec![];
+    let mut senders = vec![];
+    let mut contents = vec![];
+    let mut times = vec![];
+    for (conversation_id, (first, second)) in conversation_ids.iter().zip(pairs.iter()) {
+        let mut sent_at = random_time_within(rng, 30, 0);
+        for _ in 0..messages_per_conversation {
+            sent_at += Duration::minutes(rng.gen_range(1..90));
+            conversation_column.push(*conversation_id);
+            senders.push(if rng.gen_bool(0.5) { *first } else { *second });
+            contents.push(pick(rng, MESSAGES).to_string());
+            times.push(sent_at);
+        }
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO messages (conversation_id, sender_id, content, created_at)
+        SELECT * FROM UNNEST($1::int[], $2::int[], $3::text[], $4::timestamptz[])
+        "#,
+        &conversation_column,
+        &senders,
+        &contents,
+        &times
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Error while seeding messages: {}", e))?;
+
+    // Recipient is whichever participant did not send the message
+    sqlx::query!(
+        r#"
+        INSERT INTO message_status (message_id, recipient_id, status)
+        SELECT
+            m.message_id,
+            CASE WHEN c.user1 = m.sender_id THEN c.user2 ELSE c.user1 END,
+            CASE WHEN m.created_at < NOW() - INTERVAL '1 day' THEN 'read' ELSE 'delivered' END
+        FROM messages m
+        JOIN conversations c ON c.conversation_id = m.conversation_id
+        "#
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Error while seeding message status: {}", e))?;
+
+    sqlx::query!(
+        r#"
+        UPDATE conversations c
+        SET last_message = (
+            SELECT m.message_id
+            FROM messages m
+            WHERE m.conversation_id = c.conversation_id
+            ORDER BY m.created_at DESC, m.message_id DESC
+            LIMIT 1
+        )
+        "#
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Error while linking last messages: {}", e))?;
+
+    Ok((conversation_ids.len(), senders.len()))
+}
+
+async fn seed_appointments(
+    tx: &mut Transaction<'_, Postgres>,
+    rng: &mut StdRng,
+    staff_ids: &[i32],
+    count: usize,
+) -> Result<usize, String> {
+    if staff_ids.is_empty() {
+        return Ok(0);
+    }
+
+    const DESCRIPTIONS: &[&str] = &[
+        "Department meeting",
+        "Surgical planning review",
+        "Glaucoma clinic huddle",
+        "Case discussion",
+        "Equipment training",
+    ];
+
+    for _ in 0..count {
+        let creator = *staff_ids.choose(rng).unwrap_or(&staff_ids[0]);
+        let duration_minutes = *[15_i64, 30, 45, 60].choose(rng).unwrap_or(&30);
+
+        let appointment_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO appointments (description, appointment_time, appointment_duration, created_by)
+            VALUES ($1, $2, make_interval(mins => $3::int), $4)
+            RETURNING appointment_id
+            "#,
+            pick(rng, DESCRIPTIONS),
+            random_time_within(rng, 30, 30),
+            duration_minutes as i32,
+            creator
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding appointments: {}", e))?;
+
+        // The creator always attends, plus up to three colleagues
+        let colleagues = rng.gen_range(0..=3.min(staff_ids.len()));
+        let mut attendees: Vec<i32> = staff_ids.choose_multiple(rng, colleagues).copied().collect();
+        attendees.push(creator);
+        attendees.sort_unstable();
+        attendees.dedup();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO appointment_users (appointment_id, user_id)
+            SELECT $1, user_id FROM UNNEST($2::int[]) AS t(user_id)
+            "#,
+            appointment_id,
+            &attendees
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Error while seeding appointment users: {}", e))?;
+    }
+
+    Ok(count)
+}
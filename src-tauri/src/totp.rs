@@ -0,0 +1,206 @@
+// src-tauri/src/totp.rs
+
+// Dependencies
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+// RFC 6238 parameters understood by every common authenticator app
+pub const STEP_SECONDS: u64 = 30;
+pub const DIGITS: u32 = 6;
+// Codes from one step before or after the current one are still accepted to absorb clock drift
+pub const ALLOWED_SKEW_STEPS: u64 = 1;
+const SECRET_BYTES: usize = 20;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// RFC 4648 base32 without padding, as used in otpauth URIs
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    output
+}
+
+// Accepts lowercase, spaces and padding since users may type the secret by hand
+pub fn base32_decode(input: &str) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())
+            .ok_or_else(|| format!("Invalid base32 character: {}", c))? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Ok(output)
+}
+
+// New random shared secret, base32 encoded
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+// RFC 4226 HOTP value for a counter
+pub fn hotp(key: &[u8], counter: u64, digits: u32) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+
+    binary % 10u32.pow(digits)
+}
+
+// Time step containing the given unix time
+pub fn time_step(unix_time: u64) -> u64 {
+    unix_time / STEP_SECONDS
+}
+
+// Zero padded TOTP code for the given unix time
+pub fn code_at(secret: &str, unix_time: u64) -> Result<String, String> {
+    let key = base32_decode(secret)?;
+    Ok(format!("{:0width$}", hotp(&key, time_step(unix_time), DIGITS), width = DIGITS as usize))
+}
+
+// Function to check a code against the steps around the given time.
+// Returns the matched step, which must be stored so the same code cannot be replayed.
+// Steps at or before last_used_step are rejected.
+pub fn verify_code(secret: &str, code: &str, unix_time: u64, last_used_step: Option<u64>) -> Result<Option<u64>, String> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let key = base32_decode(secret)?;
+    let current = time_step(unix_time);
+    let first = current.saturating_sub(ALLOWED_SKEW_STEPS);
+
+    for step in first..=current + ALLOWED_SKEW_STEPS {
+        if last_used_step.is_some_and(|last| step <= last) {
+            continue;
+        }
+        let expected = format!("{:0width$}", hotp(&key, step, DIGITS), width = DIGITS as usize);
+        if expected == code {
+            return Ok(Some(step));
+        }
+    }
+
+    Ok(None)
+}
+
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// otpauth:// URI that authenticator apps read from a QR code
+pub fn provisioning_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        uri_encode(issuer),
+        uri_encode(account),
+        secret,
+        uri_encode(issuer),
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B shared secret for SHA1
+    const RFC_KEY: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn matches_rfc_6238_test_vectors() {
+        let vectors = [
+            (59, 94287082),
+            (1111111109, 7081804),
+            (1111111111, 14050471),
+            (1234567890, 89005924),
+            (2000000000, 69279037),
+            (20000000000, 65353130),
+        ];
+
+        for (unix_time, expected) in vectors {
+            assert_eq!(hotp(RFC_KEY, time_step(unix_time), 8), expected, "time {}", unix_time);
+        }
+    }
+
+    #[test]
+    fn base32_round_trips_rfc_4648_vectors() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("mzxw 6ytb oi======").unwrap(), b"foobar");
+
+        let secret = base32_encode(RFC_KEY);
+        assert_eq!(base32_decode(&secret).unwrap(), RFC_KEY);
+        assert!(base32_decode("not-base32!").is_err());
+    }
+
+    #[test]
+    fn verifies_codes_within_skew_at_fixed_time() {
+        let secret = base32_encode(RFC_KEY);
+        let now = 1111111111;
+
+        let current = code_at(&secret, now).unwrap();
+        let previous = code_at(&secret, now - STEP_SECONDS).unwrap();
+        let too_old = code_at(&secret, now - 3 * STEP_SECONDS).unwrap();
+
+        assert_eq!(current, "050471");
+        assert_eq!(verify_code(&secret, &current, now, None).unwrap(), Some(time_step(now)));
+        assert_eq!(verify_code(&secret, &previous, now, None).unwrap(), Some(time_step(now) - 1));
+        assert_eq!(verify_code(&secret, &too_old, now, None).unwrap(), None);
+        assert_eq!(verify_code(&secret, "12345", now, None).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_replayed_steps() {
+        let secret = base32_encode(RFC_KEY);
+        let now = 1234567890;
+        let code = code_at(&secret, now).unwrap();
+
+        let step = verify_code(&secret, &code, now, None).unwrap().unwrap();
+        assert_eq!(verify_code(&secret, &code, now, Some(step)).unwrap(), None);
+    }
+
+    #[test]
+    fn builds_provisioning_uri() {
+        let uri = provisioning_uri("EHR Portal", "jane.doe@example.com", "JBSWY3DPEHPK3PXP");
+        assert_eq!(
+            uri,
+            "otpauth://totp/EHR%20Portal:jane.doe%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=EHR%20Portal&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
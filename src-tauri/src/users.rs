@@ -0,0 +1,532 @@
+// src-tauri/src/users.rs
+
+// Dependencies
+use bcrypt::{hash, DEFAULT_COST};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::config::AppConfig;
+use crate::auth::{revoke_all_sessions, AuthUser, UserProfile};
+use crate::crypto::random_token;
+use crate::db::{is_unique_violation, DatabaseState};
+use crate::error::AppError;
+use crate::permissions::{authenticate, require, Permission, Role};
+use crate::repository::{PgRepository, UserRepo};
+
+#[derive(Serialize, Deserialize)]
+pub struct InviteUserQuery {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub role: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateUserQuery {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String
+}
+
+// Account together with a one-time password the admin hands over to the user
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TemporaryCredentials {
+    pub user: UserProfile,
+    pub temporary_password: String
+}
+
+// Account to insert, with the password already hashed
+#[derive(Clone, Debug)]
+pub struct NewUser {
+    pub role: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub password_hash: String,
+    pub status: String,
+    pub must_reset_password: bool
+}
+
+fn parse_status(status: &str) -> Result<&str, AppError> {
+    match status {
+        "PENDING" | "ACTIVE" | "DEACTIVATED" => Ok(status),
+        _ => Err(AppError::validation("status", &format!("Unknown status: {}", status)))
+    }
+}
+
+fn map_write_error(err: sqlx::Error, action: &str) -> AppError {
+    if is_unique_violation(&err) {
+        AppError::conflict("A user with this email already exists.")
+    } else {
+        AppError::database(&format!("Error while {}", action), err)
+    }
+}
+
+// Admins cannot lock themselves out by deactivating or demoting their own account
+fn ensure_not_self(admin_id: i32, user_id: i32) -> Result<(), AppError> {
+    if admin_id == user_id {
+        return Err(AppError::Forbidden("Administrators cannot change their own account status or role.".to_string()));
+    }
+
+    Ok(())
+}
+
+impl UserRepo for PgRepository<'_> {
+    async fn list_users(&self, role: Option<&str>, status: Option<&str>) -> Result<Vec<UserProfile>, AppError> {
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            SELECT user_id, role, first_name, last_name, email, status, must_reset_password, locked_until, created_at
+            FROM users
+            WHERE ($1::VARCHAR IS NULL OR role = $1)
+            AND ($2::VARCHAR IS NULL OR status = $2)
+            ORDER BY last_name, first_name, user_id
+            "#,
+            role,
+            status
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching users", e))
+    }
+
+    async fn list_active_doctors(&self) -> Result<Vec<UserProfile>, AppError> {
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            SELECT
+                user_id,
+                role,
+                first_name,
+                last_name,
+                email,
+                status,
+                must_reset_password,
+                locked_until,
+                created_at
+            FROM
+                users
+            WHERE
+                role = 'DOCTOR'
+            AND
+                status = 'ACTIVE'
+            "#
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching doctors", e))
+    }
+
+    async fn create_user(&self, user: &NewUser) -> Result<UserProfile, AppError> {
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            INSERT INTO users (role, first_name, last_name, email, password, status, must_reset_password)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING user_id, role, first_name, last_name, email, status, must_reset_password, locked_until, created_at
+            "#,
+            &user.role,
+            &user.first_name,
+            &user.last_name,
+            &user.email,
+            &user.password_hash,
+            &user.status,
+            user.must_reset_password
+        )
+        .fetch_one(self.pool)
+        .await
+        .map_err(|e| map_write_error(e, "creating user"))
+    }
+
+    async fn set_status(&self, user_id: i32, from: &[&str], to: &str) -> Result<Option<UserProfile>, AppError> {
+        let from: Vec<String> = from.iter().map(|status| status.to_string()).collect();
+
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            UPDATE users
+            SET status = $3, updated_at = NOW()
+            WHERE user_id = $1 AND status = ANY($2)
+            RETURNING user_id, role, first_name, last_name, email, status, must_reset_password, locked_until, created_at
+            "#,
+            &user_id,
+            &from,
+            to
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while updating user status", e))
+    }
+
+    async fn set_role(&self, user_id: i32, role: &str) -> Result<Option<UserProfile>, AppError> {
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            UPDATE users
+            SET role = $2, updated_at = NOW()
+            WHERE user_id = $1
+            RETURNING user_id, role, first_name, last_name, email, status, must_reset_password, locked_until, created_at
+            "#,
+            &user_id,
+            role
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while changing role", e))
+    }
+
+    async fn reset_password(&self, user_id: i32, password_hash: &str) -> Result<Option<UserProfile>, AppError> {
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            UPDATE users
+            SET password = $2, must_reset_password = TRUE, failed_login_attempts = 0, locked_until = NULL, updated_at = NOW()
+            WHERE user_id = $1
+            RETURNING user_id, role, first_name, last_name, email, status, must_reset_password, locked_until, created_at
+            "#,
+            &user_id,
+            password_hash
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while resetting password", e))
+    }
+
+    async fn update_user(&self, user_id: i32, first_name: &str, last_name: &str, email: &str) -> Result<Option<UserProfile>, AppError> {
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            UPDATE users
+            SET first_name = $2, last_name = $3, email = $4, updated_at = NOW()
+            WHERE user_id = $1
+            RETURNING user_id, role, first_name, last_name, email, status, must_reset_password, locked_until, created_at
+            "#,
+            &user_id,
+            first_name,
+            last_name,
+            email
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| map_write_error(e, "updating user"))
+    }
+
+    async fn unlock_user(&self, user_id: i32) -> Result<Option<UserProfile>, AppError> {
+        sqlx::query_as!(
+            UserProfile,
+            r#"
+            UPDATE users
+            SET failed_login_attempts = 0, locked_until = NULL, updated_at = NOW()
+            WHERE user_id = $1
+            RETURNING user_id, role, first_name, last_name, email, status, must_reset_password, locked_until, created_at
+            "#,
+            &user_id
+        )
+        .fetch_optional(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while unlocking user", e))
+    }
+
+    async fn revoke_sessions(&self, user_id: i32, reason: &str) -> Result<u64, AppError> {
+        revoke_all_sessions(self.pool, user_id, reason).await
+    }
+}
+
+// Function to move a user from one of the given statuses to a new one
+async fn set_status<R: UserRepo>(repo: &R, user_id: i32, from: &[&str], to: &str) -> Result<UserProfile, AppError> {
+    repo.set_status(user_id, from, to)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User not found or not in status {}.", from.join("/"))))
+}
+
+// Function to generate and hash a one-time password
+fn temporary_password() -> Result<(String, String), AppError> {
+    let temporary_password = random_token(8);
+    let hashed_password = hash(&temporary_password, DEFAULT_COST).map_err(|e| AppError::crypto("Error while hashing password", e))?;
+
+    Ok((temporary_password, hashed_password))
+}
+
+// Function to list users, optionally filtered by role and status
+pub async fn list_accounts<R: UserRepo>(
+    repo: &R,
+    admin: &AuthUser,
+    role: Option<&str>,
+    status: Option<&str>
+) -> Result<Vec<UserProfile>, AppError> {
+    require(admin, Permission::ManageUsers)?;
+
+    let role = role.map(|role| Role::parse(role).map(|role| role.as_str())).transpose().map_err(|e| AppError::validation("role", &e))?;
+    let status = status.map(parse_status).transpose()?;
+
+    repo.list_users(role, status).await
+}
+
+// Function to create an active account with a temporary password that must be changed on first login
+pub async fn invite_account<R: UserRepo>(repo: &R, admin: &AuthUser, invite_query: &InviteUserQuery) -> Result<TemporaryCredentials, AppError> {
+    require(admin, Permission::ManageUsers)?;
+
+    let role = Role::parse(&invite_query.role).map_err(|e| AppError::validation("role", &e))?;
+    let (temporary_password, password_hash) = temporary_password()?;
+
+    let user = repo
+        .create_user(&NewUser {
+            role: role.as_str().to_string(),
+            first_name: invite_query.first_name.clone(),
+            last_name: invite_query.last_name.clone(),
+            email: invite_query.email.trim().to_string(),
+            password_hash,
+            status: "ACTIVE".to_string(),
+            must_reset_password: true
+        })
+        .await?;
+
+    Ok(TemporaryCredentials { user, temporary_password })
+}
+
+// Function to approve a pending self-service signup
+pub async fn approve_account<R: UserRepo>(repo: &R, admin: &AuthUser, user_id: i32) -> Result<UserProfile, AppError> {
+    require(admin, Permission::ManageUsers)?;
+
+    set_status(repo, user_id, &["PENDING"], "ACTIVE").await
+}
+
+// Function to deactivate an account and sign it out everywhere
+pub async fn deactivate_account<R: UserRepo>(repo: &R, admin: &AuthUser, user_id: i32) -> Result<UserProfile, AppError> {
+    require(admin, Permission::ManageUsers)?;
+    ensure_not_self(admin.user_id, user_id)?;
+
+    let user = set_status(repo, user_id, &["PENDING", "ACTIVE"], "DEACTIVATED").await?;
+    repo.revoke_sessions(user_id, "DEACTIVATED").await?;
+
+    Ok(user)
+}
+
+// Function to reactivate a deactivated account
+pub async fn reactivate_account<R: UserRepo>(repo: &R, admin: &AuthUser, user_id: i32) -> Result<UserProfile, AppError> {
+    require(admin, Permission::ManageUsers)?;
+
+    set_status(repo, user_id, &["DEACTIVATED"], "ACTIVE").await
+}
+
+// Function to change a user's role. Existing sessions are revoked so the new role applies on next login.
+pub async fn change_account_role<R: UserRepo>(repo: &R, admin: &AuthUser, user_id: i32, role: &str) -> Result<UserProfile, AppError> {
+    require(admin, Permission::ManageUsers)?;
+    ensure_not_self(admin.user_id, user_id)?;
+
+    let role = Role::parse(role).map_err(|e| AppError::validation("role", &e))?;
+    let user = repo
+        .set_role(user_id, role.as_str())
+        .await?
+        .ok_or_else(|| AppError::not_found("User not found."))?;
+
+    repo.revoke_sessions(user_id, "ROLE_CHANGED").await?;
+
+    Ok(user)
+}
+
+// Function to replace a user's password with a temporary one and force a change on next login
+pub async fn reset_account_password<R: UserRepo>(repo: &R, admin: &AuthUser, user_id: i32) -> Result<TemporaryCredentials, AppError> {
+    require(admin, Permission::ManageUsers)?;
+
+    let (temporary_password, password_hash) = temporary_password()?;
+    let user = repo
+        .reset_password(user_id, &password_hash)
+        .await?
+        .ok_or_else(|| AppError::not_found("User not found."))?;
+
+    repo.revoke_sessions(user_id, "PASSWORD_RESET").await?;
+
+    Ok(TemporaryCredentials { user, temporary_password })
+}
+
+// Function to edit a user's name and email
+pub async fn update_account<R: UserRepo>(repo: &R, admin: &AuthUser, user_id: i32, update_query: &UpdateUserQuery) -> Result<UserProfile, AppError> {
+    require(admin, Permission::ManageUsers)?;
+
+    repo.update_user(user_id, &update_query.first_name, &update_query.last_name, update_query.email.trim())
+        .await?
+        .ok_or_else(|| AppError::not_found("User not found."))
+}
+
+// Function to lift a temporary lockout before it expires
+pub async fn unlock_account<R: UserRepo>(repo: &R, admin: &AuthUser, user_id: i32) -> Result<UserProfile, AppError> {
+    require(admin, Permission::ManageUsers)?;
+
+    repo.unlock_user(user_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("User not found."))
+}
+
+// Endpoint to list users, optionally filtered by role and status
+#[tauri::command]
+pub async fn list_users(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    role: Option<String>,
+    status: Option<String>
+) -> Result<Vec<UserProfile>, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    list_accounts(&PgRepository::new(pool, &config.keyring), &admin, role.as_deref(), status.as_deref()).await
+}
+
+// Endpoint to create an active account with a temporary password that must be changed on first login
+#[tauri::command]
+pub async fn invite_user(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    invite_query: InviteUserQuery
+) -> Result<TemporaryCredentials, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    invite_account(&PgRepository::new(pool, &config.keyring), &admin, &invite_query).await
+}
+
+// Endpoint to approve a pending self-service signup
+#[tauri::command]
+pub async fn approve_user(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, user_id: i32) -> Result<UserProfile, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    approve_account(&PgRepository::new(pool, &config.keyring), &admin, user_id).await
+}
+
+// Endpoint to deactivate an account and sign it out everywhere
+#[tauri::command]
+pub async fn deactivate_user(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, user_id: i32) -> Result<UserProfile, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    deactivate_account(&PgRepository::new(pool, &config.keyring), &admin, user_id).await
+}
+
+// Endpoint to reactivate a deactivated account
+#[tauri::command]
+pub async fn reactivate_user(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, user_id: i32) -> Result<UserProfile, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    reactivate_account(&PgRepository::new(pool, &config.keyring), &admin, user_id).await
+}
+
+// Endpoint to change a user's role
+#[tauri::command]
+pub async fn change_user_role(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    user_id: i32,
+    role: String
+) -> Result<UserProfile, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    change_account_role(&PgRepository::new(pool, &config.keyring), &admin, user_id, &role).await
+}
+
+// Endpoint to replace a user's password with a temporary one and force a change on next login
+#[tauri::command]
+pub async fn force_password_reset(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    user_id: i32
+) -> Result<TemporaryCredentials, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    reset_account_password(&PgRepository::new(pool, &config.keyring), &admin, user_id).await
+}
+
+// Endpoint to edit a user's name and email
+#[tauri::command]
+pub async fn update_user(
+    state: State<'_, DatabaseState>,
+    config: State<'_, AppConfig>,
+    token: String,
+    user_id: i32,
+    update_query: UpdateUserQuery
+) -> Result<UserProfile, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    update_account(&PgRepository::new(pool, &config.keyring), &admin, user_id, &update_query).await
+}
+
+// Endpoint to lift a temporary lockout before it expires
+#[tauri::command]
+pub async fn unlock_user(state: State<'_, DatabaseState>, config: State<'_, AppConfig>, token: String, user_id: i32) -> Result<UserProfile, AppError> {
+    let pool = &state.pool;
+    let admin = authenticate(pool, &config, &token).await?;
+
+    unlock_account(&PgRepository::new(pool, &config.keyring), &admin, user_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{test_user, MemoryRepository};
+
+    #[tokio::test]
+    async fn invited_users_must_reset_their_password() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let invite = InviteUserQuery {
+            first_name: "Grace".to_string(),
+            last_name: "Hopper".to_string(),
+            email: " grace@example.com ".to_string(),
+            role: "NURSE".to_string()
+        };
+
+        let credentials = invite_account(&repo, &admin, &invite).await.unwrap();
+        assert_eq!(credentials.user.email, "grace@example.com");
+        assert_eq!(credentials.user.status, "ACTIVE");
+        assert!(credentials.user.must_reset_password);
+        assert_eq!(credentials.temporary_password.len(), 16);
+
+        let err = invite_account(&repo, &admin, &invite).await.unwrap_err();
+        assert_eq!(err, AppError::conflict("A user with this email already exists."));
+    }
+
+    #[tokio::test]
+    async fn deactivating_revokes_sessions() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let nurse = test_user(&repo, "NURSE");
+
+        let user = deactivate_account(&repo, &admin, nurse.user_id).await.unwrap();
+        assert_eq!(user.status, "DEACTIVATED");
+        assert_eq!(repo.revoked_sessions(), vec![(nurse.user_id, "DEACTIVATED".to_string())]);
+
+        let err = deactivate_account(&repo, &admin, nurse.user_id).await.unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+        assert_eq!(reactivate_account(&repo, &admin, nurse.user_id).await.unwrap().status, "ACTIVE");
+    }
+
+    #[tokio::test]
+    async fn admins_cannot_change_their_own_account() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+
+        assert_eq!(deactivate_account(&repo, &admin, admin.user_id).await.unwrap_err().code(), "FORBIDDEN");
+        assert_eq!(change_account_role(&repo, &admin, admin.user_id, "NURSE").await.unwrap_err().code(), "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn listing_filters_by_role_and_status() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let doctor = test_user(&repo, "DOCTOR");
+        test_user(&repo, "NURSE");
+
+        change_account_role(&repo, &admin, doctor.user_id, "NURSE").await.unwrap();
+        assert_eq!(list_accounts(&repo, &admin, Some("NURSE"), None).await.unwrap().len(), 2);
+        assert_eq!(list_accounts(&repo, &admin, None, Some("ACTIVE")).await.unwrap().len(), 3);
+        assert_eq!(list_accounts(&repo, &admin, None, Some("GONE")).await.unwrap_err().code(), "VALIDATION");
+        assert_eq!(list_accounts(&repo, &doctor, None, None).await.unwrap_err(), AppError::forbidden());
+    }
+}
@@ -1,29 +1,69 @@
 // src-tauri/src/vision.rs
 
 // Dependencies
+use crate::config::AppConfig;
+use crate::audit::{record_audit, AuditAction, AuditEvent, ENTITY_EYE_MEASUREMENT, ENTITY_REFRACTION, ENTITY_VISION};
+use crate::auth::AuthUser;
 use crate::db::DatabaseState;
+use crate::error::AppError;
+use crate::patients::reject_inactive_patient;
+use crate::permissions::{authenticate, require, Permission};
+use crate::repository::{AuditRepo, PatientRepo, PgRepository, VisionRepo};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 // Sturct to store input for get_vision_data
 #[derive(Serialize, Deserialize)]
 pub struct VisionQuery {
-    patient_id: i32,
-    side: String,
-    value_type: String,
+    pub patient_id: i32,
+    pub side: String,
+    pub value_type: String,
 }
 
 // Struct to store input for get_refraction_data
 #[derive(Serialize, Deserialize)]
 pub struct RefractionQuery {
-    patient_id: i32,
-    side: String,
-    value_type: String,
-    vision_type: String,
+    pub patient_id: i32,
+    pub side: String,
+    pub value_type: String,
+    pub vision_type: String,
+}
+
+// Struct to store input for update_vision_data
+#[derive(Clone, Debug)]
+pub struct VisionInput {
+    pub patient_id: i32,
+    pub near_vision: String,
+    pub distant_vision: String,
+    pub side: String,
+    pub value_type: String,
+}
+
+// Struct to store input for update_refraction_data
+#[derive(Clone, Debug)]
+pub struct RefractionInput {
+    pub patient_id: i32,
+    pub spherical: String,
+    pub cylindrical: String,
+    pub axis: String,
+    pub side: String,
+    pub value_type: String,
+    pub vision_type: String,
+}
+
+// Struct to store input for update_patient_eye_measurement_data
+#[derive(Clone, Debug)]
+pub struct EyeMeasurementInput {
+    pub patient_id: i32,
+    pub iop_at: String,
+    pub iop_nct: String,
+    pub cct: String,
+    pub tond: String,
+    pub side: String,
 }
 
 // Struct to store result of get_vision_data
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct VisionData {
     pub vision_id: i32,
     pub patient_id: Option<i32>,
@@ -38,7 +78,7 @@ pub struct VisionData {
 }
 
 // Struct to store patient refraction data
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RefractionData {
     pub refraction_id: i32,
     pub patient_id: Option<i32>,
@@ -55,241 +95,665 @@ pub struct RefractionData {
 }
 
 // Struct to store patient eye measurements
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct EyeMeasurementData {
-    measurement_id: i32,
-    patient_id: Option<i32>,
-    iop_at: Option<String>,
-    iop_nct: Option<String>,
-    cct: Option<String>,
-    tond: Option<String>,
-    side: String,
-    created_at: Option<DateTime<Utc>>,
-    created_by: Option<i32>,
-    updated_at: Option<DateTime<Utc>>,
-    updated_by: Option<i32>,
+    pub measurement_id: i32,
+    pub patient_id: Option<i32>,
+    pub iop_at: Option<String>,
+    pub iop_nct: Option<String>,
+    pub cct: Option<String>,
+    pub tond: Option<String>,
+    pub side: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub created_by: Option<i32>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub updated_by: Option<i32>,
 }
 
-// Endpoint to get uncorrected vision values for a patient
-#[tauri::command]
-pub async fn get_vision_data(
-    state: tauri::State<'_, DatabaseState>,
-    query: VisionQuery,
-) -> Result<Option<VisionData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
-        }
-    };
-
-    if query.side != "LEFT" && query.side != "RIGHT" {
-        return Err(format!("Invalid side input"));
-    }
+impl VisionRepo for PgRepository<'_> {
+    async fn find_vision(&self, patient_id: i32, side: &str, value_type: &str) -> Result<Option<VisionData>, AppError> {
+        let vision_data = sqlx::query_as!(
+            VisionData,
+            r#"
+            SELECT
+                vision_id,
+                patient_id,
+                pgp_sym_decrypt(near_vision::bytea, ($1::TEXT[])[key_id]) as near_vision,
+                pgp_sym_decrypt(distant_vision::bytea, ($1::TEXT[])[key_id]) as distant_vision,
+                side,
+                value_type,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            FROM
+                vision
+            WHERE
+                patient_id = $2
+            AND
+                side = $3
+            AND
+                value_type = $4
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id,
+            side,
+            value_type
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching uncorrected vision values", e))?;
 
-    if query.value_type != "UC" && query.value_type != "BCVA" && query.value_type != "PH" {
-        return Err(format!("Invalid vision type"));
+        Ok(single(vision_data))
     }
 
-    match sqlx::query_as!(
-        VisionData,
-        r#"
-        SELECT
-            vision_id,
-            patient_id,
-            pgp_sym_decrypt(near_vision::bytea, $1) as near_vision,
-            pgp_sym_decrypt(distant_vision::bytea, $1) as distant_vision,
+    async fn find_refraction(
+        &self,
+        patient_id: i32,
+        side: &str,
+        value_type: &str,
+        vision_type: &str
+    ) -> Result<Option<RefractionData>, AppError> {
+        let refraction_data = sqlx::query_as!(
+            RefractionData,
+            r#"
+            SELECT
+                refraction_id,
+                patient_id,
+                pgp_sym_decrypt(spherical::bytea, ($1::TEXT[])[key_id]) as spherical,
+                pgp_sym_decrypt(cylindrical::bytea, ($1::TEXT[])[key_id]) as cylindrical,
+                pgp_sym_decrypt(axis::bytea, ($1::TEXT[])[key_id]) as axis,
+                side,
+                value_type,
+                vision_type,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            FROM
+                refraction
+            WHERE
+                patient_id = $2
+            AND
+                side = $3
+            AND
+                value_type = $4
+            AND
+                vision_type = $5
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id,
             side,
             value_type,
-            created_at,
-            created_by,
-            updated_at,
-            updated_by
-        FROM
-            vision
-        WHERE
-            patient_id = $2
-        AND
-            side = $3
-        AND
-            value_type = $4
-        "#,
-        &encryption_key,
-        &query.patient_id,
-        &query.side,
-        &query.value_type
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(vision_data) => {
-            if vision_data.len() == 1 {
-                Ok(Some(vision_data[0].clone()))
-            } else {
-                Ok(None)
-            }
-        }
-        Err(err) => Err(format!(
-            "Error while fetching uncorrected vision values: {}",
-            err
-        )),
+            vision_type
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching refraction data", e))?;
+
+        Ok(single(refraction_data))
+    }
+
+    async fn find_eye_measurement(&self, patient_id: i32, side: &str) -> Result<Option<EyeMeasurementData>, AppError> {
+        let eye_measurement_data = sqlx::query_as!(
+            EyeMeasurementData,
+            r#"
+            SELECT
+                measurement_id,
+                patient_id,
+                pgp_sym_decrypt(iop_at::bytea, ($1::TEXT[])[key_id]) as iop_at,
+                pgp_sym_decrypt(iop_nct::bytea, ($1::TEXT[])[key_id]) as iop_nct,
+                pgp_sym_decrypt(cct::bytea, ($1::TEXT[])[key_id]) as cct,
+                pgp_sym_decrypt(tond::bytea, ($1::TEXT[])[key_id]) as tond,
+                side,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            FROM
+                eye_measurement
+            WHERE
+                patient_id = $2
+            AND
+                side = $3
+            "#,
+            &self.keyring.sql_keys(),
+            &patient_id,
+            side
+        )
+        .fetch_all(self.pool)
+        .await
+        .map_err(|e| AppError::database("Error while fetching patient eye measurement data", e))?;
+
+        Ok(single(eye_measurement_data))
+    }
+
+    async fn upsert_vision(&self, user_id: i32, input: &VisionInput) -> Result<VisionData, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        // Current values, for the audit trail
+        let before = sqlx::query_as!(
+            VisionData,
+            r#"
+            SELECT
+                vision_id,
+                patient_id,
+                pgp_sym_decrypt(near_vision::bytea, ($1::TEXT[])[key_id]) as near_vision,
+                pgp_sym_decrypt(distant_vision::bytea, ($1::TEXT[])[key_id]) as distant_vision,
+                side,
+                value_type,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            FROM vision
+            WHERE patient_id = $2 AND side = $3 AND value_type = $4
+            FOR UPDATE
+            "#,
+            &keyring.sql_keys(),
+            &input.patient_id,
+            &input.side,
+            &input.value_type
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating vision data", e))?;
+
+        let after = sqlx::query_as!(
+            VisionData,
+            r#"
+            INSERT INTO vision (
+                patient_id,
+                near_vision,
+                distant_vision,
+                side,
+                value_type,
+                created_by,
+                updated_at,
+                updated_by,
+                key_id
+            )
+            VALUES (
+                $1,
+                pgp_sym_encrypt($2, $3),
+                pgp_sym_encrypt($4, $3),
+                $5,
+                $6,
+                $7,
+                NULL,
+                NULL,
+                $8
+            )
+            ON CONFLICT (patient_id, side, value_type)
+            DO UPDATE SET
+                near_vision = EXCLUDED.near_vision,
+                distant_vision = EXCLUDED.distant_vision,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = EXCLUDED.created_by,
+                key_id = EXCLUDED.key_id
+            RETURNING
+                vision_id,
+                patient_id,
+                pgp_sym_decrypt(near_vision::bytea, $3) as near_vision,
+                pgp_sym_decrypt(distant_vision::bytea, $3) as distant_vision,
+                side,
+                value_type,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            "#,
+            &input.patient_id,
+            &input.near_vision,
+            &keyring.current().secret,
+            &input.distant_vision,
+            &input.side,
+            &input.value_type,
+            &user_id,
+            keyring.current().id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating vision data", e))?;
+
+        let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+        record_audit(
+            &mut *tx,
+            self.keyring,
+            AuditEvent::new(user_id, action, ENTITY_VISION)
+                .patient(input.patient_id)
+                .entity_id(after.vision_id)
+                .before(before.as_ref())
+                .after(&after),
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(after)
+    }
+
+    async fn upsert_refraction(&self, user_id: i32, input: &RefractionInput) -> Result<RefractionData, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        // Current values, for the audit trail
+        let before = sqlx::query_as!(
+            RefractionData,
+            r#"
+            SELECT
+                refraction_id,
+                patient_id,
+                pgp_sym_decrypt(spherical::bytea, ($1::TEXT[])[key_id]) as spherical,
+                pgp_sym_decrypt(cylindrical::bytea, ($1::TEXT[])[key_id]) as cylindrical,
+                pgp_sym_decrypt(axis::bytea, ($1::TEXT[])[key_id]) as axis,
+                side,
+                value_type,
+                vision_type,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            FROM refraction
+            WHERE patient_id = $2 AND side = $3 AND value_type = $4 AND vision_type = $5
+            FOR UPDATE
+            "#,
+            &keyring.sql_keys(),
+            &input.patient_id,
+            &input.side,
+            &input.value_type,
+            &input.vision_type
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating refraction data", e))?;
+
+        let after = sqlx::query_as!(
+            RefractionData,
+            r#"
+            INSERT INTO refraction (
+                patient_id,
+                spherical,
+                cylindrical,
+                axis,
+                side,
+                value_type,
+                vision_type,
+                created_by,
+                updated_at,
+                updated_by,
+                key_id
+            )
+            VALUES (
+                $1,
+                pgp_sym_encrypt($2, $3),
+                pgp_sym_encrypt($4, $3),
+                pgp_sym_encrypt($5, $3),
+                $6,
+                $7,
+                $8,
+                $9,
+                NULL,
+                NULL,
+                $10
+            )
+            ON CONFLICT (patient_id, side, value_type, vision_type)
+            DO UPDATE SET
+                spherical = EXCLUDED.spherical,
+                cylindrical = EXCLUDED.cylindrical,
+                axis = EXCLUDED.axis,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = EXCLUDED.created_by,
+                key_id = EXCLUDED.key_id
+            RETURNING
+                refraction_id,
+                patient_id,
+                pgp_sym_decrypt(spherical::bytea, $3) as spherical,
+                pgp_sym_decrypt(cylindrical::bytea, $3) as cylindrical,
+                pgp_sym_decrypt(axis::bytea, $3) as axis,
+                side,
+                value_type,
+                vision_type,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            "#,
+            &input.patient_id,
+            &input.spherical,
+            &keyring.current().secret,
+            &input.cylindrical,
+            &input.axis,
+            &input.side,
+            &input.value_type,
+            &input.vision_type,
+            &user_id,
+            keyring.current().id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating refraction data", e))?;
+
+        let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+        record_audit(
+            &mut *tx,
+            self.keyring,
+            AuditEvent::new(user_id, action, ENTITY_REFRACTION)
+                .patient(input.patient_id)
+                .entity_id(after.refraction_id)
+                .before(before.as_ref())
+                .after(&after),
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(after)
+    }
+
+    async fn upsert_eye_measurement(&self, user_id: i32, input: &EyeMeasurementInput) -> Result<EyeMeasurementData, AppError> {
+        let keyring = self.keyring;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::database("Error while starting transaction", e))?;
+
+        // Current values, for the audit trail
+        let before = sqlx::query_as!(
+            EyeMeasurementData,
+            r#"
+            SELECT
+                measurement_id,
+                patient_id,
+                pgp_sym_decrypt(iop_at::bytea, ($1::TEXT[])[key_id]) as iop_at,
+                pgp_sym_decrypt(iop_nct::bytea, ($1::TEXT[])[key_id]) as iop_nct,
+                pgp_sym_decrypt(cct::bytea, ($1::TEXT[])[key_id]) as cct,
+                pgp_sym_decrypt(tond::bytea, ($1::TEXT[])[key_id]) as tond,
+                side,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            FROM eye_measurement
+            WHERE patient_id = $2 AND side = $3
+            FOR UPDATE
+            "#,
+            &keyring.sql_keys(),
+            &input.patient_id,
+            &input.side
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating patient eye measurement data", e))?;
+
+        let after = sqlx::query_as!(
+            EyeMeasurementData,
+            r#"
+            INSERT INTO eye_measurement (
+                patient_id,
+                iop_at,
+                iop_nct,
+                cct,
+                tond,
+                side,
+                created_by,
+                updated_at,
+                updated_by,
+                key_id
+            )
+            VALUES (
+                $1,
+                pgp_sym_encrypt($2, $3),
+                pgp_sym_encrypt($4, $3),
+                pgp_sym_encrypt($5, $3),
+                pgp_sym_encrypt($6, $3),
+                $7,
+                $8,
+                NULL,
+                NULL,
+                $9
+            )
+            ON CONFLICT (patient_id, side)
+            DO UPDATE SET
+                iop_at = EXCLUDED.iop_at,
+                iop_nct = EXCLUDED.iop_nct,
+                cct = EXCLUDED.cct,
+                tond = EXCLUDED.tond,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = EXCLUDED.created_by,
+                key_id = EXCLUDED.key_id
+            RETURNING
+                measurement_id,
+                patient_id,
+                pgp_sym_decrypt(iop_at::bytea, $3) as iop_at,
+                pgp_sym_decrypt(iop_nct::bytea, $3) as iop_nct,
+                pgp_sym_decrypt(cct::bytea, $3) as cct,
+                pgp_sym_decrypt(tond::bytea, $3) as tond,
+                side,
+                created_at,
+                created_by,
+                updated_at,
+                updated_by
+            "#,
+            &input.patient_id,
+            &input.iop_at,
+            &keyring.current().secret,
+            &input.iop_nct,
+            &input.cct,
+            &input.tond,
+            &input.side,
+            &user_id,
+            keyring.current().id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::database("Error while updating patient eye measurement data", e))?
+        .ok_or_else(|| AppError::not_found("Patient does not exist."))?;
+
+        let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+        record_audit(
+            &mut *tx,
+            self.keyring,
+            AuditEvent::new(user_id, action, ENTITY_EYE_MEASUREMENT)
+                .patient(input.patient_id)
+                .entity_id(after.measurement_id)
+                .before(before.as_ref())
+                .after(&after),
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::database("Error while committing transaction", e))?;
+
+        Ok(after)
     }
 }
 
-// Endpoint to get refraction data for a paritcular patient
-#[tauri::command]
-pub async fn get_refraction_data(
-    state: tauri::State<'_, DatabaseState>,
-    query: RefractionQuery,
-) -> Result<Option<RefractionData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
-        }
-    };
+// Function to keep a lookup result only when exactly one row matched
+fn single<T>(mut rows: Vec<T>) -> Option<T> {
+    if rows.len() == 1 {
+        rows.pop()
+    } else {
+        None
+    }
+}
 
-    if query.side != "LEFT" && query.side != "RIGHT" {
-        return Err(format!("Invalid side input"));
+// Function to check a side value
+fn validate_side(side: &str) -> Result<(), AppError> {
+    if side != "LEFT" && side != "RIGHT" {
+        return Err(AppError::validation("side", "Side must be LEFT or RIGHT."));
+    }
+    Ok(())
+}
+
+// Function to read a patient's uncorrected vision values for one eye
+pub async fn find_vision<R: VisionRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    query: &VisionQuery
+) -> Result<Option<VisionData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+    validate_side(&query.side)?;
+
+    if query.value_type != "UC" && query.value_type != "BCVA" && query.value_type != "PH" {
+        return Err(AppError::validation("value_type", "Invalid vision type."));
     }
 
+    let vision_data = repo.find_vision(query.patient_id, &query.side, &query.value_type).await?;
+
+    let mut event = AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_VISION).patient(query.patient_id);
+    event.entity_id = vision_data.as_ref().map(|data| data.vision_id);
+    repo.record_audit(event).await?;
+
+    Ok(vision_data)
+}
+
+// Function to read a patient's refraction values for one eye
+pub async fn find_refraction<R: VisionRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    query: &RefractionQuery
+) -> Result<Option<RefractionData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+    validate_side(&query.side)?;
+
     if query.value_type != "DL" && query.value_type != "UD" {
-        return Err(format!("Invalid refraction type"));
+        return Err(AppError::validation("value_type", "Invalid refraction type."));
     }
 
     if query.vision_type != "DV" && query.vision_type != "NV" {
-        return Err(format!("Invalid vision type"));
+        return Err(AppError::validation("vision_type", "Invalid vision type."));
     }
 
-    match sqlx::query_as!(
-        RefractionData,
-        r#"
-        SELECT 
-            refraction_id,
-            patient_id,
-            pgp_sym_decrypt(spherical::bytea, $1) as spherical,
-            pgp_sym_decrypt(cylindrical::bytea, $1) as cylindrical,
-            pgp_sym_decrypt(axis::bytea, $1) as axis,
-            side,
-            value_type,
-            vision_type,
-            created_at,
-            created_by,
-            updated_at,
-            updated_by
-        FROM
-            refraction
-        WHERE
-            patient_id = $2
-        AND
-            side = $3
-        AND
-            value_type = $4
-        AND
-            vision_type = $5
-        "#,
-        &encryption_key,
-        &query.patient_id,
-        &query.side,
-        &query.value_type,
-        &query.vision_type
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(refraction_data) => {
-            if refraction_data.len() == 1 {
-                Ok(Some(refraction_data[0].clone()))
-            } else {
-                Ok(None)
-            }
-        }
-        Err(err) => Err(format!("Error while fetching refraction data: {}", err)),
-    }
+    let refraction_data = repo
+        .find_refraction(query.patient_id, &query.side, &query.value_type, &query.vision_type)
+        .await?;
+
+    let mut event = AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_REFRACTION).patient(query.patient_id);
+    event.entity_id = refraction_data.as_ref().map(|data| data.refraction_id);
+    repo.record_audit(event).await?;
+
+    Ok(refraction_data)
+}
+
+// Function to read a patient's eye measurements for one eye
+pub async fn find_eye_measurement<R: VisionRepo + AuditRepo>(
+    repo: &R,
+    user: &AuthUser,
+    patient_id: i32,
+    side: &str
+) -> Result<Option<EyeMeasurementData>, AppError> {
+    require(user, Permission::ReadClinical)?;
+
+    let eye_measurement_data = repo.find_eye_measurement(patient_id, side).await?;
+
+    let mut event = AuditEvent::new(user.user_id, AuditAction::Read, ENTITY_EYE_MEASUREMENT).patient(patient_id);
+    event.entity_id = eye_measurement_data.as_ref().map(|data| data.measurement_id);
+    repo.record_audit(event).await?;
+
+    Ok(eye_measurement_data)
+}
+
+// Function to store a patient's vision values
+pub async fn save_vision<R: VisionRepo + PatientRepo>(repo: &R, user: &AuthUser, input: &VisionInput) -> Result<VisionData, AppError> {
+    require(user, Permission::WriteMeasurements)?;
+    reject_inactive_patient(repo, input.patient_id).await?;
+
+    repo.upsert_vision(user.user_id, input).await
+}
+
+// Function to store a patient's refraction values
+pub async fn save_refraction<R: VisionRepo + PatientRepo>(repo: &R, user: &AuthUser, input: &RefractionInput) -> Result<RefractionData, AppError> {
+    require(user, Permission::WriteMeasurements)?;
+    reject_inactive_patient(repo, input.patient_id).await?;
+
+    repo.upsert_refraction(user.user_id, input).await
+}
+
+// Function to store a patient's eye measurements
+pub async fn save_eye_measurement<R: VisionRepo + PatientRepo>(
+    repo: &R,
+    user: &AuthUser,
+    input: &EyeMeasurementInput
+) -> Result<EyeMeasurementData, AppError> {
+    require(user, Permission::WriteMeasurements)?;
+    reject_inactive_patient(repo, input.patient_id).await?;
+
+    repo.upsert_eye_measurement(user.user_id, input).await
+}
+
+// Endpoint to get uncorrected vision values for a patient
+#[tauri::command]
+pub async fn get_vision_data(
+    state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
+    query: VisionQuery,
+) -> Result<Option<VisionData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    find_vision(&PgRepository::new(pool, &config.keyring), &user, &query).await
+}
+
+// Endpoint to get refraction data for a paritcular patient
+#[tauri::command]
+pub async fn get_refraction_data(
+    state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
+    query: RefractionQuery,
+) -> Result<Option<RefractionData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    find_refraction(&PgRepository::new(pool, &config.keyring), &user, &query).await
 }
 
 // Endpoint to update vision data for a patient
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_vision_data(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
     near_vision: String,
     distant_vision: String,
     side: String,
     value_type: String,
-    updated_by: i32,
-) -> Result<String, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
-        }
-    };
+) -> Result<String, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    match sqlx::query_as!(
-        VisionData,
-        r#"
-        INSERT INTO vision (
-            patient_id,
-            near_vision,
-            distant_vision,
-            side,
-            value_type,
-            created_by,
-            updated_at,
-            updated_by
-        ) 
-        VALUES (
-            $1,
-            pgp_sym_encrypt($2, $3),
-            pgp_sym_encrypt($4, $3),
-            $5,
-            $6,
-            $7,
-            NULL,
-            NULL
-        )
-        ON CONFLICT (patient_id, side, value_type)
-        DO UPDATE SET
-            near_vision = EXCLUDED.near_vision,
-            distant_vision = EXCLUDED.distant_vision,
-            updated_at = CURRENT_TIMESTAMP,
-            updated_by = EXCLUDED.created_by
-        RETURNING
-            vision_id,
-            patient_id,
-            pgp_sym_decrypt(near_vision::bytea, $3) as near_vision,
-            pgp_sym_decrypt(distant_vision::bytea, $3) as distant_vision,
-            side,
-            value_type,
-            created_at,
-            created_by,
-            updated_at,
-            updated_by
-        "#,
-        &patient_id,
-        &near_vision,
-        &encryption_key,
-        &distant_vision,
-        &side,
-        &value_type,
-        &updated_by
-    )
-    .fetch_one(&*pool)
-    .await
-    {
-        Ok(_record) => Ok(format!("Successfully updated vision data")),
-        Err(err) => Err(format!("Error while updating vision data: {}", err)),
-    }
+    let input = VisionInput { patient_id, near_vision, distant_vision, side, value_type };
+    save_vision(&PgRepository::new(pool, &config.keyring), &user, &input).await?;
+
+    Ok("Successfully updated vision data".to_string())
 }
 
 // Endpoint to update refraction data for a patient
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_refraction_data(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
     spherical: String,
     cylindrical: String,
@@ -297,228 +761,157 @@ pub async fn update_refraction_data(
     side: String,
     value_type: String,
     vision_type: String,
-    updated_by: i32,
-) -> Result<String, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
-        }
-    };
+) -> Result<String, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    match sqlx::query_as!(
-        RefractionData,
-        r#"
-        INSERT INTO refraction (
-            patient_id,
-            spherical,
-            cylindrical,
-            axis,
-            side,
-            value_type,
-            vision_type,
-            created_by,
-            updated_at,
-            updated_by
-        ) 
-        VALUES (
-            $1,
-            pgp_sym_encrypt($2, $3),
-            pgp_sym_encrypt($4, $3),
-            pgp_sym_encrypt($5, $3),
-            $6,
-            $7,
-            $8,
-            $9,
-            NULL,
-            NULL
-        )
-        ON CONFLICT (patient_id, side, value_type, vision_type)
-        DO UPDATE SET
-            spherical = EXCLUDED.spherical,
-            cylindrical = EXCLUDED.cylindrical,
-            axis = EXCLUDED.axis,
-            updated_at = CURRENT_TIMESTAMP,
-            updated_by = EXCLUDED.created_by
-        RETURNING 
-            refraction_id, 
-            patient_id, 
-            pgp_sym_decrypt(spherical::bytea, $3) as spherical,
-            pgp_sym_decrypt(cylindrical::bytea, $3) as cylindrical,
-            pgp_sym_decrypt(axis::bytea, $3) as axis,
-            side, 
-            value_type, 
-            vision_type,
-            created_at, 
-            created_by, 
-            updated_at, 
-            updated_by
-        "#,
-        &patient_id,
-        &spherical,
-        &encryption_key,
-        &cylindrical,
-        &axis,
-        &side,
-        &value_type,
-        &vision_type,
-        &updated_by
-    )
-    .fetch_one(&*pool)
-    .await
-    {
-        Ok(_record) => Ok(format!("Successfully updated refraction data")),
-        Err(err) => Err(format!("Error while updating refraction data: {}", err)),
-    }
+    let input = RefractionInput { patient_id, spherical, cylindrical, axis, side, value_type, vision_type };
+    save_refraction(&PgRepository::new(pool, &config.keyring), &user, &input).await?;
+
+    Ok("Successfully updated refraction data".to_string())
 }
 
 // Endpoint to get eye measurement data for a patient
 #[tauri::command]
 pub async fn get_patient_eye_measurement_data(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
     side: String,
-) -> Result<Option<EyeMeasurementData>, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
-        }
-    };
+) -> Result<Option<EyeMeasurementData>, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
 
-    match sqlx::query_as!(
-        EyeMeasurementData,
-        r#"
-        SELECT 
-            measurement_id,
-            patient_id,
-            pgp_sym_decrypt(iop_at::bytea, $1) as iop_at,
-            pgp_sym_decrypt(iop_nct::bytea, $1) as iop_nct,
-            pgp_sym_decrypt(cct::bytea, $1) as cct,
-            pgp_sym_decrypt(tond::bytea, $1) as tond,
-            side,
-            created_at,
-            created_by,
-            updated_at,
-            updated_by
-        FROM
-            eye_measurement
-        WHERE
-            patient_id = $2
-        AND
-            side = $3
-        "#,
-        &encryption_key,
-        &patient_id,
-        &side
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(data) => {
-            if data.len() != 1 {
-                Ok(None)
-            } else {
-                Ok(Some(data[0].clone()))
-            }
-        }
-        Err(err) => Err(format!(
-            "Error while fetching patient eye measurement data: {}",
-            err
-        )),
-    }
+    find_eye_measurement(&PgRepository::new(pool, &config.keyring), &user, patient_id, &side).await
 }
 
 // Endpoint to update patient eye measurement data
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_patient_eye_measurement_data(
     state: tauri::State<'_, DatabaseState>,
+    config: tauri::State<'_, AppConfig>,
+    token: String,
     patient_id: i32,
     iop_at: String,
     iop_nct: String,
     cct: String,
     tond: String,
     side: String,
-    updated_by: i32,
-) -> Result<EyeMeasurementData, String> {
-    let pool = state.pool.lock().await;
-    let encryption_key = match std::env::var("ENCRYPTION_KEY") {
-        Ok(key) => key,
-        Err(_err) => {
-            return Err("Encryption key not provided.".to_string());
-        }
-    };
+) -> Result<EyeMeasurementData, AppError> {
+    let pool = &state.pool;
+    let user = authenticate(pool, &config, &token).await?;
+
+    let input = EyeMeasurementInput { patient_id, iop_at, iop_nct, cct, tond, side };
+    save_eye_measurement(&PgRepository::new(pool, &config.keyring), &user, &input).await
+}
 
-    match sqlx::query_as!(
-        EyeMeasurementData,
-        r#"
-        INSERT INTO eye_measurement (
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_repository::{test_user, MemoryRepository};
+
+    fn vision_input(patient_id: i32, near_vision: &str) -> VisionInput {
+        VisionInput {
             patient_id,
-            iop_at,
-            iop_nct,
-            cct,
-            tond,
-            side,
-            created_by,
-            updated_at,
-            updated_by
-        ) 
-        VALUES (
-            $1,
-            pgp_sym_encrypt($2, $3),
-            pgp_sym_encrypt($4, $3),
-            pgp_sym_encrypt($5, $3),
-            pgp_sym_encrypt($6, $3),
-            $7,
-            $8,
-            NULL,
-            NULL
-        )
-        ON CONFLICT (patient_id, side)
-        DO UPDATE SET
-            iop_at = EXCLUDED.iop_at,
-            iop_nct = EXCLUDED.iop_nct,
-            cct = EXCLUDED.cct,
-            tond = EXCLUDED.tond,
-            updated_at = CURRENT_TIMESTAMP,
-            updated_by = EXCLUDED.created_by
-        RETURNING 
-            measurement_id, 
-            patient_id, 
-            pgp_sym_decrypt(iop_at::bytea, $3) as iop_at,
-            pgp_sym_decrypt(iop_nct::bytea, $3) as iop_nct,
-            pgp_sym_decrypt(cct::bytea, $3) as cct,
-            pgp_sym_decrypt(tond::bytea, $3) as tond,
-            side,
-            created_at, 
-            created_by, 
-            updated_at, 
-            updated_by
-        "#,
-        &patient_id,
-        &iop_at,
-        &encryption_key,
-        &iop_nct,
-        &cct,
-        &tond,
-        &side,
-        &updated_by
-    )
-    .fetch_all(&*pool)
-    .await
-    {
-        Ok(data) => {
-            if data.len() == 0 {
-                Err(format!("Error while updating records"))
-            } else {
-                Ok(data[0].clone())
-            }
+            near_vision: near_vision.to_string(),
+            distant_vision: "6/6".to_string(),
+            side: "LEFT".to_string(),
+            value_type: "UC".to_string(),
         }
-        Err(err) => Err(format!(
-            "Error while updating patient eye measurement data: {}",
-            err
-        )),
+    }
+
+    #[tokio::test]
+    async fn vision_upsert_creates_then_updates() {
+        let repo = MemoryRepository::default();
+        let nurse = test_user(&repo, "NURSE");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+
+        let created = save_vision(&repo, &nurse, &vision_input(patient_id, "N6")).await.unwrap();
+        let updated = save_vision(&repo, &nurse, &vision_input(patient_id, "N8")).await.unwrap();
+        assert_eq!(created.vision_id, updated.vision_id);
+        assert_eq!(updated.updated_by, Some(nurse.user_id));
+
+        let query = VisionQuery { patient_id, side: "LEFT".to_string(), value_type: "UC".to_string() };
+        let found = find_vision(&repo, &nurse, &query).await.unwrap().unwrap();
+        assert_eq!(found.near_vision.as_deref(), Some("N8"));
+
+        let actions: Vec<_> = repo.audit_events().iter().map(|event| event.action).collect();
+        assert_eq!(actions, vec![AuditAction::Create, AuditAction::Update, AuditAction::Read]);
+    }
+
+    #[tokio::test]
+    async fn lookups_validate_their_query() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+
+        let query = VisionQuery { patient_id: 1, side: "BOTH".to_string(), value_type: "UC".to_string() };
+        assert_eq!(find_vision(&repo, &doctor, &query).await.unwrap_err(), AppError::validation("side", "Side must be LEFT or RIGHT."));
+
+        let query = RefractionQuery {
+            patient_id: 1,
+            side: "RIGHT".to_string(),
+            value_type: "DL".to_string(),
+            vision_type: "XX".to_string(),
+        };
+        assert_eq!(find_refraction(&repo, &doctor, &query).await.unwrap_err(), AppError::validation("vision_type", "Invalid vision type."));
+        assert!(repo.audit_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refraction_is_keyed_by_vision_type() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+        let input = RefractionInput {
+            patient_id,
+            spherical: "-1.25".to_string(),
+            cylindrical: "-0.50".to_string(),
+            axis: "90".to_string(),
+            side: "RIGHT".to_string(),
+            value_type: "DL".to_string(),
+            vision_type: "DV".to_string(),
+        };
+        save_refraction(&repo, &doctor, &input).await.unwrap();
+
+        let query = RefractionQuery {
+            patient_id,
+            side: "RIGHT".to_string(),
+            value_type: "DL".to_string(),
+            vision_type: "NV".to_string(),
+        };
+        assert!(find_refraction(&repo, &doctor, &query).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn measurements_need_an_existing_patient() {
+        let repo = MemoryRepository::default();
+        let doctor = test_user(&repo, "DOCTOR");
+        let input = EyeMeasurementInput {
+            patient_id: 99,
+            iop_at: "14".to_string(),
+            iop_nct: "15".to_string(),
+            cct: "540".to_string(),
+            tond: "0.3".to_string(),
+            side: "LEFT".to_string(),
+        };
+
+        let err = save_eye_measurement(&repo, &doctor, &input).await.unwrap_err();
+        assert_eq!(err.code(), "CONFLICT");
+
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+        let saved = save_eye_measurement(&repo, &doctor, &EyeMeasurementInput { patient_id, ..input }).await.unwrap();
+        let found = find_eye_measurement(&repo, &doctor, patient_id, "LEFT").await.unwrap().unwrap();
+        assert_eq!(found.measurement_id, saved.measurement_id);
+    }
+
+    #[tokio::test]
+    async fn admins_cannot_write_measurements() {
+        let repo = MemoryRepository::default();
+        let admin = test_user(&repo, "ADMIN");
+        let patient_id = repo.add_patient("Ada", "Lovelace");
+
+        assert_eq!(save_vision(&repo, &admin, &vision_input(patient_id, "N6")).await.unwrap_err(), AppError::forbidden());
     }
 }
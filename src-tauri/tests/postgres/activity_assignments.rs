@@ -0,0 +1,116 @@
+// src-tauri/tests/postgres/activity_assignments.rs
+
+// Dependencies
+use chrono::{DateTime, Duration, Utc};
+use ehrportal_lib::activity_assignments::{list_my_worklist, reassign_activity, Worklist};
+use ehrportal_lib::activity_workflow::{move_activity, ActivityStatus};
+use ehrportal_lib::error::AppError;
+use ehrportal_lib::patients::{add_patient_activity, list_patient_activity, list_todays_appointments, NewPatientActivity};
+use crate::harness::{new_activity, test_db};
+
+fn around_now() -> Worklist {
+    Worklist::Today { start: Utc::now() - Duration::hours(12), end: Utc::now() + Duration::hours(12) }
+}
+
+#[tokio::test]
+async fn clinicians_are_recorded_reassigned_and_audited() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let colleague = db.signed_in("DOCTOR").await;
+    let nurse = db.signed_in("NURSE").await;
+    let patient_id = db.add_patient("Nora", "Lind").await;
+    let procedure_id = db.add_procedure("Visual field test").await;
+    let activity = NewPatientActivity {
+        doctor_id: Some(doctor.user_id),
+        nurse_id: Some(nurse.user_id),
+        ..new_activity(patient_id, procedure_id)
+    };
+    add_patient_activity(&db.repo(), &doctor, &activity).await.unwrap();
+    let activity = &list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap()[0];
+    move_activity(&db.repo(), &nurse, activity.activity_id, ActivityStatus::ToBeReviewed, None).await.unwrap();
+    assert_eq!((activity.doctor_id, activity.nurse_id), (Some(doctor.user_id), Some(nurse.user_id)));
+    assert_eq!(activity.doctor_name.as_deref(), Some("Test doctor"));
+
+    let today = list_todays_appointments(&db.repo(), &doctor).await.unwrap();
+    assert_eq!(today[0].nurse_id, Some(nurse.user_id));
+    assert_eq!(list_my_worklist(&db.repo(), &nurse, around_now()).await.unwrap().len(), 1);
+    assert_eq!(list_my_worklist(&db.repo(), &doctor, Worklist::AwaitingReview).await.unwrap().len(), 1);
+
+    let not_a_doctor = reassign_activity(&db.repo(), &doctor, activity.activity_id, nurse.user_id, None).await;
+    assert!(matches!(not_a_doctor, Err(AppError::Validation(_))));
+
+    let moved = reassign_activity(&db.repo(), &doctor, activity.activity_id, colleague.user_id, None).await.unwrap();
+    assert_eq!((moved.doctor_id, moved.nurse_id), (Some(colleague.user_id), None));
+    assert!(list_my_worklist(&db.repo(), &doctor, Worklist::AwaitingReview).await.unwrap().is_empty());
+    assert!(list_my_worklist(&db.repo(), &nurse, around_now()).await.unwrap().is_empty());
+    let reviews = list_my_worklist(&db.repo(), &colleague, Worklist::AwaitingReview).await.unwrap();
+    assert_eq!(reviews[0].doctor_id, Some(colleague.user_id));
+
+    let audited = db
+        .count(
+            "SELECT COUNT(*) FROM audit_log WHERE entity = 'patient_activity' AND action = 'UPDATE' AND entity_id = $1",
+            activity.activity_id,
+        )
+        .await;
+    // The move to review and the reassignment
+    assert_eq!(audited, 2);
+
+    // Removing a user no longer removes the activities they attended
+    sqlx::query("DELETE FROM users WHERE user_id = $1").bind(nurse.user_id).execute(&db.pool).await.unwrap();
+    assert_eq!(list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn activities_closed_while_waiting_for_the_lock_are_not_reassigned() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let colleague = db.signed_in("DOCTOR").await;
+    let patient_id = db.add_patient("Otto", "Berg").await;
+    let procedure_id = db.add_procedure("Gonioscopy").await;
+    add_patient_activity(&db.repo(), &doctor, &new_activity(patient_id, procedure_id)).await.unwrap();
+    let activity_id = list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap()[0].activity_id;
+
+    // Another session holds the row and completes the activity while the reassignment waits for it
+    let mut other = db.pool.begin().await.unwrap();
+    sqlx::query("SELECT 1 FROM patient_activity WHERE activity_id = $1 FOR UPDATE")
+        .bind(activity_id)
+        .execute(&mut *other)
+        .await
+        .unwrap();
+    let repo = db.repo();
+    let (reassigned, _) = tokio::join!(reassign_activity(&repo, &doctor, activity_id, colleague.user_id, None), async {
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        sqlx::query("UPDATE patient_activity SET status = 'COMPLETED' WHERE activity_id = $1")
+            .bind(activity_id)
+            .execute(&mut *other)
+            .await
+            .unwrap();
+        other.commit().await.unwrap();
+    });
+
+    assert!(matches!(reassigned, Err(AppError::Conflict(_))));
+    let activity = &list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap()[0];
+    assert_eq!(activity.doctor_id, Some(doctor.user_id));
+}
+
+#[tokio::test]
+async fn the_day_worklist_follows_the_clinicians_midnight() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+    // Midnight at UTC+2, which is still the previous day in UTC
+    let local_midnight: DateTime<Utc> = "2026-03-10T22:00:00Z".parse().unwrap();
+    for minutes in [-30, 90] {
+        let activity = NewPatientActivity {
+            doctor_id: Some(doctor.user_id),
+            activity_time: local_midnight + Duration::minutes(minutes),
+            ..new_activity(db.add_patient("Lena", "Vogel").await, procedure_id)
+        };
+        add_patient_activity(&db.repo(), &doctor, &activity).await.unwrap();
+    }
+
+    let day = Worklist::Today { start: local_midnight, end: local_midnight + Duration::hours(24) };
+    let listed = list_my_worklist(&db.repo(), &doctor, day).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].activity_time, Some(local_midnight + Duration::minutes(90)));
+}
@@ -0,0 +1,46 @@
+// src-tauri/tests/postgres/activity_workflow.rs
+
+// Dependencies
+use ehrportal_lib::activity_workflow::{list_activity_transitions, move_activity, ActivityStatus};
+use ehrportal_lib::error::AppError;
+use ehrportal_lib::patients::{list_patient_activity, NewPatientActivity};
+use ehrportal_lib::repository::PatientRepo;
+use crate::harness::{new_activity, test_db};
+
+#[tokio::test]
+async fn transitions_are_recorded_with_encrypted_reasons() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let admin = db.signed_in("ADMIN").await;
+    let patient_id = db.add_patient("Mara", "Quinn").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+    let activity = NewPatientActivity { status: "SCHEDULED".to_string(), ..new_activity(patient_id, procedure_id) };
+    let activity_id = db.repo().create_activity(doctor.user_id, &activity).await.unwrap();
+
+    move_activity(&db.repo(), &admin, activity_id, ActivityStatus::CheckedIn, None).await.unwrap();
+    let skipped = move_activity(&db.repo(), &admin, activity_id, ActivityStatus::ToBeReviewed, None).await;
+    assert!(matches!(skipped, Err(AppError::Validation(_))));
+    let cancelled = move_activity(&db.repo(), &admin, activity_id, ActivityStatus::Cancelled, Some("Patient left"))
+        .await
+        .unwrap();
+    assert_eq!(cancelled.reason.as_deref(), Some("Patient left"));
+    assert!(cancelled.transitioned_by_name.is_some());
+
+    let transitions = list_activity_transitions(&db.repo(), &doctor, activity_id).await.unwrap();
+    let statuses: Vec<&str> = transitions.iter().map(|transition| transition.to_status.as_str()).collect();
+    assert_eq!(statuses, vec!["SCHEDULED", "CHECKED_IN", "CANCELLED"]);
+    assert_eq!(transitions[0].from_status, None);
+    assert_eq!(transitions[2].reason.as_deref(), Some("Patient left"));
+    db.assert_encrypted("activity_transitions", "reason", "transition_id", cancelled.transition_id, "Patient left")
+        .await;
+
+    let activities = list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap();
+    assert_eq!(activities[0].status.as_deref(), Some("CANCELLED"));
+    let updates = db
+        .count(
+            "SELECT COUNT(*) FROM audit_log WHERE entity = 'patient_activity' AND action = 'UPDATE' AND entity_id = $1",
+            activity_id,
+        )
+        .await;
+    assert_eq!(updates, 2);
+}
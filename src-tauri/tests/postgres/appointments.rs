@@ -0,0 +1,61 @@
+// src-tauri/tests/postgres/appointments.rs
+
+// Dependencies
+use chrono::{Duration, Utc};
+use ehrportal_lib::appointment::{list_appointments, schedule_appointment, NewAppointment};
+use crate::harness::test_db;
+
+#[tokio::test]
+async fn scheduled_appointments_are_listed_for_every_participant() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let nurse = db.signed_in("NURSE").await;
+    let starts = Utc::now() + Duration::days(1);
+
+    let created = schedule_appointment(
+        &db.repo(),
+        &doctor,
+        &NewAppointment {
+            description: "Pre-op review".to_string(),
+            appointment_time: starts,
+            appointment_duration: 45 * 60,
+            users: vec![doctor.user_id, nurse.user_id],
+            patient_id: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(created.appointment_duration, Some(45 * 60 * 1_000_000));
+    assert_eq!(created.created_by, Some(doctor.user_id));
+
+    for participant in [&doctor, &nurse] {
+        let appointments = list_appointments(&db.repo(), participant, None).await.unwrap();
+        assert_eq!(appointments.len(), 1);
+        assert_eq!(appointments[0].appointment_id, created.appointment_id);
+        assert_eq!(appointments[0].description.as_deref(), Some("Pre-op review"));
+    }
+}
+
+#[tokio::test]
+async fn unknown_participant_rolls_back_the_appointment() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+
+    let error = schedule_appointment(
+        &db.repo(),
+        &doctor,
+        &NewAppointment {
+            description: "Team huddle".to_string(),
+            appointment_time: Utc::now(),
+            appointment_duration: 15 * 60,
+            users: vec![doctor.user_id, 9999],
+            patient_id: None,
+        },
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(error.code(), "CONFLICT");
+    assert_eq!(db.count("SELECT COUNT(*) FROM appointments WHERE created_by = $1", doctor.user_id).await, 0);
+    assert!(list_appointments(&db.repo(), &doctor, None).await.unwrap().is_empty());
+}
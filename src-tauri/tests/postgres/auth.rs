@@ -0,0 +1,164 @@
+// src-tauri/tests/postgres/auth.rs
+
+// Dependencies
+use ehrportal_lib::auth::{change_password, register_user, SignupQuery};
+use ehrportal_lib::crypto::sha256_hex;
+use ehrportal_lib::mfa::{check_second_factor, check_totp_code, SecondFactor};
+use ehrportal_lib::patients::get_patient_data;
+use ehrportal_lib::permissions::authenticate;
+use ehrportal_lib::totp;
+use ehrportal_lib::users::{approve_account, deactivate_account, invite_account, InviteUserQuery};
+use tauri::Manager;
+use crate::harness::{test_db, MAX_FAILED_ATTEMPTS, TEST_PASSWORD};
+
+#[tokio::test]
+async fn signup_requires_approval_before_login() {
+    let db = test_db!();
+    let admin = db.signed_in("ADMIN").await;
+    let signup = SignupQuery {
+        first_name: "Nia".to_string(),
+        last_name: "Okafor".to_string(),
+        role: "NURSE".to_string(),
+        email: "nia.okafor@example.test".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    };
+
+    register_user(&db.repo(), &db.config.password_policy, &signup).await.unwrap();
+    let stored: String = sqlx::query_scalar("SELECT password FROM users WHERE email = $1")
+        .bind(&signup.email)
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_ne!(stored, TEST_PASSWORD);
+    assert!(bcrypt::verify(TEST_PASSWORD, &stored).unwrap());
+
+    let pending = db.login(&signup.email, TEST_PASSWORD).await.err().expect("pending accounts cannot log in");
+    assert_eq!(pending.code(), "UNAUTHORIZED");
+
+    let user_id: i32 = sqlx::query_scalar("SELECT user_id FROM users WHERE email = $1")
+        .bind(&signup.email)
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    approve_account(&db.repo(), &admin, user_id).await.unwrap();
+
+    let session = db.login("NIA.OKAFOR@example.test", TEST_PASSWORD).await.unwrap();
+    let user = authenticate(&db.pool, &db.config, &session.token).await.unwrap();
+    assert_eq!(user.user_id, user_id);
+    assert_eq!(user.role, "NURSE");
+}
+
+#[tokio::test]
+async fn duplicate_signup_is_a_conflict() {
+    let db = test_db!();
+    db.create_user("DOCTOR", "taken@example.test", "ACTIVE").await;
+    let signup = SignupQuery {
+        first_name: "Ada".to_string(),
+        last_name: "Lee".to_string(),
+        role: "DOCTOR".to_string(),
+        email: "Taken@example.test".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    };
+
+    let error = register_user(&db.repo(), &db.config.password_policy, &signup).await.unwrap_err();
+    assert_eq!(error.code(), "CONFLICT");
+}
+
+#[tokio::test]
+async fn deactivation_revokes_existing_sessions() {
+    let db = test_db!();
+    let admin = db.signed_in("ADMIN").await;
+    let doctor_id = db.create_user("DOCTOR", "doctor@example.test", "ACTIVE").await;
+    let session = db.login("doctor@example.test", TEST_PASSWORD).await.unwrap();
+    authenticate(&db.pool, &db.config, &session.token).await.unwrap();
+
+    deactivate_account(&db.repo(), &admin, doctor_id).await.unwrap();
+
+    let error = authenticate(&db.pool, &db.config, &session.token).await.unwrap_err();
+    assert_eq!(error.code(), "UNAUTHORIZED");
+    assert!(db.login("doctor@example.test", TEST_PASSWORD).await.is_err());
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_the_account() {
+    let db = test_db!();
+    let nurse_id = db.create_user("NURSE", "nurse@example.test", "ACTIVE").await;
+
+    for _ in 0..MAX_FAILED_ATTEMPTS {
+        assert!(db.login("nurse@example.test", "wrong password").await.is_err());
+    }
+    let locked = db.count("SELECT COUNT(*) FROM users WHERE user_id = $1 AND locked_until > NOW()", nurse_id).await;
+    assert_eq!(locked, 1);
+
+    // The correct password is refused while the lock lasts
+    assert!(db.login("nurse@example.test", TEST_PASSWORD).await.is_err());
+    let locked_attempts = db
+        .count("SELECT COUNT(*) FROM login_attempts WHERE user_id = $1 AND reason = 'LOCKED'", nurse_id)
+        .await;
+    assert_eq!(locked_attempts, 1);
+}
+
+#[tokio::test]
+async fn unconfirmed_totp_secrets_do_not_sign_in_and_enrollment_ignores_recovery_codes() {
+    let db = test_db!();
+    let user_id = db.create_user("DOCTOR", "totp@example.test", "ACTIVE").await;
+    let secret = totp::generate_secret();
+    let key = db.config.keyring.current();
+    sqlx::query("INSERT INTO user_totp (user_id, secret, enabled, key_id) VALUES ($1, pgp_sym_encrypt($2, $3), FALSE, $4)")
+        .bind(user_id)
+        .bind(&secret)
+        .bind(&key.secret)
+        .bind(key.id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO user_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(sha256_hex(b"abcde12345"))
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let now = 1_700_000_000;
+    let code = totp::code_at(&secret, now).unwrap();
+    assert!(!check_totp_code(&db.pool, &db.config.keyring, user_id, "abcde-12345", now, false).await.unwrap());
+    let unused = db.count("SELECT COUNT(*) FROM user_recovery_codes WHERE user_id = $1 AND used_at IS NULL", user_id).await;
+    assert_eq!(unused, 1);
+
+    // A pending secret only confirms enrollment
+    let pending = check_second_factor(&db.pool, &db.config.keyring, user_id, &code, now).await.unwrap();
+    assert_ne!(pending, Some(SecondFactor::Totp));
+    assert!(check_totp_code(&db.pool, &db.config.keyring, user_id, &code, now, false).await.unwrap());
+
+    sqlx::query("UPDATE user_totp SET enabled = TRUE WHERE user_id = $1").bind(user_id).execute(&db.pool).await.unwrap();
+    let later = now + 30;
+    let code = totp::code_at(&secret, later).unwrap();
+    let enabled = check_second_factor(&db.pool, &db.config.keyring, user_id, &code, later).await.unwrap();
+    assert_eq!(enabled, Some(SecondFactor::Totp));
+}
+
+#[tokio::test]
+async fn invited_users_change_the_temporary_password_before_reading_records() {
+    let db = test_db!();
+    let admin = db.signed_in("ADMIN").await;
+    let patient_id = db.add_patient("Rosa", "Marin").await;
+    let invite = InviteUserQuery {
+        first_name: "Dana".to_string(),
+        last_name: "Okafor".to_string(),
+        email: "dana.okafor@example.test".to_string(),
+        role: "DOCTOR".to_string(),
+    };
+    let credentials = invite_account(&db.repo(), &admin, &invite).await.unwrap();
+
+    let session = db.login(&invite.email, &credentials.temporary_password).await.unwrap();
+    assert!(session.user.must_reset_password);
+    let app = db.app();
+    let blocked = get_patient_data(app.state(), app.state(), session.token.clone(), patient_id).await;
+    assert_eq!(blocked.unwrap_err().code(), "PASSWORD_CHANGE_REQUIRED");
+
+    change_password(app.state(), app.state(), session.token.clone(), credentials.temporary_password, "Battery-Staple-77".to_string())
+        .await
+        .unwrap();
+    let patient = get_patient_data(app.state(), app.state(), session.token, patient_id).await.unwrap();
+    assert_eq!(patient.first_name, "Rosa");
+}
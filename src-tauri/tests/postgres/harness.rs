@@ -0,0 +1,270 @@
+// src-tauri/tests/postgres/harness.rs
+
+// Dependencies
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use chrono::{NaiveDate, Utc};
+use ehrportal_lib::auth::{password_login, AuthUser, LoginOutcome, LoginResponse};
+use ehrportal_lib::config::AppConfig;
+use ehrportal_lib::crypto::random_token;
+use ehrportal_lib::db::{connect_to_database, DatabaseState, PoolSettings};
+use ehrportal_lib::error::AppError;
+use ehrportal_lib::migrations::run_migrations;
+use ehrportal_lib::patients::NewPatientActivity;
+use ehrportal_lib::permissions::authenticate;
+use ehrportal_lib::repository::PgRepository;
+use sqlx::{Connection, PgConnection, Pool, Postgres, Row};
+use tauri::test::{mock_app, MockRuntime};
+use tauri::Manager;
+
+pub const TEST_ENCRYPTION_KEY: &str = "integration-test-encryption-key";
+pub const TEST_PASSWORD: &str = "Correct-Horse-42";
+pub const MAX_FAILED_ATTEMPTS: i32 = 3;
+
+static DATABASE_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+// Function to build an activity in progress now, for tests to override what they care about
+pub fn new_activity(patient_id: i32, procedure_id: i32) -> NewPatientActivity {
+    NewPatientActivity {
+        patient_id,
+        procedure_id,
+        status: "IN_PROGRESS".to_string(),
+        doctors_note: "Suspected early glaucoma".to_string(),
+        patient_complaint: "Blurred vision at night".to_string(),
+        activity_time: Utc::now(),
+        ..Default::default()
+    }
+}
+
+// Returns a fresh TestDb, or ends the test early when TEST_DATABASE_URL is not set.
+// test_db!(empty) skips the migrations.
+macro_rules! test_db {
+    () => {
+        match $crate::harness::TestDb::create().await {
+            Some(db) => db,
+            None => return,
+        }
+    };
+    (empty) => {
+        match $crate::harness::TestDb::create_empty().await {
+            Some(db) => db,
+            None => return,
+        }
+    };
+}
+pub(crate) use test_db;
+
+// A database created for one test from the migrations, dropped when the test ends
+pub struct TestDb {
+    pub pool: Pool<Postgres>,
+    pub config: AppConfig,
+    settings: HashMap<String, String>,
+    admin_url: String,
+    name: String,
+}
+
+impl TestDb {
+    pub async fn create() -> Option<TestDb> {
+        let db = TestDb::create_empty().await?;
+        run_migrations(&db.pool).await.expect("apply migrations");
+        Some(db)
+    }
+
+    // A database with no tables yet, for testing the migrations themselves
+    pub async fn create_empty() -> Option<TestDb> {
+        let admin_url = match std::env::var("TEST_DATABASE_URL") {
+            Ok(url) if !url.trim().is_empty() => url,
+            _ => {
+                eprintln!("TEST_DATABASE_URL is not set, skipping Postgres integration test");
+                return None;
+            }
+        };
+
+        let name = format!(
+            "ehr_test_{}_{}_{}",
+            std::process::id(),
+            DATABASE_COUNTER.fetch_add(1, Ordering::SeqCst),
+            random_token(4)
+        );
+        let mut admin = PgConnection::connect(&admin_url).await.expect("connect to TEST_DATABASE_URL");
+        sqlx::query(&format!("CREATE DATABASE {}", name))
+            .execute(&mut admin)
+            .await
+            .expect("create test database");
+        admin.close().await.ok();
+
+        let database_url = with_database(&admin_url, &name);
+        let settings: HashMap<String, String> = [
+            ("DATABASE_URL", database_url.as_str()),
+            ("ENCRYPTION_KEY", TEST_ENCRYPTION_KEY),
+            ("JWT_SECRET", "integration-test-jwt-secret"),
+            ("SEARCH_INDEX_KEY", "integration-test-search-key"),
+            ("AUDIT_HASH_KEY", "integration-test-audit-key"),
+            ("LOGIN_MAX_FAILED_ATTEMPTS", "3"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+        let config = AppConfig::from_settings(&settings).expect("test configuration");
+
+        let pool_settings = PoolSettings { max_connections: 4, min_connections: 0, ..PoolSettings::default() };
+        let pool = connect_to_database(&database_url, &pool_settings).await.expect("connect to test database");
+
+        Some(TestDb { pool, config, settings, admin_url, name })
+    }
+
+    pub fn repo(&self) -> PgRepository<'_> {
+        PgRepository::new(&self.pool, &self.config.keyring)
+    }
+
+    // Configuration with some settings replaced, e.g. a new ENCRYPTION_KEY for a rotation
+    pub fn config_with(&self, overrides: &[(&str, &str)]) -> AppConfig {
+        let mut settings = self.settings.clone();
+        for (key, value) in overrides {
+            settings.insert(key.to_string(), value.to_string());
+        }
+        AppConfig::from_settings(&settings).expect("test configuration")
+    }
+
+    // App with the managed state the commands expect, for calling them directly
+    pub fn app(&self) -> tauri::App<MockRuntime> {
+        let app = mock_app();
+        app.manage(DatabaseState { pool: self.pool.clone() });
+        app.manage(AppConfig::from_settings(&self.settings).expect("test configuration"));
+        app
+    }
+
+    // Inserts an account straight into users, bypassing signup and approval
+    pub async fn create_user(&self, role: &str, email: &str, status: &str) -> i32 {
+        let password_hash = bcrypt::hash(TEST_PASSWORD, 4).expect("hash password");
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO users (role, first_name, last_name, email, password, status)
+            VALUES ($1, 'Test', $2, $3, $4, $5)
+            RETURNING user_id
+            "#,
+        )
+        .bind(role)
+        .bind(role.to_lowercase())
+        .bind(email)
+        .bind(password_hash)
+        .bind(status)
+        .fetch_one(&self.pool)
+        .await
+        .expect("insert user")
+    }
+
+    pub async fn login(&self, email: &str, password: &str) -> Result<LoginResponse, AppError> {
+        match password_login(&self.pool, &self.config, email, password).await? {
+            LoginOutcome::Authenticated(response) => Ok(response),
+            _ => panic!("unexpected second factor step for {}", email),
+        }
+    }
+
+    // Creates an active user and signs it in, returning the session as commands see it
+    pub async fn signed_in(&self, role: &str) -> AuthUser {
+        let email = format!("{}.{}@example.test", role.to_lowercase(), random_token(4));
+        self.create_user(role, &email, "ACTIVE").await;
+        let response = self.login(&email, TEST_PASSWORD).await.expect("login");
+        authenticate(&self.pool, &self.config, &response.token).await.expect("authenticate")
+    }
+
+    pub async fn add_patient(&self, first_name: &str, last_name: &str) -> i32 {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO patients (mr_number, first_name, last_name, date_of_birth, gender)
+            VALUES ($1, $2, $3, $4, 'OTHERS')
+            RETURNING patient_id
+            "#,
+        )
+        .bind(format!("MR-{}", random_token(4)))
+        .bind(first_name)
+        .bind(last_name)
+        .bind(NaiveDate::from_ymd_opt(1980, 1, 1))
+        .fetch_one(&self.pool)
+        .await
+        .expect("insert patient")
+    }
+
+    pub async fn add_procedure(&self, name: &str) -> i32 {
+        let key = self.config.keyring.current();
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO procedures (procedure_name, description, key_id)
+            VALUES (pgp_sym_encrypt($1, $2), pgp_sym_encrypt('', $2), $3)
+            RETURNING procedure_id
+            "#,
+        )
+        .bind(name)
+        .bind(&key.secret)
+        .bind(key.id)
+        .fetch_one(&self.pool)
+        .await
+        .expect("insert procedure")
+    }
+
+    pub async fn count(&self, sql: &str, id: i32) -> i64 {
+        sqlx::query_scalar(sql).bind(id).fetch_one(&self.pool).await.expect("count rows")
+    }
+
+    // Checks that a column holds ciphertext that decrypts to the plaintext with the current key
+    pub async fn assert_encrypted(&self, table: &str, column: &str, id_column: &str, id: i32, plaintext: &str) {
+        let row = sqlx::query(&format!(
+            "SELECT {column} AS raw, pgp_sym_decrypt({column}::bytea, $2) AS decrypted FROM {table} WHERE {id_column} = $1"
+        ))
+        .bind(id)
+        .bind(&self.config.keyring.current().secret)
+        .fetch_one(&self.pool)
+        .await
+        .expect("read raw column");
+
+        let raw: Vec<u8> = row.get("raw");
+        let decrypted: String = row.get("decrypted");
+        assert!(
+            !raw.windows(plaintext.len()).any(|window| window == plaintext.as_bytes()),
+            "{}.{} stores plaintext",
+            table,
+            column
+        );
+        assert_eq!(decrypted, plaintext, "{}.{} does not decrypt to the stored value", table, column);
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let admin_url = self.admin_url.clone();
+        let name = self.name.clone();
+        // Tests run inside a runtime that cannot block, so the drop runs on its own thread
+        let dropped = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+            runtime.block_on(async {
+                let mut admin = PgConnection::connect(&admin_url).await?;
+                sqlx::query(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name)).execute(&mut admin).await?;
+                admin.close().await
+            })
+            .map_err(std::io::Error::other)
+        })
+        .join();
+
+        if !matches!(dropped, Ok(Ok(()))) {
+            eprintln!("Could not drop test database {}", self.name);
+        }
+    }
+}
+
+// Replaces the database name in a connection URL, keeping any query parameters
+fn with_database(url: &str, name: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (url, None),
+    };
+    let prefix = match base.rfind('/') {
+        Some(index) if index > base.find("//").map_or(0, |start| start + 1) => &base[..index],
+        _ => base,
+    };
+
+    match query {
+        Some(query) => format!("{}/{}?{}", prefix, name, query),
+        None => format!("{}/{}", prefix, name),
+    }
+}
@@ -0,0 +1,103 @@
+// src-tauri/tests/postgres/key_rotation.rs
+
+// Dependencies
+use chrono::NaiveDate;
+use ehrportal_lib::key_rotation::{lock_rotation, rotate_encryption_keys, run_rotation, ENCRYPTED_TABLES};
+use ehrportal_lib::patients::{add_patient_activity, register_patient, PatientInput};
+use ehrportal_lib::vision::{save_vision, VisionInput};
+use crate::harness::{new_activity, test_db, TEST_ENCRYPTION_KEY};
+
+const SECOND_KEY: &str = "integration-test-encryption-key-2";
+
+fn patient_input(first_name: &str, phone: &str) -> PatientInput {
+    PatientInput {
+        first_name: first_name.to_string(),
+        last_name: "Okafor".to_string(),
+        date_of_birth: NaiveDate::from_ymd_opt(1988, 2, 14).unwrap(),
+        gender: "FEMALE".to_string(),
+        phone: Some(phone.to_string()),
+        email: Some(format!("{}@example.test", first_name.to_lowercase())),
+        address: Some("12 Marina Road".to_string()),
+        emergency_contact_name: None,
+        emergency_contact_phone: None,
+        preferred_language: None,
+        national_id: None,
+    }
+}
+
+#[tokio::test]
+async fn an_interrupted_rotation_resumes_and_moves_every_column_to_the_new_key() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let pattern = &db.config.mr_number_pattern;
+    let procedure_id = db.add_procedure("Fundoscopy").await;
+    let mut patient_ids = vec![];
+    for (name, phone) in [("Adaeze", "+234 801 000 1111"), ("Chioma", "+234 801 000 2222"), ("Ngozi", "+234 801 000 3333")] {
+        let patient = register_patient(&db.repo(), &doctor, pattern, &patient_input(name, phone)).await.unwrap();
+        add_patient_activity(&db.repo(), &doctor, &new_activity(patient.patient_id, procedure_id)).await.unwrap();
+        let vision = VisionInput {
+            patient_id: patient.patient_id,
+            near_vision: "N6".to_string(),
+            distant_vision: "6/9".to_string(),
+            side: "LEFT".to_string(),
+            value_type: "UC".to_string(),
+        };
+        save_vision(&db.repo(), &doctor, &vision).await.unwrap();
+        patient_ids.push(patient.patient_id);
+    }
+
+    let retired = format!("1:{}", TEST_ENCRYPTION_KEY);
+    let rotated = db.config_with(&[("ENCRYPTION_KEY", SECOND_KEY), ("ENCRYPTION_KEY_ID", "2"), ("ENCRYPTION_RETIRED_KEYS", &retired)]);
+
+    // Fail the second batch of patients, after the first one has committed its cursor
+    sqlx::raw_sql(
+        r#"
+        CREATE FUNCTION interrupt_rotation() RETURNS trigger AS $$
+        BEGIN
+            IF OLD.last_id > 0 THEN
+                RAISE EXCEPTION 'rotation interrupted';
+            END IF;
+            RETURN NEW;
+        END
+        $$ LANGUAGE plpgsql;
+        CREATE TRIGGER interrupt_rotation BEFORE UPDATE OF last_id ON key_rotation_progress
+        FOR EACH ROW WHEN (NEW.last_id <> OLD.last_id) EXECUTE FUNCTION interrupt_rotation();
+        "#,
+    )
+    .execute(&db.pool)
+    .await
+    .unwrap();
+
+    let held = lock_rotation(&db.pool).await.unwrap();
+    let error = rotate_encryption_keys(&db.pool, &rotated.keyring, 1).await.unwrap_err();
+    assert_eq!(error.code(), "CONFLICT");
+    assert!(run_rotation(held, &rotated.keyring, 1).await.is_err());
+
+    let cursor = db.count("SELECT last_id FROM key_rotation_progress WHERE table_name = 'patients' AND target_key_id = $1", 2).await;
+    assert_eq!(cursor, i64::from(patient_ids[0]));
+    assert_eq!(db.count("SELECT COUNT(*) FROM patients WHERE key_id = $1", 2).await, 1);
+
+    sqlx::raw_sql("DROP TRIGGER interrupt_rotation ON key_rotation_progress").execute(&db.pool).await.unwrap();
+    let summary = rotate_encryption_keys(&db.pool, &rotated.keyring, 1).await.unwrap();
+    let patients = summary.iter().find(|table| table.table_name == "patients").unwrap();
+    assert_eq!(patients.rows_rotated, patient_ids.len() as i64 - 1);
+
+    for table in ENCRYPTED_TABLES {
+        let stale = db.count(&format!("SELECT COUNT(*) FROM {} WHERE key_id <> $1", table.table), 2).await;
+        assert_eq!(stale, 0, "{} still has rows on key 1", table.table);
+        for column in table.columns {
+            let sql = format!(
+                "SELECT COUNT(pgp_sym_decrypt({column}, $1)) FROM {table} WHERE {column} IS NOT NULL",
+                column = column,
+                table = table.table
+            );
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(SECOND_KEY)
+                .fetch_one(&db.pool)
+                .await
+                .unwrap_or_else(|err| panic!("{}.{} does not decrypt with key 2: {}", table.table, column, err));
+        }
+    }
+    let encrypted_phones = db.count("SELECT COUNT(*) FROM patients WHERE phone IS NOT NULL AND key_id = $1", 2).await;
+    assert_eq!(encrypted_phones, patient_ids.len() as i64);
+}
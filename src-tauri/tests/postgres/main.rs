@@ -0,0 +1,28 @@
+// src-tauri/tests/postgres/main.rs
+//
+// End-to-end tests that run the service functions against a real Postgres with pgcrypto.
+//
+// Set TEST_DATABASE_URL to a server where the user may create databases, e.g.
+// postgres://postgres@localhost:5432/postgres. Each test creates its own database from the
+// migrations and drops it afterwards, so the database named in the URL is never written to.
+// Without TEST_DATABASE_URL every test returns early. scripts/test-postgres.sh starts a
+// throwaway server and runs these tests against it.
+
+mod harness;
+
+mod activity_assignments;
+mod activity_workflow;
+mod appointments;
+mod auth;
+mod key_rotation;
+mod medical_history;
+mod migrations;
+mod messaging;
+mod patient_merge;
+mod patient_photos;
+mod patient_search;
+mod patient_timeline;
+mod patients;
+mod procedure_catalog;
+mod procedure_comments;
+mod vision;
@@ -0,0 +1,57 @@
+// src-tauri/tests/postgres/medical_history.rs
+
+// Dependencies
+use chrono::NaiveDate;
+use ehrportal_lib::medical_history::{
+    add_history_entry, delete_history_entry, edit_history_entry, find_medical_history, list_medical_history_changes,
+    HistoryDetails, HistoryKind, Medication
+};
+use crate::harness::test_db;
+
+fn metformin(dose: &str) -> HistoryDetails {
+    HistoryDetails::Medication(Medication {
+        name: "Metformin".to_string(),
+        dose: Some(dose.to_string()),
+        frequency: Some("Twice daily".to_string()),
+        start_date: NaiveDate::from_ymd_opt(2023, 1, 10),
+        stop_date: None,
+        notes: None,
+    })
+}
+
+#[tokio::test]
+async fn entries_are_encrypted_and_every_version_is_kept() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    let doctor = db.signed_in("DOCTOR").await;
+    let patient_id = db.add_patient("Grace", "Hopper").await;
+
+    let added = add_history_entry(&db.repo(), &nurse, patient_id, &metformin("500 mg")).await.unwrap();
+    let plaintext = serde_json::to_string(&added.details).unwrap();
+    db.assert_encrypted("medical_history_entries", "details", "entry_id", added.entry_id, &plaintext).await;
+    assert!(added.created_by_name.is_some());
+
+    let updated = edit_history_entry(&db.repo(), &doctor, added.entry_id, &metformin("1000 mg")).await.unwrap();
+    assert_eq!(updated.details, metformin("1000 mg"));
+    assert_eq!(updated.updated_by, Some(doctor.user_id));
+
+    let history = find_medical_history(&db.repo(), &doctor, patient_id).await.unwrap();
+    assert_eq!(history.medications, vec![updated]);
+
+    let error = delete_history_entry(&db.repo(), &doctor, added.entry_id, HistoryKind::Allergy).await.unwrap_err();
+    assert_eq!(error.code(), "NOT_FOUND");
+    delete_history_entry(&db.repo(), &doctor, added.entry_id, HistoryKind::Medication).await.unwrap();
+    assert!(find_medical_history(&db.repo(), &doctor, patient_id).await.unwrap().medications.is_empty());
+
+    let changes = list_medical_history_changes(&db.repo(), &doctor, patient_id).await.unwrap();
+    let actions: Vec<&str> = changes.iter().map(|change| change.action.as_str()).collect();
+    assert_eq!(actions, vec!["DELETE", "UPDATE", "CREATE"]);
+    assert_eq!(changes[2].changed_by, Some(nurse.user_id));
+    assert_eq!(changes[2].details, metformin("500 mg"));
+    assert!(changes[2].changed_by_name.is_some());
+
+    let audited = db
+        .count("SELECT COUNT(*) FROM audit_log WHERE entity = 'medical_history' AND action <> 'READ' AND patient_id = $1", patient_id)
+        .await;
+    assert_eq!(audited, 3);
+}
@@ -0,0 +1,54 @@
+// src-tauri/tests/postgres/messaging.rs
+
+// Dependencies
+use ehrportal_lib::messaging::{
+    list_conversation_messages, list_conversations, list_unread_messages, open_conversation, post_message
+};
+use crate::harness::test_db;
+
+#[tokio::test]
+async fn messages_are_delivered_to_the_other_participant() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let nurse = db.signed_in("NURSE").await;
+
+    // Either side opening the conversation finds the same row
+    let conversation = open_conversation(&db.repo(), &nurse, doctor.user_id).await.unwrap();
+    let same = open_conversation(&db.repo(), &doctor, nurse.user_id).await.unwrap();
+    assert_eq!(conversation.conversation_id, same.conversation_id);
+
+    post_message(&db.repo(), &doctor, conversation.conversation_id, "Room 3 is ready").await.unwrap();
+    let last = post_message(&db.repo(), &doctor, conversation.conversation_id, "Bring the chart").await.unwrap();
+
+    let unread = list_unread_messages(&db.repo(), &nurse).await.unwrap();
+    assert_eq!(unread.len(), 2);
+    assert!(unread.iter().all(|message| message.recipient_id == Some(nurse.user_id)));
+    assert!(list_unread_messages(&db.repo(), &doctor).await.unwrap().is_empty());
+
+    let messages = list_conversation_messages(&db.repo(), &nurse, conversation.conversation_id).await.unwrap();
+    let contents: Vec<_> = messages.iter().filter_map(|message| message.content.as_deref()).collect();
+    assert_eq!(contents, vec!["Bring the chart", "Room 3 is ready"]);
+
+    let conversations = list_conversations(&db.repo(), &nurse).await.unwrap();
+    assert_eq!(conversations.len(), 1);
+    assert_eq!(conversations[0].last_message, Some(last.message_id));
+}
+
+#[tokio::test]
+async fn outsiders_cannot_read_or_post() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let nurse = db.signed_in("NURSE").await;
+    let outsider = db.signed_in("NURSE").await;
+    let conversation = open_conversation(&db.repo(), &doctor, nurse.user_id).await.unwrap();
+
+    let read = list_conversation_messages(&db.repo(), &outsider, conversation.conversation_id).await.unwrap_err();
+    let post = post_message(&db.repo(), &outsider, conversation.conversation_id, "Hello").await.unwrap_err();
+
+    assert_eq!(read.code(), "FORBIDDEN");
+    assert_eq!(post.code(), "FORBIDDEN");
+    assert_eq!(
+        db.count("SELECT COUNT(*) FROM messages WHERE conversation_id = $1", conversation.conversation_id).await,
+        0
+    );
+}
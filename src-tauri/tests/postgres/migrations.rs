@@ -0,0 +1,50 @@
+// src-tauri/tests/postgres/migrations.rs
+
+// Dependencies
+use ehrportal_lib::migrations::{run_migrations, MIGRATIONS};
+use crate::harness::test_db;
+
+#[tokio::test]
+async fn a_second_run_applies_nothing() {
+    let db = test_db!();
+
+    assert_eq!(run_migrations(&db.pool).await.unwrap(), Vec::<i64>::new());
+    assert_eq!(db.count("SELECT COUNT(*) FROM schema_migrations WHERE version <= $1", i32::MAX).await, MIGRATIONS.len() as i64);
+}
+
+#[tokio::test]
+async fn an_edited_migration_is_refused() {
+    let db = test_db!();
+    sqlx::query("UPDATE schema_migrations SET checksum = 'edited' WHERE version = 1").execute(&db.pool).await.unwrap();
+
+    let error = run_migrations(&db.pool).await.unwrap_err();
+    assert!(error.contains("Checksum mismatch for applied migration 1"), "{}", error);
+}
+
+#[tokio::test]
+async fn a_database_from_a_newer_build_is_refused() {
+    let db = test_db!();
+    let newer = MIGRATIONS.last().unwrap().version + 1;
+    sqlx::query("INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, 'from_a_newer_build', '')")
+        .bind(newer)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let error = run_migrations(&db.pool).await.unwrap_err();
+    assert!(error.contains(&format!("Database schema version {} is newer", newer)), "{}", error);
+}
+
+#[tokio::test]
+async fn concurrent_runs_take_turns() {
+    let db = test_db!(empty);
+
+    // Without the lock both would create the same tables and one would fail
+    let (first, second) = tokio::join!(run_migrations(&db.pool), run_migrations(&db.pool));
+    let (first, second) = (first.unwrap(), second.unwrap());
+
+    let mut applied = [first, second];
+    applied.sort_by_key(Vec::len);
+    assert!(applied[0].is_empty());
+    assert_eq!(applied[1], MIGRATIONS.iter().map(|migration| migration.version).collect::<Vec<_>>());
+}
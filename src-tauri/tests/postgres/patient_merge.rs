@@ -0,0 +1,87 @@
+// src-tauri/tests/postgres/patient_merge.rs
+
+// Dependencies
+use ehrportal_lib::patient_merge::{merge_patient_records, undo_patient_merge_record, MergeRequest};
+use ehrportal_lib::patients::{add_patient_activity, list_patients};
+use ehrportal_lib::vision::{find_vision, save_vision, VisionInput, VisionQuery};
+use crate::harness::{new_activity, test_db};
+
+fn vision_input(patient_id: i32, near_vision: &str) -> VisionInput {
+    VisionInput {
+        patient_id,
+        near_vision: near_vision.to_string(),
+        distant_vision: "6/6".to_string(),
+        side: "RIGHT".to_string(),
+        value_type: "UC".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn merge_reparents_rows_and_can_be_undone() {
+    let db = test_db!();
+    let admin = db.signed_in("ADMIN").await;
+    let doctor = db.signed_in("DOCTOR").await;
+    let surviving = db.add_patient("Ines", "Duarte").await;
+    let merged = db.add_patient("Ines", "Duart").await;
+    let procedure_id = db.add_procedure("Slit lamp").await;
+    add_patient_activity(&db.repo(), &doctor, &new_activity(merged, procedure_id)).await.unwrap();
+    let older = save_vision(&db.repo(), &doctor, &vision_input(surviving, "N10")).await.unwrap();
+    let newer = save_vision(&db.repo(), &doctor, &vision_input(merged, "N6")).await.unwrap();
+
+    let request = MergeRequest { surviving_patient_id: surviving, merged_patient_id: merged };
+    let merge = merge_patient_records(&db.repo(), &admin, &request).await.unwrap();
+
+    let owner = "SELECT COUNT(*) FROM patient_activity WHERE patient_id = $1";
+    assert_eq!(db.count(owner, surviving).await, 1);
+    let query = VisionQuery { patient_id: surviving, side: "RIGHT".to_string(), value_type: "UC".to_string() };
+    let kept = find_vision(&db.repo(), &doctor, &query).await.unwrap().unwrap();
+    assert_eq!((kept.vision_id, kept.near_vision.as_deref()), (newer.vision_id, Some("N6")));
+    assert_eq!(db.count("SELECT COUNT(*) FROM vision WHERE patient_id = $1", merged).await, 1);
+    assert_eq!(db.count("SELECT COUNT(*) FROM patients WHERE merged_into = $1", surviving).await, 1);
+    assert_eq!(db.count("SELECT COUNT(*) FROM patient_merge_rows WHERE merge_id = $1", merge.merge_id).await, 3);
+    let listed: Vec<i32> = list_patients(&db.repo(), &admin).await.unwrap().iter().map(|patient| patient.patient_id).collect();
+    assert!(!listed.contains(&merged));
+    let late = add_patient_activity(&db.repo(), &doctor, &new_activity(merged, procedure_id)).await;
+    assert_eq!(late.unwrap_err().code(), "VALIDATION");
+    assert_eq!(save_vision(&db.repo(), &doctor, &vision_input(merged, "N8")).await.unwrap_err().code(), "VALIDATION");
+
+    undo_patient_merge_record(&db.repo(), &admin, merge.merge_id).await.unwrap();
+
+    assert_eq!(db.count(owner, merged).await, 1);
+    let restored = find_vision(&db.repo(), &doctor, &query).await.unwrap().unwrap();
+    assert_eq!(restored.vision_id, older.vision_id);
+    let reactivated = "SELECT COUNT(*) FROM patients WHERE patient_id = $1 AND active AND merged_into IS NULL";
+    assert_eq!(db.count(reactivated, merged).await, 1);
+    let audited = db
+        .count("SELECT COUNT(*) FROM audit_log WHERE entity = 'patient_merge' AND entity_id = $1", merge.merge_id)
+        .await;
+    assert_eq!(audited, 4);
+}
+
+#[tokio::test]
+async fn chained_merges_are_undone_newest_first() {
+    let db = test_db!();
+    let admin = db.signed_in("ADMIN").await;
+    let doctor = db.signed_in("DOCTOR").await;
+    let first = db.add_patient("Rui", "Barros").await;
+    let second = db.add_patient("Rui", "Baros").await;
+    let third = db.add_patient("Rui", "Barroso").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+    add_patient_activity(&db.repo(), &doctor, &new_activity(first, procedure_id)).await.unwrap();
+
+    let request = MergeRequest { surviving_patient_id: second, merged_patient_id: first };
+    let first_merge = merge_patient_records(&db.repo(), &admin, &request).await.unwrap();
+    let request = MergeRequest { surviving_patient_id: third, merged_patient_id: second };
+    let second_merge = merge_patient_records(&db.repo(), &admin, &request).await.unwrap();
+
+    let error = undo_patient_merge_record(&db.repo(), &admin, first_merge.merge_id).await.unwrap_err();
+    assert_eq!(error.code(), "CONFLICT");
+    let owner = "SELECT COUNT(*) FROM patient_activity WHERE patient_id = $1";
+    assert_eq!(db.count(owner, third).await, 1);
+    let open = "SELECT COUNT(*) FROM patient_merges WHERE merge_id = $1 AND undone_at IS NULL";
+    assert_eq!(db.count(open, first_merge.merge_id).await, 1);
+
+    undo_patient_merge_record(&db.repo(), &admin, second_merge.merge_id).await.unwrap();
+    undo_patient_merge_record(&db.repo(), &admin, first_merge.merge_id).await.unwrap();
+    assert_eq!(db.count(owner, first).await, 1);
+}
@@ -0,0 +1,51 @@
+// src-tauri/tests/postgres/patient_photos.rs
+
+// Dependencies
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use ehrportal_lib::audit::hash_value;
+use ehrportal_lib::crypto::sha256_hex;
+use ehrportal_lib::patient_photos::{find_patient_photo, list_patient_thumbnails, save_patient_photo};
+use ehrportal_lib::patients::{find_patient, list_patients};
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, Rgba, RgbaImage};
+use crate::harness::test_db;
+
+#[tokio::test]
+async fn photos_are_stored_encrypted_with_a_thumbnail_for_lists() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    let patient_id = db.add_patient("Grace", "Hopper").await;
+    let mut png = vec![];
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(1600, 1200, Rgba([30, 90, 160, 255])))
+        .write_with_encoder(PngEncoder::new(&mut png))
+        .unwrap();
+
+    let thumbnail = save_patient_photo(&db.repo(), &nurse, patient_id, &STANDARD.encode(&png)).await.unwrap();
+
+    let photo = find_patient_photo(&db.repo(), &nurse, patient_id).await.unwrap().unwrap();
+    assert!(photo.data_url.starts_with("data:image/jpeg;base64,"));
+    assert!(photo.data_url.len() > thumbnail.data_url.len());
+    db.assert_encrypted("patients", "patient_photo", "patient_id", patient_id, &photo.data_url).await;
+    db.assert_encrypted("patients", "patient_photo_thumbnail", "patient_id", patient_id, &thumbnail.data_url).await;
+
+    let listed = list_patients(&db.repo(), &nurse).await.unwrap();
+    assert_eq!(listed[0].patient_photo.as_deref(), Some(thumbnail.data_url.as_str()));
+    let found = find_patient(&db.repo(), &nurse, patient_id).await.unwrap();
+    assert_eq!(found.patient_photo.as_deref(), Some(photo.data_url.as_str()));
+    let thumbnails = list_patient_thumbnails(&db.repo(), &nurse, &[patient_id]).await.unwrap();
+    assert_eq!(thumbnails, vec![thumbnail]);
+
+    let updates = db
+        .count("SELECT COUNT(*) FROM audit_log WHERE entity = 'patient_photo' AND action = 'UPDATE' AND patient_id = $1", patient_id)
+        .await;
+    assert_eq!(updates, 1);
+    let after_hash: String = sqlx::query_scalar("SELECT after_hash FROM audit_log WHERE entity = 'patient_photo' AND patient_id = $1")
+        .bind(patient_id)
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    let json = serde_json::to_vec(&photo.data_url).unwrap();
+    assert_eq!(after_hash, hash_value(db.config.keyring.audit_key(), &json));
+    assert_ne!(after_hash, sha256_hex(&json));
+}
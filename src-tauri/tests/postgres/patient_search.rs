@@ -0,0 +1,116 @@
+// src-tauri/tests/postgres/patient_search.rs
+
+// Dependencies
+use chrono::NaiveDate;
+use ehrportal_lib::key_rotation::rotate_encryption_keys;
+use ehrportal_lib::patient_search::{find_patients, PatientSearchQuery, PatientSort};
+use ehrportal_lib::patients::{register_patient, PatientInput};
+use ehrportal_lib::repository::PgRepository;
+use crate::harness::{test_db, TEST_ENCRYPTION_KEY};
+
+fn patient_input(first_name: &str, last_name: &str, phone: &str) -> PatientInput {
+    PatientInput {
+        first_name: first_name.to_string(),
+        last_name: last_name.to_string(),
+        date_of_birth: NaiveDate::from_ymd_opt(1975, 6, 30).unwrap(),
+        gender: "MALE".to_string(),
+        phone: Some(phone.to_string()),
+        email: None,
+        address: None,
+        emergency_contact_name: None,
+        emergency_contact_phone: None,
+        preferred_language: None,
+        national_id: None,
+    }
+}
+
+#[tokio::test]
+async fn phone_prefixes_are_found_through_the_blind_index() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    let pattern = &db.config.mr_number_pattern;
+    let first = register_patient(&db.repo(), &nurse, pattern, &patient_input("Omar", "Haddad", "+44 20 7946 0018")).await.unwrap();
+    let second = register_patient(&db.repo(), &nurse, pattern, &patient_input("Omar", "Hadley", "+44 20 7946 0999")).await.unwrap();
+    register_patient(&db.repo(), &nurse, pattern, &patient_input("Ines", "Duarte", "+351 21 000 1234")).await.unwrap();
+
+    let query = PatientSearchQuery { phone: Some("4420 7946".to_string()), limit: Some(1), ..Default::default() };
+    let page = find_patients(&db.repo(), &nurse, &query).await.unwrap();
+    assert_eq!(page.patients.iter().map(|row| row.patient_id).collect::<Vec<_>>(), vec![first.patient_id]);
+
+    let query = PatientSearchQuery { cursor: page.next_cursor, ..query };
+    let page = find_patients(&db.repo(), &nurse, &query).await.unwrap();
+    assert_eq!(page.patients.iter().map(|row| row.patient_id).collect::<Vec<_>>(), vec![second.patient_id]);
+    assert!(page.next_cursor.is_none());
+
+    let query = PatientSearchQuery { phone: Some("442079460999".to_string()), ..Default::default() };
+    let found = find_patients(&db.repo(), &nurse, &query).await.unwrap();
+    assert_eq!(found.patients.iter().map(|row| row.patient_id).collect::<Vec<_>>(), vec![second.patient_id]);
+
+    // Tokens are keyed hashes, never the digits themselves
+    let plain_tokens = db
+        .count(
+            "SELECT COUNT(*) FROM patients WHERE patient_id = $1 AND '44207946' = ANY(phone_search_tokens)",
+            first.patient_id,
+        )
+        .await;
+    assert_eq!(plain_tokens, 0);
+    db.assert_encrypted("patients", "phone", "patient_id", first.patient_id, "+44 20 7946 0018").await;
+}
+
+#[tokio::test]
+async fn phones_are_still_found_after_a_key_rotation() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    let pattern = &db.config.mr_number_pattern;
+    let patient = register_patient(&db.repo(), &nurse, pattern, &patient_input("Lars", "Holm", "+46 8 123 456 78")).await.unwrap();
+
+    let retired = format!("1:{}", TEST_ENCRYPTION_KEY);
+    let rotated = db.config_with(&[
+        ("ENCRYPTION_KEY", "integration-test-encryption-key-2"),
+        ("ENCRYPTION_KEY_ID", "2"),
+        ("ENCRYPTION_RETIRED_KEYS", &retired),
+    ]);
+    rotate_encryption_keys(&db.pool, &rotated.keyring, 100).await.unwrap();
+    assert_eq!(db.count("SELECT COUNT(*) FROM patients WHERE patient_id = $1 AND key_id = 2", patient.patient_id).await, 1);
+
+    let repo = PgRepository::new(&db.pool, &rotated.keyring);
+    let query = PatientSearchQuery { phone: Some("46 8 123".to_string()), ..Default::default() };
+    let found = find_patients(&repo, &nurse, &query).await.unwrap();
+    assert_eq!(found.patients.iter().map(|row| row.patient_id).collect::<Vec<_>>(), vec![patient.patient_id]);
+}
+
+#[tokio::test]
+async fn name_search_sorts_and_pages_in_both_directions() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    for (first_name, last_name) in [("Ada", "Lovelace"), ("Adele", "Goldberg"), ("Alan", "Turing"), ("Edsger", "Dijkstra")] {
+        db.add_patient(first_name, last_name).await;
+    }
+
+    let mut query = PatientSearchQuery { name: Some("a".to_string()), limit: Some(2), ..Default::default() };
+    let mut names = vec![];
+    loop {
+        let page = find_patients(&db.repo(), &nurse, &query).await.unwrap();
+        names.extend(page.patients.into_iter().map(|row| row.last_name));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    assert_eq!(names, vec!["Goldberg", "Lovelace", "Turing"]);
+
+    let query = PatientSearchQuery { name: Some("turing al".to_string()), ..Default::default() };
+    let found = find_patients(&db.repo(), &nurse, &query).await.unwrap();
+    assert_eq!(found.patients.len(), 1);
+    assert_eq!(found.patients[0].first_name, "Alan");
+
+    let query = PatientSearchQuery { sort: PatientSort::MrNumber, descending: true, limit: Some(3), ..Default::default() };
+    let first_page = find_patients(&db.repo(), &nurse, &query).await.unwrap();
+    let query = PatientSearchQuery { cursor: first_page.next_cursor.clone(), ..query };
+    let second_page = find_patients(&db.repo(), &nurse, &query).await.unwrap();
+    let mr_numbers: Vec<String> = first_page.patients.iter().chain(&second_page.patients).map(|row| row.mr_number.clone()).collect();
+    let mut expected = mr_numbers.clone();
+    expected.sort_by(|a, b| b.cmp(a));
+    assert_eq!(mr_numbers.len(), 4);
+    assert_eq!(mr_numbers, expected);
+}
@@ -0,0 +1,126 @@
+// src-tauri/tests/postgres/patient_timeline.rs
+
+// Dependencies
+use chrono::{Duration, Utc};
+use ehrportal_lib::alert::{issue_alert, NewAlert};
+use ehrportal_lib::appointment::{schedule_appointment, NewAppointment};
+use ehrportal_lib::patient_timeline::{find_patient_timeline, TimelineData, TimelineKind, TimelineQuery};
+use ehrportal_lib::patients::{add_patient_activity, list_patient_activity, NewPatientActivity};
+use ehrportal_lib::procedure_comments::add_procedure_comment;
+use ehrportal_lib::vision::{save_eye_measurement, EyeMeasurementInput};
+use crate::harness::{new_activity, test_db};
+
+#[tokio::test]
+async fn timeline_pages_through_every_source_in_order() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let nurse = db.signed_in("NURSE").await;
+    let patient_id = db.add_patient("Grace", "Hopper").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+    let now = Utc::now();
+
+    let activity = NewPatientActivity { activity_time: now - Duration::days(2), ..new_activity(patient_id, procedure_id) };
+    add_patient_activity(&db.repo(), &doctor, &activity).await.unwrap();
+    let activity_id = list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap()[0].activity_id;
+    add_procedure_comment(&db.repo(), &nurse, activity_id, None, "Repeat in two weeks").await.unwrap();
+    save_eye_measurement(&db.repo(), &nurse, &EyeMeasurementInput {
+        patient_id,
+        iop_at: "24".to_string(),
+        iop_nct: "".to_string(),
+        cct: "540".to_string(),
+        tond: "".to_string(),
+        side: "LEFT".to_string(),
+    })
+    .await
+    .unwrap();
+    schedule_appointment(&db.repo(), &doctor, &NewAppointment {
+        description: "Visual field test".to_string(),
+        appointment_time: now + Duration::days(14),
+        appointment_duration: 1800,
+        users: vec![doctor.user_id],
+        patient_id: Some(patient_id),
+    })
+    .await
+    .unwrap();
+    issue_alert(&db.repo(), &nurse, &NewAlert {
+        priority_level: "EMERGENCY".to_string(),
+        title: "IOP above 21".to_string(),
+        message: "Left eye".to_string(),
+        issued_for: doctor.user_id,
+        patient_id: Some(patient_id),
+    })
+    .await
+    .unwrap();
+
+    let mut query = TimelineQuery { limit: Some(2), ..Default::default() };
+    let mut events = vec![];
+    loop {
+        let page = find_patient_timeline(&db.repo(), &doctor, patient_id, &query).await.unwrap();
+        events.extend(page.events);
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+
+    let kinds: Vec<TimelineKind> = events.iter().map(|event| event.kind()).collect();
+    assert_eq!(kinds.len(), 6);
+    assert_eq!(kinds[0], TimelineKind::Appointment);
+    assert_eq!(kinds[4..], [TimelineKind::Activity, TimelineKind::Note]);
+    assert!(events.windows(2).all(|pair| pair[0].occurred_at >= pair[1].occurred_at));
+    // Comments sit at the time they were written, not at their activity's time
+    let comment = events.iter().find_map(|event| match &event.data {
+        TimelineData::Comment(comment) => Some(comment),
+        _ => None,
+    });
+    assert_eq!(comment.and_then(|comment| comment.body.as_deref()), Some("Repeat in two weeks"));
+    assert_eq!(comment.and_then(|comment| comment.created_by), Some(nurse.user_id));
+
+    let query = TimelineQuery {
+        kinds: vec![TimelineKind::EyeMeasurement, TimelineKind::Alert],
+        from: Some(now - Duration::days(1)),
+        ..Default::default()
+    };
+    let page = find_patient_timeline(&db.repo(), &doctor, patient_id, &query).await.unwrap();
+    let mut kinds: Vec<TimelineKind> = page.events.iter().map(|event| event.kind()).collect();
+    kinds.sort();
+    assert_eq!(kinds, vec![TimelineKind::EyeMeasurement, TimelineKind::Alert]);
+}
+
+#[tokio::test]
+async fn events_at_the_same_moment_are_paged_exactly_once() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let patient_id = db.add_patient("Ada", "Lovelace").await;
+    let procedure_id = db.add_procedure("Slit lamp").await;
+    let moment = Utc::now() - Duration::days(1);
+    for note in ["Cornea clear", "", "Lens opacity"] {
+        let activity = NewPatientActivity { activity_time: moment, doctors_note: note.to_string(), ..new_activity(patient_id, procedure_id) };
+        add_patient_activity(&db.repo(), &doctor, &activity).await.unwrap();
+    }
+
+    let mut query = TimelineQuery { limit: Some(1), ..Default::default() };
+    let mut seen = vec![];
+    loop {
+        let page = find_patient_timeline(&db.repo(), &doctor, patient_id, &query).await.unwrap();
+        seen.extend(page.events.iter().map(|event| (event.kind(), event.source_id)));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+
+    let mut activity_ids: Vec<i32> = seen.iter().filter(|(kind, _)| *kind == TimelineKind::Activity).map(|(_, id)| *id).collect();
+    assert_eq!(activity_ids.len(), 3);
+    assert!(activity_ids.windows(2).all(|pair| pair[0] > pair[1]));
+    activity_ids.remove(1);
+    let expected: Vec<(TimelineKind, i32)> = seen[..3]
+        .iter()
+        .copied()
+        .chain(activity_ids.iter().map(|id| (TimelineKind::Note, *id)))
+        .collect();
+    assert_eq!(seen, expected);
+
+    let missing = find_patient_timeline(&db.repo(), &doctor, patient_id + 1000, &TimelineQuery::default()).await;
+    assert_eq!(missing.unwrap_err().code(), "NOT_FOUND");
+}
@@ -0,0 +1,131 @@
+// src-tauri/tests/postgres/patients.rs
+
+// Dependencies
+use chrono::{NaiveDate, Utc};
+use ehrportal_lib::patients::{
+    add_patient_activity, deactivate_patient_record, edit_patient, find_patient, list_patient_activity,
+    list_patient_complaints, list_patients, register_patient, PatientInput
+};
+use crate::harness::{new_activity, test_db};
+
+#[tokio::test]
+async fn activity_is_stored_encrypted_and_audited() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let patient_id = db.add_patient("Mara", "Quinn").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+
+    add_patient_activity(&db.repo(), &doctor, &new_activity(patient_id, procedure_id)).await.unwrap();
+
+    let activities = list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap();
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0].activity.as_deref(), Some("Tonometry"));
+    assert_eq!(activities[0].status.as_deref(), Some("IN_PROGRESS"));
+    let complaints = list_patient_complaints(&db.repo(), &doctor, patient_id).await.unwrap();
+    assert_eq!(complaints, vec!["Blurred vision at night".to_string()]);
+
+    let activity_id = activities[0].activity_id;
+    db.assert_encrypted("patient_activity", "doctors_note", "activity_id", activity_id, "Suspected early glaucoma")
+        .await;
+    db.assert_encrypted("patient_activity", "patient_complaint", "activity_id", activity_id, "Blurred vision at night")
+        .await;
+    db.assert_encrypted("procedures", "procedure_name", "procedure_id", procedure_id, "Tonometry").await;
+
+    let created = db
+        .count(
+            "SELECT COUNT(*) FROM audit_log WHERE entity = 'patient_activity' AND action = 'CREATE' AND entity_id = $1",
+            activity_id,
+        )
+        .await;
+    assert_eq!(created, 1);
+    let reads = db
+        .count("SELECT COUNT(*) FROM audit_log WHERE action = 'READ' AND patient_id = $1", patient_id)
+        .await;
+    assert_eq!(reads, 2);
+}
+
+#[tokio::test]
+async fn nurses_cannot_create_activities() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    let patient_id = db.add_patient("Mara", "Quinn").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+
+    let error = add_patient_activity(&db.repo(), &nurse, &new_activity(patient_id, procedure_id)).await.unwrap_err();
+
+    assert_eq!(error.code(), "FORBIDDEN");
+    assert_eq!(db.count("SELECT COUNT(*) FROM patient_activity WHERE patient_id = $1", patient_id).await, 0);
+}
+
+#[tokio::test]
+async fn activity_for_missing_patient_is_a_conflict() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+
+    let error = add_patient_activity(&db.repo(), &doctor, &new_activity(9999, procedure_id)).await.unwrap_err();
+
+    assert_eq!(error.code(), "CONFLICT");
+    assert_eq!(db.count("SELECT COUNT(*) FROM audit_log WHERE user_id = $1", doctor.user_id).await, 0);
+}
+
+fn patient_input(phone: &str) -> PatientInput {
+    PatientInput {
+        first_name: "Lena".to_string(),
+        last_name: "Varga".to_string(),
+        date_of_birth: NaiveDate::from_ymd_opt(1962, 11, 4).unwrap(),
+        gender: "FEMALE".to_string(),
+        phone: Some(phone.to_string()),
+        email: Some("lena.varga@example.test".to_string()),
+        address: Some("12 Harbour Road".to_string()),
+        emergency_contact_name: Some("Tomas Varga".to_string()),
+        emergency_contact_phone: Some("+36 1 555 0100".to_string()),
+        preferred_language: Some("Hungarian".to_string()),
+        national_id: Some("HU-884412".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn registered_demographics_are_encrypted_and_editable() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    db.add_patient("Seeded", "Patient").await;
+
+    let first = register_patient(&db.repo(), &nurse, &db.config.mr_number_pattern, &patient_input("+36 1 555 0199")).await.unwrap();
+    let second = register_patient(&db.repo(), &nurse, &db.config.mr_number_pattern, &patient_input("+36 1 555 0199")).await.unwrap();
+    let year = Utc::now().format("%Y").to_string();
+    assert_eq!(first.mr_number, format!("MR-{}-000001", year));
+    assert_eq!(second.mr_number, format!("MR-{}-000002", year));
+
+    for (column, value) in [
+        ("phone", "+36 1 555 0199"),
+        ("email", "lena.varga@example.test"),
+        ("address", "12 Harbour Road"),
+        ("emergency_contact_name", "Tomas Varga"),
+        ("preferred_language", "Hungarian"),
+        ("national_id", "HU-884412"),
+    ] {
+        db.assert_encrypted("patients", column, "patient_id", first.patient_id, value).await;
+    }
+
+    let edited = edit_patient(&db.repo(), &nurse, first.patient_id, &patient_input("+36 1 555 0123")).await.unwrap();
+    assert_eq!(edited.phone.as_deref(), Some("+36 1 555 0123"));
+    assert!(edited.updated_at.is_some());
+    db.assert_encrypted("patients", "phone", "patient_id", first.patient_id, "+36 1 555 0123").await;
+    let read = find_patient(&db.repo(), &nurse, first.patient_id).await.unwrap();
+    assert_eq!(read.national_id.as_deref(), Some("HU-884412"));
+
+    deactivate_patient_record(&db.repo(), &nurse, first.patient_id).await.unwrap();
+    let listed: Vec<i32> = list_patients(&db.repo(), &nurse).await.unwrap().iter().map(|patient| patient.patient_id).collect();
+    assert!(!listed.contains(&first.patient_id));
+    assert!(listed.contains(&second.patient_id));
+
+    let actions: Vec<String> = sqlx::query_scalar(
+        "SELECT action FROM audit_log WHERE entity = 'patient' AND entity_id = $1 AND action <> 'READ' ORDER BY audit_id",
+    )
+    .bind(first.patient_id)
+    .fetch_all(&db.pool)
+    .await
+    .unwrap();
+    assert_eq!(actions, vec!["CREATE", "UPDATE", "UPDATE"]);
+}
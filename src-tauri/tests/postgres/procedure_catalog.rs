@@ -0,0 +1,85 @@
+// src-tauri/tests/postgres/procedure_catalog.rs
+
+// Dependencies
+use ehrportal_lib::error::AppError;
+use ehrportal_lib::patients::add_patient_activity;
+use ehrportal_lib::procedure_catalog::{
+    add_procedure, edit_procedure, list_procedures, remove_procedure, set_procedure_active, ProcedureInput
+};
+use crate::harness::{new_activity, test_db};
+
+fn cataract_surgery() -> ProcedureInput {
+    ProcedureInput {
+        procedure_name: "Cataract Surgery".to_string(),
+        description: Some("Phacoemulsification with lens implant".to_string()),
+        category: "surgical".to_string(),
+        default_duration_minutes: Some(60),
+        default_cost_cents: Some(250_000),
+        cpt_code: Some("66984".to_string()),
+        snomed_code: None,
+    }
+}
+
+#[tokio::test]
+async fn admins_edit_the_catalog_and_changes_are_audited() {
+    let db = test_db!();
+    let admin = db.signed_in("ADMIN").await;
+    let doctor = db.signed_in("DOCTOR").await;
+
+    let denied = add_procedure(&db.repo(), &doctor, &cataract_surgery()).await;
+    assert!(matches!(denied, Err(AppError::Forbidden(_))));
+
+    let created = add_procedure(&db.repo(), &admin, &cataract_surgery()).await.unwrap();
+    assert_eq!((created.category.as_str(), created.active), ("SURGICAL", true));
+    db.assert_encrypted("procedures", "procedure_name", "procedure_id", created.procedure_id, "Cataract Surgery").await;
+
+    let edited = edit_procedure(&db.repo(), &admin, created.procedure_id, &ProcedureInput {
+        snomed_code: Some("54885007".to_string()),
+        default_cost_cents: None,
+        ..cataract_surgery()
+    })
+    .await
+    .unwrap();
+    assert_eq!(edited.snomed_code.as_deref(), Some("54885007"));
+    assert_eq!(edited.default_cost_cents, None);
+    assert!(edited.updated_at.is_some());
+
+    let missing = edit_procedure(&db.repo(), &admin, -1, &cataract_surgery()).await;
+    assert!(matches!(missing, Err(AppError::NotFound(_))));
+
+    let audited = db
+        .count("SELECT COUNT(*) FROM audit_log WHERE entity = 'procedure' AND entity_id = $1", created.procedure_id)
+        .await;
+    assert_eq!(audited, 2);
+}
+
+#[tokio::test]
+async fn used_procedures_cannot_be_deleted_or_picked_once_inactive() {
+    let db = test_db!();
+    let admin = db.signed_in("ADMIN").await;
+    let doctor = db.signed_in("DOCTOR").await;
+    let patient_id = db.add_patient("Iris", "Novak").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+    let unused_id = db.add_procedure("Gonioscopy").await;
+    let activity = new_activity(patient_id, procedure_id);
+    add_patient_activity(&db.repo(), &doctor, &activity).await.unwrap();
+
+    let in_use = remove_procedure(&db.repo(), &admin, procedure_id).await;
+    assert!(matches!(in_use, Err(AppError::Conflict(_))));
+
+    // The foreign key backs up the service check, so activities are never deleted with their procedure
+    let direct = sqlx::query("DELETE FROM procedures WHERE procedure_id = $1").bind(procedure_id).execute(&db.pool).await;
+    assert!(direct.is_err());
+    assert_eq!(db.count("SELECT COUNT(*) FROM patient_activity WHERE procedure_id = $1", procedure_id).await, 1);
+
+    set_procedure_active(&db.repo(), &admin, procedure_id, false).await.unwrap();
+    let retired = add_patient_activity(&db.repo(), &doctor, &activity).await;
+    assert!(matches!(retired, Err(AppError::Validation(_))));
+
+    let offered = list_procedures(&db.repo(), &doctor, false).await.unwrap();
+    assert_eq!(offered.iter().map(|procedure| procedure.procedure_id).collect::<Vec<_>>(), vec![unused_id]);
+    assert_eq!(list_procedures(&db.repo(), &doctor, true).await.unwrap().len(), 2);
+
+    remove_procedure(&db.repo(), &admin, unused_id).await.unwrap();
+    assert_eq!(db.count("SELECT COUNT(*) FROM procedures WHERE procedure_id = $1", unused_id).await, 0);
+}
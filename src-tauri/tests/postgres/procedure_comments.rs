@@ -0,0 +1,86 @@
+// src-tauri/tests/postgres/procedure_comments.rs
+
+// Dependencies
+use ehrportal_lib::error::AppError;
+use ehrportal_lib::patients::{add_patient_activity, list_patient_activity, list_patient_procedures};
+use ehrportal_lib::procedure_comments::{
+    add_procedure_comment, delete_procedure_comment, edit_procedure_comment, list_comment_history, list_procedure_comments,
+    migrate_legacy_comments
+};
+use crate::harness::{new_activity, test_db};
+
+#[tokio::test]
+async fn legacy_comment_blobs_become_rows() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let patient_id = db.add_patient("Mara", "Quinn").await;
+    let procedure_id = db.add_procedure("Tonometry").await;
+    add_patient_activity(&db.repo(), &doctor, &new_activity(patient_id, procedure_id)).await.unwrap();
+    let activity_id = list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap()[0].activity_id;
+
+    // Blobs were built as '' || '\n' || comment, and older ones joined with a literal backslash-n
+    let key = db.config.keyring.current();
+    sqlx::query("UPDATE patient_activity SET comments = pgp_sym_encrypt($2, $3), key_id = $4 WHERE activity_id = $1")
+        .bind(activity_id)
+        .bind("\nPressure 24 mmHg\\nDrops given\n  \nRecheck in two weeks")
+        .bind(&key.secret)
+        .bind(key.id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    assert_eq!(migrate_legacy_comments(&db.pool, &db.config.keyring).await.unwrap(), 1);
+    assert_eq!(migrate_legacy_comments(&db.pool, &db.config.keyring).await.unwrap(), 0);
+
+    let comments = list_procedure_comments(&db.repo(), &doctor, activity_id).await.unwrap();
+    let bodies: Vec<Option<&str>> = comments.iter().map(|comment| comment.body.as_deref()).collect();
+    assert_eq!(bodies, vec![Some("Pressure 24 mmHg"), Some("Drops given"), Some("Recheck in two weeks")]);
+    assert!(comments.iter().all(|comment| comment.created_by.is_none()));
+    let procedures = list_patient_procedures(&db.repo(), &doctor, patient_id).await.unwrap();
+    assert_eq!(procedures[0].comments.as_deref(), Some("Pressure 24 mmHg\nDrops given\nRecheck in two weeks"));
+    let changes = db
+        .count(
+            "SELECT COUNT(*) FROM procedure_comment_changes h JOIN procedure_comments c USING (comment_id) WHERE c.activity_id = $1",
+            activity_id,
+        )
+        .await;
+    assert_eq!(changes, 3);
+}
+
+#[tokio::test]
+async fn threads_are_encrypted_and_keep_their_history() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let nurse = db.signed_in("NURSE").await;
+    let patient_id = db.add_patient("Lena", "Varga").await;
+    let procedure_id = db.add_procedure("OCT scan").await;
+    add_patient_activity(&db.repo(), &doctor, &new_activity(patient_id, procedure_id)).await.unwrap();
+    let activity_id = list_patient_activity(&db.repo(), &doctor, patient_id).await.unwrap()[0].activity_id;
+
+    let question = add_procedure_comment(&db.repo(), &doctor, activity_id, None, "Was the pupil dilated?").await.unwrap();
+    let answer = add_procedure_comment(&db.repo(), &nurse, activity_id, Some(question.comment_id), "Yes, tropicamide")
+        .await
+        .unwrap();
+    db.assert_encrypted("procedure_comments", "body", "comment_id", answer.comment_id, "Yes, tropicamide").await;
+
+    let not_author = edit_procedure_comment(&db.repo(), &doctor, answer.comment_id, "No").await;
+    assert!(matches!(not_author, Err(AppError::Forbidden(_))));
+    edit_procedure_comment(&db.repo(), &nurse, answer.comment_id, "Yes, tropicamide 1%").await.unwrap();
+    delete_procedure_comment(&db.repo(), &doctor, question.comment_id).await.unwrap();
+
+    let threads = list_procedure_comments(&db.repo(), &nurse, activity_id).await.unwrap();
+    assert_eq!(threads.len(), 1);
+    assert_eq!(threads[0].body, None);
+    assert!(threads[0].deleted_at.is_some());
+    assert_eq!(threads[0].replies[0].body.as_deref(), Some("Yes, tropicamide 1%"));
+    assert!(threads[0].replies[0].created_by_name.is_some());
+
+    let history = list_comment_history(&db.repo(), &doctor, question.comment_id).await.unwrap();
+    let actions: Vec<(&str, Option<&str>)> = history.iter().map(|change| (change.action.as_str(), change.body.as_deref())).collect();
+    assert_eq!(actions, vec![("CREATE", Some("Was the pupil dilated?")), ("DELETE", Some("Was the pupil dilated?"))]);
+
+    let audited = db
+        .count("SELECT COUNT(*) FROM audit_log WHERE entity = 'procedure_comment' AND action <> 'READ' AND patient_id = $1", patient_id)
+        .await;
+    assert_eq!(audited, 4);
+}
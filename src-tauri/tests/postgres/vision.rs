@@ -0,0 +1,102 @@
+// src-tauri/tests/postgres/vision.rs
+
+// Dependencies
+use ehrportal_lib::vision::{
+    find_refraction, find_vision, save_eye_measurement, save_refraction, save_vision, EyeMeasurementInput,
+    RefractionInput, RefractionQuery, VisionInput, VisionQuery
+};
+use crate::harness::test_db;
+
+fn vision_input(patient_id: i32, near_vision: &str, distant_vision: &str) -> VisionInput {
+    VisionInput {
+        patient_id,
+        near_vision: near_vision.to_string(),
+        distant_vision: distant_vision.to_string(),
+        side: "LEFT".to_string(),
+        value_type: "BCVA".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn vision_upsert_creates_then_updates_one_row() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    let patient_id = db.add_patient("Omar", "Haddad").await;
+
+    let created = save_vision(&db.repo(), &nurse, &vision_input(patient_id, "N6", "6/9")).await.unwrap();
+    assert!(created.updated_at.is_none());
+    let updated = save_vision(&db.repo(), &nurse, &vision_input(patient_id, "N5", "6/6")).await.unwrap();
+
+    assert_eq!(updated.vision_id, created.vision_id);
+    assert_eq!(updated.updated_by, Some(nurse.user_id));
+    let query = VisionQuery { patient_id, side: "LEFT".to_string(), value_type: "BCVA".to_string() };
+    let stored = find_vision(&db.repo(), &nurse, &query).await.unwrap().unwrap();
+    assert_eq!(stored.near_vision.as_deref(), Some("N5"));
+    assert_eq!(stored.distant_vision.as_deref(), Some("6/6"));
+    assert_eq!(db.count("SELECT COUNT(*) FROM vision WHERE patient_id = $1", patient_id).await, 1);
+
+    db.assert_encrypted("vision", "near_vision", "vision_id", created.vision_id, "N5").await;
+    db.assert_encrypted("vision", "distant_vision", "vision_id", created.vision_id, "6/6").await;
+
+    let actions: Vec<String> = sqlx::query_scalar(
+        "SELECT action FROM audit_log WHERE entity = 'vision' AND entity_id = $1 AND action <> 'READ' ORDER BY audit_id",
+    )
+    .bind(created.vision_id)
+    .fetch_all(&db.pool)
+    .await
+    .unwrap();
+    assert_eq!(actions, vec!["CREATE", "UPDATE"]);
+}
+
+#[tokio::test]
+async fn refraction_upsert_keeps_vision_types_apart() {
+    let db = test_db!();
+    let doctor = db.signed_in("DOCTOR").await;
+    let patient_id = db.add_patient("Omar", "Haddad").await;
+    let input = |vision_type: &str, spherical: &str| RefractionInput {
+        patient_id,
+        spherical: spherical.to_string(),
+        cylindrical: "-0.75".to_string(),
+        axis: "180".to_string(),
+        side: "RIGHT".to_string(),
+        value_type: "DL".to_string(),
+        vision_type: vision_type.to_string(),
+    };
+
+    save_refraction(&db.repo(), &doctor, &input("DV", "-1.25")).await.unwrap();
+    let near = save_refraction(&db.repo(), &doctor, &input("NV", "+1.00")).await.unwrap();
+    let distant = save_refraction(&db.repo(), &doctor, &input("DV", "-1.50")).await.unwrap();
+
+    assert_ne!(near.refraction_id, distant.refraction_id);
+    let query = RefractionQuery {
+        patient_id,
+        side: "RIGHT".to_string(),
+        value_type: "DL".to_string(),
+        vision_type: "DV".to_string(),
+    };
+    let stored = find_refraction(&db.repo(), &doctor, &query).await.unwrap().unwrap();
+    assert_eq!(stored.spherical.as_deref(), Some("-1.50"));
+    assert_eq!(db.count("SELECT COUNT(*) FROM refraction WHERE patient_id = $1", patient_id).await, 2);
+
+    db.assert_encrypted("refraction", "spherical", "refraction_id", distant.refraction_id, "-1.50").await;
+    db.assert_encrypted("refraction", "axis", "refraction_id", distant.refraction_id, "180").await;
+}
+
+#[tokio::test]
+async fn measurement_for_missing_patient_is_a_conflict() {
+    let db = test_db!();
+    let nurse = db.signed_in("NURSE").await;
+    let input = EyeMeasurementInput {
+        patient_id: 9999,
+        iop_at: "16".to_string(),
+        iop_nct: "17".to_string(),
+        cct: "540".to_string(),
+        tond: "0.3".to_string(),
+        side: "LEFT".to_string(),
+    };
+
+    let error = save_eye_measurement(&db.repo(), &nurse, &input).await.unwrap_err();
+
+    assert_eq!(error.code(), "CONFLICT");
+    assert_eq!(db.count("SELECT COUNT(*) FROM audit_log WHERE user_id = $1", nurse.user_id).await, 0);
+}
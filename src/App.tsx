@@ -1,15 +1,68 @@
 // src/pages/UnderConstruction.tsx
 
 // Dependencies
-import React from 'react';
+import React, { useEffect, useRef } from 'react';
 import { useNavigate } from 'react-router-dom';
+import { useDispatch, useSelector } from 'react-redux';
+import { invoke } from '@tauri-apps/api/core';
 import { Box, Typography, useTheme } from '@mui/material';
 import ConstructionIcon from '@mui/icons-material/Construction';
 import AllRoutes from './components/AllRoutes';
+import { RootState } from './redux/store';
+import { clearCredentials, setCredentials } from './redux/auth/authSlice';
+import { TOKEN_REFRESH_INTERVAL_MS } from './utils/utils';
 
 const App = () => {
     const theme = useTheme();
     const navigate = useNavigate();
+    const dispatch = useDispatch();
+    const { refreshToken } = useSelector((state: RootState) => state.auth);
+    const hasSession = Boolean(refreshToken);
+    // Refresh tokens rotate on every use, so the timers below always read the latest one
+    const refreshTokenRef = useRef(refreshToken);
+    refreshTokenRef.current = refreshToken;
+
+    // Keep the short lived access token fresh while a session exists
+    useEffect(() => {
+        if (!hasSession) {
+            return;
+        }
+
+        let refreshing = false;
+        const refresh = async () => {
+            const current = refreshTokenRef.current;
+            // A second call with the same token would look like reuse and end the session
+            if (refreshing || !current) {
+                return;
+            }
+            refreshing = true;
+            try {
+                const data: any = await invoke('refresh_session', { refreshToken: current });
+                dispatch(setCredentials({ token: data.token, refreshToken: data.refresh_token, user: data.user }));
+            } catch (error) {
+                console.error('Error while refreshing session: ', error);
+                dispatch(clearCredentials());
+            } finally {
+                refreshing = false;
+            }
+        };
+        const refreshWhenVisible = () => {
+            if (document.visibilityState === 'visible') {
+                refresh();
+            }
+        };
+
+        // The access token may have expired while the app was closed, hidden or the machine asleep
+        refresh();
+        const interval = setInterval(refresh, TOKEN_REFRESH_INTERVAL_MS);
+        document.addEventListener('visibilitychange', refreshWhenVisible);
+        window.addEventListener('online', refresh);
+        return () => {
+            clearInterval(interval);
+            document.removeEventListener('visibilitychange', refreshWhenVisible);
+            window.removeEventListener('online', refresh);
+        };
+    }, [hasSession, dispatch]);
 
     return (
         <Box
@@ -1,5 +1,6 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import {
     Box,
     Typography,
@@ -46,10 +47,14 @@ export type AppointmentData = {
     patient_complaint: string;
     activity_time: string;
     activity_created_at: string;
+    doctor_id: number | null;
+    doctor_name: string | null;
+    nurse_id: number | null;
+    nurse_name: string | null;
 };
 
 const fetchAppointmentData = async (): Promise<AppointmentData[]> => {
-    return await invoke<AppointmentData[]>('get_appointment_data');
+    return await invoke<AppointmentData[]>('get_appointment_data', { token: store.getState().auth.token });
 }
 
 const Appointments = () => {
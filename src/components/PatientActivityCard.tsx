@@ -3,6 +3,7 @@
 import React, { useEffect, useState } from 'react';
 import { Box, Typography, Button, Chip, useTheme } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import { useNavigate } from 'react-router-dom';
 import { useQuery } from 'react-query';
 
@@ -20,7 +21,7 @@ interface PatientActivityCardProps {
 const fetchPatientActivityData = async (
     patientId: number
 ): Promise<PatientActivity[]> => {
-    return await invoke<PatientActivity[]>('get_patient_activity_data', {patientId});
+    return await invoke<PatientActivity[]>('get_patient_activity_data', { token: store.getState().auth.token, patientId});
 };
 
 const PatientActivityCard: React.FC<PatientActivityCardProps> = ({ patient_id }) => {
@@ -44,7 +45,7 @@ const PatientActivityCard: React.FC<PatientActivityCardProps> = ({ patient_id })
                 return theme.palette.paperGreen.default;
             case 'TO_BE_REVIEWED':
                 return theme.palette.paperYellow.default;
-            case 'INCOMPLETE':
+            case 'IN_PROGRESS':
                 return theme.palette.paperRed.default;
             default:
                 return theme.palette.text.primary;
@@ -68,10 +69,10 @@ const PatientActivityCard: React.FC<PatientActivityCardProps> = ({ patient_id })
                 return 'COMPLETED';
             case 'TO_BE_REVIEWED':
                 return 'PENDING';
-            case 'INCOMPLETE':
-                return 'INCOMPLETE';
+            case 'IN_PROGRESS':
+                return 'IN PROGRESS';
             default:
-                return 'N/A';
+                return status.replace(/_/g, ' ');
         }
     };
 
@@ -4,6 +4,7 @@ import React, { useEffect, useState } from 'react';
 import { Patient } from '../pages/ConsultantPage';
 import { Box, Typography, Avatar, useTheme, CircularProgress, Button } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import { useNavigate } from 'react-router-dom';
 import { useQuery } from 'react-query';
 
@@ -14,7 +15,7 @@ interface PatientCardProps {
 const fetchPatientData = async (
     patientId: number
 ): Promise<Patient> => {
-    return await invoke<Patient>('get_patient_data', { patientId });
+    return await invoke<Patient>('get_patient_data', { token: store.getState().auth.token, patientId });
 };
 
 const PatientCard: React.FC<PatientCardProps> = ({ patient_id }) => {
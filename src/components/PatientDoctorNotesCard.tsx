@@ -2,6 +2,7 @@
 
 import { Box, CircularProgress, Typography, useTheme } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import React, { useEffect, useState } from 'react';
 import { LockOpen, Lock } from '@mui/icons-material';
 import { useQuery } from 'react-query';
@@ -19,7 +20,7 @@ export type PatientDoctorData = {
 const fetchPatientDoctorData = async (
     patientId: number
 ): Promise<PatientDoctorData[]> => {
-    return await invoke<PatientDoctorData[]>('get_patient_doctor_data', { patientId });
+    return await invoke<PatientDoctorData[]>('get_patient_doctor_data', { token: store.getState().auth.token, patientId });
 };
 
 const PatientDoctorNotesCard: React.FC<PatientDoctorNotesCardProps> = ({ patient_id }) => {
@@ -2,6 +2,7 @@
 
 import { Box, Typography, useTheme, CircularProgress } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import React, { useEffect, useState } from 'react';
 import { useQuery } from 'react-query';
 
@@ -58,7 +59,7 @@ const renderBubbles = (items: string[] | undefined, emptyMessage: string) => {
 const fetchPatientHistoryData = async (
     patientId: number
 ): Promise<PatientHistoryData> => {
-    const data: any = await invoke('get_patient_history_data', { patientId });
+    const data: any = await invoke('get_patient_history_data', { token: store.getState().auth.token, patientId });
 
     const formattedData: PatientHistoryData = {
         last_visit: data.last_visit,
@@ -18,6 +18,7 @@ import {
 import { Person as PersonIcon, EventNote as DateIcon, Assignment as MRIcon, Wc as GenderIcon } from '@mui/icons-material';
 import React, { useEffect, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import { useNavigate } from 'react-router-dom';
 import { useQuery } from 'react-query';
 
@@ -33,7 +34,7 @@ export type Patient = {
 };
 
 const fetchPatientsData = async (): Promise<Patient[]> => {
-    return await invoke<Patient[]>('get_patients_data');
+    return await invoke<Patient[]>('get_patients_data', { token: store.getState().auth.token });
 }
 
 const PatientList: React.FC = () => {
@@ -2,6 +2,7 @@
 
 import { Box, Typography, Chip, TextField, useTheme, IconButton, InputAdornment, CircularProgress } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import React, { useEffect, useState } from 'react';
 import SendIcon from '@mui/icons-material/Send';
 import { useToast } from '@chakra-ui/react';
@@ -11,6 +12,7 @@ import ProcedureDetailsModal from './ProcedureDetailsModal';
 import ProcedureListArc from './ProcedureListArc';
 import { DragDropContext, Droppable, Draggable } from 'react-beautiful-dnd';
 import { useQuery, useQueryClient } from 'react-query';
+import { errorMessage } from '../utils/utils';
 
 interface PatientProcedureGridProps {
     patient_id: number;
@@ -31,7 +33,14 @@ export type Procedure = {
     procedure_id: number;
     procedure_name: string;
     description: string;
+    category: 'DIAGNOSTIC' | 'SURGICAL' | 'IMAGING' | 'OPTOMETRY';
+    default_duration_minutes: number | null;
+    default_cost_cents: number | null;
+    cpt_code: string | null;
+    snomed_code: string | null;
+    active: boolean;
     created_at: string;
+    updated_at: string | null;
 };
 
 // This is the shape of the data returned by the API.
@@ -56,7 +65,7 @@ const sanitizeComments = (comments: string): string[] => {
 
 // Fetch function returns an array of sanitized patient procedures.
 const fetchPatientProcedureData = async (patientId: number): Promise<PatientProcedureData[]> => {
-    const data = await invoke<PatientProcedureDataFromAPI[]>('get_patient_procedures', { patientId });
+    const data = await invoke<PatientProcedureDataFromAPI[]>('get_patient_procedures', { token: store.getState().auth.token, patientId });
     return data.map((proc) => ({
         ...proc,
         comments: proc.comments ? sanitizeComments(proc.comments) : [],
@@ -64,7 +73,7 @@ const fetchPatientProcedureData = async (patientId: number): Promise<PatientProc
 };
 
 const fetchAllProcedures = async (): Promise<Procedure[]> => {
-    return await invoke<Procedure[]>('get_all_procedures');
+    return await invoke<Procedure[]>('get_all_procedures', { token: store.getState().auth.token });
 };
 
 const PatientProcedureGrid: React.FC<PatientProcedureGridProps> = ({ patient_id }) => {
@@ -103,7 +112,7 @@ const PatientProcedureGrid: React.FC<PatientProcedureGridProps> = ({ patient_id
                 return theme.palette.paperGreen.default;
             case 'TO_BE_REVIEWED':
                 return theme.palette.paperYellow.light;
-            case 'INCOMPLETE':
+            case 'IN_PROGRESS':
                 return theme.palette.paperRed.default;
             default:
                 return theme.palette.text.primary;
@@ -116,10 +125,10 @@ const PatientProcedureGrid: React.FC<PatientProcedureGridProps> = ({ patient_id
                 return 'COMPLETED';
             case 'TO_BE_REVIEWED':
                 return 'PENDING';
-            case 'INCOMPLETE':
-                return 'INCOMPLETE';
+            case 'IN_PROGRESS':
+                return 'IN PROGRESS';
             default:
-                return status.toUpperCase();
+                return status.replace(/_/g, ' ');
         }
     };
 
@@ -134,12 +143,12 @@ const PatientProcedureGrid: React.FC<PatientProcedureGridProps> = ({ patient_id
                 throw Error('Please enter comment before submitting.');
             }
 
-            await invoke('add_comment_to_procedure', { activityId: patientProcedureData[index].activity_id, comment: comment });
+            await invoke('add_comment_to_procedure', { token: store.getState().auth.token, activityId: patientProcedureData[index].activity_id, comment: comment });
             queryClient.invalidateQueries(['patient_procedures', patient_id]);
         } catch (error) {
             console.error('Error while submitting comment: ', error);
             toast({
-                title: `Error while submitting comment: ${error}`,
+                title: `Error while submitting comment: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
@@ -2,6 +2,7 @@
 
 import { Box, Typography, useTheme, CircularProgress } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import React, { useEffect, useState } from 'react';
 import { useQuery } from 'react-query';
 
@@ -10,7 +11,7 @@ interface PatientSummaryCardProps {
 };
 
 const fetchPatientSummaryData = async (patientId: number): Promise<string[]> => {
-    return await invoke<string[]>('get_patient_summary_data', { patientId });
+    return await invoke<string[]>('get_patient_summary_data', { token: store.getState().auth.token, patientId });
 };
 
 const PatientSummaryCard: React.FC<PatientSummaryCardProps> = ({ patient_id }) => {
@@ -2,6 +2,7 @@
 
 import { Box, Typography, Chip, TextField, useTheme, IconButton, CircularProgress, Modal, Button, Select, MenuItem } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import React, { useEffect, useState } from 'react';
 import { useToast } from '@chakra-ui/react';
 import CloseIcon from '@mui/icons-material/Close';
@@ -11,6 +12,7 @@ import { AdapterDayjs } from '@mui/x-date-pickers/AdapterDayjs';
 import dayjs from 'dayjs';
 import utc from 'dayjs/plugin/utc';
 import { Procedure } from './PatientProcedureGrid';
+import { errorMessage } from '../utils/utils';
 
 dayjs.extend(utc);
 
@@ -22,7 +24,7 @@ const ProcedureDetailsModal: React.FC<{
     const theme = useTheme();
     const toast = useToast();
 
-    const [status, setStatus] = useState('TO_BE_REVIEWED');
+    const [status, setStatus] = useState('IN_PROGRESS');
     const [doctorNote, setDoctorNote] = useState('');
     const [patientComplaint, setPatientComplaint] = useState('');
     const [isLoading, setIsLoading] = useState<boolean>(false);
@@ -38,9 +40,10 @@ const ProcedureDetailsModal: React.FC<{
             }
             const formattedTime = activityTime.utc().format('YYYY-MM-DDTHH:mm:ss[Z]');
             await invoke('create_patient_activity', {
+                token: store.getState().auth.token,
                 patientId: patient_id,
                 procedureId: procedure.procedure_id,
-                status: status || 'TO_BE_REVIEWED',
+                status: status || 'IN_PROGRESS',
                 doctorsNote: doctorNote || '',
                 patientComplaint: patientComplaint || '',
                 activityTime: formattedTime,
@@ -59,7 +62,7 @@ const ProcedureDetailsModal: React.FC<{
         } catch (error) {
             console.error('Error while creating procedure: ', error);
             toast({
-                title: `Error while creating procedure: ${error}`,
+                title: `Error while creating procedure: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
@@ -97,36 +100,22 @@ const ProcedureDetailsModal: React.FC<{
                     {procedure.description}
                 </Typography>
                 <Select
-                    value={status === 'TO_BE_REVIEWED' ? 'PENDING' : status}
+                    value={status}
                     onChange={(e) => setStatus(e.target.value)}
                     fullWidth
                     sx={{ mb: 2 }}
-                    renderValue={(selected) => <Chip label={selected} />}
+                    renderValue={(selected) => <Chip label={selected.replace(/_/g, ' ')} />}
                     label='Status'
                 >
-                    <MenuItem value='TO_BE_REVIEWED'>
-                        <Chip
-                            label={'PENDING'}
-                            sx={{
-                                backgroundColor: theme.palette.paperYellow.light,
-                                color: theme.palette.common.black,
-                                fontWeight: 'bold',
-                            }}
-                        />
+                    <MenuItem value='SCHEDULED'>
+                        <Chip label={'SCHEDULED'} sx={{ fontWeight: 'bold' }} />
                     </MenuItem>
-                    <MenuItem value='COMPLETED'>
-                        <Chip
-                            label={'COMPLETED'}
-                            sx={{
-                                backgroundColor: theme.palette.paperGreen.default,
-                                color: theme.palette.common.black,
-                                fontWeight: 'bold',
-                            }}
-                        />
+                    <MenuItem value='CHECKED_IN'>
+                        <Chip label={'CHECKED IN'} sx={{ fontWeight: 'bold' }} />
                     </MenuItem>
-                    <MenuItem value='INCOMPLETE'>
+                    <MenuItem value='IN_PROGRESS'>
                         <Chip
-                            label={'INCOMPLETE'}
+                            label={'IN PROGRESS'}
                             sx={{
                                 backgroundColor: theme.palette.paperRed.default,
                                 color: theme.palette.common.white,
@@ -27,6 +27,7 @@ import AddIcon from '@mui/icons-material/Add';
 import CloseIcon from '@mui/icons-material/Close';
 import CustomToggleButton from '../../common-components/ToggleButton';
 import { useQuery, useQueryClient } from 'react-query';
+import { errorMessage } from '../../utils/utils';
 
 export type Alert = {
     alert_id: number;
@@ -142,7 +143,7 @@ const Alerts: React.FC = () => {
         } catch (error) {
             console.error('Error while sending alert: ', error);
             toast({
-                title: `Error while sending alert: ${error}`,
+                title: `Error while sending alert: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
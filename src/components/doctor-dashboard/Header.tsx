@@ -9,9 +9,10 @@ import LogoutIcon from '@mui/icons-material/Logout';
 import SearchIcon from '@mui/icons-material/Search';
 import CloseIcon from '@mui/icons-material/Close';
 import { clearCredentials } from '../../redux/auth/authSlice';
+import { invoke } from '@tauri-apps/api/core';
 
 const Header: React.FC = () => {
-    const { user } = useSelector((state: RootState) => state.auth);
+    const { user, token } = useSelector((state: RootState) => state.auth);
     const dispatch = useDispatch();
 
     const [currentTime, setCurrentTime] = useState(new Date().toLocaleString('en-GB'));
@@ -26,6 +27,16 @@ const Header: React.FC = () => {
         return () => clearInterval(interval); // Cleanup on unmount
     }, []);
 
+    const handleLogout = async () => {
+        try {
+            await invoke('logout', { token });
+        } catch (error) {
+            console.error('Error while logging out: ', error);
+        } finally {
+            dispatch(clearCredentials());
+        }
+    };
+
     return (
         <Box display='flex' justifyContent='space-between' alignItems='center' p={2} borderBottom='1px solid #ccc' gap='2rem' minHeight='3rem' maxHeight='3rem'>
             <Typography variant='h4'>Welcome, Dr. {user?.first_name + ' ' + user?.last_name}</Typography>
@@ -48,7 +59,7 @@ const Header: React.FC = () => {
                     </IconButton>
                 )}
 
-                <Button variant='outlined' startIcon={<LogoutIcon />} onClick={() => dispatch(clearCredentials())}>
+                <Button variant='outlined' startIcon={<LogoutIcon />} onClick={handleLogout}>
                     Logout
                 </Button>
             </Box>
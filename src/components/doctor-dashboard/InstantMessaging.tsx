@@ -25,6 +25,7 @@ import {
 import { Send as SendIcon, Add as AddIcon } from '@mui/icons-material';
 import { ArrowBack } from '@mui/icons-material';
 import { useQuery, useQueryClient } from 'react-query';
+import { errorMessage } from '../../utils/utils';
 
 // Type definitions remain unchanged
 export type Conversation = {
@@ -147,7 +148,7 @@ const InstantMessaging: React.FC = () => {
         } catch (error) {
             console.error('Error while sending message: ', error);
             toast({
-                title: `Error while sending message: ${error}`,
+                title: `Error while sending message: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 position: 'top',
@@ -4,11 +4,12 @@
 import { useToast } from '@chakra-ui/react';
 import { Box, CircularProgress, Typography, useTheme } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../../redux/store';
 import React, { useEffect, useState } from 'react';
 import { useQuery } from 'react-query';
 
 const fetchPatientComplaints = async (patientId: number): Promise<string[]> => {
-    return await invoke<string[]>('get_patient_complaints', { patientId });
+    return await invoke<string[]>('get_patient_complaints', { token: store.getState().auth.token, patientId });
 };
 
 const ChiefComplaints: React.FC<{ patient_id: number }> = ({ patient_id }) => {
@@ -5,7 +5,9 @@ import LockOpenIcon from '@mui/icons-material/LockOpen';
 import { useTheme } from '@mui/material';
 import { useToast } from '@chakra-ui/react';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../../redux/store';
 import { useQuery, useQueryClient } from 'react-query';
+import { errorMessage } from '../../utils/utils';
 
 export type EyeMeasurementData = {
     measurement_id: number;
@@ -30,6 +32,7 @@ const measurements = [
 
 const fetchEyeMeasurementData = async (patientId: number, side: string): Promise<EyeMeasurementData> => {
     return await invoke<EyeMeasurementData>('get_patient_eye_measurement_data', {
+        token: store.getState().auth.token,
         patientId,
         side,
     });
@@ -54,22 +57,22 @@ const EyeMeasurement: React.FC<{ patient_id: number }> = ({ patient_id }) => {
         try {
             setUpdateLoading(true);
             await invoke('update_patient_eye_measurement_data', {
+                token: store.getState().auth.token,
                 patientId: patient_id,
                 iopAt: leftEyeMeasurement?.iop_at,
                 iopNct: leftEyeMeasurement?.iop_nct,
                 cct: leftEyeMeasurement?.cct,
                 tond: leftEyeMeasurement?.tond,
                 side: 'LEFT',
-                updatedBy: 1,
             });
             await invoke('update_patient_eye_measurement_data', {
+                token: store.getState().auth.token,
                 patientId: patient_id,
                 iopAt: rightEyeMeasurement?.iop_at,
                 iopNct: rightEyeMeasurement?.iop_nct,
                 cct: rightEyeMeasurement?.cct,
                 tond: rightEyeMeasurement?.tond,
                 side: 'RIGHT',
-                updatedBy: 1,
             });
 
             queryClient.invalidateQueries(['eye_measurement', patient_id, 'LEFT']);
@@ -77,7 +80,7 @@ const EyeMeasurement: React.FC<{ patient_id: number }> = ({ patient_id }) => {
         } catch (error) {
             console.error('Error while updating eye measurement data: ', error);
             toast({
-                title: `Error while updating eye measurement data: ${error}`,
+                title: `Error while updating eye measurement data: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
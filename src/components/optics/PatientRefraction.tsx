@@ -4,10 +4,12 @@
 import { useToast } from '@chakra-ui/react';
 import { Box, CircularProgress, Typography, IconButton, Divider, CardContent, Card, Select, MenuItem } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../../redux/store';
 import React, { useEffect, useState } from 'react';
 import { Lock, LockOpen } from '@mui/icons-material';
 import ShimmerPatientRefraction from './ShimmerPatientRefraction';
 import { useQuery, useQueryClient } from 'react-query';
+import { errorMessage } from '../../utils/utils';
 
 export type RefractionData = {
     vision_id: number;
@@ -46,6 +48,7 @@ const axisOptions = generateAxisOptions();
 
 const fetchRefractionData = async (patient_id: number, side: string, value_type: string, vision_type: string): Promise<RefractionData> => {
     return await invoke<RefractionData>('get_refraction_data', {
+        token: store.getState().auth.token,
         query: { patient_id, side, value_type, vision_type },
     });
 };
@@ -134,6 +137,7 @@ const PatientRefraction: React.FC<{
         try {
             setUpdateLoading(true);
             await invoke('update_refraction_data', {
+                token: store.getState().auth.token,
                 patientId: patient_id,
                 spherical: params.spherical,
                 cylindrical: params.cylindrical,
@@ -141,14 +145,13 @@ const PatientRefraction: React.FC<{
                 side: side,
                 valueType: value_type,
                 visionType: vision_type,
-                updatedBy: 1,
             });
             // Invalidate the corresponding query to refetch updated data
             queryClient.invalidateQueries(['refraction', patient_id, side, value_type, vision_type]);
         } catch (error) {
             console.error('Error while updating refraction data: ', error);
             toast({
-                title: `Error while updating refraction data: ${error}`,
+                title: `Error while updating refraction data: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
@@ -2,10 +2,12 @@
 import { useToast } from '@chakra-ui/react';
 import { Box, CircularProgress, Paper, Typography, IconButton, TextField, useTheme, CardContent, Card, Select, MenuItem } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../../redux/store';
 import React, { useEffect, useState } from 'react';
 import { Lock, LockOpen } from '@mui/icons-material';
 import ShimmerPatientVision from './ShimmerPatientVision';
 import { useQuery, useQueryClient } from 'react-query';
+import { errorMessage } from '../../utils/utils';
 
 export type VisionData = {
     vision_id: number;
@@ -49,7 +51,7 @@ const visionOptions = [
 ];
 
 const fetchVisionData = async (patient_id: number, side: string, value_type: string): Promise<VisionData> => {
-    return await invoke<VisionData>('get_vision_data', { query: { patient_id: patient_id, side: side, value_type: value_type } });
+    return await invoke<VisionData>('get_vision_data', { token: store.getState().auth.token, query: { patient_id: patient_id, side: side, value_type: value_type } });
 };
 
 const PatientVision: React.FC<{
@@ -74,19 +76,19 @@ const PatientVision: React.FC<{
         try {
             setUpdateLoading(true);
             await invoke('update_vision_data', {
+                token: store.getState().auth.token,
                 patientId: patient_id,
                 nearVision: nearVision,
                 distantVision: distantVision,
                 side: side,
                 valueType: value_type,
-                updatedBy: 1,
             });
 
             queryClient.invalidateQueries(['vision', patient_id, side, value_type]);
         } catch (error) {
             console.error('Error while updating vision data: ', error);
             toast({
-                title: `Error while updating vision data: ${error}`,
+                title: `Error while updating vision data: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
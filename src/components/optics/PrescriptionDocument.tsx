@@ -6,8 +6,10 @@ import React, { useEffect, useRef, useState } from 'react';
 import { Patient } from '../../pages/ConsultantPage';
 import { Document, Page, Text, View, StyleSheet, PDFViewer, PDFDownloadLink, Font } from '@react-pdf/renderer';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../../redux/store';
 import { useToast } from '@chakra-ui/react';
 import { RefractionData } from './PatientRefraction';
+import { errorMessage } from '../../utils/utils';
 
 // Define prescription PDF document
 const PrescriptionDocument: React.FC<{ patient_id: number }> = ({ patient_id }) => {
@@ -21,12 +23,12 @@ const PrescriptionDocument: React.FC<{ patient_id: number }> = ({ patient_id })
     const fetchPatientData = async () => {
         try {
             setIsLoading(true);
-            const data: Patient = await invoke('get_patient_data', { patientId: patient_id });
+            const data: Patient = await invoke('get_patient_data', { token: store.getState().auth.token, patientId: patient_id });
             setPatientData(data);
         } catch (error) {
             console.error('Error fetching patient data:', error);
             toast({
-                title: `Error while fetching patient data: ${error}`,
+                title: `Error while fetching patient data: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
@@ -43,6 +45,7 @@ const PrescriptionDocument: React.FC<{ patient_id: number }> = ({ patient_id })
 
             // Fetch Distance Vision data (Left)
             const leftDataDV: RefractionData = await invoke('get_refraction_data', {
+                token: store.getState().auth.token,
                 query: {
                     patient_id: patient_id,
                     side: 'LEFT',
@@ -53,6 +56,7 @@ const PrescriptionDocument: React.FC<{ patient_id: number }> = ({ patient_id })
 
             // Fetch Near Vision data (Left)
             const leftDataNV: RefractionData = await invoke('get_refraction_data', {
+                token: store.getState().auth.token,
                 query: {
                     patient_id: patient_id,
                     side: 'LEFT',
@@ -63,6 +67,7 @@ const PrescriptionDocument: React.FC<{ patient_id: number }> = ({ patient_id })
 
             // Fetch Distance Vision data (Right)
             const rightDataDV: RefractionData = await invoke('get_refraction_data', {
+                token: store.getState().auth.token,
                 query: {
                     patient_id: patient_id,
                     side: 'RIGHT',
@@ -73,6 +78,7 @@ const PrescriptionDocument: React.FC<{ patient_id: number }> = ({ patient_id })
 
             // Fetch Near Vision data (Right)
             const rightDataNV: RefractionData = await invoke('get_refraction_data', {
+                token: store.getState().auth.token,
                 query: {
                     patient_id: patient_id,
                     side: 'RIGHT',
@@ -94,7 +100,7 @@ const PrescriptionDocument: React.FC<{ patient_id: number }> = ({ patient_id })
         } catch (error) {
             console.error('Error while fetching refraction data: ', error);
             toast({
-                title: `Error while fetching refraction data: ${error}`,
+                title: `Error while fetching refraction data: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
@@ -11,6 +11,7 @@ import { useDispatch } from 'react-redux';
 import { clearCredentials } from '../redux/auth/authSlice';
 import { useSelector } from 'react-redux';
 import { RootState } from '../redux/store';
+import { invoke } from '@tauri-apps/api/core';
 
 export type Patient = {
     patient_id: number;
@@ -30,6 +31,16 @@ const ConsultantPage = () => {
 
     const [update, setUpdate] = useState<any>(null);
 
+    const handleLogout = async () => {
+        try {
+            await invoke('logout', { token });
+        } catch (error) {
+            console.error('Error while logging out: ', error);
+        } finally {
+            dispatch(clearCredentials());
+        }
+    };
+
     return (
         <Box
             sx={{
@@ -52,7 +63,7 @@ const ConsultantPage = () => {
                 }}
             >
                 <Box>
-                    <CustomButton onClick={() => dispatch(clearCredentials())}>Logout</CustomButton>
+                    <CustomButton onClick={handleLogout}>Logout</CustomButton>
                 </Box>
                 <Box
                     sx={{
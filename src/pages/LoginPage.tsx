@@ -7,6 +7,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { useDispatch } from 'react-redux';
 import { useNavigate } from 'react-router-dom';
 import { setCredentials } from '../redux/auth/authSlice';
+import { errorMessage } from '../utils/utils';
 
 const LoginPage: React.FC = () => {
     const theme = useTheme();
@@ -18,6 +19,17 @@ const LoginPage: React.FC = () => {
     const [password, setPassword] = useState<string>('');
     const [isLoading, setIsLoading] = useState<boolean>(false);
     const [showPassword, setShowPassword] = useState<boolean>(false);
+    // Second factor step, entered when the password is correct but a TOTP code is still needed
+    const [mfaMode, setMfaMode] = useState<'verify' | 'enroll' | null>(null);
+    const [mfaToken, setMfaToken] = useState<string>('');
+    const [code, setCode] = useState<string>('');
+    const [enrollment, setEnrollment] = useState<{ secret: string; provisioning_uri: string } | null>(null);
+    const [recoveryCodes, setRecoveryCodes] = useState<string[]>([]);
+    const [pendingSession, setPendingSession] = useState<any>(null);
+    // Accounts invited or reset by an admin sign in with a temporary password and must replace it first
+    const [resetRequired, setResetRequired] = useState<boolean>(false);
+    const [newPassword, setNewPassword] = useState<string>('');
+    const [confirmPassword, setConfirmPassword] = useState<string>('');
 
     const handleClickShowPassword = () => {
         setShowPassword(!showPassword);
@@ -28,25 +40,107 @@ const LoginPage: React.FC = () => {
             setIsLoading(true);
 
             const data: any = await invoke('login', { email, password });
-            console.log('data: ', data);
 
-            if (!data || !data.token || !data.user) {
-                throw Error(data.error || 'Error while loggin in');
+            if (data?.status === 'MFA_REQUIRED') {
+                setMfaToken(data.mfa_token);
+                setMfaMode('verify');
+                return;
             }
 
+            if (data?.status === 'MFA_ENROLLMENT_REQUIRED') {
+                const enrollmentData: any = await invoke('begin_totp_enrollment', { token: data.mfa_token });
+                setEnrollment(enrollmentData);
+                setMfaToken(data.mfa_token);
+                setMfaMode('enroll');
+                return;
+            }
+
+            completeLogin(data);
+        } catch (error) {
+            console.error('Error while logging in: ', error);
             toast({
-                title: `Logged in successfully`,
-                status: 'success',
+                title: `Error while logging in: ${errorMessage(error)}`,
+                status: 'error',
                 duration: 4000,
                 isClosable: true,
                 position: 'top',
             });
-            dispatch(setCredentials({ token: data?.token, user: data.user }));
-            navigate('/');
+        } finally {
+            setIsLoading(false);
+        }
+    };
+
+    const completeLogin = (data: any) => {
+        if (!data || !data.token || !data.user) {
+            throw Error(data?.error || 'Error while loggin in');
+        }
+
+        if (data.user.must_reset_password) {
+            setPendingSession(data);
+            setRecoveryCodes([]);
+            setMfaMode(null);
+            setResetRequired(true);
+            return;
+        }
+
+        toast({
+            title: `Logged in successfully`,
+            status: 'success',
+            duration: 4000,
+            isClosable: true,
+            position: 'top',
+        });
+        dispatch(setCredentials({ token: data.token, refreshToken: data.refresh_token, user: data.user }));
+        navigate('/');
+    };
+
+    const handleChangePassword = async () => {
+        try {
+            setIsLoading(true);
+
+            if (newPassword !== confirmPassword) {
+                throw Error('The new passwords do not match.');
+            }
+
+            await invoke('change_password', {
+                token: pendingSession.token,
+                currentPassword: password,
+                newPassword,
+            });
+            setResetRequired(false);
+            completeLogin({ ...pendingSession, user: { ...pendingSession.user, must_reset_password: false } });
         } catch (error) {
-            console.error('Error while logging in: ', error);
+            console.error('Error while changing password: ', error);
             toast({
-                title: `Error while logging in: ${error}`,
+                title: `Error while changing password: ${errorMessage(error)}`,
+                status: 'error',
+                duration: 4000,
+                isClosable: true,
+                position: 'top',
+            });
+        } finally {
+            setIsLoading(false);
+        }
+    };
+
+    const handleVerifyCode = async () => {
+        try {
+            setIsLoading(true);
+
+            if (mfaMode === 'verify') {
+                const data: any = await invoke('verify_login_totp', { mfaToken, code });
+                completeLogin(data);
+                return;
+            }
+
+            // Recovery codes are only shown once, so keep the session until the user continues
+            const data: any = await invoke('confirm_totp_enrollment', { token: mfaToken, code });
+            setRecoveryCodes(data.recovery_codes);
+            setPendingSession(data.session);
+        } catch (error) {
+            console.error('Error while verifying code: ', error);
+            toast({
+                title: `Error while verifying code: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
@@ -102,6 +196,84 @@ const LoginPage: React.FC = () => {
                         Welcome Back
                     </Typography>
 
+                    {resetRequired ? (
+                        <Box component='form' noValidate autoComplete='off'>
+                            <Typography variant='body2' sx={{ mb: 2 }}>
+                                You signed in with a temporary password. Choose a new password to continue.
+                            </Typography>
+                            <TextField
+                                label='New password'
+                                type='password'
+                                variant='outlined'
+                                value={newPassword}
+                                onChange={(e: any) => setNewPassword(e.target.value)}
+                                required
+                                fullWidth
+                                sx={{ mb: 2 }}
+                            />
+                            <TextField
+                                label='Confirm new password'
+                                type='password'
+                                variant='outlined'
+                                value={confirmPassword}
+                                onChange={(e: any) => setConfirmPassword(e.target.value)}
+                                required
+                                fullWidth
+                                sx={{ mb: 3 }}
+                            />
+                            <CustomButton fullWidth size='large' variant='contained' onClick={handleChangePassword} sx={{ width: '100%' }}>
+                                {isLoading ? <CircularProgress /> : 'Change password'}
+                            </CustomButton>
+                        </Box>
+                    ) : mfaMode ? (
+                        <Box component='form' noValidate autoComplete='off'>
+                            {recoveryCodes.length > 0 ? (
+                                <>
+                                    <Typography variant='body2' sx={{ mb: 2 }}>
+                                        Save these recovery codes somewhere safe. Each one can be used once if you lose access to your authenticator app.
+                                    </Typography>
+                                    <Box sx={{ mb: 3, fontFamily: 'monospace', display: 'grid', gridTemplateColumns: '1fr 1fr', gap: 1 }}>
+                                        {recoveryCodes.map((recoveryCode) => (
+                                            <Typography key={recoveryCode} variant='body2' sx={{ fontFamily: 'monospace' }}>
+                                                {recoveryCode}
+                                            </Typography>
+                                        ))}
+                                    </Box>
+                                    <CustomButton fullWidth size='large' variant='contained' onClick={() => completeLogin(pendingSession)} sx={{ width: '100%' }}>
+                                        Continue
+                                    </CustomButton>
+                                </>
+                            ) : (
+                                <>
+                                    {mfaMode === 'enroll' && enrollment && (
+                                        <Box sx={{ mb: 3 }}>
+                                            <Typography variant='body2' sx={{ mb: 1 }}>
+                                                Your role requires two-factor authentication. Add this account to your authenticator app, then enter the code it shows.
+                                            </Typography>
+                                            <Typography variant='body2' sx={{ fontFamily: 'monospace', wordBreak: 'break-all', mb: 1 }}>
+                                                {enrollment.secret}
+                                            </Typography>
+                                            <Link href={enrollment.provisioning_uri} underline='hover' variant='body2'>
+                                                Open in authenticator app
+                                            </Link>
+                                        </Box>
+                                    )}
+                                    <TextField
+                                        label={mfaMode === 'verify' ? 'Authentication or recovery code' : 'Authentication code'}
+                                        variant='outlined'
+                                        value={code}
+                                        onChange={(e: any) => setCode(e.target.value)}
+                                        required
+                                        fullWidth
+                                        sx={{ mb: 3 }}
+                                    />
+                                    <CustomButton fullWidth size='large' variant='contained' onClick={handleVerifyCode} sx={{ width: '100%' }}>
+                                        {isLoading ? <CircularProgress /> : 'Verify'}
+                                    </CustomButton>
+                                </>
+                            )}
+                        </Box>
+                    ) : (
                     <Box component='form' noValidate autoComplete='off'>
                         <TextField
                             label='Email'
@@ -178,6 +350,7 @@ const LoginPage: React.FC = () => {
                             </Typography>
                         </Box>
                     </Box>
+                    )}
                 </Box>
             </Box>
 
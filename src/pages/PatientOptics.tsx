@@ -25,6 +25,7 @@ import { useNavigate, useParams } from 'react-router-dom';
 import { Patient } from './ConsultantPage';
 import { useToast } from '@chakra-ui/react';
 import { invoke } from '@tauri-apps/api/core';
+import { store } from '../redux/store';
 import PatientVision from '../components/optics/PatientVision';
 import PatientRefraction from '../components/optics/PatientRefraction';
 import ConsoleBox from '../components/optics/ConsoleBox';
@@ -39,7 +40,7 @@ import { useQuery } from 'react-query';
 const LazyPatientRefraction = lazy(() => import('../components/optics/PatientRefraction'));
 
 const fetchPatientData = async (patientId: number): Promise<Patient> => {
-    return await invoke<Patient>('get_patient_data', { patientId });
+    return await invoke<Patient>('get_patient_data', { token: store.getState().auth.token, patientId });
 };
 
 const PatientOptics: React.FC = () => {
@@ -136,7 +137,7 @@ const PatientOptics: React.FC = () => {
         const fileName = `glass_prescription_${patientData?.mr_number || 'patient'}.pdf`;
 
         try {
-            await invoke('save_pdf_file', { fileName, base64Data: base64String, downloadPath: downloadPath });
+            await invoke('save_pdf_file', { token: store.getState().auth.token, fileName, base64Data: base64String, downloadPath: downloadPath });
             toast({
                 title: 'Prescription downloaded successfully.',
                 status: 'success',
@@ -23,6 +23,7 @@ import { useToast } from '@chakra-ui/react';
 import { invoke } from '@tauri-apps/api/core';
 import { useDispatch } from 'react-redux';
 import { useNavigate } from 'react-router-dom';
+import { errorMessage } from '../utils/utils';
 
 const SignupPage: React.FC = () => {
     const theme = useTheme();
@@ -105,6 +106,7 @@ const SignupPage: React.FC = () => {
             await invoke('signup', { signupQuery: { first_name: firstName, last_name: lastName, role: role.toUpperCase(), email, password } });
             toast({
                 title: 'Signup successful',
+                description: 'An administrator must approve your account before you can log in.',
                 status: 'success',
                 duration: 4000,
                 isClosable: true,
@@ -114,7 +116,7 @@ const SignupPage: React.FC = () => {
         } catch (error) {
             console.error('Error while signing up:', error);
             toast({
-                title: `Error while signing up: ${error}`,
+                title: `Error while signing up: ${errorMessage(error)}`,
                 status: 'error',
                 duration: 4000,
                 isClosable: true,
@@ -214,7 +216,6 @@ const SignupPage: React.FC = () => {
                             <Select labelId='role-label' value={role} label='Role' onChange={(e) => setRole(e.target.value)}>
                                 <MenuItem value='Doctor'>Doctor</MenuItem>
                                 <MenuItem value='Nurse'>Nurse</MenuItem>
-                                <MenuItem value='Admin'>Admin</MenuItem>
                             </Select>
                             {errors.role && (
                                 <Typography variant='caption' color='error'>
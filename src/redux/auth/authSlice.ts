@@ -2,11 +2,12 @@
 
 // Dependencies
 import { createSlice, PayloadAction } from '@reduxjs/toolkit';
-import { TOKEN_KEY, USER_KEY } from '../../utils/utils';
+import { REFRESH_TOKEN_KEY, TOKEN_KEY, USER_KEY } from '../../utils/utils';
 import { AuthState, UserInterface } from './interfaces';
 
 const initialState: AuthState = {
     token: localStorage.getItem(TOKEN_KEY) || null,
+    refreshToken: localStorage.getItem(REFRESH_TOKEN_KEY) || null,
     user: localStorage.getItem(USER_KEY) ? JSON.parse(localStorage.getItem(USER_KEY) as string) : null,
 };
 
@@ -14,16 +15,20 @@ const authSlice = createSlice({
     name: 'auth',
     initialState,
     reducers: {
-        setCredentials: (state, action: PayloadAction<{ token: string; user: UserInterface }>) => {
+        setCredentials: (state, action: PayloadAction<{ token: string; refreshToken: string; user: UserInterface }>) => {
             state.token = action.payload.token;
+            state.refreshToken = action.payload.refreshToken;
             state.user = action.payload.user;
             localStorage.setItem(TOKEN_KEY, action.payload.token);
+            localStorage.setItem(REFRESH_TOKEN_KEY, action.payload.refreshToken);
             localStorage.setItem(USER_KEY, JSON.stringify(action.payload.user));
         },
         clearCredentials: (state) => {
             state.token = null;
+            state.refreshToken = null;
             state.user = null;
             localStorage.removeItem(TOKEN_KEY);
+            localStorage.removeItem(REFRESH_TOKEN_KEY);
             localStorage.removeItem(USER_KEY);
         },
     },
@@ -5,10 +5,15 @@ export interface UserInterface {
     role: string;
     first_name: string;
     last_name: string;
+    email: string;
+    status: string;
+    must_reset_password: boolean;
+    locked_until: string | null;
     created_at: string;
 }
 
 export interface AuthState {
     token: string | null;
+    refreshToken: string | null;
     user: UserInterface | null;
 }
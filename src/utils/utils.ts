@@ -1,2 +1,19 @@
 export const TOKEN_KEY = 'HammerTong_AppAuthToken';
 export const USER_KEY = 'Hammertong_AppUser';
+export const REFRESH_TOKEN_KEY = 'Hammertong_AppRefreshToken';
+
+// Access tokens live for 15 minutes, refresh a little before that
+export const TOKEN_REFRESH_INTERVAL_MS = 10 * 60 * 1000;
+
+// Error returned by every backend command
+export interface AppError {
+    code: 'NOT_FOUND' | 'UNAUTHORIZED' | 'FORBIDDEN' | 'VALIDATION' | 'CONFLICT' | 'DATABASE' | 'CRYPTO' | 'PASSWORD_CHANGE_REQUIRED';
+    message: string;
+    fields?: { field: string; message: string }[];
+}
+
+export const isAppError = (error: unknown): error is AppError =>
+    typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+
+// Readable text for anything caught from invoke()
+export const errorMessage = (error: unknown): string => (isAppError(error) ? error.message : String(error));